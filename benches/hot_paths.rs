@@ -0,0 +1,81 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use hecto::{Document, FileType, Position, Row, SearchDirection, Theme};
+
+const LINE: &str = "    let total = values.iter().map(|v| v * 2 + 0x1f).sum::<u64>(); // \"sum\"";
+
+fn text(lines: usize) -> String {
+    vec![LINE; lines].join("\n")
+}
+
+fn document(lines: usize) -> Document {
+    let mut document = Document::scratch("bench.rs");
+    document.insert_str(&Position::default(), &text(lines));
+    document
+}
+
+fn row(c: &mut Criterion) {
+    let options = FileType::from("bench.rs").highlighting_options();
+    c.bench_function("row highlight", |b| {
+        let mut row = Row::from(LINE);
+        b.iter(|| row.highlight(options, black_box(Some("total"))));
+    });
+    c.bench_function("row render", |b| {
+        let mut row = Row::from(LINE);
+        row.highlight(options, None);
+        let theme = Theme::builtin();
+        b.iter(|| row.render(0, 80, 4, theme, &(0..0), true, true));
+    });
+    c.bench_function("row find", |b| {
+        let row = Row::from(LINE);
+        b.iter(|| row.find(black_box("sum"), 0, SearchDirection::Forward));
+    });
+    c.bench_function("row insert", |b| {
+        b.iter_batched_ref(
+            || Row::from(LINE),
+            |row| row.insert(black_box(20), 'x'),
+            criterion::BatchSize::SmallInput,
+        );
+    });
+}
+
+fn document_paths(c: &mut Criterion) {
+    c.bench_function("document insert", |b| {
+        b.iter_batched_ref(
+            || document(1000),
+            |document| document.insert(&Position { x: 10, y: 500 }, 'x'),
+            criterion::BatchSize::LargeInput,
+        );
+    });
+    c.bench_function("document insert newline", |b| {
+        b.iter_batched_ref(
+            || document(1000),
+            |document| document.insert(&Position { x: 10, y: 500 }, '\n'),
+            criterion::BatchSize::LargeInput,
+        );
+    });
+    c.bench_function("document insert_str", |b| {
+        let pasted = text(100);
+        b.iter_batched_ref(
+            Document::default,
+            |document| document.insert_str(&Position::default(), &pasted),
+            criterion::BatchSize::SmallInput,
+        );
+    });
+    c.bench_function("document find", |b| {
+        let document = document(1000);
+        b.iter(|| {
+            document.find(
+                black_box("missing"),
+                &Position::default(),
+                SearchDirection::Forward,
+            )
+        });
+    });
+    c.bench_function("document text", |b| {
+        let document = document(1000);
+        b.iter(|| document.text());
+    });
+}
+
+criterion_group!(benches, row, document_paths);
+criterion_main!(benches);
@@ -0,0 +1,67 @@
+#![no_main]
+
+use hecto::{Document, Position, SearchDirection};
+use libfuzzer_sys::fuzz_target;
+
+// Each edit is an operation, a row and a column byte, followed by the text
+// of the operations that take one: a length byte and that many bytes.
+fuzz_target!(|data: &[u8]| {
+    let mut document = Document::scratch("fuzz");
+    let mut bytes = data.iter().copied();
+    while let (Some(operation), Some(y), Some(x)) = (bytes.next(), bytes.next(), bytes.next()) {
+        let at = Position {
+            x: usize::from(x % 16),
+            y: usize::from(y) % document.len().saturating_add(2),
+        };
+        let to = Position {
+            x: usize::from(x / 16),
+            y: at.y.saturating_add(usize::from(y / 8 % 4)),
+        };
+        match operation % 12 {
+            0 => {
+                if let Some(c) = text(&mut bytes).chars().next() {
+                    document.insert(&at, c);
+                }
+            }
+            1 => {
+                document.insert_str(&at, &text(&mut bytes));
+            }
+            2 => document.delete(&at),
+            3 => document.delete_row(at.y),
+            4 => document.delete_range(&at, &to),
+            5 => {
+                document.replace_rows(at.y, to.y, &text(&mut bytes));
+            }
+            6 => {
+                document.patch_rows(at.y, to.y, &text(&mut bytes));
+            }
+            7 => document.swap_rows(at.y, to.y),
+            8 => {
+                document.undo();
+            }
+            9 => {
+                document.redo();
+            }
+            10 => document.highlight(Some(&text(&mut bytes))),
+            _ => {
+                let direction = if x % 2 == 0 {
+                    SearchDirection::Forward
+                } else {
+                    SearchDirection::Backward
+                };
+                if let Some(found) = document.find(&text(&mut bytes), &at, direction) {
+                    assert!(found.y < document.len());
+                    assert!(document.row(found.y).is_some_and(|row| found.x < row.len()));
+                }
+            }
+        }
+        document.end_undo_group(at, to, None);
+        document.check_invariants();
+    }
+});
+
+fn text(bytes: &mut impl Iterator<Item = u8>) -> String {
+    let len = bytes.next().map_or(0, |len| usize::from(len % 32));
+    let text: Vec<u8> = bytes.take(len).collect();
+    String::from_utf8_lossy(&text).into_owned()
+}
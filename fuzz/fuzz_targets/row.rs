@@ -0,0 +1,65 @@
+#![no_main]
+
+use hecto::{FileType, Row, SearchDirection, Theme};
+use libfuzzer_sys::fuzz_target;
+
+// The first byte picks how the row is highlighted and how long its text is.
+// Each edit after the text is an operation and a column byte, followed by
+// a character for the operations that take one.
+fuzz_target!(|data: &[u8]| {
+    let Some((&first, data)) = data.split_first() else {
+        return;
+    };
+    let file_name = if first % 2 == 0 {
+        "fuzz.rs"
+    } else {
+        "fuzz.txt"
+    };
+    let options = FileType::from(file_name).highlighting_options();
+    let (text, edits) = data.split_at(usize::from(first / 2).min(data.len()));
+    let mut row = Row::from(String::from_utf8_lossy(text).as_ref());
+    let theme = Theme::builtin();
+    let mut bytes = edits.iter().copied();
+    while let (Some(operation), Some(x)) = (bytes.next(), bytes.next()) {
+        let at = usize::from(x % 64);
+        match operation % 7 {
+            0 => row.insert(at, char::from(bytes.next().unwrap_or(b'a'))),
+            1 => row.delete(at),
+            2 => {
+                let tail = row.split(at);
+                assert!(row.len() <= at);
+                row.append(&tail);
+            }
+            3 if row.len() < 256 => {
+                let copy = Row::from(row.as_str());
+                row.append(&copy);
+            }
+            4 => {
+                let query = char::from(bytes.next().unwrap_or(b'a')).to_string();
+                if let Some(found) = row.find(&query, at, SearchDirection::Forward) {
+                    assert!(found >= at && found < row.len());
+                }
+                if let Some(found) = row.find(&query, at, SearchDirection::Backward) {
+                    assert!(found < at);
+                }
+            }
+            5 => {
+                let _ = row.render(
+                    at,
+                    at.saturating_add(80),
+                    usize::from(x % 9),
+                    theme,
+                    &(0..at),
+                    true,
+                );
+            }
+            _ => {
+                let word = char::from(bytes.next().unwrap_or(b'a')).to_string();
+                row.highlight(options, Some(&word));
+                row.check_invariants();
+            }
+        }
+        row.highlight(options, None);
+        row.check_invariants();
+    }
+});
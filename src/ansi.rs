@@ -0,0 +1,78 @@
+/// The escape character that starts ANSI escape sequences.
+const ESCAPE: char = '\u{1b}';
+const BELL: char = '\u{7}';
+
+/// Returns whether `text` holds ANSI escape sequences, as output captured
+/// from a terminal program does.
+#[must_use]
+pub fn has_codes(text: &str) -> bool {
+    text.contains(ESCAPE)
+}
+
+/// Returns `text` without the escape sequences that set colors and other
+/// attributes, leaving those that move the cursor or clear the screen.
+#[must_use]
+pub fn strip_styles(text: &str) -> String {
+    let mut stripped = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("\u{1b}[") {
+        stripped.push_str(&rest[..start]);
+        let sequence = &rest[start..];
+        let end = sequence
+            .char_indices()
+            .skip(2)
+            .find(|(_, c)| ('@'..='~').contains(c))
+            .map_or(sequence.len(), |(index, c)| {
+                index.saturating_add(c.len_utf8())
+            });
+        if !sequence[..end].ends_with('m') {
+            stripped.push_str(&sequence[..end]);
+        }
+        rest = &sequence[end..];
+    }
+    stripped.push_str(rest);
+    stripped
+}
+
+/// Returns `text` without its ANSI escape sequences: colors and other
+/// control sequences, operating system commands such as titles and links,
+/// and two-character escapes. A stray escape character is dropped too.
+#[must_use]
+pub fn strip(text: &str) -> String {
+    let mut stripped = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != ESCAPE {
+            stripped.push(c);
+            continue;
+        }
+        match chars.next() {
+            // Control sequences run up to a final byte from `@` to `~`.
+            Some('[') => {
+                for c in chars.by_ref() {
+                    if ('@'..='~').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            // Operating system commands end with a bell or `ESC \`.
+            Some(']') => {
+                while let Some(c) = chars.next() {
+                    if c == BELL {
+                        break;
+                    }
+                    if c == ESCAPE && chars.peek() == Some(&'\\') {
+                        chars.next();
+                        break;
+                    }
+                }
+            }
+            // Character set designations take one more character.
+            Some('(' | ')' | '*' | '+') => {
+                chars.next();
+            }
+            Some(_) | None => {}
+        }
+    }
+    stripped
+}
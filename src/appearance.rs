@@ -0,0 +1,131 @@
+use std::env;
+use std::process::{Command, Stdio};
+use termion::color::Rgb;
+
+/// Whether the terminal draws on a light or a dark background.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Background {
+    Light,
+    Dark,
+}
+
+impl Background {
+    /// Returns the background `color` counts as, going by how bright it looks.
+    #[must_use]
+    pub fn of(color: Rgb) -> Self {
+        let Rgb(red, green, blue) = color;
+        // Relative luminance, with the weights of ITU-R BT.709, out of 255.
+        let luminance =
+            0.2126 * f64::from(red) + 0.7152 * f64::from(green) + 0.0722 * f64::from(blue);
+        if luminance > 127.5 {
+            Self::Light
+        } else {
+            Self::Dark
+        }
+    }
+
+    /// Reads `light` or `dark`.
+    #[must_use]
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "light" => Some(Self::Light),
+            "dark" => Some(Self::Dark),
+            _ => None,
+        }
+    }
+
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Light => "light",
+            Self::Dark => "dark",
+        }
+    }
+}
+
+/// Reads the color in the terminal's answer to `OSC 11 ; ?`, as in
+/// `11;rgb:ffff/ffff/ffff`, with one to four hex digits a channel.
+#[must_use]
+pub fn parse_reply(reply: &str) -> Option<Rgb> {
+    let color = reply.strip_prefix("11;")?.strip_prefix("rgb:")?;
+    let mut channels = color.split('/').map(|channel| {
+        let value = u32::from_str_radix(channel, 16).ok()?;
+        let digits = u32::try_from(channel.len())
+            .ok()
+            .filter(|len| (1..=4).contains(len))?;
+        let max = 16_u32.pow(digits).saturating_sub(1);
+        u8::try_from(value.saturating_mul(255) / max).ok()
+    });
+    let color = Rgb(channels.next()??, channels.next()??, channels.next()??);
+    channels.next().is_none().then_some(color)
+}
+
+/// Guesses the background from what the environment says about it: the
+/// `COLORFGBG` variable some terminals set, or else whether the system is
+/// in dark mode.
+#[must_use]
+pub fn hint() -> Option<Background> {
+    if let Ok(colors) = env::var("COLORFGBG") {
+        // The last of the ANSI color numbers is the background's.
+        match colors.rsplit(';').next().map(str::parse::<u8>) {
+            Some(Ok(7 | 15)) => return Some(Background::Light),
+            Some(Ok(_)) => return Some(Background::Dark),
+            _ => {}
+        }
+    }
+    system_mode()
+}
+
+/// Asks the system whether it is in dark mode.
+fn system_mode() -> Option<Background> {
+    if cfg!(target_os = "macos") {
+        // The setting is only there in dark mode.
+        let dark = output("defaults", &["read", "-g", "AppleInterfaceStyle"])
+            .is_some_and(|style| style.trim() == "Dark");
+        Some(if dark {
+            Background::Dark
+        } else {
+            Background::Light
+        })
+    } else if cfg!(windows) {
+        let value = output(
+            "reg",
+            &[
+                "query",
+                r"HKCU\Software\Microsoft\Windows\CurrentVersion\Themes\Personalize",
+                "/v",
+                "AppsUseLightTheme",
+            ],
+        )?;
+        let light = value.split_whitespace().last()?;
+        Some(if light == "0x0" {
+            Background::Dark
+        } else {
+            Background::Light
+        })
+    } else {
+        let scheme = output(
+            "gsettings",
+            &["get", "org.gnome.desktop.interface", "color-scheme"],
+        )?;
+        match scheme.trim().trim_matches('\'') {
+            "prefer-dark" => Some(Background::Dark),
+            "prefer-light" => Some(Background::Light),
+            _ => None,
+        }
+    }
+}
+
+/// Returns what `program` printed, if it ran and succeeded.
+fn output(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
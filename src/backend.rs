@@ -0,0 +1,222 @@
+use crate::appearance::Background;
+use crate::Position;
+use std::io::Error;
+use std::rc::Rc;
+use std::time::Duration;
+use termion::{color, event::Key};
+
+pub enum Event {
+    Key(Key),
+    FocusGained,
+    FocusLost,
+    Paste(String),
+    /// The terminal now has `width` by `height` cells, the status and message
+    /// bars included. The backend reports the new size from then on.
+    Resize {
+        width: u16,
+        height: u16,
+    },
+    /// Time passed without input, so the editor gets on with what it does
+    /// while idle, such as highlighting the rest of the document.
+    Tick,
+    /// The terminal's background turned out to be, or became, light or
+    /// dark.
+    Background(Background),
+}
+
+#[derive(Copy, Clone)]
+pub enum CursorShape {
+    Block,
+    Bar,
+    Underline,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Size {
+    pub width: u16,
+    pub height: u16,
+}
+
+/// Everything the editor needs from the screen it runs on: a size, a sink for
+/// output and a source of input events.
+///
+/// Drawing helpers are expressed as escape sequences passed to
+/// [`Backend::write`], so a backend only has to implement the five required
+/// methods.
+pub trait Backend {
+    /// Returns the size of the area available to text, which excludes the
+    /// status and message bars.
+    fn size(&self) -> Size;
+
+    fn write(&self, text: &str);
+
+    /// # Errors
+    ///
+    /// It is considered an error if not all bytes could be written due to I/O errors or EOF being reached.
+    fn flush(&self) -> Result<(), Error>;
+
+    /// # Errors
+    ///
+    /// Returns an error of kind `UnexpectedEof` once no more input is available.
+    fn read_event(&self) -> Result<Event, Error>;
+
+    /// Returns the next event if one arrives within `timeout`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error of kind `UnexpectedEof` once no more input is available.
+    fn poll_event(&self, timeout: Duration) -> Result<Option<Event>, Error>;
+
+    /// # Errors
+    ///
+    /// Returns an error of kind `UnexpectedEof` once no more input is available.
+    fn read_key(&self) -> Result<Key, Error> {
+        loop {
+            if let Event::Key(key) = self.read_event()? {
+                return Ok(key);
+            }
+        }
+    }
+
+    fn writeln(&self, text: &str) {
+        self.write(text);
+        self.write("\r\n");
+    }
+
+    fn clear_screen(&self) {
+        self.write(termion::clear::All.as_ref());
+    }
+
+    fn clear_current_line(&self) {
+        self.write(termion::clear::CurrentLine.as_ref());
+    }
+
+    fn set_bg_color(&self, color: color::Rgb) {
+        self.write(&color::Bg(color).to_string());
+    }
+
+    fn reset_bg_color(&self) {
+        self.write(&color::Bg(color::Reset).to_string());
+    }
+
+    fn set_fg_color(&self, color: color::Rgb) {
+        self.write(&color::Fg(color).to_string());
+    }
+
+    fn reset_fg_color(&self) {
+        self.write(&color::Fg(color::Reset).to_string());
+    }
+
+    fn cursor_position(&self, position: &Position) {
+        // Terminals are never this big, so a position past the edge is
+        // drawn at the edge rather than treated as an error.
+        let x = u16::try_from(position.x.saturating_add(1)).unwrap_or(u16::MAX);
+        let y = u16::try_from(position.y.saturating_add(1)).unwrap_or(u16::MAX);
+        self.write(&termion::cursor::Goto(x, y).to_string());
+    }
+
+    fn cursor_hide(&self) {
+        self.write(termion::cursor::Hide.as_ref());
+    }
+
+    fn cursor_show(&self) {
+        self.write(termion::cursor::Show.as_ref());
+    }
+
+    fn set_cursor_shape(&self, shape: CursorShape) {
+        match shape {
+            CursorShape::Block => self.write(termion::cursor::SteadyBlock.as_ref()),
+            CursorShape::Bar => self.write(termion::cursor::SteadyBar.as_ref()),
+            CursorShape::Underline => self.write(termion::cursor::SteadyUnderline.as_ref()),
+        }
+    }
+
+    /// Gives the cursor back the shape configured in the user's terminal.
+    fn reset_cursor_shape(&self) {
+        self.write("\x1b[0 q");
+    }
+
+    /// Saves the current window title on the terminal's title stack so it can
+    /// be brought back with [`Backend::restore_title`].
+    fn save_title(&self) {
+        self.write("\x1b[22;0t");
+    }
+
+    fn restore_title(&self) {
+        self.write("\x1b[23;0t");
+    }
+
+    fn set_title(&self, title: &str) {
+        self.write(&format!("\x1b]2;{title}\x07"));
+    }
+
+    fn enable_bracketed_paste(&self) {
+        self.write("\x1b[?2004h");
+    }
+
+    fn disable_bracketed_paste(&self) {
+        self.write("\x1b[?2004l");
+    }
+
+    /// Asks the terminal to hold back what follows until
+    /// [`Backend::end_synchronized_update`], so half-drawn frames are never
+    /// shown. Terminals without support for mode 2026 ignore it.
+    fn begin_synchronized_update(&self) {
+        self.write("\x1b[?2026h");
+    }
+
+    fn end_synchronized_update(&self) {
+        self.write("\x1b[?2026l");
+    }
+
+    fn enable_focus_reporting(&self) {
+        self.write("\x1b[?1004h");
+    }
+
+    fn disable_focus_reporting(&self) {
+        self.write("\x1b[?1004l");
+    }
+
+    /// Asks the terminal for its background color, to be reported as an
+    /// [`Event::Background`]. Backends that cannot read the answer ignore
+    /// this.
+    fn query_background(&self) {}
+
+    /// Asks the terminal to tell when it switches between a light and a dark
+    /// background, as mode 2031 has it.
+    fn enable_appearance_reporting(&self) {
+        self.write("\x1b[?2031h");
+    }
+
+    fn disable_appearance_reporting(&self) {
+        self.write("\x1b[?2031l");
+    }
+}
+
+/// Lets a caller keep a handle on a backend it hands to the editor, e.g. to
+/// inspect a [`crate::Headless`] screen after a run.
+impl<B: Backend + ?Sized> Backend for Rc<B> {
+    fn size(&self) -> Size {
+        (**self).size()
+    }
+
+    fn write(&self, text: &str) {
+        (**self).write(text);
+    }
+
+    fn flush(&self) -> Result<(), Error> {
+        (**self).flush()
+    }
+
+    fn read_event(&self) -> Result<Event, Error> {
+        (**self).read_event()
+    }
+
+    fn poll_event(&self, timeout: Duration) -> Result<Option<Event>, Error> {
+        (**self).poll_event(timeout)
+    }
+
+    fn query_background(&self) {
+        (**self).query_background();
+    }
+}
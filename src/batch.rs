@@ -0,0 +1,28 @@
+use crate::cli::Options;
+use crate::editor::Editor;
+use crate::headless::Headless;
+use std::fs::File;
+
+/// Applies `script` to each of `files` in turn, without a terminal, as
+/// [`Editor::run_script`] does. Nothing is written unless the script says
+/// `save`, and the editor's configuration applies as usual.
+///
+/// # Errors
+///
+/// Returns a message naming the file and the script line that failed. The
+/// files before it were already processed.
+pub fn run(script: &str, files: &[String]) -> Result<(), String> {
+    for file_name in files {
+        File::open(file_name).map_err(|error| format!("{file_name}: {error}"))?;
+        let options = Options {
+            files: vec![file_name.clone()],
+            ..Options::default()
+        };
+        let terminal = Headless::new(80, 24, []);
+        let mut editor = Editor::with_options(Box::new(terminal), options);
+        editor
+            .run_script(script)
+            .map_err(|(line, message)| format!("{file_name}: line {line}: {message}"))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,149 @@
+use crate::git;
+use std::collections::HashMap;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Who last changed a line, according to `git blame`.
+#[derive(Clone)]
+pub struct Annotation {
+    /// The abbreviated commit hash, or `None` if the line is not committed.
+    pub commit: Option<String>,
+    pub author: String,
+    /// When the commit was authored, in seconds since the Unix epoch.
+    pub time: u64,
+    pub summary: String,
+}
+
+impl Annotation {
+    /// Describes the annotation for the status bar, such as
+    /// `1a2b3c4 Ada, 3 days ago - Fix the parser`.
+    #[must_use]
+    pub fn describe(&self) -> String {
+        match &self.commit {
+            Some(commit) => format!(
+                "{commit} {}, {} - {}",
+                self.author,
+                age(self.time),
+                self.summary
+            ),
+            None => "Not committed yet".to_string(),
+        }
+    }
+}
+
+/// `git blame` of a document, running in the background.
+pub struct Blame {
+    /// The document revision being blamed.
+    pub revision: u64,
+    result: Receiver<Result<Vec<Annotation>, String>>,
+}
+
+impl Blame {
+    /// Starts blaming `text`, the contents of `file_name` at `revision`, on
+    /// its own thread. Lines changed since the last commit are reported as
+    /// not committed.
+    #[must_use]
+    pub fn start(file_name: &str, text: String, revision: u64) -> Self {
+        let (sender, result) = mpsc::channel();
+        let file_name = file_name.to_string();
+        thread::spawn(move || {
+            let annotations = git::locate(&file_name).and_then(|(work_tree, path)| {
+                git::run(
+                    &work_tree,
+                    &["blame", "--porcelain", "--contents", "-", "--", &path],
+                    Some(&text),
+                )
+                .map(|output| parse(&output))
+            });
+            let _ = sender.send(annotations);
+        });
+        Self { revision, result }
+    }
+
+    /// Returns an annotation for each line once blaming has finished.
+    pub fn poll(&self) -> Option<Result<Vec<Annotation>, String>> {
+        match self.result.try_recv() {
+            Ok(result) => Some(result),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => {
+                Some(Err("git blame stopped unexpectedly".to_string()))
+            }
+        }
+    }
+}
+
+/// Reads the output of `git blame --porcelain`, which describes each commit
+/// only the first time one of its lines is listed.
+fn parse(output: &str) -> Vec<Annotation> {
+    let mut commits: HashMap<&str, Annotation> = HashMap::new();
+    let mut annotations = Vec::new();
+    let mut current: Option<&str> = None;
+    for line in output.lines() {
+        if line.starts_with('\t') {
+            if let Some(annotation) = current.and_then(|hash| commits.get(hash)) {
+                annotations.push(annotation.clone());
+            }
+            continue;
+        }
+        let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+        match key {
+            "author" => {
+                if let Some(annotation) = current.and_then(|hash| commits.get_mut(hash)) {
+                    annotation.author = value.to_string();
+                }
+            }
+            "author-time" => {
+                if let Some(annotation) = current.and_then(|hash| commits.get_mut(hash)) {
+                    annotation.time = value.parse().unwrap_or_default();
+                }
+            }
+            "summary" => {
+                if let Some(annotation) = current.and_then(|hash| commits.get_mut(hash)) {
+                    annotation.summary = value.to_string();
+                }
+            }
+            hash if hash.len() == 40 && hash.chars().all(|c| c.is_ascii_hexdigit()) => {
+                current = Some(hash);
+                commits.entry(hash).or_insert_with(|| Annotation {
+                    commit: if hash.chars().all(|c| c == '0') {
+                        None
+                    } else {
+                        Some(hash.chars().take(7).collect())
+                    },
+                    author: String::new(),
+                    time: 0,
+                    summary: String::new(),
+                });
+            }
+            _ => (),
+        }
+    }
+    annotations
+}
+
+/// Describes how long ago `time`, in seconds since the Unix epoch, was, such
+/// as `3 days ago`.
+#[must_use]
+pub fn age(time: u64) -> String {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs());
+    let seconds = now.saturating_sub(time);
+    let (count, unit) = [
+        (365 * 24 * 60 * 60, "year"),
+        (30 * 24 * 60 * 60, "month"),
+        (7 * 24 * 60 * 60, "week"),
+        (24 * 60 * 60, "day"),
+        (60 * 60, "hour"),
+        (60, "minute"),
+    ]
+    .into_iter()
+    .find(|(length, _)| seconds >= *length)
+    .map_or((0, ""), |(length, unit)| (seconds / length, unit));
+    match count {
+        0 => "just now".to_string(),
+        1 => format!("1 {unit} ago"),
+        _ => format!("{count} {unit}s ago"),
+    }
+}
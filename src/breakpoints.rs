@@ -0,0 +1,157 @@
+use crate::paths;
+use crate::Position;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+type Store = BTreeMap<String, Vec<usize>>;
+
+/// The rows of a document a debugger should stop at, kept on the same text
+/// as rows are inserted and removed around them.
+#[derive(Default)]
+pub struct Breakpoints {
+    rows: BTreeSet<usize>,
+}
+
+impl Breakpoints {
+    /// Sets a breakpoint on row `y`, or removes the one there. Returns
+    /// whether there is one now.
+    pub fn toggle(&mut self, y: usize) -> bool {
+        if self.rows.remove(&y) {
+            false
+        } else {
+            self.rows.insert(y);
+            true
+        }
+    }
+
+    #[must_use]
+    pub fn contains(&self, y: usize) -> bool {
+        self.rows.contains(&y)
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.rows.clear();
+    }
+
+    /// Returns the rows with a breakpoint, in order.
+    pub fn rows(&self) -> impl Iterator<Item = usize> + '_ {
+        self.rows.iter().copied()
+    }
+
+    /// Adjusts for `count` rows having been inserted after row `at.y`. A
+    /// breakpoint on that row moves with it if all of its text did.
+    pub fn rows_inserted(&mut self, at: &Position, count: usize) {
+        let moved = |y: usize| y > at.y || (y == at.y && at.x == 0);
+        self.shift(|y| if moved(y) { y.saturating_add(count) } else { y });
+    }
+
+    /// Adjusts for `count` rows having been inserted before row `y`.
+    pub fn rows_added(&mut self, y: usize, count: usize) {
+        self.shift(|row| {
+            if row >= y {
+                row.saturating_add(count)
+            } else {
+                row
+            }
+        });
+    }
+
+    /// Adjusts for row `y` having been removed, with its breakpoint.
+    pub fn row_removed(&mut self, y: usize) {
+        self.rows.remove(&y);
+        self.shift(|row| if row > y { row.saturating_sub(1) } else { row });
+    }
+
+    /// Adjusts for row `y + 1` having been appended to row `y`.
+    pub fn rows_joined(&mut self, y: usize) {
+        self.shift(|row| if row > y { row.saturating_sub(1) } else { row });
+    }
+
+    pub fn rows_swapped(&mut self, a: usize, b: usize) {
+        self.shift(|row| match row {
+            row if row == a => b,
+            row if row == b => a,
+            row => row,
+        });
+    }
+
+    fn shift(&mut self, to: impl Fn(usize) -> usize) {
+        self.rows = self.rows.iter().map(|row| to(*row)).collect();
+    }
+
+    /// Reads the breakpoints saved for `file_name`.
+    #[must_use]
+    pub fn load(file_name: &str) -> Self {
+        let rows = read_store()
+            .remove(&key(file_name))
+            .unwrap_or_default()
+            .into_iter()
+            .collect();
+        Self { rows }
+    }
+
+    /// Saves the breakpoints for `file_name`, replacing the ones saved
+    /// before. Errors are ignored, as for marks.
+    pub fn store(&self, file_name: &str) {
+        let Some(path) = store_path() else {
+            return;
+        };
+        let mut store = read_store();
+        store.insert(key(file_name), self.rows().collect());
+        store.retain(|_, rows| !rows.is_empty());
+        if let (Some(directory), Ok(contents)) = (path.parent(), toml::to_string(&store)) {
+            let _ = fs::create_dir_all(directory);
+            let _ = fs::write(path, contents);
+        }
+    }
+}
+
+/// The breakpoints of one file, as a Debug Adapter Protocol client hands
+/// them to the adapter.
+#[derive(Clone, PartialEq, Debug)]
+pub struct SourceBreakpoints {
+    pub path: String,
+    /// Lines counting from one.
+    pub lines: Vec<usize>,
+}
+
+impl SourceBreakpoints {
+    /// Returns the arguments of a `setBreakpoints` request, which replaces
+    /// all the breakpoints the adapter had for the file.
+    #[must_use]
+    pub fn arguments(&self) -> Value {
+        let breakpoints: Vec<Value> = self
+            .lines
+            .iter()
+            .map(|line| json!({ "line": line }))
+            .collect();
+        json!({ "source": { "path": self.path }, "breakpoints": breakpoints })
+    }
+}
+
+/// Returns `breakpoints.toml` in the state directory.
+fn store_path() -> Option<PathBuf> {
+    Some(paths::state_dir()?.join("breakpoints.toml"))
+}
+
+fn read_store() -> Store {
+    store_path()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|contents| toml::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+/// Files are remembered by their absolute path, as marks are.
+fn key(file_name: &str) -> String {
+    fs::canonicalize(file_name)
+        .unwrap_or_else(|_| Path::new(file_name).to_path_buf())
+        .to_string_lossy()
+        .into_owned()
+}
@@ -0,0 +1,346 @@
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// The result of an expression: whole numbers stay exact, anything else is
+/// a float.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Number {
+    Int(i64),
+    Float(f64),
+}
+
+/// How to write a result.
+#[derive(Clone, Copy)]
+pub enum Base {
+    Decimal,
+    Hex,
+    Binary,
+    Octal,
+}
+
+impl Number {
+    fn as_float(self) -> f64 {
+        match self {
+            #[allow(clippy::cast_precision_loss)]
+            Self::Int(value) => value as f64,
+            Self::Float(value) => value,
+        }
+    }
+
+    /// Writes the number in `base`. Only whole numbers can be written in a
+    /// base other than ten.
+    #[must_use]
+    pub fn format(self, base: Base) -> String {
+        match (self, base) {
+            (Self::Float(value), _) => value.to_string(),
+            (Self::Int(value), Base::Decimal) => value.to_string(),
+            (Self::Int(value), Base::Hex) => with_sign(value, "0x", |abs| format!("{abs:x}")),
+            (Self::Int(value), Base::Binary) => with_sign(value, "0b", |abs| format!("{abs:b}")),
+            (Self::Int(value), Base::Octal) => with_sign(value, "0o", |abs| format!("{abs:o}")),
+        }
+    }
+
+    /// Describes the number for the message bar, in every base when it is
+    /// whole.
+    #[must_use]
+    pub fn describe(self) -> String {
+        match self {
+            Self::Int(_) => [Base::Decimal, Base::Hex, Base::Binary, Base::Octal]
+                .iter()
+                .map(|base| self.format(*base))
+                .collect::<Vec<_>>()
+                .join(" = "),
+            Self::Float(_) => self.format(Base::Decimal),
+        }
+    }
+}
+
+fn with_sign(value: i64, prefix: &str, digits: impl Fn(u64) -> String) -> String {
+    let sign = if value < 0 { "-" } else { "" };
+    format!("{sign}{prefix}{}", digits(value.unsigned_abs()))
+}
+
+/// Evaluates an arithmetic expression such as `(1 + 2) * 0x10`, optionally
+/// followed by `in hex`, `in bin`, `in oct` or `in dec` to name the base the
+/// result is wanted in.
+///
+/// Numbers may be written in decimal, with a fraction or exponent, or with a
+/// `0x`, `0b` or `0o` prefix, and may contain `_` between digits. The
+/// operators are, from the loosest binding: `|`, `&`, `<<` and `>>`, `+` and
+/// `-`, `*`, `/` and `%`, then `**` or `^` for powers.
+///
+/// # Errors
+///
+/// Returns a message for the user if the expression is malformed or cannot
+/// be computed, such as a division by zero.
+pub fn evaluate(expression: &str) -> Result<(Number, Base), String> {
+    let (expression, base) = split_base(expression);
+    let mut parser = Parser {
+        chars: expression.chars().peekable(),
+    };
+    let number = parser.bitwise_or()?;
+    parser.skip_whitespace();
+    match parser.chars.next() {
+        None => Ok((number, base)),
+        Some(c) => Err(format!("Unexpected '{c}'")),
+    }
+}
+
+fn split_base(expression: &str) -> (&str, Base) {
+    let trimmed = expression.trim_end();
+    for (suffix, base) in [
+        ("hex", Base::Hex),
+        ("bin", Base::Binary),
+        ("oct", Base::Octal),
+        ("dec", Base::Decimal),
+    ] {
+        let rest = trimmed
+            .strip_suffix(suffix)
+            .map(str::trim_end)
+            .and_then(|rest| {
+                rest.strip_suffix(" in")
+                    .or_else(|| rest.strip_suffix(" as"))
+            });
+        if let Some(rest) = rest {
+            return (rest, base);
+        }
+    }
+    (expression, Base::Decimal)
+}
+
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+}
+
+impl Parser<'_> {
+    fn skip_whitespace(&mut self) {
+        while self.chars.next_if(|c| c.is_whitespace()).is_some() {}
+    }
+
+    /// Consumes `operator` if it comes next, ignoring whitespace before it.
+    fn eat(&mut self, operator: &str) -> bool {
+        self.skip_whitespace();
+        let mut lookahead = self.chars.clone();
+        for expected in operator.chars() {
+            if lookahead.next() != Some(expected) {
+                return false;
+            }
+        }
+        self.chars = lookahead;
+        true
+    }
+
+    fn bitwise_or(&mut self) -> Result<Number, String> {
+        let mut left = self.bitwise_and()?;
+        while self.eat("|") {
+            let right = self.bitwise_and()?;
+            left = Number::Int(whole(left, "|")? | whole(right, "|")?);
+        }
+        Ok(left)
+    }
+
+    fn bitwise_and(&mut self) -> Result<Number, String> {
+        let mut left = self.shift()?;
+        while self.eat("&") {
+            let right = self.shift()?;
+            left = Number::Int(whole(left, "&")? & whole(right, "&")?);
+        }
+        Ok(left)
+    }
+
+    fn shift(&mut self) -> Result<Number, String> {
+        let mut left = self.sum()?;
+        loop {
+            let operator = if self.eat("<<") {
+                "<<"
+            } else if self.eat(">>") {
+                ">>"
+            } else {
+                return Ok(left);
+            };
+            let right = self.sum()?;
+            let value = whole(left, operator)?;
+            let amount = u32::try_from(whole(right, operator)?)
+                .map_err(|_| "Shift amount out of range".to_string())?;
+            let shifted = if operator == "<<" {
+                value.checked_shl(amount)
+            } else {
+                value.checked_shr(amount)
+            };
+            left = Number::Int(shifted.ok_or("Shift amount out of range")?);
+        }
+    }
+
+    fn sum(&mut self) -> Result<Number, String> {
+        let mut left = self.product()?;
+        loop {
+            if self.eat("+") {
+                let right = self.product()?;
+                left = arithmetic(left, right, i64::checked_add, |a, b| a + b)?;
+            } else if self.eat("-") {
+                let right = self.product()?;
+                left = arithmetic(left, right, i64::checked_sub, |a, b| a - b)?;
+            } else {
+                return Ok(left);
+            }
+        }
+    }
+
+    fn product(&mut self) -> Result<Number, String> {
+        let mut left = self.unary()?;
+        loop {
+            if self.eat("*") {
+                let right = self.unary()?;
+                left = arithmetic(left, right, i64::checked_mul, |a, b| a * b)?;
+            } else if self.eat("/") {
+                let right = self.unary()?;
+                left = divide(left, right)?;
+            } else if self.eat("%") {
+                let right = self.unary()?;
+                if right.as_float() == 0.0 {
+                    return Err("Division by zero".to_string());
+                }
+                left = arithmetic(left, right, i64::checked_rem, |a, b| a % b)?;
+            } else {
+                return Ok(left);
+            }
+        }
+    }
+
+    fn unary(&mut self) -> Result<Number, String> {
+        if self.eat("-") {
+            return match self.unary()? {
+                Number::Int(value) => value
+                    .checked_neg()
+                    .map(Number::Int)
+                    .ok_or_else(|| "Overflow".to_string()),
+                Number::Float(value) => Ok(Number::Float(-value)),
+            };
+        }
+        if self.eat("+") {
+            return self.unary();
+        }
+        self.power()
+    }
+
+    fn power(&mut self) -> Result<Number, String> {
+        let base = self.atom()?;
+        if !self.eat("**") && !self.eat("^") {
+            return Ok(base);
+        }
+        // Powers group to the right, and bind tighter than a sign after them.
+        let exponent = self.unary()?;
+        match (base, exponent) {
+            (Number::Int(base), Number::Int(exponent)) if exponent >= 0 => {
+                let exponent = u32::try_from(exponent).map_err(|_| "Overflow".to_string())?;
+                base.checked_pow(exponent)
+                    .map(Number::Int)
+                    .ok_or_else(|| "Overflow".to_string())
+            }
+            _ => float(base.as_float().powf(exponent.as_float())),
+        }
+    }
+
+    fn atom(&mut self) -> Result<Number, String> {
+        self.skip_whitespace();
+        if self.eat("(") {
+            let value = self.bitwise_or()?;
+            if !self.eat(")") {
+                return Err("Missing ')'".to_string());
+            }
+            return Ok(value);
+        }
+        let mut literal = String::new();
+        while let Some(c) = self
+            .chars
+            .next_if(|c| c.is_ascii_alphanumeric() || *c == '.' || *c == '_')
+        {
+            literal.push(c);
+            // An exponent may be signed, as in `1e-3`.
+            if (c == 'e' || c == 'E')
+                && literal.starts_with(|c: char| c.is_ascii_digit())
+                && !literal.starts_with("0x")
+            {
+                if let Some(sign) = self.chars.next_if(|c| *c == '-' || *c == '+') {
+                    literal.push(sign);
+                }
+            }
+        }
+        if literal.is_empty() {
+            return match self.chars.peek() {
+                Some(c) => Err(format!("Unexpected '{c}'")),
+                None => Err("Expression ends too soon".to_string()),
+            };
+        }
+        parse_number(&literal.replace('_', ""))
+    }
+}
+
+fn parse_number(literal: &str) -> Result<Number, String> {
+    let invalid = || format!("Not a number: {literal}");
+    let radix = [
+        ("0x", 16),
+        ("0X", 16),
+        ("0b", 2),
+        ("0B", 2),
+        ("0o", 8),
+        ("0O", 8),
+    ]
+    .into_iter()
+    .find_map(|(prefix, radix)| literal.strip_prefix(prefix).map(|digits| (digits, radix)));
+    if let Some((digits, radix)) = radix {
+        return i64::from_str_radix(digits, radix)
+            .map(Number::Int)
+            .map_err(|_| invalid());
+    }
+    if let Ok(value) = literal.parse::<i64>() {
+        return Ok(Number::Int(value));
+    }
+    literal
+        .parse::<f64>()
+        .map_err(|_| invalid())
+        .and_then(float)
+}
+
+fn whole(number: Number, operator: &str) -> Result<i64, String> {
+    match number {
+        Number::Int(value) => Ok(value),
+        Number::Float(_) => Err(format!("{operator} needs whole numbers")),
+    }
+}
+
+fn arithmetic(
+    left: Number,
+    right: Number,
+    int: fn(i64, i64) -> Option<i64>,
+    float_op: fn(f64, f64) -> f64,
+) -> Result<Number, String> {
+    match (left, right) {
+        (Number::Int(a), Number::Int(b)) => int(a, b)
+            .map(Number::Int)
+            .ok_or_else(|| "Overflow".to_string()),
+        _ => float(float_op(left.as_float(), right.as_float())),
+    }
+}
+
+/// Divides, keeping the result whole when it divides evenly.
+fn divide(left: Number, right: Number) -> Result<Number, String> {
+    if right.as_float() == 0.0 {
+        return Err("Division by zero".to_string());
+    }
+    match (left, right) {
+        (Number::Int(a), Number::Int(b)) if a.checked_rem(b) == Some(0) => a
+            .checked_div(b)
+            .map(Number::Int)
+            .ok_or_else(|| "Overflow".to_string()),
+        _ => float(left.as_float() / right.as_float()),
+    }
+}
+
+fn float(value: f64) -> Result<Number, String> {
+    if value.is_finite() {
+        Ok(Number::Float(value))
+    } else {
+        Err("The result is not a finite number".to_string())
+    }
+}
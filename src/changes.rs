@@ -0,0 +1,144 @@
+use crate::Position;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// A change to the text of a [`crate::Document`], as sent to those who
+/// subscribed to it with [`crate::Document::subscribe`]. Positions are in
+/// the text as it was before the change.
+#[derive(Clone, PartialEq)]
+pub enum Change {
+    /// `text`, which may hold line breaks, was inserted at `at`.
+    Inserted { at: Position, text: String },
+    /// The text from `start` up to `end` was deleted, joining their rows if
+    /// they are not the same.
+    Deleted { start: Position, end: Position },
+    /// The row `at.y` was split in two at `at.x`.
+    Split { at: Position },
+    /// The row after `at.y` was appended to it, at the end of it in `at.x`.
+    Joined { at: Position },
+    /// `removed` rows from `at` were replaced by the `inserted` ones, as
+    /// when undoing or replacing whole lines.
+    Rows {
+        at: usize,
+        removed: usize,
+        inserted: Vec<String>,
+    },
+}
+
+impl Change {
+    /// Returns the rows this change replaced, and how many took their place.
+    #[must_use]
+    pub fn span(&self) -> Span {
+        let (start, removed, inserted) = match self {
+            Self::Inserted { at, text } => (at.y, 1, text.matches('\n').count().saturating_add(1)),
+            Self::Deleted { start, end } => {
+                (start.y, end.y.saturating_sub(start.y).saturating_add(1), 1)
+            }
+            Self::Split { at } => (at.y, 1, 2),
+            Self::Joined { at } => (at.y, 2, 1),
+            Self::Rows {
+                at,
+                removed,
+                inserted,
+            } => (*at, *removed, inserted.len()),
+        };
+        Span {
+            start,
+            old_end: start.saturating_add(removed),
+            new_end: start.saturating_add(inserted),
+        }
+    }
+
+    /// Makes the change to `rows`, a copy of the text of the document kept
+    /// by a subscriber.
+    pub fn apply(&self, rows: &mut Vec<String>) {
+        match self {
+            Self::Inserted { at, text } => {
+                let Some(row) = rows.get_mut(at.y) else {
+                    return;
+                };
+                let split = byte_index(row, at.x);
+                let tail = row.split_off(split);
+                let mut lines: Vec<String> = text.split('\n').map(str::to_string).collect();
+                if let Some(first) = lines.first_mut() {
+                    row.push_str(first);
+                    first.clone_from(row);
+                }
+                if let Some(last) = lines.last_mut() {
+                    last.push_str(&tail);
+                }
+                rows.splice(at.y..=at.y, lines);
+            }
+            Self::Deleted { start, end } => {
+                let (Some(first), Some(last)) = (rows.get(start.y), rows.get(end.y)) else {
+                    return;
+                };
+                let joined = format!(
+                    "{}{}",
+                    first.get(..byte_index(first, start.x)).unwrap_or_default(),
+                    last.get(byte_index(last, end.x)..).unwrap_or_default()
+                );
+                rows.splice(start.y..=end.y, [joined]);
+            }
+            Self::Split { at } => {
+                if let Some(row) = rows.get_mut(at.y) {
+                    let split = byte_index(row, at.x);
+                    let tail = row.split_off(split);
+                    rows.insert(at.y.saturating_add(1), tail);
+                }
+            }
+            Self::Joined { at } => {
+                if at.y.saturating_add(1) < rows.len() {
+                    let next = rows.remove(at.y.saturating_add(1));
+                    if let Some(row) = rows.get_mut(at.y) {
+                        row.push_str(&next);
+                    }
+                }
+            }
+            Self::Rows {
+                at,
+                removed,
+                inserted,
+            } => {
+                let at = (*at).min(rows.len());
+                let end = at.saturating_add(*removed).min(rows.len());
+                rows.splice(at..end, inserted.iter().cloned());
+            }
+        }
+    }
+}
+
+/// The rows a run of changes touched: the rows from `start` up to `old_end`
+/// in the text before them are those up to `new_end` in the text after.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Span {
+    pub start: usize,
+    pub old_end: usize,
+    pub new_end: usize,
+}
+
+impl Span {
+    /// Returns the span touched by the changes in `self` followed by those
+    /// in `next`.
+    #[must_use]
+    pub fn then(self, next: Self) -> Self {
+        let start = self.start.min(next.start);
+        // The end of both, in the text between the two runs of changes.
+        let end = self.new_end.max(next.old_end);
+        Self {
+            start,
+            old_end: end
+                .saturating_sub(self.new_end)
+                .saturating_add(self.old_end),
+            new_end: end
+                .saturating_sub(next.old_end)
+                .saturating_add(next.new_end),
+        }
+    }
+}
+
+/// Returns the byte index of the grapheme `x` of `row`, or its length.
+fn byte_index(row: &str, x: usize) -> usize {
+    row.grapheme_indices(true)
+        .nth(x)
+        .map_or(row.len(), |(index, _)| index)
+}
@@ -0,0 +1,83 @@
+use crate::paths;
+use std::fs;
+use std::path::PathBuf;
+
+/// How many recently inserted characters are remembered.
+const MAX_RECENT: usize = 20;
+
+/// Returns the characters Unicode gives a name, with the name in lowercase,
+/// such as `→` with "rightwards arrow" or `🤷` with "shrug".
+///
+/// Ideographs and Hangul syllables are left out: their names only spell out
+/// the code point or the sound, and there are so many of them that searching
+/// the rest would slow down.
+#[must_use]
+pub fn named() -> Vec<(char, String)> {
+    (0..=u32::from(char::MAX))
+        .filter_map(char::from_u32)
+        .filter_map(|c| Some((c, unicode_names2::name(c)?.to_string().to_lowercase())))
+        .filter(|(_, name)| !is_numbered(name) && !name.starts_with("hangul syllable "))
+        .collect()
+}
+
+/// Lists the code points of `grapheme` with their names, as in
+/// "U+0065 LATIN SMALL LETTER E, U+0301 COMBINING ACUTE ACCENT". Control
+/// characters have no name, so only their code point is given.
+#[must_use]
+pub fn code_points(grapheme: &str) -> String {
+    grapheme
+        .chars()
+        .map(|c| match unicode_names2::name(c) {
+            Some(name) => format!("U+{:04X} {name}", u32::from(c)),
+            None => format!("U+{:04X}", u32::from(c)),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Returns the bytes of `grapheme` in UTF-8, in hexadecimal.
+#[must_use]
+pub fn utf8_bytes(grapheme: &str) -> String {
+    grapheme
+        .bytes()
+        .map(|byte| format!("{byte:02X}"))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Returns whether `name` is made up from the code point, as in
+/// "cjk unified ideograph-4e00".
+fn is_numbered(name: &str) -> bool {
+    name.rsplit_once('-')
+        .is_some_and(|(_, code)| code.len() >= 4 && code.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+/// Returns the characters inserted lately, most recent first.
+#[must_use]
+pub fn recent() -> Vec<char> {
+    store_path()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .map(|contents| contents.trim_end_matches('\n').chars().collect())
+        .unwrap_or_default()
+}
+
+/// Puts `c` first among the recent characters. Failing to save them is not
+/// worth bothering the user with, so errors are ignored.
+pub fn remember(c: char) {
+    let Some(path) = store_path() else {
+        return;
+    };
+    let mut recent = recent();
+    recent.retain(|recent| *recent != c);
+    recent.insert(0, c);
+    recent.truncate(MAX_RECENT);
+    if let Some(directory) = path.parent() {
+        let _ = fs::create_dir_all(directory);
+        let _ = fs::write(path, recent.into_iter().collect::<String>());
+    }
+}
+
+/// Returns `characters` in the state directory.
+fn store_path() -> Option<PathBuf> {
+    Some(paths::state_dir()?.join("characters"))
+}
@@ -0,0 +1,111 @@
+use std::path::PathBuf;
+
+pub const USAGE: &str = "\
+Usage: hecto [options] [file]
+       hecto --diff file1 file2
+       hecto --batch SCRIPT file...
+       hecto --serve [--socket PATH]
+
+Options:
+  -R, --readonly       Open the file without allowing changes
+  -l, --line N[:COL]   Put the cursor on line N, and column COL if given
+      --no-highlight   Turn syntax highlighting off
+      --config PATH    Read the configuration from PATH
+  -c COMMAND           Run COMMAND once started, as if typed after Ctrl-P;
+                       may be given more than once
+      --diff           Compare the two files given side by side
+  -v, --verbose        Log debugging details, such as language server traffic
+      --batch SCRIPT   Apply the commands in SCRIPT, one per line, to each
+                       file without opening the editor; `save` writes them
+      --serve          Serve documents over JSON-RPC on standard input and
+                       output, one message per line, instead of editing
+      --socket PATH    With --serve, listen on a Unix socket at PATH instead
+  -h, --help           Show this help
+  -V, --version        Show the version
+";
+
+/// What the command line asked for.
+#[derive(Default)]
+pub struct Options {
+    /// The files named. The editor opens the first; only batch mode takes
+    /// more than one, and comparing takes two.
+    pub files: Vec<String>,
+    pub read_only: bool,
+    /// The `line[:column]` to put the cursor on.
+    pub line: Option<String>,
+    pub no_highlight: bool,
+    /// Compare the two files named side by side.
+    pub diff: bool,
+    /// A configuration file to read instead of the usual one.
+    pub config: Option<PathBuf>,
+    /// Command lines to run once the editor has started, in order.
+    pub commands: Vec<String>,
+    /// Log at debug level rather than only information and warnings.
+    pub verbose: bool,
+    /// A script of commands to apply to the files instead of editing them.
+    pub batch: Option<PathBuf>,
+    /// Serve documents over JSON-RPC instead of editing them.
+    pub serve: bool,
+    /// The Unix socket to serve on instead of standard input and output.
+    pub socket: Option<PathBuf>,
+    pub help: bool,
+    pub version: bool,
+}
+
+impl Options {
+    /// Parses the arguments given after the program name.
+    ///
+    /// # Errors
+    ///
+    /// Returns a message for the user if an option is unknown, lacks its
+    /// value, or more than one file is given outside batch mode and
+    /// comparing.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut options = Self::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let (flag, inline) = match arg.split_once('=') {
+                Some((flag, value)) if arg.starts_with("--") => (flag, Some(value.to_string())),
+                _ => (arg.as_str(), None),
+            };
+            let mut value = |name: &str| {
+                inline
+                    .clone()
+                    .or_else(|| args.next())
+                    .ok_or_else(|| format!("{name} needs a value"))
+            };
+            match flag {
+                "-R" | "--readonly" => options.read_only = true,
+                "-l" | "--line" => options.line = Some(value(flag)?),
+                "--no-highlight" => options.no_highlight = true,
+                "--diff" => options.diff = true,
+                "--config" => options.config = Some(PathBuf::from(value(flag)?)),
+                "-c" => options.commands.push(value(flag)?),
+                "-v" | "--verbose" => options.verbose = true,
+                "--batch" => options.batch = Some(PathBuf::from(value(flag)?)),
+                "--serve" => options.serve = true,
+                "--socket" => options.socket = Some(PathBuf::from(value(flag)?)),
+                "-h" | "--help" => options.help = true,
+                "-V" | "--version" => options.version = true,
+                "--" => options.files.extend(args.by_ref()),
+                _ if flag.starts_with('-') && flag.len() > 1 => {
+                    return Err(format!("Unknown option: {flag}"));
+                }
+                _ => options.files.push(arg),
+            }
+        }
+        if options.diff && options.files.len() != 2 {
+            return Err("--diff needs two files".to_string());
+        }
+        if options.batch.is_none() && !options.diff && options.files.len() > 1 {
+            return Err("Only one file can be opened at a time.".to_string());
+        }
+        if options.socket.is_some() && !options.serve {
+            return Err("--socket needs --serve".to_string());
+        }
+        if options.batch.is_some() && options.files.is_empty() {
+            return Err("--batch needs at least one file".to_string());
+        }
+        Ok(options)
+    }
+}
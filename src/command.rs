@@ -0,0 +1,886 @@
+use crate::ignore::Ignore;
+use crate::paths;
+use std::fs;
+use std::path::Path;
+
+pub enum Argument {
+    None,
+    Path,
+    Option,
+    Line,
+    Mark,
+    Shell,
+    /// Extra arguments passed on to a program.
+    Arguments,
+    Symbol,
+    Register,
+    Expression,
+    Substitution,
+    /// One of the words given.
+    Choice(&'static [&'static str]),
+}
+
+pub struct Command {
+    pub name: &'static str,
+    pub aliases: &'static [&'static str],
+    pub argument: Argument,
+    pub description: &'static str,
+}
+
+/// Every command the editor understands. Key bindings and the command line
+/// both refer to commands by name, so a new feature only has to be listed here
+/// and handled in `Editor::execute` to be reachable from either.
+pub const COMMANDS: &[Command] = &[
+    Command {
+        name: "quit",
+        aliases: &["q"],
+        argument: Argument::None,
+        description: "Close the buffer, or quit after the last one; `quit!` discards changes",
+    },
+    Command {
+        name: "quit_all",
+        aliases: &["qa", "quitall"],
+        argument: Argument::None,
+        description: "Quit the editor, asking about unsaved buffers; `quit_all!` discards them",
+    },
+    Command {
+        name: "save",
+        aliases: &["w", "write"],
+        argument: Argument::Path,
+        description: "Save the file, optionally under a new name",
+    },
+    Command {
+        name: "save_quit",
+        aliases: &["wq", "x"],
+        argument: Argument::None,
+        description: "Save the file and close the buffer",
+    },
+    Command {
+        name: "save_quit_all",
+        aliases: &["wqa", "xa"],
+        argument: Argument::None,
+        description: "Save every modified buffer and quit",
+    },
+    Command {
+        name: "open",
+        aliases: &["e", "edit"],
+        argument: Argument::Path,
+        description: "Open a file in a new buffer, `open!` reloads it from disk",
+    },
+    Command {
+        name: "revert",
+        aliases: &[],
+        argument: Argument::None,
+        description: "Read the file again from disk, dropping unsaved changes (also `open!`)",
+    },
+    Command {
+        name: "read",
+        aliases: &["r"],
+        argument: Argument::Path,
+        description: "Insert a file, or the output of `read !command`, at the cursor",
+    },
+    Command {
+        name: "shell",
+        aliases: &[],
+        argument: Argument::Shell,
+        description: "Run a shell command (`!command`) and show its output",
+    },
+    Command {
+        name: "filter",
+        aliases: &[],
+        argument: Argument::Shell,
+        description: "Replace the selected lines with their output through a command",
+    },
+    Command {
+        name: "format",
+        aliases: &["fmt"],
+        argument: Argument::None,
+        description: "Run the file type's formatter over the selected lines or the whole buffer",
+    },
+    Command {
+        name: "next_diagnostic",
+        aliases: &[],
+        argument: Argument::None,
+        description: "Jump to the next problem the linter found in the buffer",
+    },
+    Command {
+        name: "previous_diagnostic",
+        aliases: &[],
+        argument: Argument::None,
+        description: "Jump to the previous problem the linter found in the buffer",
+    },
+    Command {
+        name: "complete",
+        aliases: &[],
+        argument: Argument::None,
+        description: "Pick a completion for the path at the cursor from the files next to it, or for the word at the cursor from the language server",
+    },
+    Command {
+        name: "hover",
+        aliases: &[],
+        argument: Argument::None,
+        description: "Show what the language server knows about the symbol at the cursor",
+    },
+    Command {
+        name: "goto_definition",
+        aliases: &["def"],
+        argument: Argument::None,
+        description: "Jump to where the symbol at the cursor is defined",
+    },
+    Command {
+        name: "tag",
+        aliases: &["ta"],
+        argument: Argument::Symbol,
+        description: "Jump to the definition of a symbol, by default the one at the cursor, from the tags file",
+    },
+    Command {
+        name: "tags",
+        aliases: &[],
+        argument: Argument::None,
+        description: "Pick a symbol from the tags file to jump to",
+    },
+    Command {
+        name: "tag_pop",
+        aliases: &["pop"],
+        argument: Argument::None,
+        description: "Go back to where the last tag jump started",
+    },
+    Command {
+        name: "print",
+        aliases: &[],
+        argument: Argument::Path,
+        description: "Print the document, or write it to a PostScript or `.pdf` file",
+    },
+    Command {
+        name: "export_state",
+        aliases: &[],
+        argument: Argument::Path,
+        description: "Write the buffers, options, registers and histories to a JSON file",
+    },
+    Command {
+        name: "import_state",
+        aliases: &[],
+        argument: Argument::Path,
+        description: "Restore the buffers, options, registers and histories from a JSON file",
+    },
+    Command {
+        name: "make",
+        aliases: &[],
+        argument: Argument::Arguments,
+        description: "Run the build command and jump to the first error it reports",
+    },
+    Command {
+        name: "next_error",
+        aliases: &["cn"],
+        argument: Argument::None,
+        description: "Jump to the next error from the last build",
+    },
+    Command {
+        name: "previous_error",
+        aliases: &["cp"],
+        argument: Argument::None,
+        description: "Jump to the previous error from the last build",
+    },
+    Command {
+        name: "stage_hunk",
+        aliases: &[],
+        argument: Argument::None,
+        description: "Stage the change under the cursor for the next git commit",
+    },
+    Command {
+        name: "revert_hunk",
+        aliases: &[],
+        argument: Argument::None,
+        description: "Undo the change under the cursor since the last git commit",
+    },
+    Command {
+        name: "next_conflict",
+        aliases: &[],
+        argument: Argument::None,
+        description: "Jump to the next conflict a merge left in the buffer",
+    },
+    Command {
+        name: "previous_conflict",
+        aliases: &[],
+        argument: Argument::None,
+        description: "Jump to the previous conflict a merge left in the buffer",
+    },
+    Command {
+        name: "next_section",
+        aliases: &[],
+        argument: Argument::None,
+        description: "Jump to the next top-level definition or heading",
+    },
+    Command {
+        name: "previous_section",
+        aliases: &[],
+        argument: Argument::None,
+        description: "Jump to the previous top-level definition or heading",
+    },
+    Command {
+        name: "accept_ours",
+        aliases: &[],
+        argument: Argument::None,
+        description: "Resolve the conflict under the cursor by keeping our side",
+    },
+    Command {
+        name: "accept_theirs",
+        aliases: &[],
+        argument: Argument::None,
+        description: "Resolve the conflict under the cursor by keeping their side",
+    },
+    Command {
+        name: "accept_both",
+        aliases: &[],
+        argument: Argument::None,
+        description: "Resolve the conflict under the cursor by keeping our side, then theirs",
+    },
+    Command {
+        name: "blame",
+        aliases: &[],
+        argument: Argument::None,
+        description: "Show the commit that last changed the cursor's line",
+    },
+    Command {
+        name: "terminal",
+        aliases: &["term"],
+        argument: Argument::None,
+        description: "Open a shell below the document or switch to it and back",
+    },
+    Command {
+        name: "split",
+        aliases: &["sp"],
+        argument: Argument::None,
+        description: "Show the document in a second view or switch between the two; `split!` closes it",
+    },
+    Command {
+        name: "strip_ansi",
+        aliases: &[],
+        argument: Argument::None,
+        description: "Remove ANSI escape codes, such as colors, from the document",
+    },
+    Command {
+        name: "file_history",
+        aliases: &[],
+        argument: Argument::None,
+        description: "Show how the buffer differs from a kept version of its file; `file_history!` restores it",
+    },
+    Command {
+        name: "breakpoint",
+        aliases: &["bp"],
+        argument: Argument::None,
+        description: "Set a breakpoint on the cursor's line or remove it; `breakpoint!` removes all in the file",
+    },
+    Command {
+        name: "undo",
+        aliases: &["u"],
+        argument: Argument::None,
+        description: "Undo the last change",
+    },
+    Command {
+        name: "redo",
+        aliases: &[],
+        argument: Argument::None,
+        description: "Redo the last change undone",
+    },
+    Command {
+        name: "undo_tree",
+        aliases: &[],
+        argument: Argument::None,
+        description: "Pick any earlier state of the document, including undone branches",
+    },
+    Command {
+        name: "undo_status",
+        aliases: &[],
+        argument: Argument::None,
+        description: "Show how much memory undo history takes, here and in all buffers",
+    },
+    Command {
+        name: "yank",
+        aliases: &["y"],
+        argument: Argument::Register,
+        description: "Copy the selection, or the current line, into a register",
+    },
+    Command {
+        name: "cut",
+        aliases: &[],
+        argument: Argument::Register,
+        description: "Move the selection, or the current line, into a register",
+    },
+    Command {
+        name: "paste",
+        aliases: &["p", "put"],
+        argument: Argument::Register,
+        description: "Paste a register at the cursor, or below it for whole lines",
+    },
+    Command {
+        name: "register",
+        aliases: &[],
+        argument: Argument::Register,
+        description: "Name the register the next yank, cut or paste uses",
+    },
+    Command {
+        name: "tasks",
+        aliases: &["todo"],
+        argument: Argument::None,
+        description: "List the TODO and FIXME comments in the project by file; Enter jumps to one",
+    },
+    Command {
+        name: "registers",
+        aliases: &["reg"],
+        argument: Argument::None,
+        description: "Show what each register holds",
+    },
+    Command {
+        name: "yank_history",
+        aliases: &["yanks"],
+        argument: Argument::None,
+        description: "Pick one of the latest yanks and deletions to paste",
+    },
+    Command {
+        name: "outline",
+        aliases: &[],
+        argument: Argument::None,
+        description: "Show the symbols of the document beside it or switch to them and back",
+    },
+    Command {
+        name: "background",
+        aliases: &[],
+        argument: Argument::Choice(&["auto", "light", "dark"]),
+        description: "Use the theme for a light or dark background, or for the terminal's with `auto`",
+    },
+    Command {
+        name: "calc",
+        aliases: &["="],
+        argument: Argument::Expression,
+        description: "Work out an expression such as `0x1f * 3 in hex`; `calc!` inserts the result",
+    },
+    Command {
+        name: "character",
+        aliases: &["unicode", "emoji"],
+        argument: Argument::None,
+        description: "Insert a character found by its name, such as `right arrow` or `shrug`",
+    },
+    Command {
+        name: "inspect",
+        aliases: &["ga"],
+        argument: Argument::None,
+        description: "Show the code points, UTF-8 bytes and width of the character under the cursor",
+    },
+    Command {
+        name: "zen",
+        aliases: &[],
+        argument: Argument::None,
+        description: "Show only the text, centered and without colors, or everything again",
+    },
+    Command {
+        name: "perf",
+        aliases: &[],
+        argument: Argument::None,
+        description: "Show or hide how long the last frame took to handle input and draw",
+    },
+    Command {
+        name: "messages",
+        aliases: &[],
+        argument: Argument::None,
+        description: "Show the messages logged so far",
+    },
+    Command {
+        name: "plugins",
+        aliases: &[],
+        argument: Argument::None,
+        description: "List the plugins loaded and the commands they added",
+    },
+    Command {
+        name: "log",
+        aliases: &[],
+        argument: Argument::None,
+        description: "Show the log file, which `--verbose` makes more detailed",
+    },
+    Command {
+        name: "set",
+        aliases: &[],
+        argument: Argument::Option,
+        description: "Turn an option on (`set number`) or off (`set nonumber`)",
+    },
+    Command {
+        name: "config_reload",
+        aliases: &["config-reload"],
+        argument: Argument::None,
+        description: "Read the configuration file again and apply it",
+    },
+    Command {
+        name: "help",
+        aliases: &["h"],
+        argument: Argument::None,
+        description: "Show the key bindings and commands",
+    },
+    Command {
+        name: "close",
+        aliases: &["bd"],
+        argument: Argument::None,
+        description: "Close the buffer and return to the previous one",
+    },
+    Command {
+        name: "scratch",
+        aliases: &["new", "enew"],
+        argument: Argument::None,
+        description: "Open an unnamed notepad buffer that is never asked about",
+    },
+    Command {
+        name: "buffers",
+        aliases: &["ls", "b"],
+        argument: Argument::None,
+        description: "Pick an open buffer to switch to",
+    },
+    Command {
+        name: "buffer_previous",
+        aliases: &["bp"],
+        argument: Argument::None,
+        description: "Switch to the buffer used before this one",
+    },
+    Command {
+        name: "command_line",
+        aliases: &[],
+        argument: Argument::None,
+        description: "Prompt for a command to run",
+    },
+    Command {
+        name: "goto",
+        aliases: &["g"],
+        argument: Argument::Line,
+        description: "Go to a line, and optionally a column (`goto 42:7`), or part of the way through the document (`goto 50%`)",
+    },
+    Command {
+        name: "jump_back",
+        aliases: &[],
+        argument: Argument::None,
+        description: "Go back to where the cursor jumped from",
+    },
+    Command {
+        name: "jump_forward",
+        aliases: &[],
+        argument: Argument::None,
+        description: "Go forward again through the jump list",
+    },
+    Command {
+        name: "mark",
+        aliases: &["m"],
+        argument: Argument::Mark,
+        description: "Mark the cursor position with a letter",
+    },
+    Command {
+        name: "goto_mark",
+        aliases: &["'"],
+        argument: Argument::Mark,
+        description: "Go to a marked position",
+    },
+    Command {
+        name: "find",
+        aliases: &[],
+        argument: Argument::None,
+        description: "Search the document",
+    },
+    Command {
+        name: "find_next",
+        aliases: &[],
+        argument: Argument::None,
+        description: "Go to the next match of the last search, wrapping around",
+    },
+    Command {
+        name: "find_previous",
+        aliases: &[],
+        argument: Argument::None,
+        description: "Go to the match of the last search the other way, wrapping around",
+    },
+    Command {
+        name: "substitute",
+        aliases: &["s"],
+        argument: Argument::Substitution,
+        description: "Replace text in every line; flags: `g` each match, not only the first, `i` any case, `r` regex",
+    },
+    Command {
+        name: "delete",
+        aliases: &[],
+        argument: Argument::None,
+        description: "Delete the character under the cursor",
+    },
+    Command {
+        name: "backspace",
+        aliases: &[],
+        argument: Argument::None,
+        description: "Delete the character before the cursor",
+    },
+    Command {
+        name: "delete_line",
+        aliases: &[],
+        argument: Argument::None,
+        description: "Delete the current line",
+    },
+    Command {
+        name: "move_up",
+        aliases: &[],
+        argument: Argument::None,
+        description: "Move the cursor up",
+    },
+    Command {
+        name: "move_down",
+        aliases: &[],
+        argument: Argument::None,
+        description: "Move the cursor down",
+    },
+    Command {
+        name: "move_left",
+        aliases: &[],
+        argument: Argument::None,
+        description: "Move the cursor left",
+    },
+    Command {
+        name: "move_right",
+        aliases: &[],
+        argument: Argument::None,
+        description: "Move the cursor right",
+    },
+    Command {
+        name: "page_up",
+        aliases: &[],
+        argument: Argument::None,
+        description: "Scroll up one screen, keeping the cursor's place on it",
+    },
+    Command {
+        name: "page_down",
+        aliases: &[],
+        argument: Argument::None,
+        description: "Scroll down one screen, keeping the cursor's place on it",
+    },
+    Command {
+        name: "half_page_up",
+        aliases: &[],
+        argument: Argument::None,
+        description: "Scroll up half a screen, keeping the cursor's place on it",
+    },
+    Command {
+        name: "half_page_down",
+        aliases: &[],
+        argument: Argument::None,
+        description: "Scroll down half a screen, keeping the cursor's place on it",
+    },
+    Command {
+        name: "document_start",
+        aliases: &[],
+        argument: Argument::None,
+        description: "Go to the start of the document",
+    },
+    Command {
+        name: "document_end",
+        aliases: &[],
+        argument: Argument::None,
+        description: "Go to the end of the document",
+    },
+    Command {
+        name: "line_start",
+        aliases: &[],
+        argument: Argument::None,
+        description: "Move the cursor to the start of the line",
+    },
+    Command {
+        name: "line_end",
+        aliases: &[],
+        argument: Argument::None,
+        description: "Move the cursor to the end of the line",
+    },
+    Command {
+        name: "next_word",
+        aliases: &[],
+        argument: Argument::None,
+        description: "Move to the start of the next word",
+    },
+    Command {
+        name: "previous_word",
+        aliases: &[],
+        argument: Argument::None,
+        description: "Move to the start of the word, or of the one before",
+    },
+    Command {
+        name: "word_end",
+        aliases: &[],
+        argument: Argument::None,
+        description: "Move to the end of the word, or of the next one",
+    },
+    Command {
+        name: "next_paragraph",
+        aliases: &[],
+        argument: Argument::None,
+        description: "Move to the blank line after the paragraph",
+    },
+    Command {
+        name: "previous_paragraph",
+        aliases: &[],
+        argument: Argument::None,
+        description: "Move to the blank line before the paragraph",
+    },
+    Command {
+        name: "next_sentence",
+        aliases: &[],
+        argument: Argument::None,
+        description: "Move to the start of the next sentence",
+    },
+    Command {
+        name: "previous_sentence",
+        aliases: &[],
+        argument: Argument::None,
+        description: "Move to the start of the sentence, or of the one before",
+    },
+    Command {
+        name: "select",
+        aliases: &[],
+        argument: Argument::None,
+        description: "Start selecting from the cursor, or stop selecting",
+    },
+    Command {
+        name: "deselect",
+        aliases: &[],
+        argument: Argument::None,
+        description: "Stop selecting",
+    },
+    Command {
+        name: "select_up",
+        aliases: &[],
+        argument: Argument::None,
+        description: "Extend the selection up",
+    },
+    Command {
+        name: "select_down",
+        aliases: &[],
+        argument: Argument::None,
+        description: "Extend the selection down",
+    },
+    Command {
+        name: "select_left",
+        aliases: &[],
+        argument: Argument::None,
+        description: "Extend the selection left",
+    },
+    Command {
+        name: "select_right",
+        aliases: &[],
+        argument: Argument::None,
+        description: "Extend the selection right",
+    },
+    Command {
+        name: "move_line_up",
+        aliases: &[],
+        argument: Argument::None,
+        description: "Swap the current line with the one above",
+    },
+    Command {
+        name: "move_line_down",
+        aliases: &[],
+        argument: Argument::None,
+        description: "Swap the current line with the one below",
+    },
+    Command {
+        name: "toggle_replace",
+        aliases: &[],
+        argument: Argument::None,
+        description: "Switch between inserting and overwriting text",
+    },
+];
+
+/// Options understood by the `set` command.
+pub const OPTIONS: &[&str] = &[
+    "accessible",
+    "autoindent",
+    "autosave",
+    "blame",
+    "highlight",
+    "number",
+    "perf",
+    "subword",
+    "sync",
+    "title",
+    "zen",
+];
+
+pub struct Invocation<'a> {
+    pub command: &'static Command,
+    pub force: bool,
+    pub args: &'a str,
+    /// How many times to run the command, if a count came before it.
+    pub count: Option<usize>,
+}
+
+#[must_use]
+pub fn find(name: &str) -> Option<&'static Command> {
+    COMMANDS
+        .iter()
+        .find(|command| command.name == name || command.aliases.contains(&name))
+}
+
+/// Splits a command line such as `open! src/main.rs` into the command, whether
+/// it was forced with `!`, and its arguments. A number followed by a command,
+/// such as `3 delete_line`, runs it that many times. A line starting with a
+/// number otherwise, such as `42`, is short for `goto 42`, one starting with
+/// `!` is short for `shell`, and one starting with `=`, such as `= 6 * 7`, for
+/// `calc`.
+///
+/// # Errors
+///
+/// Returns a message for the user if the line does not name a known command.
+pub fn parse(line: &str) -> Result<Invocation<'_>, String> {
+    let line = line.trim();
+    if line.starts_with(|c: char| c.is_ascii_digit()) {
+        if let Some((count, rest)) = line.split_once(char::is_whitespace) {
+            if let (Ok(count), Ok(invocation)) = (count.parse::<usize>(), parse(rest)) {
+                return Ok(Invocation {
+                    count: Some(count),
+                    ..invocation
+                });
+            }
+        }
+        return Ok(Invocation {
+            command: find("goto").expect("goto is a command"),
+            force: false,
+            args: line,
+            count: None,
+        });
+    }
+    if let Some(args) = line.strip_prefix('!') {
+        return Ok(Invocation {
+            command: find("shell").expect("shell is a command"),
+            force: false,
+            args: args.trim(),
+            count: None,
+        });
+    }
+    if let Some(args) = line.strip_prefix('=') {
+        let (args, force) = match args.strip_prefix('!') {
+            Some(args) => (args, true),
+            None => (args, false),
+        };
+        return Ok(Invocation {
+            command: find("calc").expect("calc is a command"),
+            force,
+            args: args.trim(),
+            count: None,
+        });
+    }
+    let (name, args) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+    let (name, force) = match name.strip_suffix('!') {
+        Some(name) => (name, true),
+        None => (name, false),
+    };
+    match find(name) {
+        Some(command) => Ok(Invocation {
+            command,
+            force,
+            args: args.trim(),
+            count: None,
+        }),
+        None => Err(format!("Not a command: {name}")),
+    }
+}
+
+/// Describes what a bound command line does, for hints shown to the user.
+#[must_use]
+pub fn describe(line: &str) -> &str {
+    match parse(line) {
+        Ok(invocation) if invocation.args.is_empty() => invocation.command.description,
+        _ => line,
+    }
+}
+
+/// Returns the possible completions for a partially typed command line,
+/// leaving out paths that are to be ignored.
+#[must_use]
+pub fn complete(line: &str, ignore: &Ignore) -> Vec<String> {
+    let Some((name, arg)) = line.split_once(' ') else {
+        return COMMANDS
+            .iter()
+            .filter(|command| command.name.starts_with(line))
+            .map(|command| command.name.to_string())
+            .collect();
+    };
+    let candidates = match find(name.trim_end_matches('!')).map(|command| &command.argument) {
+        Some(Argument::Path) => complete_path(arg, ignore),
+        Some(Argument::Option) => OPTIONS
+            .iter()
+            .flat_map(|option| [option.to_string(), format!("no{option}")])
+            .filter(|option| option.starts_with(arg))
+            .collect(),
+        Some(Argument::Choice(choices)) => choices
+            .iter()
+            .filter(|choice| choice.starts_with(arg))
+            .map(|choice| choice.to_string())
+            .collect(),
+        _ => Vec::new(),
+    };
+    candidates
+        .into_iter()
+        .map(|candidate| format!("{name} {candidate}"))
+        .collect()
+}
+
+/// Lists the files and directories whose path starts with `partial`, but
+/// for ignored ones. Directories end with a `/` so completion can continue
+/// inside them. A `~` or variables in `partial` are expanded to look for
+/// them, but kept as typed.
+#[must_use]
+pub fn complete_path(partial: &str, ignore: &Ignore) -> Vec<String> {
+    complete_path_from(Path::new(""), partial, ignore)
+}
+
+/// Lists paths as [`complete_path`] does, but looks for a relative
+/// `partial` in `base` rather than in the current directory, as for a path
+/// written in a file, which is relative to where the file is.
+#[must_use]
+pub fn complete_path_from(base: &Path, partial: &str, ignore: &Ignore) -> Vec<String> {
+    let (directory, prefix) = match partial.rfind('/') {
+        Some(index) => partial.split_at(index.saturating_add(1)),
+        None => ("", partial),
+    };
+    let search_in = base.join(paths::expand(directory));
+    let search_in = if search_in.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        search_in.as_path()
+    };
+    let Ok(entries) = fs::read_dir(search_in) else {
+        return Vec::new();
+    };
+    let mut candidates: Vec<String> = entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            if !name.starts_with(prefix)
+                || (name.starts_with('.') && !prefix.starts_with('.'))
+                || ignore.is_ignored(&entry.path())
+            {
+                return None;
+            }
+            let suffix = if entry.path().is_dir() { "/" } else { "" };
+            Some(format!("{directory}{name}{suffix}"))
+        })
+        .collect();
+    candidates.sort();
+    candidates
+}
+
+/// Parses a `percent%` argument, as in `50%`, into the line that far through
+/// a document of `len` lines, counted from 1.
+#[must_use]
+pub fn parse_percent(args: &str, len: usize) -> Option<usize> {
+    let percent: usize = args.trim().strip_suffix('%')?.trim().parse().ok()?;
+    let line = percent.min(100).saturating_mul(len).saturating_add(99) / 100;
+    Some(line.max(1))
+}
+
+/// Parses a `line[:column]` argument, both counted from 1.
+#[must_use]
+pub fn parse_line_column(args: &str) -> Option<(usize, Option<usize>)> {
+    let (line, column) = match args.split_once(':') {
+        Some((line, column)) => (line, Some(column.trim().parse().ok()?)),
+        None => (args, None),
+    };
+    Some((line.trim().parse().ok()?, column))
+}
@@ -0,0 +1,224 @@
+use crate::diff;
+use std::ops::Range;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Two texts laid side by side, with the lines they have in common across
+/// from one another and the differences between them marked, scrolled
+/// together.
+pub struct Comparison {
+    pub left_name: String,
+    pub right_name: String,
+    rows: Vec<Pair>,
+    /// The row each run of differing rows starts at.
+    differences: Vec<usize>,
+    /// The difference last moved to.
+    selected: usize,
+    /// The first row shown.
+    pub offset: usize,
+}
+
+/// A row of a [`Comparison`]: a line of each text, or of only one where the
+/// other has none there.
+pub struct Pair {
+    pub left: Option<Line>,
+    pub right: Option<Line>,
+}
+
+/// A line of one of the texts compared.
+pub struct Line {
+    /// Counting from one.
+    pub number: usize,
+    pub text: String,
+    /// Byte ranges of the text that differ from the line across from it.
+    pub changed: Vec<Range<usize>>,
+}
+
+impl Pair {
+    /// Returns whether both sides hold the same line.
+    #[must_use]
+    pub fn is_same(&self) -> bool {
+        match (&self.left, &self.right) {
+            (Some(left), Some(right)) => left.text == right.text,
+            _ => false,
+        }
+    }
+}
+
+impl Comparison {
+    #[must_use]
+    pub fn new(left_name: &str, left: &str, right_name: &str, right: &str) -> Self {
+        let left_lines: Vec<&str> = left.lines().collect();
+        let right_lines: Vec<&str> = right.lines().collect();
+        let mut layout = Layout::default();
+        // Lines removed and added since the last line in common, paired up
+        // once it comes.
+        let mut removed: Vec<&str> = Vec::new();
+        let mut added: Vec<&str> = Vec::new();
+        for line in diff::lines(&left_lines, &right_lines) {
+            match line {
+                diff::Line::Removed(text) => removed.push(text),
+                diff::Line::Added(text) => added.push(text),
+                diff::Line::Same(text) => {
+                    layout.push_changed(&removed, &added);
+                    removed.clear();
+                    added.clear();
+                    layout.push_same(text);
+                }
+            }
+        }
+        layout.push_changed(&removed, &added);
+        let rows = layout.rows;
+        let differences = rows
+            .iter()
+            .enumerate()
+            .filter(|(index, pair)| {
+                !pair.is_same()
+                    && index
+                        .checked_sub(1)
+                        .and_then(|previous| rows.get(previous))
+                        .is_none_or(Pair::is_same)
+            })
+            .map(|(index, _)| index)
+            .collect();
+        Self {
+            left_name: left_name.to_string(),
+            right_name: right_name.to_string(),
+            rows,
+            differences,
+            selected: 0,
+            offset: 0,
+        }
+    }
+
+    #[must_use]
+    pub fn rows(&self) -> &[Pair] {
+        &self.rows
+    }
+
+    /// Returns the number of runs of differing rows.
+    #[must_use]
+    pub fn difference_count(&self) -> usize {
+        self.differences.len()
+    }
+
+    /// Returns the difference last moved to, counting from zero.
+    #[must_use]
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    /// Moves to the difference at `index`, scrolling it into the upper part
+    /// of `height` rows. Returns `false` if there is none.
+    pub fn select(&mut self, index: usize, height: usize) -> bool {
+        let Some(row) = self.differences.get(index) else {
+            return false;
+        };
+        self.selected = index;
+        self.offset = row.saturating_sub(height / 3);
+        self.clamp(height);
+        true
+    }
+
+    /// Scrolls down by `rows`, or up if it is negative, within `height`.
+    pub fn scroll(&mut self, rows: isize, height: usize) {
+        self.offset = self.offset.saturating_add_signed(rows);
+        self.clamp(height);
+    }
+
+    fn clamp(&mut self, height: usize) {
+        self.offset = self
+            .offset
+            .min(self.rows.len().saturating_sub(height.max(1)));
+    }
+}
+
+/// Rows of a [`Comparison`] as they are laid out, with the number of the
+/// last line of each side so far.
+#[derive(Default)]
+struct Layout {
+    rows: Vec<Pair>,
+    left_number: usize,
+    right_number: usize,
+}
+
+impl Layout {
+    fn push_same(&mut self, text: &str) {
+        let left = self.left_line(text, Vec::new());
+        let right = self.right_line(text, Vec::new());
+        self.rows.push(Pair {
+            left: Some(left),
+            right: Some(right),
+        });
+    }
+
+    /// Pairs lines `removed` from the left with those `added` on the right,
+    /// in order, marking the words that differ between them.
+    fn push_changed(&mut self, removed: &[&str], added: &[&str]) {
+        for index in 0..removed.len().max(added.len()) {
+            let old = removed.get(index).copied();
+            let new = added.get(index).copied();
+            let (left_changed, right_changed) = match (old, new) {
+                (Some(old), Some(new)) => changed_words(old, new),
+                _ => (Vec::new(), Vec::new()),
+            };
+            let left = old.map(|text| self.left_line(text, left_changed));
+            let right = new.map(|text| self.right_line(text, right_changed));
+            self.rows.push(Pair { left, right });
+        }
+    }
+
+    fn left_line(&mut self, text: &str, changed: Vec<Range<usize>>) -> Line {
+        self.left_number = self.left_number.saturating_add(1);
+        Line::new(self.left_number, text, changed)
+    }
+
+    fn right_line(&mut self, text: &str, changed: Vec<Range<usize>>) -> Line {
+        self.right_number = self.right_number.saturating_add(1);
+        Line::new(self.right_number, text, changed)
+    }
+}
+
+impl Line {
+    fn new(number: usize, text: &str, changed: Vec<Range<usize>>) -> Self {
+        Self {
+            number,
+            text: text.to_string(),
+            changed,
+        }
+    }
+}
+
+/// Returns the byte ranges of `old` and of `new` that differ, word by word.
+fn changed_words(old: &str, new: &str) -> (Vec<Range<usize>>, Vec<Range<usize>>) {
+    let old_words: Vec<&str> = old.split_word_bounds().collect();
+    let new_words: Vec<&str> = new.split_word_bounds().collect();
+    let (mut old_changed, mut new_changed) = (Vec::new(), Vec::new());
+    let (mut old_at, mut new_at) = (0_usize, 0_usize);
+    for word in diff::lines(&old_words, &new_words) {
+        match word {
+            diff::Line::Same(word) => {
+                old_at = old_at.saturating_add(word.len());
+                new_at = new_at.saturating_add(word.len());
+            }
+            diff::Line::Removed(word) => {
+                let end = old_at.saturating_add(word.len());
+                extend(&mut old_changed, old_at..end);
+                old_at = end;
+            }
+            diff::Line::Added(word) => {
+                let end = new_at.saturating_add(word.len());
+                extend(&mut new_changed, new_at..end);
+                new_at = end;
+            }
+        }
+    }
+    (old_changed, new_changed)
+}
+
+/// Adds `range` to `ranges`, joining it to the last one if they meet.
+fn extend(ranges: &mut Vec<Range<usize>>, range: Range<usize>) {
+    match ranges.last_mut() {
+        Some(last) if last.end == range.start => last.end = range.end,
+        _ => ranges.push(range),
+    }
+}
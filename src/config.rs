@@ -0,0 +1,291 @@
+use crate::paths;
+use crate::undo::Limits;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+/// The name of the file a project's settings are kept in.
+const PROJECT_FILE_NAME: &str = ".hecto.toml";
+
+/// Settings read from `config.toml` in the user's configuration directory.
+/// Anything left out keeps its default.
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Number of columns a tab advances to.
+    pub tab_width: usize,
+    /// Name of one of the built-in themes. Its light or dark variant is used
+    /// to match the terminal's background.
+    pub theme: String,
+    /// `light` or `dark` to pick the variant of the theme for that
+    /// background, or `auto` to ask the terminal or the system.
+    pub background: String,
+    /// Language of the interface, such as `en` or `es`. Empty takes it from
+    /// `$LC_ALL`, `$LC_MESSAGES` or `$LANG`.
+    pub language: String,
+    pub line_numbers: bool,
+    /// Color numbers, strings and the like by their meaning.
+    pub syntax_highlighting: bool,
+    /// Columns the text is centered in by the `zen` command.
+    pub zen_width: usize,
+    /// Draw for screen readers: no colors, tildes or rulers, and a status
+    /// bar that only changes when the status does.
+    pub accessible: bool,
+    /// Stop word motions at the parts of `camelCase` and `snake_case` names.
+    pub subword: bool,
+    /// Start a new line as indented as the one it was split from, and a
+    /// level further after an opening bracket or a colon.
+    pub auto_indent: bool,
+    /// Save the file whenever the terminal loses focus.
+    pub autosave: bool,
+    /// Show the file name in the terminal's title.
+    pub title: bool,
+    /// Wrap each frame in a synchronized update.
+    pub synchronized_output: bool,
+    /// Show who last changed the cursor's line after it.
+    pub blame: bool,
+    /// Kilobytes of undo history kept for each file between sessions. Zero
+    /// keeps none.
+    pub undo_history_size: usize,
+    /// Days undo history is kept between sessions.
+    pub undo_history_days: u64,
+    /// States of undo history each buffer keeps in memory. Zero sets no
+    /// limit.
+    pub undo_depth: usize,
+    /// Megabytes the undo history of all buffers may take in memory. Past
+    /// that, the states used least recently are dropped. Zero sets no limit.
+    pub undo_memory: usize,
+    /// Megabytes above which a file opens with highlighting, the outline,
+    /// blame and language servers off. Zero never does.
+    pub large_file_size: u64,
+    /// Megabytes the rows of a document may take in memory. Past that, rows
+    /// far from the screen drop their highlighting, then have their text
+    /// paged out to a temporary file. Zero sets no limit.
+    pub memory_budget: usize,
+    /// Shell command run by `make`.
+    pub make: String,
+    /// Shell command `print` sends PostScript to on its standard input.
+    pub print_command: String,
+    /// Whether `print` colors highlighted text.
+    pub print_highlighting: bool,
+    /// Yanks and deletions `yank_history` offers to paste. Zero keeps none.
+    pub yank_history_size: usize,
+    /// Keep the yank history between sessions.
+    pub persist_yank_history: bool,
+    /// Snapshots of each file kept for `file_history`, taken when it is
+    /// saved. Zero takes none.
+    pub file_history_size: usize,
+    /// Seconds between snapshots of a buffer with unsaved changes, besides
+    /// those taken on saving. Zero takes them only on saving.
+    pub file_history_interval: u64,
+    /// Columns marked by a line beside the text.
+    pub rulers: Vec<Ruler>,
+    /// Rulers for one file type, keyed like `formatters`, used instead of
+    /// `rulers`, such as `rust = [100]`.
+    pub file_type_rulers: BTreeMap<String, Vec<Ruler>>,
+    /// Formatters keyed by the file type they apply to, such as `rust`, or by
+    /// a file extension, such as `py`.
+    pub formatters: BTreeMap<String, Formatter>,
+    /// Linters run in the background after every save, keyed like
+    /// `formatters`. They may print `cargo clippy --message-format=json`
+    /// messages or `file:line:column: message` lines.
+    pub linters: BTreeMap<String, String>,
+    /// Language server commands, keyed like `formatters`, such as
+    /// `rust = "rust-analyzer"`.
+    pub language_servers: BTreeMap<String, String>,
+    /// Maps key descriptions such as `"ctrl-s"` or `"ctrl-k d"` to the
+    /// command line they run. An empty command removes the binding.
+    pub keymap: BTreeMap<String, String>,
+    /// Translates single keys into others before they are looked up in the
+    /// keymap, such as `"alt-h" = "left"`, in every mode. Ctrl-H is taken
+    /// as Backspace unless remapped here, even to itself.
+    pub remap: BTreeMap<String, String>,
+    /// Remaps for one mode only, `insert` or `replace`, tried after `remap`,
+    /// or for while a `picker` or `prompt` is open, tried before it.
+    pub mode_remap: BTreeMap<String, BTreeMap<String, String>>,
+    /// Globs of paths left out of completion, such as `target` or `*.o`.
+    pub ignore: Vec<String>,
+}
+
+/// Settings a project shares in a `.hecto.toml` file, found in the directory
+/// of the file being edited or above it, and taken over those of
+/// `config.toml`.
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Project {
+    pub tab_width: Option<usize>,
+    /// The build command, as `make` in `config.toml`.
+    pub make: Option<String>,
+    /// Formatters, added to those of `config.toml` or replacing them.
+    pub formatters: BTreeMap<String, Formatter>,
+    /// Globs of paths left out of completion, added to those of
+    /// `config.toml`. Those with a `/` start at the project's directory.
+    pub ignore: Vec<String>,
+}
+
+/// A column marked by a line beside the text: its number, such as `80`, or
+/// a table with the `column` and the `color` to draw it in, such as
+/// `{ column = 100, color = "#ff5555" }`. Rulers without a color take the
+/// theme's, in turn.
+#[derive(Deserialize, Clone)]
+#[serde(untagged)]
+pub enum Ruler {
+    Column(usize),
+    Colored { column: usize, color: String },
+}
+
+/// A program that reads text on its standard input and writes it back
+/// formatted, such as `rustfmt`, `black -q -` or `prettier --stdin-filepath x.js`.
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Formatter {
+    pub command: String,
+    /// Format the buffer every time it is saved.
+    #[serde(default)]
+    pub on_save: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            tab_width: 4,
+            theme: "default".to_string(),
+            background: "auto".to_string(),
+            language: String::new(),
+            line_numbers: false,
+            syntax_highlighting: true,
+            zen_width: 80,
+            accessible: false,
+            subword: false,
+            auto_indent: false,
+            autosave: false,
+            title: true,
+            synchronized_output: true,
+            blame: false,
+            undo_history_size: 1024,
+            undo_history_days: 30,
+            undo_depth: 0,
+            undo_memory: 64,
+            large_file_size: 10,
+            memory_budget: 0,
+            make: "make".to_string(),
+            print_command: "lpr".to_string(),
+            print_highlighting: true,
+            yank_history_size: 50,
+            persist_yank_history: false,
+            file_history_size: 20,
+            file_history_interval: 300,
+            rulers: Vec::new(),
+            // The summary of a commit message should fit in 50 columns, and
+            // the lines of its body in 72.
+            file_type_rulers: BTreeMap::from([(
+                "git commit".to_string(),
+                vec![Ruler::Column(50), Ruler::Column(72)],
+            )]),
+            formatters: BTreeMap::from([(
+                "rust".to_string(),
+                Formatter {
+                    command: "rustfmt --edition 2021".to_string(),
+                    on_save: false,
+                },
+            )]),
+            linters: BTreeMap::new(),
+            language_servers: BTreeMap::new(),
+            keymap: BTreeMap::new(),
+            remap: BTreeMap::new(),
+            mode_remap: BTreeMap::new(),
+            ignore: Vec::new(),
+        }
+    }
+}
+
+impl Project {
+    /// Returns the `.hecto.toml` that applies to `file_name`: the one in its
+    /// directory or the nearest one above.
+    #[must_use]
+    pub fn find(file_name: &str) -> Option<PathBuf> {
+        let path = Path::new(file_name);
+        let absolute = fs::canonicalize(path)
+            .or_else(|_| env::current_dir().map(|directory| directory.join(path)))
+            .ok()?;
+        absolute
+            .ancestors()
+            .skip(1)
+            .map(|directory| directory.join(PROJECT_FILE_NAME))
+            .find(|path| path.is_file())
+    }
+
+    /// # Errors
+    ///
+    /// Returns a message for the user if the file cannot be read or parsed.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let contents =
+            fs::read_to_string(path).map_err(|error| format!("{}: {error}", path.display()))?;
+        toml::from_str(&contents)
+            .map_err(|error| format!("{}: {}", path.display(), error.message()))
+    }
+}
+
+impl Config {
+    /// Returns `config.toml` in the configuration directory.
+    #[must_use]
+    pub fn path() -> Option<PathBuf> {
+        Some(paths::config_dir()?.join("config.toml"))
+    }
+
+    /// Reads the configuration file, or returns the defaults if there is none.
+    ///
+    /// # Errors
+    ///
+    /// Returns a message for the user if the file cannot be read or parsed.
+    pub fn load() -> Result<Self, String> {
+        let Some(path) = Self::path() else {
+            return Ok(Self::default());
+        };
+        match fs::read_to_string(&path) {
+            Ok(contents) => Self::parse(&path, &contents),
+            Err(error) if error.kind() == ErrorKind::NotFound => Ok(Self::default()),
+            Err(error) => Err(format!("{}: {error}", path.display())),
+        }
+    }
+
+    /// Reads the configuration from `path`, which unlike the usual file must
+    /// exist.
+    ///
+    /// # Errors
+    ///
+    /// Returns a message for the user if the file cannot be read or parsed.
+    pub fn load_from(path: &Path) -> Result<Self, String> {
+        let contents =
+            fs::read_to_string(path).map_err(|error| format!("{}: {error}", path.display()))?;
+        Self::parse(path, &contents)
+    }
+
+    fn parse(path: &Path, contents: &str) -> Result<Self, String> {
+        toml::from_str(contents).map_err(|error| format!("{}: {}", path.display(), error.message()))
+    }
+
+    /// Takes the settings of `project` over these.
+    pub fn merge(&mut self, project: Project) {
+        if let Some(tab_width) = project.tab_width {
+            self.tab_width = tab_width;
+        }
+        if let Some(make) = project.make {
+            self.make = make;
+        }
+        self.formatters.extend(project.formatters);
+        self.ignore.extend(project.ignore);
+    }
+
+    /// Returns the limits on undo history kept between sessions.
+    #[must_use]
+    pub fn undo_limits(&self) -> Limits {
+        Limits {
+            size: self.undo_history_size.saturating_mul(1024),
+            age: self.undo_history_days.saturating_mul(24 * 60 * 60),
+        }
+    }
+}
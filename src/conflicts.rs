@@ -0,0 +1,104 @@
+use std::ops::Range;
+
+/// A conflict a merge left in a file, between a `<<<<<<<` and a `>>>>>>>`
+/// line, given by the rows of its markers.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Conflict {
+    /// The `<<<<<<<` line, above our side.
+    pub start: usize,
+    /// The `|||||||` line above the common ancestor, in the `diff3` style.
+    pub base: Option<usize>,
+    /// The `=======` line, above their side.
+    pub separator: usize,
+    /// The `>>>>>>>` line, below their side.
+    pub end: usize,
+}
+
+/// The part of a conflict a row is in.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Section {
+    Marker,
+    Ours,
+    Base,
+    Theirs,
+}
+
+impl Conflict {
+    /// Returns the rows of our side.
+    #[must_use]
+    pub fn ours(&self) -> Range<usize> {
+        self.start.saturating_add(1)..self.base.unwrap_or(self.separator)
+    }
+
+    /// Returns the rows of their side.
+    #[must_use]
+    pub fn theirs(&self) -> Range<usize> {
+        self.separator.saturating_add(1)..self.end
+    }
+
+    #[must_use]
+    pub fn contains(&self, row: usize) -> bool {
+        (self.start..=self.end).contains(&row)
+    }
+
+    /// Returns the part of the conflict `row` is in, if it is in it.
+    #[must_use]
+    pub fn section(&self, row: usize) -> Option<Section> {
+        if !self.contains(row) {
+            None
+        } else if row == self.start
+            || Some(row) == self.base
+            || row == self.separator
+            || row == self.end
+        {
+            Some(Section::Marker)
+        } else if self.ours().contains(&row) {
+            Some(Section::Ours)
+        } else if self.theirs().contains(&row) {
+            Some(Section::Theirs)
+        } else {
+            Some(Section::Base)
+        }
+    }
+}
+
+/// Returns the conflicts in `lines`, in order. Markers that do not make up a
+/// whole conflict are left alone.
+pub fn find<'a>(lines: impl Iterator<Item = &'a str>) -> Vec<Conflict> {
+    let mut conflicts = Vec::new();
+    let mut start = None;
+    let mut base = None;
+    let mut separator = None;
+    for (index, line) in lines.enumerate() {
+        if is_marker(line, '<') {
+            start = Some(index);
+            base = None;
+            separator = None;
+        } else if is_marker(line, '|') && start.is_some() && separator.is_none() {
+            base = Some(index);
+        } else if is_marker(line, '=') && start.is_some() && separator.is_none() {
+            separator = Some(index);
+        } else if is_marker(line, '>') {
+            if let (Some(start), Some(separator)) = (start, separator) {
+                conflicts.push(Conflict {
+                    start,
+                    base,
+                    separator,
+                    end: index,
+                });
+            }
+            start = None;
+            base = None;
+            separator = None;
+        }
+    }
+    conflicts
+}
+
+/// Returns whether `line` is a marker of seven `c`, followed by nothing or
+/// by a space and a label.
+fn is_marker(line: &str, c: char) -> bool {
+    let rest = line.trim_start_matches(c);
+    line.len().saturating_sub(rest.len()) == 7
+        && (rest.is_empty() || (c != '=' && rest.starts_with(' ')))
+}
@@ -0,0 +1,183 @@
+use std::fmt::Write;
+
+/// Beyond this many pairs of lines to compare, the lines between the common
+/// start and end are taken as all replaced rather than compared one by one.
+const MAX_PAIRS: usize = 4_000_000;
+
+/// A line of the difference between an old and a new text.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Line<'a> {
+    /// In both.
+    Same(&'a str),
+    /// Only in the old text.
+    Removed(&'a str),
+    /// Only in the new text.
+    Added(&'a str),
+}
+
+/// Returns the lines of `old` and `new` in order, keeping as many of them
+/// the same as possible.
+#[must_use]
+pub fn lines<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<Line<'a>> {
+    let prefix = old
+        .iter()
+        .zip(new)
+        .take_while(|(old, new)| old == new)
+        .count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(old, new)| old == new)
+        .count();
+    let old_middle = &old[prefix..old.len().saturating_sub(suffix)];
+    let new_middle = &new[prefix..new.len().saturating_sub(suffix)];
+    let mut lines: Vec<Line> = old[..prefix].iter().map(|line| Line::Same(line)).collect();
+    lines.extend(middle(old_middle, new_middle));
+    lines.extend(
+        old[old.len().saturating_sub(suffix)..]
+            .iter()
+            .map(|line| Line::Same(line)),
+    );
+    lines
+}
+
+/// Compares the lines by their longest common subsequence.
+fn middle<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<Line<'a>> {
+    let pairs = old.len().saturating_mul(new.len());
+    if pairs == 0 || pairs > MAX_PAIRS {
+        return old
+            .iter()
+            .map(|line| Line::Removed(line))
+            .chain(new.iter().map(|line| Line::Added(line)))
+            .collect();
+    }
+    // The length of the longest common subsequence of the lines of each from
+    // `i` and `j` on, in row `i` and column `j`.
+    let width = new.len().saturating_add(1);
+    let cell = |i: usize, j: usize| i.saturating_mul(width).saturating_add(j);
+    let mut lengths = vec![0_u32; cell(old.len().saturating_add(1), 0)];
+    let length =
+        |lengths: &[u32], i: usize, j: usize| lengths.get(cell(i, j)).copied().unwrap_or(0);
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            let (next_i, next_j) = (i.saturating_add(1), j.saturating_add(1));
+            let value = if old.get(i) == new.get(j) {
+                length(&lengths, next_i, next_j).saturating_add(1)
+            } else {
+                length(&lengths, next_i, j).max(length(&lengths, i, next_j))
+            };
+            if let Some(cell) = lengths.get_mut(cell(i, j)) {
+                *cell = value;
+            }
+        }
+    }
+    let mut lines = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    loop {
+        match (old.get(i), new.get(j)) {
+            (Some(removed), Some(added)) if removed == added => {
+                lines.push(Line::Same(removed));
+                i = i.saturating_add(1);
+                j = j.saturating_add(1);
+            }
+            (Some(removed), Some(_))
+                if length(&lengths, i.saturating_add(1), j)
+                    >= length(&lengths, i, j.saturating_add(1)) =>
+            {
+                lines.push(Line::Removed(removed));
+                i = i.saturating_add(1);
+            }
+            (Some(removed), None) => {
+                lines.push(Line::Removed(removed));
+                i = i.saturating_add(1);
+            }
+            (_, Some(added)) => {
+                lines.push(Line::Added(added));
+                j = j.saturating_add(1);
+            }
+            (None, None) => break,
+        }
+    }
+    lines
+}
+
+/// Describes how `old` became `new` as a unified diff, each change with up
+/// to `context` lines around it, headed by the names of the two. Returns an
+/// empty string if they are the same.
+#[must_use]
+pub fn unified(
+    old_name: &str,
+    new_name: &str,
+    old: &[&str],
+    new: &[&str],
+    context: usize,
+) -> String {
+    let lines = lines(old, new);
+    // The range of lines of each hunk, joining changes whose context meets.
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+    for (index, line) in lines.iter().enumerate() {
+        if matches!(line, Line::Same(_)) {
+            continue;
+        }
+        let start = index.saturating_sub(context);
+        let end = index
+            .saturating_add(context)
+            .saturating_add(1)
+            .min(lines.len());
+        match hunks.last_mut() {
+            Some((_, last_end)) if start <= *last_end => *last_end = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+    if hunks.is_empty() {
+        return String::new();
+    }
+    let mut diff = format!("--- {old_name}\n+++ {new_name}\n");
+    // The line of each text the line at each index is at or comes before.
+    let mut positions = Vec::with_capacity(lines.len());
+    let (mut old_line, mut new_line) = (0_usize, 0_usize);
+    for line in &lines {
+        positions.push((old_line, new_line));
+        if !matches!(line, Line::Added(_)) {
+            old_line = old_line.saturating_add(1);
+        }
+        if !matches!(line, Line::Removed(_)) {
+            new_line = new_line.saturating_add(1);
+        }
+    }
+    for (start, end) in hunks {
+        let hunk = lines.get(start..end).unwrap_or_default();
+        let old_count = hunk
+            .iter()
+            .filter(|line| !matches!(line, Line::Added(_)))
+            .count();
+        let new_count = hunk
+            .iter()
+            .filter(|line| !matches!(line, Line::Removed(_)))
+            .count();
+        let (old_start, new_start) = positions.get(start).copied().unwrap_or_default();
+        // Counting from one, or the line before an empty range.
+        let first = |start: usize, count: usize| {
+            if count == 0 {
+                start
+            } else {
+                start.saturating_add(1)
+            }
+        };
+        let _ = writeln!(
+            diff,
+            "@@ -{},{old_count} +{},{new_count} @@",
+            first(old_start, old_count),
+            first(new_start, new_count)
+        );
+        for line in hunk {
+            let _ = match line {
+                Line::Same(text) => writeln!(diff, " {text}"),
+                Line::Removed(text) => writeln!(diff, "-{text}"),
+                Line::Added(text) => writeln!(diff, "+{text}"),
+            };
+        }
+    }
+    diff
+}
@@ -1,16 +1,84 @@
+use crate::ansi;
+use crate::breakpoints::Breakpoints;
+use crate::changes::Change;
+use crate::indent::{self, Indent};
+use crate::marks::Marks;
+use crate::paging::PageFile;
+use crate::replace::{ReplaceOptions, ReplaceReport, Replacer};
+use crate::save::Save;
+use crate::undo::{Edit, Limits, Step, UndoTree};
 use crate::FileType;
 use crate::SearchDirection;
 use crate::Position;
 use crate::Row;
 use std::fs;
 use std::io::{Error, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::time::{Instant, SystemTime};
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Source of [`Document::revision`] numbers, shared by all documents so a
+/// reloaded document never reuses a number.
+static REVISIONS: AtomicU64 = AtomicU64::new(1);
 
 #[derive(Default)]
 pub struct Document {
     rows: Vec<Row>,
     pub file_name: Option<String>,
+    /// The canonical path of the file, found when it is opened or saved.
+    path: Option<PathBuf>,
     dirty: bool,
     file_type: FileType,
+    modified: Option<SystemTime>,
+    marks: Marks,
+    breakpoints: Breakpoints,
+    /// Name shown for a document that is not backed by a file.
+    name: Option<String>,
+    read_only: bool,
+    /// A notepad that is never worth asking about until it is saved.
+    scratch: bool,
+    revision: u64,
+    undo: UndoTree,
+    /// Opened with highlighting off, being too big to highlight quickly.
+    large: bool,
+    /// The word last given to [`Document::highlight`], for rows highlighted
+    /// later.
+    word: Option<String>,
+    /// Where rows are paged out to by [`Document::fit_memory`].
+    pages: Option<Arc<PageFile>>,
+    /// The row [`Document::highlight_pending`] goes on from, and when it
+    /// started, until every row of a file just opened is highlighted.
+    pending_highlight: Option<(usize, Instant)>,
+    /// Where each change is sent, see [`Document::subscribe`].
+    subscribers: Vec<Sender<Change>>,
+    /// How the file was indented when it was opened.
+    indent: Option<Indent>,
+}
+
+/// Creates a document holding `text` as if it were read from a file, but
+/// that is not backed by one, without reading or writing anything on disk.
+impl From<&str> for Document {
+    fn from(text: &str) -> Self {
+        let file_type = FileType::default();
+        let rows = text
+            .lines()
+            .map(|line| {
+                let mut row = Row::from(line);
+                row.highlight(file_type.highlighting_options(), None);
+                row
+            })
+            .collect();
+        Self {
+            rows,
+            file_type,
+            revision: next_revision(),
+            ..Self::default()
+        }
+    }
 }
 
 impl Document {
@@ -19,22 +87,268 @@ impl Document {
     /// Will return `Err` if `filename` does not exist or the user does not have
     /// permission to read it.
     pub fn open(filename: &str) -> Result<Self, std::io::Error> {
-        let contents = fs::read_to_string(filename)?;
-        let file_type = FileType::from(filename);
-        let mut rows = Vec::new();
-        for value in contents.lines() {
-            let mut row = Row::from(value);
-            row.highlight(file_type.highlighting_options(), None);
-            rows.push(row);
-        }
+        Self::load(filename, false)
+    }
+
+    /// Opens a file too big to highlight without stalling: its rows are
+    /// never highlighted, and the editor leaves out other features that go
+    /// over the whole document.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if `filename` does not exist or the user does not have
+    /// permission to read it.
+    pub fn open_large(filename: &str) -> Result<Self, std::io::Error> {
+        Self::load(filename, true)
+    }
+
+    fn load(filename: &str, large: bool) -> Result<Self, std::io::Error> {
+        let contents = fs::read_to_string(filename).inspect_err(|error| {
+            log::warn!("Could not open {filename}: {error}");
+        })?;
+        let rows: Vec<Row> = contents.lines().map(Row::from).collect();
+        log::info!(
+            "Opened {filename}: {} lines, {} bytes",
+            rows.len(),
+            contents.len()
+        );
         Ok(Self {
             rows,
             file_name: Some(filename.to_string()),
+            path: fs::canonicalize(filename).ok(),
             dirty: false,
-            file_type
+            file_type: FileType::from(filename),
+            modified: modified_time(filename),
+            marks: Marks::load(filename),
+            breakpoints: Breakpoints::load(filename),
+            name: None,
+            read_only: false,
+            scratch: false,
+            revision: next_revision(),
+            undo: UndoTree::load(filename, contents.lines()).unwrap_or_default(),
+            large,
+            word: None,
+            pages: None,
+            pending_highlight: (!large).then(Instant::now).map(|started| (0, started)),
+            subscribers: Vec::new(),
+            indent: indent::detect(contents.lines()),
         })
     }
 
+    /// Creates a document that is not backed by a file and cannot be edited,
+    /// such as the help screen.
+    #[must_use]
+    pub fn read_only(name: &str, contents: &str) -> Self {
+        Self {
+            rows: contents.lines().map(Row::from).collect(),
+            name: Some(name.to_string()),
+            read_only: true,
+            ..Self::default()
+        }
+    }
+
+    /// Creates an empty notepad. Its changes are not reported as unsaved, so
+    /// it can be thrown away freely, until it is saved under a file name.
+    #[must_use]
+    pub fn scratch(name: &str) -> Self {
+        Self {
+            name: Some(name.to_string()),
+            scratch: true,
+            ..Self::default()
+        }
+    }
+
+    #[must_use]
+    pub fn is_scratch(&self) -> bool {
+        self.scratch
+    }
+
+    /// Returns the name to show for this document.
+    #[must_use]
+    pub fn name(&self) -> &str {
+        self.file_name
+            .as_deref()
+            .or(self.name.as_deref())
+            .unwrap_or("[No name]")
+    }
+
+    /// Returns the canonical path of the file as it was last opened or saved.
+    #[must_use]
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Stops the document from being changed, such as a file opened only to
+    /// be read.
+    pub fn set_read_only(&mut self) {
+        self.read_only = true;
+    }
+
+    #[must_use]
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Returns how the file was indented when it was opened, if its lines
+    /// were.
+    #[must_use]
+    pub fn indent(&self) -> Option<Indent> {
+        self.indent
+    }
+
+    /// Returns whether the document was opened as too big to highlight.
+    #[must_use]
+    pub fn is_large(&self) -> bool {
+        self.large
+    }
+
+    /// Returns a number that changes with every edit, so others can tell
+    /// whether they have seen the current text.
+    #[must_use]
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// Returns the whole text, each row ending in a newline as it is saved.
+    #[must_use]
+    pub fn text(&self) -> String {
+        self.rows
+            .iter()
+            .map(|row| format!("{}\n", row.as_str()))
+            .collect()
+    }
+
+    fn changed(&mut self) {
+        self.dirty = true;
+        self.revision = next_revision();
+    }
+
+    /// Returns a receiver of every change made to the text from now on, so
+    /// what depends on it can keep up without reading it all again. A
+    /// document read again from disk is a new one, whose changes are not
+    /// sent to it.
+    pub fn subscribe(&mut self) -> Receiver<Change> {
+        let (sender, receiver) = mpsc::channel();
+        self.subscribers.push(sender);
+        receiver
+    }
+
+    /// Sends `change` to the subscribers, forgetting those that went away.
+    fn notify(&mut self, change: &Change) {
+        self.subscribers
+            .retain(|subscriber| subscriber.send(change.clone()).is_ok());
+    }
+
+    /// Returns the text of up to `count` rows from `at`.
+    fn texts(&self, at: usize, count: usize) -> Vec<String> {
+        self.rows
+            .iter()
+            .skip(at)
+            .take(count)
+            .map(|row| row.as_str().to_string())
+            .collect()
+    }
+
+    /// Records for undo that the rows from `at` that read `removed`, when the
+    /// document had `len` rows, were replaced by the rows there now.
+    fn record(&mut self, at: usize, removed: Vec<String>, len: usize) {
+        let count = removed.len().saturating_add(self.len()).saturating_sub(len);
+        self.check_rows(at, count);
+        let inserted = self.texts(at, count);
+        if inserted != removed {
+            self.undo.record(Edit {
+                at,
+                removed,
+                inserted,
+            });
+        }
+    }
+
+    /// Ends the group of edits undone together, made while the cursor moved
+    /// from `before` to `after`. If they typed the character `typed`, they
+    /// are grouped with the rest of the word.
+    pub fn end_undo_group(&mut self, before: Position, after: Position, typed: Option<char>) {
+        self.undo.commit(before, after, typed);
+    }
+
+    /// Reverts the last group of edits, returning where the cursor was
+    /// before them.
+    pub fn undo(&mut self) -> Option<Position> {
+        let step = self.undo.undo()?;
+        Some(self.apply(step))
+    }
+
+    /// Makes the last group of edits undone again, returning where the cursor
+    /// was after them.
+    pub fn redo(&mut self) -> Option<Position> {
+        let step = self.undo.redo()?;
+        Some(self.apply(step))
+    }
+
+    /// Brings the document to one of the states in [`Document::undo_tree`],
+    /// returning where the cursor was in it.
+    pub fn undo_to(&mut self, state: usize) -> Option<Position> {
+        let mut cursor = None;
+        for step in self.undo.go_to(state) {
+            cursor = Some(self.apply(step));
+        }
+        cursor
+    }
+
+    #[must_use]
+    pub fn undo_tree(&self) -> &UndoTree {
+        &self.undo
+    }
+
+    /// Drops the undo states used least recently until at most `depth` are
+    /// left, taking at most `memory` bytes. Returns how many were dropped.
+    pub fn trim_undo(&mut self, depth: usize, memory: usize) -> usize {
+        self.undo.trim(depth, memory)
+    }
+
+    /// Keeps the undo history of the file as saved, within `limits`, for
+    /// the next time it is opened.
+    pub fn store_undo(&self, limits: Limits) {
+        if let Some(file_name) = &self.file_name {
+            self.undo
+                .store(file_name, self.rows.iter().map(Row::as_str), limits);
+        }
+    }
+
+    fn apply(&mut self, step: Step) -> Position {
+        for edit in &step.edits {
+            let lines: Vec<&str> = edit.inserted.iter().map(String::as_str).collect();
+            self.put_rows(edit.at, edit.removed.len(), &lines);
+            self.check_rows(edit.at, lines.len());
+            self.notify(&Change::Rows {
+                at: edit.at,
+                removed: edit.removed.len(),
+                inserted: edit.inserted.clone(),
+            });
+        }
+        self.dirty = !self.undo.is_saved();
+        self.revision = next_revision();
+        step.cursor
+    }
+
+    /// Checks, in debug builds, that every row is consistent with its text.
+    /// Edits only check the rows they touched.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if a row is not, see [`Row::check_invariants`].
+    pub fn check_invariants(&self) {
+        self.check_rows(0, self.len());
+    }
+
+    fn check_rows(&self, at: usize, count: usize) {
+        if cfg!(debug_assertions) {
+            for row in self.rows.iter().skip(at).take(count) {
+                row.check_invariants();
+            }
+        }
+    }
+
     pub fn file_type(&self) -> String {
         self.file_type.name()
     }
@@ -67,32 +381,114 @@ impl Document {
         let mut new_row = current_row.split(at.x);
         current_row.highlight(self.file_type.highlighting_options(), None);
         new_row.highlight(self.file_type.highlighting_options(), None);
-        #[allow(clippy::integer_arithmetic)]
+        #[allow(clippy::arithmetic_side_effects)]
         self.rows.insert(at.y + 1, new_row);
+        self.marks.rows_inserted(at, 1, 0);
+        self.breakpoints.rows_inserted(at, 1);
+        self.notify(&Change::Split { at: at.clone() });
     }
 
     /// # Panics
     ///
     /// Will panic if the new capacity exceeds `isize::MAX` bytes
     pub fn insert(&mut self, at: &Position, c: char) {
-        if at.y > self.len() {
+        if at.y > self.len() || self.read_only {
             return;
         }
-        self.dirty = true;
+        self.changed();
+        let len = self.len();
+        let removed = self.texts(at.y, 1);
         if c == '\n' {
             self.insert_newline(at);
-            return;
-        }
-        if at.y == self.len() {
+        } else if at.y == self.len() {
             let mut row = Row::default();
             row.insert(0, c);
             row.highlight(self.file_type.highlighting_options(), None);
             self.rows.push(row);
-        } else {
-            let row = self.rows.get_mut(at.y).unwrap();
+            self.notify(&Change::Rows {
+                at: at.y,
+                removed: 0,
+                inserted: vec![c.to_string()],
+            });
+        } else if let Some(row) = self.rows.get_mut(at.y) {
             row.insert(at.x, c);
             row.highlight(self.file_type.highlighting_options(), None);
+            let at = Position {
+                x: at.x.min(row.len().saturating_sub(1)),
+                y: at.y,
+            };
+            self.notify(&Change::Inserted {
+                at,
+                text: c.to_string(),
+            });
+        }
+        self.record(at.y, removed, len);
+    }
+
+    /// Inserts `text` verbatim at `at` and returns the position right after
+    /// the inserted text. Every affected row is highlighted only once, so
+    /// large pastes stay cheap.
+    pub fn insert_str(&mut self, at: &Position, text: &str) -> Position {
+        if at.y > self.len() || text.is_empty() || self.read_only {
+            return at.clone();
+        }
+        self.changed();
+        let len = self.len();
+        let removed = self.texts(at.y, 1);
+        let appended = at.y == self.len();
+        if appended {
+            self.rows.push(Row::default());
+        }
+        #[allow(clippy::indexing_slicing)]
+        let tail = self.rows[at.y].split(at.x);
+        let start = Position {
+            x: self.rows.get(at.y).map_or(0, Row::len),
+            y: at.y,
+        };
+        let mut position = at.clone();
+        for (index, line) in text.split('\n').enumerate() {
+            let line = line.strip_suffix('\r').unwrap_or(line);
+            if index > 0 {
+                position.y = position.y.saturating_add(1);
+                position.x = 0;
+                self.rows.insert(position.y, Row::default());
+            }
+            #[allow(clippy::indexing_slicing)]
+            let row = &mut self.rows[position.y];
+            row.append(&Row::from(line));
+            position.x = row.len();
+        }
+        for y in at.y..=position.y {
+            #[allow(clippy::indexing_slicing)]
+            let row = &mut self.rows[y];
+            if y == position.y {
+                row.append(&tail);
+            }
+            row.highlight(self.file_type.highlighting_options(), None);
         }
+        self.marks
+            .rows_inserted(at, position.y.saturating_sub(at.y), position.x);
+        self.breakpoints
+            .rows_inserted(at, position.y.saturating_sub(at.y));
+        self.record(at.y, removed, len);
+        let change = if appended {
+            Change::Rows {
+                at: at.y,
+                removed: 0,
+                inserted: self.texts(at.y, position.y.saturating_sub(at.y).saturating_add(1)),
+            }
+        } else {
+            let lines: Vec<&str> = text
+                .split('\n')
+                .map(|line| line.strip_suffix('\r').unwrap_or(line))
+                .collect();
+            Change::Inserted {
+                at: start,
+                text: lines.join("\n"),
+            }
+        };
+        self.notify(&change);
+        position
     }
 
     /// # Panics
@@ -100,48 +496,544 @@ impl Document {
     /// Will panic if the index in `row` is out of bounds.
     pub fn delete(&mut self, at: &Position) {
         let len = self.len();
-        if at.y >= len {
+        if at.y >= len || self.read_only {
             return;
         }
-        self.dirty = true;
-        if at.x == self.rows.get_mut(at.y).unwrap().len() && at.y < len - 1 {
+        self.changed();
+        let joining = self.rows.get(at.y).is_some_and(|row| at.x == row.len()) && at.y < len - 1;
+        let removed = self.texts(at.y, if joining { 2 } else { 1 });
+        if joining {
             let next_row = self.rows.remove(at.y + 1);
-            let row = self.rows.get_mut(at.y).unwrap();
-            row.append(&next_row);
-            row.highlight(self.file_type.highlighting_options(), None);
+            if let Some(row) = self.rows.get_mut(at.y) {
+                row.append(&next_row);
+                row.highlight(self.file_type.highlighting_options(), None);
+            }
+            self.marks.rows_joined(at.y, at.x);
+            self.breakpoints.rows_joined(at.y);
+            self.notify(&Change::Joined { at: at.clone() });
+        } else if let Some(row) = self.rows.get_mut(at.y) {
+            if at.x < row.len() {
+                row.delete(at.x);
+                row.highlight(self.file_type.highlighting_options(), None);
+                self.notify(&Change::Deleted {
+                    start: at.clone(),
+                    end: Position {
+                        x: at.x.saturating_add(1),
+                        y: at.y,
+                    },
+                });
+            }
+        }
+        self.record(at.y, removed, len);
+    }
+
+    pub fn delete_row(&mut self, index: usize) {
+        if index >= self.len() || self.read_only {
+            return;
+        }
+        self.changed();
+        let len = self.len();
+        let removed = self.texts(index, 1);
+        self.rows.remove(index);
+        self.marks.row_removed(index);
+        self.breakpoints.row_removed(index);
+        self.record(index, removed, len);
+        self.notify(&Change::Rows {
+            at: index,
+            removed: 1,
+            inserted: Vec::new(),
+        });
+    }
+
+    /// Returns the text from `start` up to `end`, rows separated by newlines.
+    #[must_use]
+    pub fn text_range(&self, start: &Position, end: &Position) -> String {
+        let mut lines = Vec::new();
+        for y in start.y..=end.y {
+            let Some(row) = self.rows.get(y) else {
+                break;
+            };
+            let from = if y == start.y { start.x } else { 0 };
+            let to = if y == end.y { end.x } else { row.len() };
+            lines.push(
+                row.as_str()
+                    .graphemes(true)
+                    .skip(from)
+                    .take(to.saturating_sub(from))
+                    .collect::<String>(),
+            );
+        }
+        lines.join("\n")
+    }
+
+    /// Removes the text from `start` up to `end`, as a single edit.
+    pub fn delete_range(&mut self, start: &Position, end: &Position) {
+        if (start.y, start.x) >= (end.y, end.x) || end.y >= self.len() || self.read_only {
+            return;
+        }
+        let (Some(first), Some(last)) = (self.rows.get(start.y), self.rows.get(end.y)) else {
+            return;
+        };
+        let joined: String = first
+            .as_str()
+            .graphemes(true)
+            .take(start.x)
+            .chain(last.as_str().graphemes(true).skip(end.x))
+            .collect();
+        let start = Position {
+            x: start.x.min(first.len()),
+            y: start.y,
+        };
+        let end = Position {
+            x: end.x.min(last.len()),
+            y: end.y,
+        };
+        self.splice_rows(start.y, end.y, &[&joined]);
+        self.notify(&Change::Deleted { start, end });
+    }
+
+    /// Replaces the matches of `pattern` with `replacement` on every line, as
+    /// `options` say, and reports where. Lines without a match are left
+    /// alone, so marks on them stay where they were.
+    ///
+    /// # Errors
+    ///
+    /// Returns a message for the user if the document is read-only, or the
+    /// pattern is empty or not a valid regular expression.
+    pub fn replace_all(
+        &mut self,
+        pattern: &str,
+        replacement: &str,
+        options: &ReplaceOptions,
+    ) -> Result<ReplaceReport, String> {
+        if self.read_only {
+            return Err("This buffer is read-only.".to_string());
+        }
+        let replacer = Replacer::new(pattern, replacement, *options)?;
+        let mut report = ReplaceReport::default();
+        // From the bottom up, so a replacement with line breaks in it does
+        // not move the lines still to come.
+        for y in (0..self.len()).rev() {
+            let Some((line, count)) = self.row(y).and_then(|row| replacer.replace(row.as_str()))
+            else {
+                continue;
+            };
+            self.patch_rows(y, y, &line);
+            report.count = report.count.saturating_add(count);
+            report.lines.push(y);
+        }
+        report.lines.reverse();
+        Ok(report)
+    }
+
+    /// Replaces the rows from `first` through `last` with the lines of `text`,
+    /// as a single edit, and returns how many rows took their place.
+    pub fn replace_rows(&mut self, first: usize, last: usize, text: &str) -> usize {
+        if first > last || last >= self.len() || self.read_only {
+            return 0;
+        }
+        let lines: Vec<&str> = if text.is_empty() {
+            Vec::new()
         } else {
-            let row = self.rows.get_mut(at.y).unwrap();
-            row.delete(at.x);
+            text.split('\n')
+                .map(|line| line.strip_suffix('\r').unwrap_or(line))
+                .collect()
+        };
+        self.splice_rows(first, last, &lines);
+        self.notify_rows(first, last, &lines);
+        lines.len()
+    }
+
+    /// Replaces the rows from `first` through `last` with the lines of `text`
+    /// like [`Document::replace_rows`], but keeps the rows at either end that
+    /// `text` leaves unchanged, so marks on them stay where they were.
+    ///
+    /// Returns the first row that changed, how many rows were replaced from
+    /// there and how many took their place, or `None` if nothing changed.
+    pub fn patch_rows(
+        &mut self,
+        first: usize,
+        last: usize,
+        text: &str,
+    ) -> Option<(usize, usize, usize)> {
+        if first > last || last >= self.len() || self.read_only {
+            return None;
+        }
+        let old: Vec<String> = self
+            .rows
+            .get(first..=last)?
+            .iter()
+            .map(|row| row.as_str().to_string())
+            .collect();
+        let new: Vec<&str> = text
+            .split('\n')
+            .map(|line| line.strip_suffix('\r').unwrap_or(line))
+            .collect();
+        if old == new {
+            return None;
+        }
+        let shorter = old.len().min(new.len());
+        let mut prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+        let mut suffix = old
+            .iter()
+            .rev()
+            .zip(new.iter().rev())
+            .take(shorter.saturating_sub(prefix))
+            .take_while(|(a, b)| a == b)
+            .count();
+        // Rows can only be replaced, not inserted, so keep at least one of
+        // the old rows in the change.
+        if prefix.saturating_add(suffix) == old.len() {
+            if prefix > 0 {
+                prefix = prefix.saturating_sub(1);
+            } else {
+                suffix = suffix.saturating_sub(1);
+            }
+        }
+        let replaced = old.len().saturating_sub(prefix).saturating_sub(suffix);
+        let lines = new.get(prefix..new.len().saturating_sub(suffix))?;
+        let start = first.saturating_add(prefix);
+        let last = start.saturating_add(replaced).saturating_sub(1);
+        self.splice_rows(start, last, lines);
+        self.notify_rows(start, last, lines);
+        Some((start, replaced, lines.len()))
+    }
+
+    /// Returns whether any row holds ANSI escape sequences.
+    #[must_use]
+    pub fn has_ansi_codes(&self) -> bool {
+        self.rows.iter().any(|row| ansi::has_codes(row.as_str()))
+    }
+
+    /// Removes the ANSI escape sequences from every row, one row at a time
+    /// so marks on the others stay put. Returns how many rows had some.
+    pub fn strip_ansi_codes(&mut self) -> usize {
+        let mut stripped = 0_usize;
+        for y in 0..self.len() {
+            let Some(text) = self
+                .rows
+                .get(y)
+                .map(Row::as_str)
+                .filter(|text| ansi::has_codes(text))
+                .map(ansi::strip)
+            else {
+                continue;
+            };
+            if self.patch_rows(y, y, &text).is_some() {
+                stripped = stripped.saturating_add(1);
+            }
+        }
+        stripped
+    }
+
+    fn splice_rows(&mut self, first: usize, last: usize, lines: &[&str]) {
+        self.changed();
+        let len = self.len();
+        let count = last.saturating_sub(first).saturating_add(1);
+        let removed = self.texts(first, count);
+        self.put_rows(first, count, lines);
+        self.record(first, removed, len);
+    }
+
+    /// Tells the subscribers the rows from `first` through `last` were
+    /// replaced with `lines`.
+    fn notify_rows(&mut self, first: usize, last: usize, lines: &[&str]) {
+        self.notify(&Change::Rows {
+            at: first,
+            removed: last.saturating_sub(first).saturating_add(1),
+            inserted: lines.iter().map(ToString::to_string).collect(),
+        });
+    }
+
+    /// Replaces `count` rows from `at` with `lines`, without recording it.
+    fn put_rows(&mut self, at: usize, count: usize, lines: &[&str]) {
+        for _ in 0..count.min(self.len().saturating_sub(at)) {
+            self.rows.remove(at);
+            self.marks.row_removed(at);
+            self.breakpoints.row_removed(at);
+        }
+        let at = at.min(self.len());
+        for (index, line) in lines.iter().enumerate() {
+            let mut row = Row::from(*line);
             row.highlight(self.file_type.highlighting_options(), None);
+            self.rows.insert(at.saturating_add(index), row);
+        }
+        self.marks.rows_added(at, lines.len());
+        self.breakpoints.rows_added(at, lines.len());
+    }
+
+    /// Swaps two rows, doing nothing if either does not exist.
+    pub fn swap_rows(&mut self, a: usize, b: usize) {
+        if a >= self.len() || b >= self.len() || a == b || self.read_only {
+            return;
+        }
+        self.changed();
+        let len = self.len();
+        let (removed_a, removed_b) = (self.texts(a, 1), self.texts(b, 1));
+        self.rows.swap(a, b);
+        self.marks.rows_swapped(a, b);
+        self.breakpoints.rows_swapped(a, b);
+        self.record(a, removed_a, len);
+        self.record(b, removed_b, len);
+        for at in [a, b] {
+            let inserted = self.texts(at, 1);
+            self.notify(&Change::Rows {
+                at,
+                removed: 1,
+                inserted,
+            });
+        }
+    }
+
+    /// Sets the mark `name` at `at`, remembering it for the next time this
+    /// file is opened.
+    pub fn set_mark(&mut self, name: char, at: &Position) {
+        self.marks.set(name, at.clone());
+        if let Some(file_name) = &self.file_name {
+            self.marks.store(file_name);
         }
     }
 
+    #[must_use]
+    pub fn mark(&self, name: char) -> Option<&Position> {
+        self.marks.get(name)
+    }
+
+    /// Sets a breakpoint on row `y`, or removes the one there, remembering
+    /// them for the next time this file is opened. Returns whether there is
+    /// one now.
+    pub fn toggle_breakpoint(&mut self, y: usize) -> bool {
+        let set = self.breakpoints.toggle(y);
+        self.store_breakpoints();
+        set
+    }
+
+    pub fn clear_breakpoints(&mut self) {
+        self.breakpoints.clear();
+        self.store_breakpoints();
+    }
+
+    fn store_breakpoints(&self) {
+        if let Some(file_name) = &self.file_name {
+            self.breakpoints.store(file_name);
+        }
+    }
+
+    #[must_use]
+    pub fn breakpoints(&self) -> &Breakpoints {
+        &self.breakpoints
+    }
+
     /// # Errors
     ///
-    /// This function will return the first error of Error kind that [write] returns.
+    /// This function will return the first error of Error kind that [write] returns,
+    /// or, before the file is touched, one for a row paged out that cannot be
+    /// read back.
     pub fn save(&mut self) -> Result<(), Error> {
-        if let Some(file_name) = &self.file_name {
-            let mut file = fs::File::create(file_name)?;
-            self.file_type = FileType::from(file_name);
+        if let Some(file_name) = self.file_name.clone() {
+            self.check_readable()?;
+            let mut file = fs::File::create(&file_name).inspect_err(|error| {
+                log::warn!("Could not save {file_name}: {error}");
+            })?;
+            self.file_type = FileType::from(&file_name);
             for row in &mut self.rows {
                 file.write_all(row.as_bytes())?;
                 file.write_all(b"\n")?;
-                row.highlight(self.file_type.highlighting_options(), None)
+                if !self.large {
+                    row.highlight(self.file_type.highlighting_options(), None);
+                }
             }
-            self.dirty = false;
+            self.saved(&file_name, self.revision);
         }
         Ok(())
     }
-    
+
+    /// Starts writing the document to its file on another thread, for
+    /// documents big enough that saving would hold up the editor. Returns
+    /// `None` if the document has no file name.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if a row paged out cannot be read back.
+    pub fn save_in_background(&self) -> Result<Option<Save>, Error> {
+        let Some(file_name) = self.file_name.as_ref() else {
+            return Ok(None);
+        };
+        self.check_readable()?;
+        let lines = self.texts(0, self.rows.len());
+        Ok(Some(Save::start(file_name, lines, self.revision)))
+    }
+
+    /// Checks that the text of every row can be read, so a row paged out
+    /// that cannot be read back is not saved as an empty line over the text
+    /// in the file.
+    fn check_readable(&self) -> Result<(), Error> {
+        for (y, row) in self.rows.iter().enumerate() {
+            row.text().map_err(|error| {
+                Error::new(
+                    error.kind(),
+                    format!("could not read line {} back: {error}", y.saturating_add(1)),
+                )
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Takes note that `save` was written. Edits made meanwhile still need
+    /// saving.
+    pub fn finish_save(&mut self, save: &Save) {
+        self.file_type = FileType::from(&save.file_name);
+        self.saved(&save.file_name, save.revision);
+    }
+
+    fn saved(&mut self, file_name: &str, revision: u64) {
+        if revision == self.revision {
+            self.dirty = false;
+            self.undo.mark_saved();
+        }
+        self.scratch = false;
+        self.name = None;
+        self.path = fs::canonicalize(file_name).ok();
+        self.modified = modified_time(file_name);
+        self.marks.store(file_name);
+        self.breakpoints.store(file_name);
+        log::info!("Saved {file_name}: {} lines", self.rows.len());
+    }
+
+    /// Returns how many bytes the document takes when saved.
+    #[must_use]
+    pub fn byte_len(&self) -> u64 {
+        self.rows
+            .iter()
+            .map(|row| row.as_str().len().saturating_add(1) as u64)
+            .sum()
+    }
+
+    /// Returns `true` if the file backing this document was modified by
+    /// someone else since it was last opened or saved.
+    #[must_use]
+    pub fn changed_on_disk(&self) -> bool {
+        match &self.file_name {
+            Some(file_name) => modified_time(file_name) != self.modified,
+            None => false,
+        }
+    }
+
+    /// Highlights every row, marking the matches of `word`. Rows that are not
+    /// highlighted are left for [`Document::highlight_rows`].
     pub fn highlight(&mut self, word: Option<&str>) {
+        self.word = word.map(str::to_string);
+        if self.large {
+            return;
+        }
         for row in &mut self.rows {
-            row.highlight(self.file_type.highlighting_options(), word)
+            if row.is_highlighted() {
+                row.highlight(self.file_type.highlighting_options(), word);
+            }
         }
+        self.check_invariants();
+    }
+
+    /// Highlights the rows in `rows` that are not, such as those whose
+    /// highlighting was dropped by [`Document::fit_memory`].
+    pub fn highlight_rows(&mut self, rows: Range<usize>) {
+        if self.large {
+            return;
+        }
+        for row in self.rows.iter_mut().take(rows.end).skip(rows.start) {
+            if !row.is_highlighted() {
+                row.highlight(self.file_type.highlighting_options(), self.word.as_deref());
+            }
+        }
+    }
+
+    /// Returns `true` while the rows of the file as opened are not all
+    /// highlighted yet.
+    #[must_use]
+    pub fn is_highlighting(&self) -> bool {
+        self.pending_highlight.is_some()
+    }
+
+    /// Highlights the next `count` rows of the file as opened, and returns
+    /// whether any are left. Files are opened without highlighting, so the
+    /// first screen is drawn at once, with the rows on it highlighted by
+    /// [`Document::highlight_rows`], and the others are left for when the
+    /// user is idle.
+    pub fn highlight_pending(&mut self, count: usize) -> bool {
+        let Some((start, started)) = self.pending_highlight else {
+            return false;
+        };
+        let end = start.saturating_add(count);
+        self.highlight_rows(start..end);
+        if end < self.len() {
+            self.pending_highlight = Some((end, started));
+            return true;
+        }
+        self.pending_highlight = None;
+        log::debug!("Highlighted {} in {:?}", self.name(), started.elapsed());
+        false
+    }
+
+    /// Keeps the rows within about `budget` bytes of memory. The rows
+    /// farthest from `near` lose their highlighting first, then have their
+    /// text paged out to a temporary file, until the rest fit; rows in
+    /// `near` are kept as they are.
+    pub fn fit_memory(&mut self, budget: usize, near: Range<usize>) {
+        let mut used: usize = self.rows.iter().map(Row::memory).sum();
+        if used <= budget {
+            return;
+        }
+        let before = used;
+        for index in farthest_first(self.len(), &near) {
+            if used <= budget {
+                break;
+            }
+            #[allow(clippy::indexing_slicing)]
+            let row = &mut self.rows[index];
+            let memory = row.memory();
+            row.drop_highlighting();
+            used = used.saturating_sub(memory.saturating_sub(row.memory()));
+        }
+        if used > budget {
+            used = self.page_out(budget, used, &near);
+        }
+        log::debug!("Fit {} in {before} -> {used} bytes", self.name());
+    }
+
+    /// Pages out the text of the rows farthest from `near` until the `used`
+    /// bytes fit in `budget`, and returns how many are used then.
+    fn page_out(&mut self, budget: usize, mut used: usize, near: &Range<usize>) -> usize {
+        if self.pages.is_none() {
+            match PageFile::create() {
+                Ok(pages) => self.pages = Some(Arc::new(pages)),
+                Err(error) => {
+                    log::warn!("Could not page out rows: {error}");
+                    return used;
+                }
+            }
+        }
+        let Some(pages) = &self.pages else {
+            return used;
+        };
+        for index in farthest_first(self.rows.len(), near) {
+            if used <= budget {
+                break;
+            }
+            #[allow(clippy::indexing_slicing)]
+            let row = &mut self.rows[index];
+            let memory = row.memory();
+            if let Err(error) = row.page_out(pages) {
+                log::warn!("Could not page out rows: {error}");
+                break;
+            }
+            used = used.saturating_sub(memory.saturating_sub(row.memory()));
+        }
+        used
     }
 
     #[must_use]
     pub fn is_dirty(&self) -> bool {
-        self.dirty
+        self.dirty && !self.scratch
     }
 
     #[allow(clippy::indexing_slicing)]
@@ -163,7 +1055,7 @@ impl Document {
         };
         for _ in start..end {
             if let Some(row) = self.rows.get(position.y) {
-                if let Some(x) = row.find(&query, position.x, direction) {
+                if let Some(x) = row.find(query, position.x, direction) {
                     position.x = x;
                     return Some(position);
                 }
@@ -181,3 +1073,29 @@ impl Document {
         None
     }
 }
+
+/// Returns the indexes up to `len` outside `near`, farthest from it first.
+fn farthest_first(len: usize, near: &Range<usize>) -> impl Iterator<Item = usize> {
+    let Range { start, end } = *near;
+    let mut before = 0..start.min(len);
+    let mut after = end.min(len)..len;
+    std::iter::from_fn(move || {
+        let above = start.saturating_sub(before.start);
+        let below = after.end.saturating_sub(end);
+        if above >= below {
+            before.next().or_else(|| after.next_back())
+        } else {
+            after.next_back().or_else(|| before.next())
+        }
+    })
+}
+
+fn modified_time(filename: &str) -> Option<SystemTime> {
+    fs::metadata(filename)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+fn next_revision() -> u64 {
+    REVISIONS.fetch_add(1, Ordering::Relaxed)
+}
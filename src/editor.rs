@@ -1,15 +1,105 @@
+use crate::appearance::Background;
+use crate::backend::{Backend, CursorShape, Event};
+use crate::blame::{self, Annotation, Blame};
+use crate::breakpoints::SourceBreakpoints;
+use crate::calc;
+use crate::changes::{Change, Span};
+use crate::characters;
+use crate::cli::Options;
+use crate::command::{self, Argument, Invocation};
+use crate::comparison::{self, Comparison};
+use crate::config::{Config, Formatter, Project, Ruler};
+use crate::conflicts::{self, Conflict, Section};
+use crate::diff;
 use crate::document::Document;
-use crate::terminal::Terminal;
+use crate::error::Error;
+use crate::events::{self, EditorEvent, Subscriber};
+use crate::filetype::COMMIT_MESSAGE;
+use crate::git::{self, Base, GitStatus, Hunk};
+use crate::glob;
+use crate::help;
+use crate::ignore::Ignore;
+use crate::indent::Indent;
+use crate::jumplist::{Jump, JumpList};
+use crate::keymap::{self, Dispatch, Dispatcher, Keymap};
+use crate::lineedit::{History, LineEditor};
+use crate::lint::{Diagnostic, Lint, Severity};
+use crate::locale::{self, tr, Language};
+use crate::locations::{self, Location, LocationList};
+use crate::logger::Logger;
+use crate::lsp::{self, LanguageServer, Message, Request, RowsChanged};
+use crate::motion;
+use crate::outline::Outline;
+use crate::pane::Pane;
+use crate::paths;
+use crate::perf::{self, Frame};
+use crate::picker::{Picked, Picker};
+use crate::plugins::{Action, Context, Plugins};
+use crate::print::{self, Format};
+use crate::pty;
+use crate::recovery;
+use crate::registers::{self, Register, Registers};
+use crate::remap::Remapper;
+use crate::replace::ReplaceOptions;
+use crate::save::Save;
+use crate::screen::Screen;
+use crate::shell;
+use crate::snapshots;
+use crate::state::{BufferState, State};
+use crate::tags::{self, Address, Tag};
+use crate::tasks::{self, Scan};
+use crate::theme::{self, Theme};
+use crate::undo::Limits;
+use crate::view::{Split, View};
+use crate::views::{self, ViewState};
 use crate::Row;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::cell::Cell;
+use std::cmp;
+use std::collections::{BTreeMap, HashMap, VecDeque};
 use std::env;
-use std::io::stdout;
+use std::fs;
+use std::io::ErrorKind;
+use std::iter;
+use std::mem;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{Receiver, TryRecvError};
 use std::time::{Duration, Instant};
-use termion::{color, event::Key, raw::IntoRawMode};
+use termion::color::Rgb;
+use termion::event::Key;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
 
-const STATUS_FG_COLOR: color::Rgb = color::Rgb(63, 63, 63);
-const STATUS_BG_COLOR: color::Rgb = color::Rgb(239, 239, 239);
 const VERSION: &str = env!("CARGO_PKG_VERSION");
-const QUIT_TIMES: u8 = 3;
+const MAX_FRAMES_PER_SECOND: u64 = 60;
+const FRAME_INTERVAL: Duration = Duration::from_millis(1000 / MAX_FRAMES_PER_SECOND);
+/// How many times longer than the terminal takes to show a frame input is
+/// gathered before drawing the next, so a slow link is not flooded.
+const SLOW_OUTPUT_FACTOR: u32 = 4;
+/// The longest input is gathered for, however slow the terminal is.
+const MAX_FRAME_INTERVAL: Duration = Duration::from_millis(250);
+/// Most lines listing the contents of a directory while a path is typed.
+const MAX_PATH_HINTS: usize = 10;
+const PANE_MIN_HEIGHT: u16 = 3;
+const OUTLINE_WIDTH: usize = 30;
+const BACKGROUND_POLL_INTERVAL: Duration = Duration::from_millis(20);
+/// Rows highlighted at a time while waiting for input.
+const IDLE_HIGHLIGHT_ROWS: usize = 500;
+const MAX_LOGGED_MESSAGES: usize = 1000;
+const MAX_RECENT_COMMANDS: usize = 50;
+const MAX_TAG_DEPTH: usize = 50;
+/// The most times a count repeats a command, so a mistyped count cannot
+/// keep the editor busy for long.
+const MAX_COUNT: usize = 10_000;
+/// The mark that remembers where the cursor was when a file was last left.
+const LAST_POSITION_MARK: char = '"';
+const VIRTUAL_TEXT_GAP: &str = "  ";
+/// Columns a commit summary should fit in.
+const COMMIT_SUMMARY_WIDTH: usize = 50;
+/// The name of the buffer listing the tasks found in the project.
+const TASKS_NAME: &str = "[Tasks]";
 
 #[derive(PartialEq, Copy, Clone)]
 pub enum SearchDirection {
@@ -17,7 +107,29 @@ pub enum SearchDirection {
     Backward,
 }
 
-#[derive(Default, Clone)]
+#[derive(PartialEq, Copy, Clone)]
+enum Mode {
+    Insert,
+    Replace,
+}
+
+impl Mode {
+    fn cursor_shape(self) -> CursorShape {
+        match self {
+            Self::Insert => CursorShape::Bar,
+            Self::Replace => CursorShape::Underline,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Insert => "insert",
+            Self::Replace => "replace",
+        }
+    }
+}
+
+#[derive(Default, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Position {
     pub x: usize,
     pub y: usize,
@@ -37,142 +149,931 @@ impl StatusMessage {
     }
 }
 
+/// A document the user switched away from, with where they were in it.
+struct Buffer {
+    document: Document,
+    view: View,
+}
+
 pub struct Editor {
     should_quit: bool,
-    terminal: Terminal,
-    cursor_position: Position,
-    offset: Position,
+    terminal: Screen,
+    view: View,
+    /// A second view of the document, shown with the first.
+    split: Option<Split>,
     document: Document,
     status_message: StatusMessage,
-    quit_times: u8,
+    terminal_title: bool,
+    title: String,
+    autosave: bool,
+    keymap: Keymap,
+    /// Translates keys before they reach the keymap, or a prompt.
+    remapper: Remapper,
+    dispatcher: Dispatcher,
+    mode: Mode,
+    synchronized_output: bool,
+    line_numbers: bool,
+    tab_width: usize,
+    theme: &'static Theme,
+    /// The theme the configuration names, of which `theme` is the variant
+    /// for the background.
+    configured_theme: &'static Theme,
+    /// The background the theme is chosen for whatever the terminal says, if
+    /// it was set.
+    background_setting: Option<Background>,
+    /// The background the terminal or the system reported last.
+    background: Option<Background>,
+    jumps: JumpList,
+    /// The query and direction of the last search, which `find_next` and
+    /// `find_previous` repeat.
+    last_search: Option<(String, SearchDirection)>,
+    /// Buffers below the current one, most recent last.
+    hidden_buffers: Vec<Buffer>,
+    /// Earlier answers to each prompt, keyed by the prompt.
+    histories: HashMap<String, History>,
+    /// Column of the cursor in the message bar while a prompt is shown.
+    prompt_cursor: Option<usize>,
+    /// The files and directories a path being typed at a prompt could go on
+    /// to, shown above it.
+    prompt_hints: Vec<String>,
+    git: Option<GitStatus>,
+    /// The list shown while the user picks from one.
+    picker: Option<Picker>,
+    /// Number of scratch buffers opened so far, to tell them apart.
+    scratch_buffers: usize,
+    /// The last command lines run, oldest first, for crash reports.
+    recent_commands: VecDeque<String>,
+    /// Messages worth keeping after they left the message bar.
+    message_log: Vec<String>,
+    /// Where the selection started; it extends to the cursor.
+    selection: Option<Position>,
+    /// The shell shown below the document.
+    pane: Option<Pane>,
+    /// Whether keys go to the shell rather than the document.
+    pane_focused: bool,
+    /// Shell command run by `make`.
+    make: String,
+    /// Shell command printed documents are sent to.
+    print_command: String,
+    print_highlighting: bool,
+    /// Whether the yank history is kept between sessions.
+    persist_yank_history: bool,
+    /// Snapshots kept of each file.
+    file_history_size: usize,
+    /// Time between snapshots of unsaved changes, if they are taken.
+    file_history_interval: Option<Duration>,
+    last_snapshot: Instant,
+    /// Errors reported by the last build.
+    locations: LocationList,
+    /// Formatters keyed by file type or extension.
+    formatters: BTreeMap<String, Formatter>,
+    /// Columns marked beside the text, in order, with their own colors.
+    rulers: Vec<(usize, Option<Rgb>)>,
+    /// Rulers keyed by file type or extension.
+    file_type_rulers: BTreeMap<String, Vec<(usize, Option<Rgb>)>>,
+    /// Paths left out of completion.
+    ignore: Ignore,
+    /// The `.hecto.toml` whose settings are in effect.
+    project: Option<PathBuf>,
+    /// The file `project` was looked up for.
+    project_looked_up: Option<String>,
+    /// Linter commands keyed by file type or extension.
+    linters: BTreeMap<String, String>,
+    /// The linter running in the background, if any.
+    lint: Option<Lint>,
+    /// A large document being saved in the background, if any.
+    saving: Option<Save>,
+    /// What the last linter run found, in every file it checked.
+    diagnostics: Vec<Diagnostic>,
+    /// Language server commands keyed by file type or extension.
+    language_servers: BTreeMap<String, String>,
+    /// Running language servers keyed by their command, or `None` for those
+    /// that failed, so they are not started over and over.
+    servers: HashMap<String, Option<LanguageServer>>,
+    /// The changes to the file named here since the given revision, for
+    /// language servers that take only the rows that changed.
+    server_changes: Option<(Option<String>, u64, Receiver<Change>)>,
+    /// Where each tag jump started, most recent last.
+    tag_stack: Vec<Jump>,
+    /// The symbols shown beside the document.
+    outline: Option<Outline>,
+    /// Whether keys move through the outline rather than the document.
+    outline_focused: bool,
+    /// Two files shown side by side instead of the document, as asked for
+    /// with `--diff`.
+    comparison: Option<Comparison>,
+    /// The merge conflicts in the document, as of `conflicts_revision`.
+    conflicts: Vec<Conflict>,
+    conflicts_revision: u64,
+    /// The search for `TODO` and `FIXME` comments running in the background,
+    /// if any.
+    task_scan: Option<Scan>,
+    /// The task on each row of the task list, if there is one.
+    tasks: Vec<Option<Location>>,
+    /// Whether the cursor's line is followed by who last changed it.
+    blame_inline: bool,
+    /// `git blame` running in the background, if any.
+    blame: Option<Blame>,
+    /// What the last blame found, with the document revision it was for.
+    annotations: Option<(u64, Vec<Annotation>)>,
+    /// Whether to show the cursor line's annotation once blaming finishes.
+    blame_requested: bool,
+    /// The changes staged for the commit whose message is being edited.
+    commit_summary: Option<Vec<String>>,
+    /// How much undo history is kept for a file between sessions.
+    undo_limits: Limits,
+    registers: Registers,
+    /// The register named for the next yank, cut or paste.
+    register: Option<char>,
+    /// The count typed so far for the next command.
+    count: Option<usize>,
+    syntax_highlighting: bool,
+    /// The configuration file given on the command line, read instead of
+    /// the usual one.
+    config_path: Option<PathBuf>,
+    /// Command lines to run once the editor has started.
+    startup_commands: Vec<String>,
+    plugins: Plugins,
+    /// Whether only the text is shown, centered, for writing undisturbed.
+    zen: bool,
+    /// Columns the text takes in zen mode.
+    zen_width: usize,
+    /// Whether to draw for screen readers.
+    accessible: bool,
+    /// Whether the terminal has focus, as last reported by it.
+    has_focus: bool,
+    /// Whether word motions stop inside `camelCase` and `snake_case` names.
+    subword: bool,
+    /// Whether a new line starts as indented as the one above.
+    auto_indent: bool,
+    /// Bytes above which a file is opened as a large document.
+    large_file_size: u64,
+    /// Bytes of memory the rows of the document may take, or zero.
+    memory_budget: usize,
+    /// States of undo history each buffer keeps, and bytes the undo history
+    /// of all buffers may take, or zero.
+    undo_depth: usize,
+    undo_memory: usize,
+    /// Whether the timings of the last frame are shown over the text.
+    perf_overlay: bool,
+    /// The frame being measured, and the one measured before it.
+    frame: Frame,
+    last_frame: Frame,
+    /// Allocations counted when the last frame was measured.
+    allocations_seen: u64,
+    /// How long the terminal takes to take in a frame, averaged over the
+    /// last few.
+    output_time: Cell<Duration>,
+    /// Whether keys came faster than frames are drawn, see
+    /// [`Editor::hurried`].
+    burst: bool,
 }
 
 impl Editor {
-    pub fn run(&mut self) {
-        let _stdout = stdout().into_raw_mode().unwrap();
+    #[must_use]
+    pub fn new(terminal: Box<dyn Backend>, document: Document) -> Self {
+        Self {
+            should_quit: false,
+            terminal: Screen::new(terminal),
+            document,
+            view: View::default(),
+            split: None,
+            status_message: StatusMessage::from(String::new()),
+            terminal_title: true,
+            title: String::new(),
+            autosave: false,
+            keymap: Keymap::default(),
+            remapper: Remapper::default(),
+            dispatcher: Dispatcher::default(),
+            mode: Mode::Insert,
+            synchronized_output: true,
+            line_numbers: false,
+            tab_width: 4,
+            theme: Theme::builtin(),
+            configured_theme: Theme::builtin(),
+            background_setting: None,
+            background: None,
+            jumps: JumpList::default(),
+            last_search: None,
+            hidden_buffers: Vec::new(),
+            histories: HashMap::new(),
+            prompt_cursor: None,
+            prompt_hints: Vec::new(),
+            git: None,
+            picker: None,
+            scratch_buffers: 0,
+            recent_commands: VecDeque::new(),
+            message_log: Vec::new(),
+            selection: None,
+            pane: None,
+            pane_focused: false,
+            make: "make".to_string(),
+            print_command: "lpr".to_string(),
+            print_highlighting: true,
+            persist_yank_history: false,
+            file_history_size: 20,
+            file_history_interval: Some(Duration::from_secs(300)),
+            last_snapshot: Instant::now(),
+            locations: LocationList::default(),
+            formatters: Config::default().formatters,
+            rulers: Vec::new(),
+            ignore: Ignore::default(),
+            project: None,
+            project_looked_up: None,
+            file_type_rulers: Config::default()
+                .file_type_rulers
+                .iter()
+                .map(|(key, rulers)| (key.clone(), resolve_rulers(rulers, &mut Vec::new())))
+                .collect(),
+            linters: BTreeMap::new(),
+            lint: None,
+            saving: None,
+            diagnostics: Vec::new(),
+            language_servers: BTreeMap::new(),
+            servers: HashMap::new(),
+            server_changes: None,
+            tag_stack: Vec::new(),
+            outline: None,
+            outline_focused: false,
+            comparison: None,
+            conflicts: Vec::new(),
+            conflicts_revision: 0,
+            task_scan: None,
+            tasks: Vec::new(),
+            blame_inline: false,
+            blame: None,
+            annotations: None,
+            blame_requested: false,
+            commit_summary: None,
+            undo_limits: Config::default().undo_limits(),
+            registers: Registers::default(),
+            register: None,
+            count: None,
+            syntax_highlighting: true,
+            config_path: None,
+            startup_commands: Vec::new(),
+            plugins: Plugins::default(),
+            zen: false,
+            accessible: false,
+            has_focus: true,
+            subword: false,
+            auto_indent: false,
+            zen_width: Config::default().zen_width,
+            large_file_size: Config::default().large_file_size.saturating_mul(1 << 20),
+            memory_budget: Config::default().memory_budget.saturating_mul(1 << 20),
+            undo_depth: Config::default().undo_depth,
+            undo_memory: Config::default().undo_memory.saturating_mul(1 << 20),
+            perf_overlay: false,
+            frame: Frame::default(),
+            last_frame: Frame::default(),
+            allocations_seen: 0,
+            output_time: Cell::new(Duration::ZERO),
+            burst: false,
+        }
+    }
 
-        loop {
-            if let Err(error) = self.refresh_screen() {
-                die(&error);
+    /// Creates an editor drawing on `terminal` as the command line asked.
+    #[must_use]
+    pub fn with_options(terminal: Box<dyn Backend>, options: Options) -> Self {
+        locale::set_language(Language::from_env());
+        let mut editor = Self::new(terminal, Document::default());
+        editor.config_path = options.config;
+        editor.load_plugins();
+        // The configuration says which files count as large, and the language
+        // the help line is shown in.
+        editor.load_config();
+        if editor.status_message.text.is_empty() {
+            editor.status_message = StatusMessage::from(tr!(
+                "HELP: F1 = help | Ctrl-F = find | Ctrl-S = save | Ctrl-P = command | Ctrl-Q = quit"
+            ));
+        }
+        if let Some(file_name) = options.files.first() {
+            match editor.open_document(file_name) {
+                Ok(mut document) => {
+                    if options.read_only {
+                        document.set_read_only();
+                    }
+                    editor.document = document;
+                    editor.publish(&EditorEvent::BufferOpened(file_name.clone()));
+                }
+                Err(_) => {
+                    editor.status_message = StatusMessage::from(tr!(
+                        "ERR: Could not open file: {file_name}",
+                        file_name
+                    ));
+                }
+            }
+        }
+        editor.update_project();
+        if options.diff {
+            editor.compare(&options.files);
+        }
+        if options.no_highlight {
+            editor.set_flag("highlight", false);
+        }
+        if let Some(line) = options.line {
+            editor.startup_commands.push(format!("goto {line}"));
+        }
+        editor.startup_commands.extend(options.commands);
+        editor
+    }
+
+    /// Runs the editor until the user quits or the backend runs out of input.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if input cannot be read or the screen cannot be
+    /// drawn. The terminal is put back as it was first.
+    pub fn run(&mut self) -> Result<(), Error> {
+        self.terminal.enable_focus_reporting();
+        self.terminal.enable_bracketed_paste();
+        self.terminal.enable_appearance_reporting();
+        if self.background_setting.is_none() {
+            self.terminal.query_background();
+        }
+        self.terminal.set_cursor_shape(self.mode.cursor_shape());
+        self.refresh_git();
+        self.update_commit_summary();
+        if let Some(file_name) = self.document.file_name.clone() {
+            if self.document.is_large() && !self.confirm_large_file(&file_name) {
+                self.document = Document::default();
+                self.refresh_git();
             }
+        }
+        self.restore_cursor();
+        self.offer_to_strip_ansi();
+        self.update_conflicts();
+        for command in std::mem::take(&mut self.startup_commands) {
+            self.execute(&command);
+        }
+
+        let result = self.event_loop();
+        // Quitting must not cut a file short.
+        self.wait_for_save();
+        self.remember_cursors();
+        if self.persist_yank_history {
+            self.registers.store_history();
+        }
+        self.terminal.disable_focus_reporting();
+        self.terminal.disable_bracketed_paste();
+        self.terminal.disable_appearance_reporting();
+        self.terminal.reset_cursor_shape();
+        if !self.title.is_empty() {
+            self.terminal.restore_title();
+        }
+        let _ = self.terminal.flush();
+        result
+    }
+
+    fn event_loop(&mut self) -> Result<(), Error> {
+        loop {
+            self.update_title();
+            let frame_started = Instant::now();
+            self.prepare_rows();
+            self.refresh_screen()?;
+            self.end_frame(frame_started.elapsed());
             if self.should_quit {
-                break;
+                return Ok(());
             }
-            if let Err(error) = self.process_keypress() {
-                die(&error);
+            match self.process_events(frame_started) {
+                Err(error) if error.kind() == ErrorKind::UnexpectedEof => self.should_quit = true,
+                result => result?,
+            }
+        }
+    }
+
+    /// Finishes measuring the frame that took `render` to draw.
+    fn end_frame(&mut self, render: Duration) {
+        let allocations = perf::allocations();
+        self.frame.render = render;
+        self.frame.output = self.output_time.get();
+        self.frame.highlighting = perf::take_highlighting();
+        self.frame.allocations = allocations.saturating_sub(self.allocations_seen);
+        self.allocations_seen = allocations;
+        self.last_frame = std::mem::take(&mut self.frame);
+    }
+
+    fn update_title(&mut self) {
+        if !self.terminal_title {
+            return;
+        }
+        let file_name = self.document.name();
+        let modified_indicator = if self.document.is_dirty() { " +" } else { "" };
+        let title = format!("{file_name}{modified_indicator} - hecto");
+        if title != self.title {
+            // The user's title is only saved once we first replace it.
+            if self.title.is_empty() {
+                self.terminal.save_title();
             }
+            self.terminal.set_title(&title);
+            self.title = title;
+        }
+    }
+
+    /// Highlights the rows about to be drawn that are not, and keeps the
+    /// document within the memory budget, sparing the rows a screen away.
+    fn prepare_rows(&mut self) {
+        let height = self.text_height();
+        let start = self.view.offset.y;
+        self.document
+            .highlight_rows(start..start.saturating_add(height));
+        if let Some(split) = &self.split {
+            let start = split.view.offset.y;
+            self.document
+                .highlight_rows(start..start.saturating_add(self.text_area_height()));
+        }
+        if self.memory_budget > 0 {
+            let near = start.saturating_sub(height)..start.saturating_add(height.saturating_mul(2));
+            self.document.fit_memory(self.memory_budget, near);
         }
     }
 
     fn refresh_screen(&self) -> Result<(), std::io::Error> {
-        Terminal::cursor_hide();
-        Terminal::cursor_position(&Position { x: 0, y: 0 });
+        self.terminal.begin_frame();
+        self.terminal.cursor_position(&Position { x: 0, y: 0 });
         if self.should_quit {
-            Terminal::clear_screen();
-            println!("Goodbye.\r");
+            self.terminal.clear_screen();
+            self.terminal.writeln("Goodbye.");
+        } else if let Some(comparison) = &self.comparison {
+            self.draw_comparison(comparison);
+            self.draw_comparison_status(comparison);
+            self.draw_message_bar();
+            self.terminal.cursor_position(&Position::default());
         } else {
             self.draw_rows();
             self.draw_status_bar();
             self.draw_message_bar();
-            Terminal::cursor_position(&Position {
-                x: self.cursor_position.x.saturating_sub(self.offset.x),
-                y: self.cursor_position.y.saturating_sub(self.offset.y),
+            if self.perf_overlay {
+                self.draw_perf_overlay();
+            }
+            self.draw_cursor();
+        }
+        self.terminal.end_frame(self.synchronized_output);
+        let started = Instant::now();
+        let result = self.terminal.flush();
+        let average = self.output_time.get();
+        self.output_time
+            .set((average.saturating_mul(3) + started.elapsed()) / 4);
+        result
+    }
+
+    /// Returns how long to gather input before drawing the next frame: a
+    /// frame at the highest rate, or longer while the terminal is slow to
+    /// take output, as over a distant connection.
+    fn frame_interval(&self) -> Duration {
+        let slow = self.output_time.get().saturating_mul(SLOW_OUTPUT_FACTOR);
+        FRAME_INTERVAL.max(slow).min(MAX_FRAME_INTERVAL)
+    }
+
+    /// Returns whether keys come faster than frames are drawn, or the
+    /// terminal is slow to take them, so frames leave out the cursor line
+    /// and search matches to keep up.
+    fn hurried(&self) -> bool {
+        self.burst || self.frame_interval() > FRAME_INTERVAL
+    }
+
+    fn draw_cursor(&self) {
+        if let Some(x) = self.prompt_cursor {
+            let size = self.terminal.size();
+            self.terminal.cursor_position(&Position {
+                x: cmp::min(x, (size.width as usize).saturating_sub(1)),
+                y: (size.height as usize).saturating_add(1),
+            });
+        } else if let Some(pane) = self.pane.as_ref().filter(|_| self.pane_focused) {
+            let width = self.terminal.size().width as usize;
+            let lines = pane.lines(self.pane_height().saturating_sub(1)).len();
+            self.terminal.cursor_position(&Position {
+                x: cmp::min(pane.cursor_column(), width.saturating_sub(1)),
+                y: self.text_area_height().saturating_add(lines),
+            });
+        } else {
+            self.terminal.cursor_position(&Position {
+                x: self
+                    .cursor_render_x()
+                    .saturating_sub(self.view.offset.x)
+                    .saturating_add(self.gutter_width()),
+                y: self
+                    .view
+                    .cursor
+                    .y
+                    .saturating_sub(self.view.offset.y)
+                    .saturating_add(self.text_top()),
             });
         }
-        Terminal::cursor_show();
-        Terminal::flush()
     }
 
     fn draw_status_bar(&self) {
+        if self.zen {
+            self.terminal.clear_current_line();
+            self.terminal.writeln("");
+            return;
+        }
         let mut status;
-        let width = self.terminal.size().width as usize;
         let modified_indicator = if self.document.is_dirty() {
-            " (modified)"
+            tr!(" (modified)")
         } else {
-            ""
+            String::new()
         };
-        let mut file_name = "[No name]".to_string();
-        if let Some(name) = &self.document.file_name {
-            file_name = name.clone();
-            file_name.truncate(20);
-        }
-        //status = format!("{} - {} lines", file_name, self.document.len());
-        status = format!(
-            "{} - {} lines{}",
+        let mode_indicator = match self.mode {
+            Mode::Insert => String::new(),
+            Mode::Replace => tr!("REPLACE | "),
+        };
+        let file_name: String = self.document.name().chars().take(20).collect();
+        status = tr!(
+            "{file_name} - {lines} lines",
             file_name,
-            self.document.len(),
-            modified_indicator
+            lines = self.document.len()
         );
+        status.push_str(&modified_indicator);
 
-        let line_indicator = format!(
-            "{} | {}/{}",
-            self.document.file_type(),
-            self.cursor_position.y.saturating_add(1),
-            self.document.len()
-        );
-        let len = status.len() + line_indicator.len();
+        let git_indicator = self.git.as_ref().map_or(String::new(), |git| {
+            let dirty = if git.dirty { "*" } else { "" };
+            format!("{}{dirty} | ", git.branch)
+        });
+        let context = Context {
+            document: &self.document,
+            cursor: &self.view.cursor,
+        };
+        let segments: String = self
+            .plugins
+            .segments(&context)
+            .into_iter()
+            .map(|segment| format!("{segment} | "))
+            .collect();
+        // The cursor's line is left out for screen readers, which would read
+        // the bar out again at every move; the cursor tells where it is.
+        let line_indicator = |indent: &str| {
+            if self.accessible {
+                format!(
+                    "{segments}{mode_indicator}{git_indicator}{indent}{}",
+                    self.document.file_type()
+                )
+            } else {
+                format!(
+                    "{segments}{}{mode_indicator}{git_indicator}{indent}{} | {}/{}",
+                    self.selection_indicator(),
+                    self.document.file_type(),
+                    self.view.cursor.y.saturating_add(1),
+                    self.document.len()
+                )
+            }
+        };
+        // The indentation is only shown once it was told from the file, and
+        // is the first to go when the bar is too narrow.
+        let indent = self.document.indent().map_or(String::new(), |indent| {
+            let indent = match indent {
+                Indent::Tabs => tr!("tabs"),
+                Indent::Spaces(width) => tr!("{width} spaces", width),
+            };
+            format!("{indent} | ")
+        });
+        let mut indicator = line_indicator(&indent);
+        let width = self.terminal.size().width as usize;
+        if status.chars().count() + indicator.chars().count() > width {
+            indicator = line_indicator("");
+        }
+        self.write_status_bar(status, &indicator);
+    }
+
+    /// Returns how many lines and characters are selected, for the status
+    /// bar, or nothing if there is no selection.
+    fn selection_indicator(&self) -> String {
+        let Some((start, end)) = self.selection_range() else {
+            return String::new();
+        };
+        let lines = end.y.saturating_sub(start.y).saturating_add(1);
+        let characters = self
+            .document
+            .text_range(&start, &end)
+            .graphemes(true)
+            .count();
+        let size = match (lines, characters) {
+            (1, 1) => tr!("1 line, 1 character selected"),
+            (1, _) => tr!("1 line, {characters} characters selected", characters),
+            (_, _) => tr!(
+                "{lines} lines, {characters} characters selected",
+                lines,
+                characters
+            ),
+        };
+        format!("{size} | ")
+    }
+
+    /// Draws the status bar with `status` on the left and `indicator` on the
+    /// right, or after it as one plain line for screen readers.
+    fn write_status_bar(&self, mut status: String, indicator: &str) {
+        if self.accessible {
+            self.terminal.writeln(&format!("{status} | {indicator}"));
+            return;
+        }
+        let width = self.terminal.size().width as usize;
+        let len = status.chars().count() + indicator.chars().count();
         if width > len {
             status.push_str(&" ".repeat(width - len));
         }
-        status = format!("{status}{line_indicator}");
-        status.truncate(width);
-        Terminal::set_bg_color(STATUS_BG_COLOR);
-        Terminal::set_fg_color(STATUS_FG_COLOR);
-        println!("{status}\r");
-        Terminal::reset_fg_color();
-        Terminal::reset_bg_color();
+        status = format!("{status}{indicator}").chars().take(width).collect();
+        self.terminal.set_bg_color(self.theme.status_bg);
+        self.terminal.set_fg_color(self.theme.status_fg);
+        self.terminal.writeln(&status);
+        self.terminal.reset_fg_color();
+        self.terminal.reset_bg_color();
+    }
+
+    /// Draws the two files being compared side by side, each line after its
+    /// number. Lines only in one of them take the color of removed or added
+    /// ones, and the words that differ between two lines across from each
+    /// other are marked.
+    fn draw_comparison(&self, comparison: &Comparison) {
+        let width = self.terminal.size().width as usize;
+        let left_width = width.saturating_sub(1) / 2;
+        let number_width = comparison.rows().len().to_string().len();
+        for terminal_row in 0..self.text_area_height() {
+            self.terminal.clear_current_line();
+            let Some(pair) = comparison
+                .rows()
+                .get(comparison.offset.saturating_add(terminal_row))
+            else {
+                self.terminal
+                    .writeln(if self.accessible { "" } else { "~" });
+                continue;
+            };
+            let same = pair.is_same();
+            let drawn = self.draw_compared_line(
+                pair.left.as_ref(),
+                (!same).then_some(self.theme.removed),
+                left_width,
+                number_width,
+            );
+            self.terminal
+                .write(&" ".repeat(left_width.saturating_sub(drawn)));
+            self.terminal.set_fg_color(self.theme.line_number);
+            self.terminal.write("│");
+            self.terminal.reset_fg_color();
+            self.draw_compared_line(
+                pair.right.as_ref(),
+                (!same).then_some(self.theme.added),
+                width.saturating_sub(left_width).saturating_sub(1),
+                number_width,
+            );
+            self.terminal.writeln("");
+        }
+    }
+
+    /// Draws `line` of a file being compared in up to `width` columns, in
+    /// `color` if given, and returns the number of columns drawn.
+    fn draw_compared_line(
+        &self,
+        line: Option<&comparison::Line>,
+        color: Option<Rgb>,
+        width: usize,
+        number_width: usize,
+    ) -> usize {
+        let Some(line) = line else {
+            return 0;
+        };
+        let number: String = format!("{:>number_width$} ", line.number)
+            .chars()
+            .take(width)
+            .collect();
+        self.terminal.set_fg_color(self.theme.line_number);
+        self.terminal.write(&number);
+        self.terminal.reset_fg_color();
+        let mut column = number.chars().count();
+        let text_start = column;
+        if let Some(color) = color {
+            self.terminal.set_fg_color(color);
+        }
+        let mut marked = false;
+        for (index, grapheme) in line.text.grapheme_indices(true) {
+            let shown = if grapheme == "\t" {
+                let tab_width = self.tab_width.max(1);
+                " ".repeat(tab_width - column.saturating_sub(text_start) % tab_width)
+            } else {
+                grapheme.to_string()
+            };
+            let next = column.saturating_add(shown.chars().count());
+            if next > width {
+                break;
+            }
+            let changed = line.changed.iter().any(|range| range.contains(&index));
+            if changed != marked {
+                if changed {
+                    self.terminal.set_bg_color(self.theme.changed_bg);
+                } else {
+                    self.terminal.reset_bg_color();
+                }
+                marked = changed;
+            }
+            self.terminal.write(&shown);
+            column = next;
+        }
+        if marked {
+            self.terminal.reset_bg_color();
+        }
+        if color.is_some() {
+            self.terminal.reset_fg_color();
+        }
+        column
+    }
+
+    fn draw_comparison_status(&self, comparison: &Comparison) {
+        let names = format!("{} ↔ {}", comparison.left_name, comparison.right_name);
+        let position = match comparison.difference_count() {
+            0 => tr!("No differences"),
+            count => tr!(
+                "Difference {index}/{count}",
+                index = comparison.selected().saturating_add(1),
+                count
+            ),
+        };
+        self.write_status_bar(names, &position);
     }
 
     fn draw_message_bar(&self) {
-        Terminal::clear_current_line();
+        self.terminal.clear_current_line();
         let message = &self.status_message;
         if message.time.elapsed() < Duration::new(5, 0) {
             let mut text = message.text.clone();
             text.truncate(self.terminal.size().width as usize);
-            print!("{text}");
+            self.terminal.write(&text);
         }
     }
 
     fn save(&mut self) {
-        if self.document.file_name.is_none() {
-            let new_name = self.prompt("Save as: ", |_, _, _| {}).unwrap_or(None);
+        self.save_as(None);
+    }
+
+    /// Saves the document, under `new_name` if one is given. The document
+    /// only takes the new name once it is certain to be written.
+    fn save_as(&mut self, new_name: Option<String>) {
+        if self.document.is_read_only() {
+            self.status_message = StatusMessage::from(tr!("This buffer is read-only."));
+            return;
+        }
+        if let Some(save) = &self.saving {
+            self.status_message = StatusMessage::from(tr!(
+                "ERR: Still saving {file_name}.",
+                file_name = save.file_name
+            ));
+            return;
+        }
+        if new_name.is_some() {
+            self.document.file_name = new_name;
+        } else if self.document.file_name.is_none() {
+            let new_name = self
+                .prompt(
+                    &tr!("Save as: "),
+                    Some(command::complete_path),
+                    |_, _, _| {},
+                )
+                .unwrap_or(None);
             if new_name.is_none() {
-                self.status_message = StatusMessage::from("Save aborted.".to_string());
+                self.status_message = StatusMessage::from(tr!("Save aborted."));
                 return;
             }
-            self.document.file_name = new_name;
+            self.document.file_name = new_name.as_deref().map(paths::expand);
+        }
+        let file_name = self.document.file_name.clone().unwrap_or_default();
+        self.publish(&EditorEvent::BeforeSave(file_name.clone()));
+        if self.large_file_size > 0 && self.document.byte_len() > self.large_file_size {
+            self.status_message = StatusMessage::from(match self.document.save_in_background() {
+                Ok(saving) => {
+                    self.saving = saving;
+                    tr!("Saving {file_name}: {percent}%", file_name, percent = 0)
+                }
+                Err(error) => tr!(
+                    "ERR: Could not write {file_name}: {error}",
+                    file_name,
+                    error
+                ),
+            });
+            return;
+        }
+        match self.document.save() {
+            Ok(()) => {
+                self.status_message = StatusMessage::from(tr!("File saved successfully."));
+                self.publish(&EditorEvent::AfterSave(file_name));
+            }
+            Err(error) => {
+                self.status_message = StatusMessage::from(tr!(
+                    "ERR: Could not write {file_name}: {error}",
+                    file_name,
+                    error
+                ));
+            }
         }
+    }
 
-        if self.document.save().is_ok() {
-            self.status_message = StatusMessage::from("File saved successfull".to_string());
-        } else {
-            self.status_message = StatusMessage::from("Error writing file!".to_string());
+    /// Reports how saving in the background goes, and takes note once it is
+    /// done. Returns whether there is anything new to show.
+    fn update_save(&mut self) -> bool {
+        let Some(save) = &mut self.saving else {
+            return false;
+        };
+        let Some(result) = save.poll() else {
+            let percent = save.percent();
+            if percent == save.reported {
+                return false;
+            }
+            save.reported = percent;
+            self.status_message = StatusMessage::from(tr!(
+                "Saving {file_name}: {percent}%",
+                file_name = save.file_name,
+                percent
+            ));
+            return true;
+        };
+        self.finish_save(result);
+        true
+    }
+
+    /// Waits for the document being saved in the background, if any.
+    fn wait_for_save(&mut self) {
+        if let Some(save) = &self.saving {
+            let result = save.wait();
+            self.finish_save(result);
+        }
+    }
+
+    fn finish_save(&mut self, result: std::io::Result<()>) {
+        let Some(save) = self.saving.take() else {
+            return;
+        };
+        if result.is_err() {
+            self.status_message = StatusMessage::from(tr!("Error writing file!"));
+            return;
+        }
+        self.status_message = StatusMessage::from(tr!("File saved successfully."));
+        if self.document.file_name.as_deref() == Some(save.file_name.as_str()) {
+            self.document.finish_save(&save);
+            self.publish(&EditorEvent::AfterSave(save.file_name));
+        } else if let Some(buffer) = self
+            .hidden_buffers
+            .iter_mut()
+            .find(|buffer| buffer.document.file_name.as_deref() == Some(save.file_name.as_str()))
+        {
+            // What follows a save works on the current document, so it waits
+            // for the next save of this one.
+            buffer.document.finish_save(&save);
+        }
+    }
+
+    /// Tells the subscribers of `event` about it.
+    fn publish(&mut self, event: &EditorEvent) {
+        for subscriber in events::subscribers(event.kind()) {
+            match subscriber {
+                Subscriber::Formatter => self.format_on_save(),
+                Subscriber::UndoHistory => self.document.store_undo(self.undo_limits),
+                Subscriber::FileHistory => self.take_snapshot(),
+                Subscriber::Linter => {
+                    // A linter still checking an older version is left to
+                    // finish unheard.
+                    if let Some(command) = self.for_file_type(&self.linters) {
+                        self.lint = Some(Lint::start(command));
+                    }
+                }
+                Subscriber::Git => self.refresh_git(),
+                Subscriber::CursorShape => {
+                    self.terminal.set_cursor_shape(self.mode.cursor_shape());
+                }
+                Subscriber::Plugins => self.emit(event.kind().name(), &event.argument()),
+            }
+        }
+    }
+
+    fn format_on_save(&mut self) {
+        if self.formatter().is_some_and(|formatter| formatter.on_save) {
+            let before = self.view.cursor.clone();
+            self.run_formatter(0, self.document.len().saturating_sub(1));
+            // Formatting is undone on its own, and is part of the history
+            // kept with the file.
+            self.document
+                .end_undo_group(before, self.view.cursor.clone(), None);
         }
     }
 
     fn search(&mut self) {
-        let old_position = self.cursor_position.clone();
+        let old_position = self.view.cursor.clone();
         let mut direction = SearchDirection::Forward;
         let query = self
             .prompt(
-                "Search (ESC to cancel, Arrows to navigate): ",
+                &tr!("Search (ESC to cancel, Ctrl-N/Ctrl-P to navigate): "),
+                None,
                 |editor, key, query| {
                     let mut moved = false;
                     match key {
-                        Key::Right | Key::Down => {
+                        Key::Ctrl('n') => {
                             direction = SearchDirection::Forward;
                             editor.move_cursor(Key::Right);
                             moved = true;
                         }
-                        Key::Left | Key::Up => direction = SearchDirection::Backward,
+                        Key::Ctrl('p') => direction = SearchDirection::Backward,
                         _ => direction = SearchDirection::Forward,
                     }
                     if let Some(position) =
-                        editor
-                            .document
-                            .find(&query, &editor.cursor_position, direction)
+                        editor.document.find(query, &editor.view.cursor, direction)
                     {
-                        editor.cursor_position = position;
+                        editor.view.cursor = position;
                         editor.scroll();
                     } else if moved {
                         editor.move_cursor(Key::Left)
@@ -181,247 +1082,4771 @@ impl Editor {
                 },
             )
             .unwrap_or(None);
-        if query.is_none() {
-            self.cursor_position = old_position;
-            self.scroll();
+        match query {
+            None => {
+                self.view.cursor = old_position;
+                self.scroll();
+            }
+            Some(query) => {
+                if self.view.cursor != old_position {
+                    self.jumps.push(Jump {
+                        file_name: self.document.file_name.clone(),
+                        position: old_position,
+                    });
+                }
+                if !query.is_empty() {
+                    self.last_search = Some((query, direction));
+                }
+            }
         }
         self.document.highlight(None);
     }
 
-    fn process_keypress(&mut self) -> Result<(), std::io::Error> {
-        let pressed_key = Terminal::read_key()?;
+    /// Goes to the next match of the last search, in the direction it was
+    /// made or the other way with `reverse`, wrapping around at either end
+    /// of the document. Tells which match it is of how many.
+    fn find_again(&mut self, reverse: bool) {
+        let Some((query, direction)) = self.last_search.clone() else {
+            self.status_message = StatusMessage::from(tr!("No previous search."));
+            return;
+        };
+        let direction = match (direction, reverse) {
+            (SearchDirection::Forward, false) | (SearchDirection::Backward, true) => {
+                SearchDirection::Forward
+            }
+            _ => SearchDirection::Backward,
+        };
+        let cursor = &self.view.cursor;
+        let from = match direction {
+            SearchDirection::Forward => Position {
+                x: cursor.x.saturating_add(1),
+                y: cursor.y,
+            },
+            SearchDirection::Backward => cursor.clone(),
+        };
+        let mut wrapped = false;
+        let found = self.document.find(&query, &from, direction).or_else(|| {
+            wrapped = true;
+            let last = self.document.len().saturating_sub(1);
+            let around = match direction {
+                SearchDirection::Forward => Position::default(),
+                SearchDirection::Backward => Position {
+                    x: self.document.row(last).map_or(0, Row::len),
+                    y: last,
+                },
+            };
+            self.document.find(&query, &around, direction)
+        });
+        let Some(position) = found else {
+            self.status_message = StatusMessage::from(tr!("No match for '{query}'.", query));
+            return;
+        };
+        let jump = self.current_jump();
+        self.jumps.push(jump);
+        self.view.cursor = position;
+        self.scroll();
+        let (index, total) = self.match_index(&query);
+        self.status_message = StatusMessage::from(if wrapped {
+            tr!("Match {index} of {total}, wrapped around.", index, total)
+        } else {
+            tr!("Match {index} of {total}.", index, total)
+        });
+    }
 
-        match pressed_key {
-            Key::Ctrl('q') => {
-                if self.quit_times > 0 && self.document.is_dirty() {
-                    self.status_message = StatusMessage::from(format!(
-                        "WARNING! File has unsaved changes. Press Ctrl-Q {} more times to quit.",
-                        self.quit_times
-                    ));
-                    self.quit_times -= 1;
-                    return Ok(());
+    /// Returns which match of `query` the cursor is on, counting from one,
+    /// and how many there are, found as search highlights them.
+    fn match_index(&self, query: &str) -> (usize, usize) {
+        let step = query.graphemes(true).count().max(1);
+        let mut index = 0;
+        let mut total = 0_usize;
+        for y in 0..self.document.len() {
+            let Some(row) = self.document.row(y) else {
+                break;
+            };
+            let mut x = 0;
+            while let Some(found) = row.find(query, x, SearchDirection::Forward) {
+                total = total.saturating_add(1);
+                if (y, found) <= (self.view.cursor.y, self.view.cursor.x) {
+                    index = total;
                 }
-                self.should_quit = true;
+                x = found.saturating_add(step);
             }
-            Key::Ctrl('s') => self.save(),
-            Key::Ctrl('f') => self.search(),
-            Key::Char(c) => {
-                self.document.insert(&self.cursor_position, c);
-                self.move_cursor(Key::Right);
+        }
+        (index, total)
+    }
+
+    /// Handles the next event, then keeps handling whatever else arrives
+    /// before the next frame is due, so bursts of input (key auto-repeat,
+    /// unbracketed pastes) are painted once instead of once per key. Frames
+    /// come further apart while the terminal is slow to take them.
+    ///
+    /// While the terminal pane, a linter or a language server runs, their
+    /// output counts as an event too.
+    fn process_events(&mut self, frame_started: Instant) -> Result<(), std::io::Error> {
+        let event = loop {
+            // A chord being typed is given up on after a while, even if no
+            // other key comes.
+            if !self.has_background_work()
+                && !self.document.is_highlighting()
+                && self.dispatcher.pending().is_empty()
+            {
+                break self.terminal.read_event()?;
             }
-            Key::Delete => self.document.delete(&self.cursor_position),
-            Key::Backspace => {
-                if self.cursor_position.x > 0 || self.cursor_position.y > 0 {
-                    self.move_cursor(Key::Left);
-                    self.document.delete(&self.cursor_position);
+            // Rows left to highlight only wait for input to be handled.
+            let timeout = if self.document.is_highlighting() {
+                Duration::ZERO
+            } else {
+                BACKGROUND_POLL_INTERVAL
+            };
+            if let Some(event) = self.terminal.poll_event(timeout)? {
+                break event;
+            }
+            self.document.highlight_pending(IDLE_HIGHLIGHT_ROWS);
+            let expired = self.dispatcher.expire();
+            if self.update_background() || expired {
+                return Ok(());
+            }
+        };
+        let mut keys = usize::from(matches!(event, Event::Key(_)));
+        self.process_event(event);
+        self.update_background();
+        let frame = self.frame_interval();
+        while !self.should_quit {
+            let remaining = frame.saturating_sub(frame_started.elapsed());
+            match self.terminal.poll_event(remaining)? {
+                // Time passing without input ends the frame as waiting would.
+                Some(Event::Tick) => {
+                    self.process_event(Event::Tick);
+                    break;
+                }
+                Some(event) => {
+                    keys += usize::from(matches!(event, Event::Key(_)));
+                    self.process_event(event);
+                    self.update_background();
                 }
+                None => break,
             }
-            Key::Up
-            | Key::Down
-            | Key::Left
-            | Key::Right
-            | Key::PageUp
-            | Key::PageDown
-            | Key::End
-            | Key::Home => self.move_cursor(pressed_key),
-            _ => (),
         }
-
-        if let Key::Ctrl('q') = pressed_key {
-            self.should_quit = true;
+        self.burst = keys > 1;
+        self.sync_language_server();
+        if let Some(outline) = &mut self.outline {
+            if !self.document.is_large() {
+                outline.update(&self.document);
+            }
         }
-        self.scroll();
-        if self.quit_times < QUIT_TIMES {
-            self.quit_times = QUIT_TIMES;
-            self.status_message = StatusMessage::from(String::new());
+        if self.blame_inline {
+            self.refresh_blame();
+        }
+        self.update_commit_summary();
+        self.update_conflicts();
+        self.update_project();
+        if self
+            .file_history_interval
+            .is_some_and(|interval| self.last_snapshot.elapsed() >= interval)
+            && self.document.is_dirty()
+        {
+            self.take_snapshot();
         }
         Ok(())
     }
 
-    fn scroll(&mut self) {
-        let Position { x, y } = self.cursor_position;
-        let width = self.terminal.size().width as usize;
-        let height = self.terminal.size().height as usize;
-        let offset = &mut self.offset;
-        if y < offset.y {
-            offset.y = y;
-        } else if y >= offset.y.saturating_add(height) {
-            offset.y = y.saturating_sub(height).saturating_add(1);
+    /// Loads the configuration again if the current file belongs to another
+    /// project than the one it was loaded for.
+    fn update_project(&mut self) {
+        if self.project_looked_up == self.document.file_name {
+            return;
         }
-        if x < offset.x {
-            offset.x = x;
-        } else if x >= offset.x.saturating_add(width) {
-            offset.x = x.saturating_sub(width).saturating_add(1);
+        self.project_looked_up.clone_from(&self.document.file_name);
+        if self.document.file_name.as_deref().and_then(Project::find) != self.project {
+            self.load_config();
         }
     }
 
-    fn move_cursor(&mut self, key: Key) {
-        let terminal_height = self.terminal.size().height as usize;
-        let Position { mut y, mut x } = self.cursor_position;
-        let height = self.document.len();
-        let mut width = if let Some(row) = self.document.row(y) {
-            row.len()
-        } else {
-            0
+    /// Keeps the text of the document in its file's history.
+    fn take_snapshot(&mut self) {
+        self.last_snapshot = Instant::now();
+        let Some(file_name) = &self.document.file_name else {
+            return;
         };
-        match key {
-            Key::Up => y = y.saturating_sub(1),
-            Key::Down => {
-                if y < height {
-                    y = y.saturating_add(1);
-                }
-            }
-            Key::Left => {
-                if x > 0 {
-                    x -= 1;
-                } else if y > 0 {
-                    y -= 1;
-                    if let Some(row) = self.document.row(y) {
-                        x = row.len();
-                    } else {
-                        x = 0;
-                    }
+        if self.document.is_large() || self.document.is_read_only() {
+            return;
+        }
+        if let Err(error) =
+            snapshots::take(file_name, &self.document.text(), self.file_history_size)
+        {
+            log::warn!("Could not keep a snapshot of {file_name}: {error}");
+        }
+    }
+
+    /// Lets the user pick one of the snapshots of the file, previewing how
+    /// the buffer differs from it, and shows the difference or, with
+    /// `restore`, puts its text back into the buffer.
+    fn file_history(&mut self, restore: bool) {
+        let Some(file_name) = self.document.file_name.clone() else {
+            self.status_message = StatusMessage::from(tr!("This buffer has no file."));
+            return;
+        };
+        if restore && self.document.is_read_only() {
+            self.status_message = StatusMessage::from(tr!("This buffer is read-only."));
+            return;
+        }
+        let versions: Vec<(u64, String)> = snapshots::list(&file_name)
+            .iter()
+            .filter_map(|snapshot| Some((snapshot.time(), snapshot.read().ok()?)))
+            .collect();
+        if versions.is_empty() {
+            self.status_message =
+                StatusMessage::from(tr!("No earlier versions of this file were kept."));
+            return;
+        }
+        let text = self.document.text();
+        let current: Vec<&str> = text.lines().collect();
+        let diffs: Vec<String> = versions
+            .iter()
+            .map(|(time, version)| {
+                let old: Vec<&str> = version.lines().collect();
+                let name = blame::age(*time);
+                diff::unified(&name, self.document.name(), &old, &current, 3)
+            })
+            .collect();
+        let items = versions
+            .iter()
+            .zip(&diffs)
+            .map(|((time, version), diff)| {
+                let lines = version.lines().count();
+                let age = blame::age(*time);
+                if diff.is_empty() {
+                    tr!("{age}, {lines} lines, as in the buffer", age, lines)
+                } else {
+                    tr!("{age}, {lines} lines", age, lines)
                 }
+            })
+            .collect();
+        // The hunks, without the names of the two.
+        let previews = diffs
+            .iter()
+            .map(|diff| diff.lines().skip(2).collect::<Vec<_>>().join("\n"))
+            .collect();
+        let picker = Picker::new(items).with_previews(previews);
+        let Some(index) = self.run_picker(&tr!("Version: "), picker) else {
+            return;
+        };
+        let (Some((time, version)), Some(diff)) = (versions.get(index), diffs.get(index)) else {
+            return;
+        };
+        let age = blame::age(*time);
+        if restore {
+            let version = version.strip_suffix('\n').unwrap_or(version);
+            if self.document.is_empty() {
+                self.document.insert_str(&Position::default(), version);
+            } else {
+                let last = self.document.len().saturating_sub(1);
+                self.document.patch_rows(0, last, version);
             }
-            Key::Right => {
-                if x < width {
-                    x += 1;
-                } else if y < height {
-                    y += 1;
-                    x = 0;
-                }
+            self.selection = None;
+            self.clamp_cursor();
+            self.scroll();
+            self.status_message = StatusMessage::from(tr!("Restored the version from {age}.", age));
+        } else if diff.is_empty() {
+            self.status_message = StatusMessage::from(tr!(
+                "The version from {age} is the same as the buffer.",
+                age
+            ));
+        } else {
+            let name = format!("[{} @ {age}]", self.document.name());
+            self.show_read_only(Document::read_only(&name, diff));
+        }
+    }
+
+    fn process_event(&mut self, event: Event) {
+        let event = match event {
+            Event::Key(key) => Event::Key(self.remapper.translate(key, self.mode.name())),
+            event => event,
+        };
+        let started = Instant::now();
+        let before = self.view.cursor.clone();
+        let typed = match event {
+            Event::Key(Key::Char(c))
+                if c != '\n'
+                    && self.dispatcher.pending().is_empty()
+                    && !self.pane_focused
+                    && !self.outline_focused =>
+            {
+                Some(c)
             }
-            Key::PageUp => {
-                y = if y > terminal_height {
-                    y - terminal_height
-                } else {
-                    0
-                }
+            _ => None,
+        };
+        match event {
+            Event::Key(key) => self.process_keypress(key),
+            Event::FocusGained => self.focus_gained(),
+            Event::FocusLost => self.focus_lost(),
+            Event::Paste(text) => self.insert_pasted(&text),
+            Event::Resize { .. } => self.scroll(),
+            Event::Background(background) => {
+                self.background = Some(background);
+                self.update_theme();
             }
-            Key::PageDown => {
-                y = if y.saturating_add(terminal_height) < height {
-                    y + terminal_height
-                } else {
-                    height
-                }
+            Event::Tick => {
+                self.dispatcher.expire();
+                self.document.highlight_pending(IDLE_HIGHLIGHT_ROWS);
+                self.update_background();
             }
-            Key::Home => x = 0,
-            Key::End => x = width,
-            _ => (),
         }
-        width = if let Some(row) = self.document.row(y) {
-            row.len()
-        } else {
-            0
-        };
-        if x > width {
-            x = width;
+        if self.view.cursor != before {
+            self.publish(&EditorEvent::CursorMoved(self.view.cursor.clone()));
         }
-        self.cursor_position = Position { x, y }
+        self.document
+            .end_undo_group(before, self.view.cursor.clone(), typed);
+        self.trim_undo();
+        self.check_cursor();
+        self.frame.input += started.elapsed();
     }
 
-    fn draw_welcome_message(&self) {
-        let mut welcome_message = format!("Hector editor -- version {VERSION}");
-        let width = self.terminal.size().width as usize;
-        let len = welcome_message.len();
-        let padding = width.saturating_sub(len) / 2;
-        let spaces = " ".repeat(padding.saturating_sub(1));
-        welcome_message = format!("~{spaces}{welcome_message}");
-        welcome_message.truncate(width);
-        println!("{welcome_message}\r");
+    fn focus_gained(&mut self) {
+        self.has_focus = true;
+        // Commits and checkouts made elsewhere show up when coming back.
+        self.refresh_git();
+        // So may a switch to dark mode, in terminals that do not report it.
+        if self.background_setting.is_none() {
+            self.terminal.query_background();
+        }
+        // A file being saved looks changed, and is only half written.
+        if self.saving.is_some() || !self.document.changed_on_disk() {
+            return;
+        }
+        if self.document.is_dirty() {
+            self.status_message = StatusMessage::from(tr!(
+                "WARNING! File changed on disk, saving will overwrite it."
+            ));
+            return;
+        }
+        let file_name = self.document.file_name.clone().unwrap_or_default();
+        if let Ok(document) = self.open_document(&file_name) {
+            self.document = document;
+            self.clamp_cursor();
+            self.scroll();
+            self.status_message = StatusMessage::from(tr!("File reloaded from disk."));
+        }
     }
 
-    pub fn draw_row(&self, row: &Row) {
-        let width = self.terminal.size().width as usize;
-        let start = self.offset.x;
-        let end = self.offset.x + width;
-        let row = row.render(start, end);
-        println!("{row}\r");
+    fn refresh_git(&mut self) {
+        self.git = self.document.file_name.as_deref().and_then(GitStatus::of);
     }
 
-    fn draw_rows(&self) {
-        let height = self.terminal.size().height;
-        for terminal_row in 0..height {
-            Terminal::clear_current_line();
-            if let Some(row) = self.document.row(terminal_row as usize + self.offset.y) {
-                self.draw_row(row);
-            } else if self.document.is_empty() && terminal_row == height / 3 {
-                self.draw_welcome_message();
-            } else {
-                println!("~\r");
-            }
+    fn focus_lost(&mut self) {
+        self.has_focus = false;
+        if self.autosave && self.document.is_dirty() && self.document.file_name.is_some() {
+            self.save();
         }
     }
 
-    fn prompt<C>(&mut self, prompt: &str, mut callback: C) -> Result<Option<String>, std::io::Error>
-    where
-        C: FnMut(&mut Self, Key, &String),
-    {
-        let mut result = String::new();
-        loop {
-            self.status_message = StatusMessage::from(format!("{prompt}{result}"));
-            self.refresh_screen()?;
-            let key = Terminal::read_key()?;
+    fn has_background_work(&self) -> bool {
+        self.pane.is_some()
+            || self.saving.is_some()
+            || self.lint.is_some()
+            || self.blame.is_some()
+            || self.task_scan.is_some()
+            || self.servers.values().any(Option::is_some)
+    }
+
+    /// Takes in whatever the pane, the linter and language servers sent.
+    /// Returns whether there is anything new to show.
+    fn update_background(&mut self) -> bool {
+        let before = self.view.cursor.clone();
+        let pane = self.update_pane();
+        let saving = self.update_save();
+        let lint = self.update_lint();
+        let servers = self.update_servers();
+        let blame = self.update_blame();
+        let tasks = self.update_tasks();
+        // A completion may have been inserted.
+        self.document
+            .end_undo_group(before, self.view.cursor.clone(), None);
+        pane || saving || lint || servers || blame || tasks
+    }
+
+    /// Takes in what `git blame` found once it has finished. Returns whether
+    /// there is anything new to show.
+    fn update_blame(&mut self) -> bool {
+        let Some(blame) = &self.blame else {
+            return false;
+        };
+        let Some(result) = blame.poll() else {
+            return false;
+        };
+        let revision = blame.revision;
+        self.blame = None;
+        match result {
+            Ok(annotations) => self.annotations = Some((revision, annotations)),
+            Err(error) => {
+                // Remember the failure, so it is not retried until the
+                // document changes.
+                self.annotations = Some((revision, Vec::new()));
+                if self.blame_requested {
+                    self.log(format!("ERR: {error}"));
+                }
+            }
+        }
+        if self.blame_requested && revision == self.document.revision() {
+            self.blame_requested = false;
+            if let Some(annotation) = self.annotation(self.view.cursor.y) {
+                self.status_message = StatusMessage::from(annotation.describe());
+            }
+        }
+        if self.blame_inline {
+            self.refresh_blame();
+        }
+        true
+    }
+
+    /// Starts blaming the document unless it is already blamed, or being
+    /// blamed, as it is now.
+    fn refresh_blame(&mut self) {
+        let revision = self.document.revision();
+        let Some(file_name) = &self.document.file_name else {
+            return;
+        };
+        if self.blame.is_some() || self.git.is_none() || self.document.is_large() {
+            return;
+        }
+        if matches!(&self.annotations, Some((blamed, _)) if *blamed == revision) {
+            return;
+        }
+        self.blame = Some(Blame::start(file_name, self.document.text(), revision));
+    }
+
+    /// Returns who last changed row `y`, if the document was blamed as it is.
+    fn annotation(&self, y: usize) -> Option<&Annotation> {
+        match &self.annotations {
+            Some((revision, annotations)) if *revision == self.document.revision() => {
+                annotations.get(y)
+            }
+            _ => None,
+        }
+    }
+
+    fn show_blame(&mut self) {
+        if self.git.is_none() {
+            self.status_message = StatusMessage::from(tr!("This file is not in a git repository."));
+            return;
+        }
+        if let Some(annotation) = self.annotation(self.view.cursor.y) {
+            self.status_message = StatusMessage::from(annotation.describe());
+            return;
+        }
+        self.blame_requested = true;
+        self.refresh_blame();
+    }
+
+    /// Takes in what the linter found once it has finished. Returns whether
+    /// there is anything new to show.
+    fn update_lint(&mut self) -> bool {
+        let Some(result) = self.lint.as_ref().and_then(Lint::poll) else {
+            return false;
+        };
+        self.lint = None;
+        match result {
+            Ok(diagnostics) => {
+                let errors = diagnostics
+                    .iter()
+                    .filter(|diagnostic| diagnostic.severity == Severity::Error)
+                    .count();
+                let warnings = diagnostics.len().saturating_sub(errors);
+                self.status_message = StatusMessage::from(tr!(
+                    "Linter found {errors} errors and {warnings} warnings.",
+                    errors,
+                    warnings
+                ));
+                self.diagnostics = diagnostics;
+            }
+            Err(error) => self.log(format!("ERR: {error}")),
+        }
+        true
+    }
+
+    /// Takes in the output of the pane's shell, closing the pane once the
+    /// shell has exited. Returns whether there is anything new to show.
+    fn update_pane(&mut self) -> bool {
+        let Some(pane) = &mut self.pane else {
+            return false;
+        };
+        match pane.update() {
+            Some(changed) => changed,
+            None => {
+                self.close_pane();
+                self.status_message = StatusMessage::from(tr!("The shell exited."));
+                true
+            }
+        }
+    }
+
+    /// Opens the terminal pane, or moves focus between it and the document.
+    fn toggle_pane(&mut self) {
+        if self.pane.is_some() {
+            self.pane_focused = !self.pane_focused;
+            return;
+        }
+        let width = self.terminal.size().width;
+        let height = cmp::max(self.terminal.size().height / 3, PANE_MIN_HEIGHT);
+        match Pane::open(width, height.saturating_sub(1)) {
+            Ok(pane) => {
+                self.pane = Some(pane);
+                self.pane_focused = true;
+                self.scroll();
+            }
+            Err(error) => {
+                self.status_message =
+                    StatusMessage::from(tr!("Could not start shell: {error}", error));
+            }
+        }
+    }
+
+    fn close_pane(&mut self) {
+        self.pane = None;
+        self.pane_focused = false;
+    }
+
+    fn is_commit_message(&self) -> bool {
+        self.document.file_type() == COMMIT_MESSAGE
+    }
+
+    /// Looks up the staged changes when a commit message is opened, to show
+    /// them below it, and forgets them once it is closed.
+    fn update_commit_summary(&mut self) {
+        if !self.is_commit_message() {
+            self.commit_summary = None;
+            return;
+        }
+        if self.commit_summary.is_some() {
+            return;
+        }
+        let file_name = self.document.file_name.clone().unwrap_or_default();
+        let summary = match git::staged_summary(&file_name) {
+            Ok(summary) if summary.trim().is_empty() => vec!["Nothing is staged.".to_string()],
+            Ok(summary) => summary.lines().map(str::to_string).collect(),
+            Err(error) => vec![error],
+        };
+        self.commit_summary = Some(summary);
+    }
+
+    /// Rows taken by the terminal pane, or the staged changes shown below a
+    /// commit message, including its title bar.
+    fn pane_height(&self) -> usize {
+        if self.pane.is_none() && self.commit_summary.is_none() {
+            return 0;
+        }
+        let height = self.terminal.size().height;
+        usize::from(cmp::max(height / 3, PANE_MIN_HEIGHT)).min(usize::from(height))
+    }
+
+    /// Rows left for the document, in one view or two.
+    fn text_area_height(&self) -> usize {
+        (self.terminal.size().height as usize).saturating_sub(self.pane_height())
+    }
+
+    /// Rows of the view with focus.
+    fn text_height(&self) -> usize {
+        match &self.split {
+            Some(split) => {
+                let (top, bottom) = self.split_heights();
+                if split.above {
+                    bottom
+                } else {
+                    top
+                }
+            }
+            None => self.text_area_height(),
+        }
+    }
+
+    /// Rows of the upper and lower views, with a line between them.
+    fn split_heights(&self) -> (usize, usize) {
+        let rows = self.text_area_height().saturating_sub(1);
+        let top = rows / 2;
+        (top, rows.saturating_sub(top))
+    }
+
+    /// The screen row the view with focus starts at.
+    fn text_top(&self) -> usize {
+        match &self.split {
+            Some(split) if split.above => self.split_heights().0.saturating_add(1),
+            _ => 0,
+        }
+    }
+
+    /// Shows the outline, or moves focus between it and the document.
+    fn toggle_outline(&mut self) {
+        if self.document.is_large() {
+            self.status_message = StatusMessage::from(tr!("The outline is off for large files."));
+            return;
+        }
+        let outline = self.outline.get_or_insert_with(Outline::default);
+        outline.update(&self.document);
+        self.outline_focused = !self.outline_focused;
+        if self.outline_focused {
+            outline.selected = outline.active(self.view.cursor.y).unwrap_or(0);
+        }
+        self.scroll();
+    }
+
+    /// Moves through the outline while it has focus. Returns `false` for keys
+    /// it does not handle, which take focus back to the document.
+    fn outline_keypress(&mut self, key: Key) -> bool {
+        let rows = self.text_height().saturating_sub(1);
+        let Some(outline) = &mut self.outline else {
+            return false;
+        };
+        let last = outline.symbols().len().saturating_sub(1);
+        let selected = outline.selected;
+        outline.selected = match key {
+            Key::Up => selected.saturating_sub(1),
+            Key::Down => selected.saturating_add(1).min(last),
+            Key::PageUp => selected.saturating_sub(rows),
+            Key::PageDown => selected.saturating_add(rows).min(last),
+            Key::Home => 0,
+            Key::End => last,
+            Key::Esc => {
+                self.outline_focused = false;
+                return true;
+            }
+            Key::Char('\n') => {
+                self.outline_focused = false;
+                self.go_to_symbol(selected);
+                return true;
+            }
+            _ => {
+                self.outline_focused = false;
+                return false;
+            }
+        };
+        true
+    }
+
+    /// Shows the two `files` side by side, starting at their first
+    /// difference.
+    fn compare(&mut self, files: &[String]) {
+        let [left, right] = files else {
+            return;
+        };
+        let texts = fs::read_to_string(left)
+            .map_err(|_| left)
+            .and_then(|left| Ok((left, fs::read_to_string(right).map_err(|_| right)?)));
+        let (left_text, right_text) = match texts {
+            Ok(texts) => texts,
+            Err(file_name) => {
+                self.status_message =
+                    StatusMessage::from(tr!("ERR: Could not open file: {file_name}", file_name));
+                return;
+            }
+        };
+        let mut comparison = Comparison::new(left, &left_text, right, &right_text);
+        self.status_message = if comparison.select(0, self.text_area_height()) {
+            StatusMessage::from(tr!(
+                "n = next difference | p = previous | q = edit {file_name}",
+                file_name = left
+            ))
+        } else {
+            StatusMessage::from(tr!("The files are the same."))
+        };
+        self.comparison = Some(comparison);
+    }
+
+    /// Scrolls through the files being compared and moves between their
+    /// differences. Returns `false` for keys that quit, which are left to
+    /// the keymap; the others do nothing to the document.
+    fn comparison_keypress(&mut self, key: Key) -> bool {
+        if matches!(self.keymap.command(&[key]), Some("quit" | "quit_all")) {
+            return false;
+        }
+        let height = self.text_area_height();
+        let page = isize::try_from(height).unwrap_or(isize::MAX);
+        let Some(comparison) = &mut self.comparison else {
+            return false;
+        };
+        let moved = match key {
+            Key::Up | Key::Char('k') => {
+                comparison.scroll(-1, height);
+                true
+            }
+            Key::Down | Key::Char('j') => {
+                comparison.scroll(1, height);
+                true
+            }
+            Key::PageUp => {
+                comparison.scroll(page.saturating_neg(), height);
+                true
+            }
+            Key::PageDown | Key::Char(' ') => {
+                comparison.scroll(page, height);
+                true
+            }
+            Key::Home => {
+                comparison.scroll(isize::MIN, height);
+                true
+            }
+            Key::End => {
+                comparison.scroll(isize::MAX, height);
+                true
+            }
+            Key::Char('n') => comparison.select(comparison.selected().saturating_add(1), height),
+            Key::Char('p') => comparison
+                .selected()
+                .checked_sub(1)
+                .is_some_and(|previous| comparison.select(previous, height)),
+            Key::Char('q') | Key::Esc => {
+                self.comparison = None;
+                self.status_message = StatusMessage::from(String::new());
+                self.scroll();
+                true
+            }
+            _ => true,
+        };
+        if !moved {
+            self.status_message = StatusMessage::from(tr!("No more differences."));
+        }
+        true
+    }
+
+    fn go_to_symbol(&mut self, index: usize) {
+        let Some(symbol) = self
+            .outline
+            .as_ref()
+            .and_then(|outline| outline.symbols().get(index))
+        else {
+            return;
+        };
+        let y = symbol.line;
+        let x = self.document.row(y).map_or(0, |row| {
+            row.as_str()
+                .find(symbol.name.as_str())
+                .map_or(0, |index| row.as_str()[..index].graphemes(true).count())
+        });
+        let from = self.current_jump();
+        self.view.cursor = Position { x, y };
+        self.clamp_cursor();
+        let height = self.text_height();
+        self.view.offset.y = self.view.cursor.y.saturating_sub(height / 2);
+        self.scroll();
+        self.jumps.push(from);
+    }
+
+    fn process_keypress(&mut self, pressed_key: Key) {
+        self.publish(&EditorEvent::KeyPressed(keymap::describe(pressed_key)));
+        if self.outline_focused
+            && self.keymap.command(&[pressed_key]) != Some("outline")
+            && self.outline_keypress(pressed_key)
+        {
+            return;
+        }
+        if self.comparison.is_some() && self.comparison_keypress(pressed_key) {
+            return;
+        }
+        if self.pane_focused && self.keymap.command(&[pressed_key]) != Some("terminal") {
+            if let Some(pane) = &mut self.pane {
+                if let Err(error) = pane.send(pressed_key) {
+                    self.status_message =
+                        StatusMessage::from(tr!("Could not write to shell: {error}", error));
+                }
+            }
+            return;
+        }
+        if let Key::Alt(digit @ '0'..='9') = pressed_key {
+            if self.dispatcher.pending().is_empty() && self.keymap.command(&[pressed_key]).is_none()
+            {
+                self.add_to_count(digit);
+                return;
+            }
+        }
+        match self.dispatcher.feed(&self.keymap, pressed_key) {
+            Dispatch::Command(command) => self.execute(&command),
+            Dispatch::Pending => return,
+            Dispatch::Unbound(keys) => {
+                self.count = None;
+                if let [Key::Char(c)] = keys[..] {
+                    if c == '\n' && self.document.name() == TASKS_NAME {
+                        self.visit_task();
+                        return;
+                    }
+                    if self.document.is_read_only() {
+                        self.status_message = StatusMessage::from(tr!("This buffer is read-only."));
+                        return;
+                    }
+                    self.type_char(c);
+                } else if keys.len() > 1 {
+                    let keys: Vec<String> = keys.into_iter().map(keymap::describe).collect();
+                    self.status_message =
+                        StatusMessage::from(tr!("{keys} is not bound", keys = keys.join(" ")));
+                }
+            }
+        }
+        self.scroll();
+    }
+
+    /// Inserts pasted `text` like typed keys, but exactly as it is, without
+    /// indenting: over the text after the cursor in Replace mode.
+    fn insert_pasted(&mut self, text: &str) {
+        if self.document.is_read_only() {
+            self.status_message = StatusMessage::from(tr!("This buffer is read-only."));
+            return;
+        }
+        self.selection = None;
+        if self.mode == Mode::Replace {
+            for c in text.chars() {
+                if c != '\n' {
+                    self.document.delete(&self.view.cursor);
+                }
+                let c = c.encode_utf8(&mut [0; 4]).to_string();
+                self.view.cursor = self.document.insert_str(&self.view.cursor, &c);
+            }
+        } else {
+            self.view.cursor = self.document.insert_str(&self.view.cursor, text);
+        }
+        self.scroll();
+    }
+
+    /// Inserts `c` as typed: a tab as the document is indented, and a new
+    /// line indented as the one above if `auto_indent` is on.
+    fn type_char(&mut self, c: char) {
+        self.selection = None;
+        if self.mode == Mode::Replace && c != '\n' {
+            self.document.delete(&self.view.cursor);
+        }
+        let text = match c {
+            '\t' => self.indent().step(self.view.cursor.x),
+            '\n' if self.auto_indent => format!("\n{}", self.new_line_indent()),
+            _ => {
+                self.document.insert(&self.view.cursor, c);
+                self.move_cursor(Key::Right);
+                return;
+            }
+        };
+        self.view.cursor = self.document.insert_str(&self.view.cursor, &text);
+    }
+
+    /// Returns how the document is indented, with tabs unless it tells
+    /// otherwise.
+    fn indent(&self) -> Indent {
+        self.document.indent().unwrap_or(Indent::Tabs)
+    }
+
+    /// Returns the indentation of a line split at the cursor: that of the
+    /// cursor's line, and a level more after an opening bracket or a colon.
+    fn new_line_indent(&self) -> String {
+        let Some(row) = self.document.row(self.view.cursor.y) else {
+            return String::new();
+        };
+        let before: String = row
+            .as_str()
+            .graphemes(true)
+            .take(self.view.cursor.x)
+            .collect();
+        let text = before.trim_start();
+        let mut indent = before[..before.len().saturating_sub(text.len())].to_string();
+        if text.trim_end().ends_with(['{', '(', '[', ':']) {
+            indent.push_str(&self.indent().unit());
+        }
+        indent
+    }
+
+    /// Adds a digit typed with Alt to the count for the next command.
+    fn add_to_count(&mut self, digit: char) {
+        let value = digit.to_digit(10).map_or(0, |value| value as usize);
+        let count = self
+            .count
+            .unwrap_or(0)
+            .saturating_mul(10)
+            .saturating_add(value);
+        self.count = Some(count);
+        self.status_message = StatusMessage::from(tr!("Count: {count}", count));
+    }
+
+    /// Closes the current buffer, quitting the editor once none are left.
+    fn quit(&mut self, force: bool) {
+        if !self.hidden_buffers.is_empty() {
+            self.close_buffer(force);
+        } else if self.document.is_dirty() && !force {
+            self.status_message =
+                StatusMessage::from(tr!("File has unsaved changes, use quit! to discard them."));
+        } else {
+            self.should_quit = true;
+        }
+    }
+
+    /// Quits the editor, first asking what to do about unsaved buffers.
+    fn quit_all(&mut self, force: bool) {
+        let dirty = self.dirty_buffer_names();
+        if force || dirty.is_empty() {
+            self.should_quit = true;
+            return;
+        }
+        let question = tr!(
+            "Unsaved changes in {names}. Save all (s), discard them (d) or cancel (Esc)?",
+            names = dirty.join(", ")
+        );
+        match self.ask(&question) {
+            Some(Key::Char('s')) => self.should_quit = self.save_all(),
+            Some(Key::Char('d')) => self.should_quit = true,
+            _ => self.status_message = StatusMessage::from(tr!("Quit cancelled.")),
+        }
+    }
+
+    /// Returns the breakpoints of every open file that has some, for a
+    /// debugger to be told about. They move along with the text, so they are
+    /// worth asking for again after the documents change.
+    #[must_use]
+    pub fn breakpoints(&self) -> Vec<SourceBreakpoints> {
+        iter::once(&self.document)
+            .chain(self.hidden_buffers.iter().map(|buffer| &buffer.document))
+            .filter_map(|document| {
+                let path = document.file_name.clone()?;
+                let lines: Vec<usize> = document
+                    .breakpoints()
+                    .rows()
+                    .map(|row| row.saturating_add(1))
+                    .collect();
+                (!lines.is_empty()).then_some(SourceBreakpoints { path, lines })
+            })
+            .collect()
+    }
+
+    fn breakpoints_changed(&mut self) {
+        if let Some(file_name) = self.document.file_name.clone() {
+            self.publish(&EditorEvent::BreakpointsChanged(file_name));
+        }
+    }
+
+    /// Writes every buffer with unsaved work, along with a report of the
+    /// last panic and the commands run before it, to a new recovery
+    /// directory, and returns where. Meant for after [`Editor::run`]
+    /// panicked, so it only reads the text of each buffer.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the files cannot be written.
+    pub fn recover(&self) -> std::io::Result<PathBuf> {
+        let documents: Vec<(String, String)> = std::iter::once(&self.document)
+            .chain(self.hidden_buffers.iter().map(|buffer| &buffer.document))
+            .filter(|document| {
+                document.is_dirty() || (document.is_scratch() && !document.is_empty())
+            })
+            .map(|document| (document.name().to_string(), document.text()))
+            .collect();
+        let commands: Vec<String> = self.recent_commands.iter().cloned().collect();
+        recovery::write(&documents, &commands)
+    }
+
+    fn dirty_buffer_names(&self) -> Vec<String> {
+        std::iter::once(&self.document)
+            .chain(self.hidden_buffers.iter().map(|buffer| &buffer.document))
+            .filter(|document| document.is_dirty())
+            .map(|document| document.name().to_string())
+            .collect()
+    }
+
+    /// Saves every modified buffer, stopping at the first one that could not
+    /// be saved, which is left current.
+    fn save_all(&mut self) -> bool {
+        self.wait_for_save();
+        if self.document.is_dirty() {
+            self.save();
+            self.wait_for_save();
+            if self.document.is_dirty() {
+                return false;
+            }
+        }
+        while let Some(index) = self
+            .hidden_buffers
+            .iter()
+            .position(|buffer| buffer.document.is_dirty())
+        {
+            self.switch_to(index);
+            self.save();
+            self.wait_for_save();
+            if self.document.is_dirty() {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Runs a command line such as `save` or `open! notes.txt`, as many times
+    /// as the count typed before it or given on the line asks. A command that
+    /// takes a line number takes the count as one instead.
+    fn execute(&mut self, line: &str) {
+        self.recent_commands.push_back(line.to_string());
+        if self.recent_commands.len() > MAX_RECENT_COMMANDS {
+            self.recent_commands.pop_front();
+        }
+        let typed_count = self.count.take();
+        let invocation = match command::parse(line) {
+            Ok(invocation) => invocation,
+            Err(error) => {
+                let (name, args) = line.trim().split_once(' ').unwrap_or((line.trim(), ""));
+                if self.plugins.has_command(name) {
+                    self.run_plugin_command(name, args.trim());
+                } else {
+                    self.status_message = StatusMessage::from(error);
+                }
+                return;
+            }
+        };
+        if invocation.command.name == "command_line" {
+            self.command_line(typed_count);
+            return;
+        }
+        let Some(count) = typed_count.or(invocation.count) else {
+            self.run_command(&invocation);
+            return;
+        };
+        if matches!(invocation.command.argument, Argument::Line) && invocation.args.is_empty() {
+            let args = count.to_string();
+            self.run_command(&Invocation {
+                args: &args,
+                ..invocation
+            });
+            return;
+        }
+        for _ in 0..count.min(MAX_COUNT) {
+            self.run_command(&invocation);
+            if self.should_quit {
+                break;
+            }
+        }
+    }
+
+    fn run_command(&mut self, invocation: &Invocation) {
+        let Invocation {
+            command,
+            force,
+            args,
+            ..
+        } = *invocation;
+        // Paths may start from the home directory or hold variables; commands
+        // run by `read !` are left to the shell.
+        let expanded;
+        let args = if matches!(command.argument, Argument::Path) && !args.starts_with('!') {
+            expanded = paths::expand(args);
+            expanded.as_str()
+        } else {
+            args
+        };
+        match command.name {
+            "quit" => self.quit(force),
+            "quit_all" => self.quit_all(force),
+            "save" => {
+                self.save_as((!args.is_empty()).then(|| args.to_string()));
+            }
+            "save_quit" => {
+                self.save();
+                self.wait_for_save();
+                if !self.document.is_dirty() {
+                    self.quit(false);
+                }
+            }
+            "save_quit_all" => self.should_quit = self.save_all(),
+            "open" => self.open(args, force),
+            "revert" => self.revert(),
+            "set" => self.set_option(args),
+            "config_reload" => {
+                self.keymap = Keymap::default();
+                self.remapper = Remapper::default();
+                self.load_config();
+            }
+            "help" => {
+                let help = Document::read_only("[Help]", &help::text(&self.keymap));
+                self.show_read_only(help);
+            }
+            "close" => self.close_buffer(force),
+            "scratch" => {
+                self.scratch_buffers = self.scratch_buffers.saturating_add(1);
+                let name = match self.scratch_buffers {
+                    1 => "[Scratch]".to_string(),
+                    count => format!("[Scratch {count}]"),
+                };
+                self.push_buffer(Document::scratch(&name));
+            }
+            "buffers" => self.pick_buffer(),
+            "read" => self.read(args),
+            "shell" => self.shell(args),
+            "format" => self.format(),
+            "next_conflict" => self.visit_conflict(true),
+            "previous_conflict" => self.visit_conflict(false),
+            "next_section" => self.visit_section(true),
+            "previous_section" => self.visit_section(false),
+            "accept_ours" => self.resolve_conflict(true, false),
+            "accept_theirs" => self.resolve_conflict(false, true),
+            "accept_both" => self.resolve_conflict(true, true),
+            "next_diagnostic" => self.visit_diagnostic(true),
+            "previous_diagnostic" => self.visit_diagnostic(false),
+            "complete" => {
+                if !self.complete_path() {
+                    self.ask_server(Request::Completion, "textDocument/completion");
+                }
+            }
+            "hover" => self.ask_server(Request::Hover, "textDocument/hover"),
+            "goto_definition" => self.ask_server(Request::Definition, "textDocument/definition"),
+            "tag" => self.tag(args),
+            "tags" => self.search_tags(),
+            "tag_pop" => match self.tag_stack.pop() {
+                Some(jump) => {
+                    self.jump_to(jump);
+                }
+                None => {
+                    self.status_message = StatusMessage::from(tr!("The tag stack is empty."));
+                }
+            },
+            "make" => self.make(args),
+            "print" => self.print(args),
+            "export_state" => self.export_state(args),
+            "import_state" => self.import_state(args),
+            "next_error" => self.visit_error(true),
+            "previous_error" => self.visit_error(false),
+            "tasks" => self.scan_tasks(),
+            "background" => self.set_background(args),
+            "outline" if force => {
+                self.outline = None;
+                self.outline_focused = false;
+            }
+            "outline" => self.toggle_outline(),
+            "zen" => {
+                self.set_flag("zen", !self.zen);
+                self.scroll();
+            }
+            "perf" => {
+                self.set_flag("perf", !self.perf_overlay);
+            }
+            "blame" => self.show_blame(),
+            "undo" => self.undo(false),
+            "redo" => self.undo(true),
+            "undo_tree" => self.pick_undo_state(),
+            "undo_status" => self.show_undo_status(),
+            "yank" => self.yank(args, false),
+            "cut" => self.yank(args, true),
+            "paste" => self.paste(args),
+            "register" => {
+                if let Some(name) = self.register_name(args, &tr!("Register: ")) {
+                    self.register = Some(name);
+                    self.status_message =
+                        StatusMessage::from(tr!("Register '{name}' selected.", name));
+                }
+            }
+            "registers" => self.show_registers(),
+            "yank_history" => self.pick_yank(),
+            "file_history" => self.file_history(force),
+            "strip_ansi" => self.strip_ansi(),
+            "stage_hunk" => self.stage_hunk(),
+            "revert_hunk" => self.revert_hunk(),
+            "split" if force => {
+                self.split = None;
+                self.scroll();
+            }
+            "split" => self.split_view(),
+            "breakpoint" if force => {
+                self.document.clear_breakpoints();
+                self.breakpoints_changed();
+            }
+            "breakpoint" => {
+                let line = self.view.cursor.y.saturating_add(1);
+                self.status_message =
+                    StatusMessage::from(if self.document.toggle_breakpoint(self.view.cursor.y) {
+                        tr!("Breakpoint set on line {line}.", line)
+                    } else {
+                        tr!("Breakpoint removed from line {line}.", line)
+                    });
+                self.breakpoints_changed();
+            }
+            "terminal" if force => self.close_pane(),
+            "terminal" => self.toggle_pane(),
+            "calc" => self.calculate(args, force),
+            "character" => self.insert_character(),
+            "inspect" => self.inspect_character(),
+            "log" => self.show_log(),
+            "plugins" => self.show_plugins(),
+            "messages" => {
+                let log = Document::read_only("[Messages]", &self.message_log.join("\n"));
+                self.show_read_only(log);
+            }
+            "buffer_previous" => {
+                if self.hidden_buffers.is_empty() {
+                    self.status_message = StatusMessage::from(tr!("This is the only buffer."));
+                } else {
+                    self.switch_to(self.hidden_buffers.len().saturating_sub(1));
+                }
+            }
+            "command_line" => self.command_line(None),
+            "goto" => self.goto(args),
+            "jump_back" => {
+                let from = self.current_jump();
+                if let Some(jump) = self.jumps.back(from).cloned() {
+                    self.jump_to(jump);
+                }
+            }
+            "jump_forward" => {
+                if let Some(jump) = self.jumps.forward().cloned() {
+                    self.jump_to(jump);
+                }
+            }
+            "mark" => {
+                if let Some(name) = self.mark_name(args, &tr!("Mark: ")) {
+                    self.document.set_mark(name, &self.view.cursor);
+                    self.status_message = StatusMessage::from(tr!("Mark '{name}' set.", name));
+                }
+            }
+            "goto_mark" => {
+                if let Some(name) = self.mark_name(args, &tr!("Go to mark: ")) {
+                    self.goto_mark(name);
+                }
+            }
+            "find" => self.search(),
+            "find_next" => self.find_again(false),
+            "find_previous" => self.find_again(true),
+            "substitute" => self.substitute(args),
+            "delete" => self.document.delete(&self.view.cursor),
+            "backspace" => {
+                if self.view.cursor.x > 0 || self.view.cursor.y > 0 {
+                    self.move_cursor(Key::Left);
+                    self.document.delete(&self.view.cursor);
+                }
+            }
+            "delete_line" => {
+                let line = self
+                    .document
+                    .row(self.view.cursor.y)
+                    .map(|row| row.as_str().to_string());
+                if let (Some(text), false) = (line, self.document.is_read_only()) {
+                    let name = self.register.take().unwrap_or(registers::DEFAULT);
+                    self.registers.set(
+                        name,
+                        Register {
+                            text,
+                            linewise: true,
+                        },
+                    );
+                }
+                self.document.delete_row(self.view.cursor.y);
+                self.clamp_cursor();
+            }
+            "move_up" => self.move_cursor(Key::Up),
+            "move_down" => self.move_cursor(Key::Down),
+            "move_left" => self.move_cursor(Key::Left),
+            "move_right" => self.move_cursor(Key::Right),
+            "page_up" => self.scroll_page(self.text_height(), false),
+            "page_down" => self.scroll_page(self.text_height(), true),
+            "half_page_up" => self.scroll_page(cmp::max(self.text_height() / 2, 1), false),
+            "half_page_down" => self.scroll_page(cmp::max(self.text_height() / 2, 1), true),
+            "document_start" => self.go_to(Position::default()),
+            "document_end" => {
+                let y = self.document.len().saturating_sub(1);
+                let x = self.document.row(y).map_or(0, Row::len);
+                self.go_to(Position { x, y });
+            }
+            "line_start" => self.move_cursor(Key::Home),
+            "line_end" => self.move_cursor(Key::End),
+            "next_paragraph" => {
+                self.view.cursor = motion::next_paragraph(&self.document, &self.view.cursor);
+            }
+            "previous_paragraph" => {
+                self.view.cursor = motion::previous_paragraph(&self.document, &self.view.cursor);
+            }
+            "next_sentence" => {
+                self.view.cursor = motion::next_sentence(&self.document, &self.view.cursor);
+            }
+            "previous_sentence" => {
+                self.view.cursor = motion::previous_sentence(&self.document, &self.view.cursor);
+            }
+            "next_word" => {
+                self.view.cursor =
+                    motion::next_word(&self.document, &self.view.cursor, self.subword);
+            }
+            "previous_word" => {
+                self.view.cursor =
+                    motion::previous_word(&self.document, &self.view.cursor, self.subword);
+            }
+            "word_end" => {
+                self.view.cursor =
+                    motion::word_end(&self.document, &self.view.cursor, self.subword);
+            }
+            "select" => {
+                self.selection = match self.selection {
+                    Some(_) => None,
+                    None => Some(self.view.cursor.clone()),
+                };
+            }
+            "deselect" => self.selection = None,
+            "select_up" => self.extend_selection(Key::Up),
+            "select_down" => self.extend_selection(Key::Down),
+            "select_left" => self.extend_selection(Key::Left),
+            "select_right" => self.extend_selection(Key::Right),
+            "filter" => self.filter(args),
+            "move_line_up" if self.view.cursor.y > 0 => {
+                let y = self.view.cursor.y;
+                self.document.swap_rows(y, y.saturating_sub(1));
+                self.move_cursor(Key::Up);
+            }
+            "move_line_down" if self.view.cursor.y.saturating_add(1) < self.document.len() => {
+                let y = self.view.cursor.y;
+                self.document.swap_rows(y, y.saturating_add(1));
+                self.move_cursor(Key::Down);
+            }
+            "move_line_up" | "move_line_down" => (),
+            "toggle_replace" => match self.mode {
+                Mode::Insert => self.set_mode(Mode::Replace),
+                Mode::Replace => self.set_mode(Mode::Insert),
+            },
+            name => {
+                self.status_message = StatusMessage::from(tr!("Not implemented: {name}", name));
+            }
+        }
+    }
+
+    fn load_config(&mut self) {
+        let loaded = match &self.config_path {
+            Some(path) => Config::load_from(path),
+            None => Config::load(),
+        };
+        let mut config = match loaded {
+            Ok(config) => config,
+            Err(error) => {
+                self.status_message = StatusMessage::from(format!("ERR: {error}"));
+                return;
+            }
+        };
+        let mut errors = Vec::new();
+        self.project_looked_up.clone_from(&self.document.file_name);
+        self.project = self.document.file_name.as_deref().and_then(Project::find);
+        if let Some(path) = &self.project {
+            match Project::load(path) {
+                Ok(project) => config.merge(project),
+                Err(error) => errors.push(error),
+            }
+        }
+        let language = if config.language.is_empty() {
+            Some(Language::from_env())
+        } else {
+            Language::parse(&config.language)
+        };
+        match language {
+            Some(language) => locale::set_language(language),
+            None => errors.push(tr!(
+                "Unknown language: {language}",
+                language = config.language
+            )),
+        }
+        match Theme::find(&config.theme) {
+            Some(theme) => self.configured_theme = theme,
+            None => errors.push(tr!("Unknown theme: {theme}", theme = config.theme)),
+        }
+        match config.background.as_str() {
+            "auto" => self.background_setting = None,
+            name => match Background::parse(name) {
+                Some(background) => self.background_setting = Some(background),
+                None => errors.push(tr!(
+                    "Unknown background: {background}",
+                    background = config.background
+                )),
+            },
+        }
+        self.update_theme();
+        self.tab_width = cmp::max(config.tab_width, 1);
+        self.set_flag("autosave", config.autosave);
+        self.set_flag("number", config.line_numbers);
+        self.set_flag("highlight", config.syntax_highlighting);
+        self.set_flag("sync", config.synchronized_output);
+        self.set_flag("title", config.title);
+        self.set_flag("blame", config.blame);
+        self.set_flag("accessible", config.accessible);
+        self.set_flag("subword", config.subword);
+        self.set_flag("autoindent", config.auto_indent);
+        self.undo_limits = config.undo_limits();
+        self.zen_width = cmp::max(config.zen_width, 1);
+        self.large_file_size = config.large_file_size.saturating_mul(1 << 20);
+        self.memory_budget = config.memory_budget.saturating_mul(1 << 20);
+        self.undo_depth = config.undo_depth;
+        self.undo_memory = config.undo_memory.saturating_mul(1 << 20);
+        self.make = config.make;
+        self.print_command = config.print_command;
+        self.print_highlighting = config.print_highlighting;
+        self.registers.set_history_size(config.yank_history_size);
+        self.persist_yank_history = config.persist_yank_history;
+        self.file_history_size = config.file_history_size;
+        self.file_history_interval = (config.file_history_interval > 0)
+            .then(|| Duration::from_secs(config.file_history_interval));
+        if self.persist_yank_history {
+            self.registers.load_history();
+        }
+        self.formatters = config.formatters;
+        self.rulers = resolve_rulers(&config.rulers, &mut errors);
+        self.file_type_rulers = config
+            .file_type_rulers
+            .iter()
+            .map(|(key, rulers)| (key.clone(), resolve_rulers(rulers, &mut errors)))
+            .collect();
+        let root = match self.project.as_deref().and_then(Path::parent) {
+            Some(directory) => directory.to_path_buf(),
+            None => env::current_dir().unwrap_or_default(),
+        };
+        self.ignore = Ignore::new(root, config.ignore);
+        self.linters = config.linters;
+        self.language_servers = config.language_servers;
+        // Plugins bind their keys first, so the user's keymap wins.
+        for (keys, command) in self.plugins.bindings() {
+            match keymap::parse_keys(&keys) {
+                Ok(keys) => self.keymap.bind(&keys, &command),
+                Err(error) => errors.push(error),
+            }
+        }
+        if let Err(keymap_errors) = self.keymap.apply(&config.keymap) {
+            errors.extend(keymap_errors);
+        }
+        if let Err(remap_errors) = self.remapper.apply(&config.remap, &config.mode_remap) {
+            errors.extend(remap_errors);
+        }
+        if !errors.is_empty() {
+            self.status_message = StatusMessage::from(format!("ERR: {}", errors.join("; ")));
+        }
+        self.scroll();
+    }
+
+    /// Reads a command line and runs it. A count typed before opening the
+    /// prompt applies to the command run from it.
+    fn command_line(&mut self, count: Option<usize>) {
+        let line = self
+            .prompt(":", Some(command::complete), |_, _, _| {})
+            .unwrap_or(None);
+        if let Some(line) = line {
+            self.count = count;
+            self.execute(&line);
+        }
+    }
+
+    /// Jumps to a `line[:column]` taken from `args`, prompting for it if
+    /// `args` is empty, and centers the line on the screen.
+    fn goto(&mut self, args: &str) {
+        let args = if args.is_empty() {
+            match self.prompt(&tr!("Go to line: "), None, |_, _, _| {}) {
+                Ok(Some(args)) => args,
+                _ => return,
+            }
+        } else {
+            args.to_string()
+        };
+        let Some((line, column)) = command::parse_percent(&args, self.document.len())
+            .map(|line| (line, None))
+            .or_else(|| command::parse_line_column(&args))
+        else {
+            self.status_message = StatusMessage::from(tr!("Not a line number: {args}", args));
+            return;
+        };
+        let y = cmp::min(line, self.document.len()).saturating_sub(1);
+        let width = self.document.row(y).map_or(0, Row::len);
+        let x = cmp::min(column.unwrap_or(1).saturating_sub(1), width);
+        self.go_to(Position { x, y });
+    }
+
+    /// Moves the cursor to `position`, remembering where it jumped from, and
+    /// scrolls to put it in the middle of the screen.
+    fn go_to(&mut self, position: Position) {
+        let jump = self.current_jump();
+        self.jumps.push(jump);
+        let y = position.y;
+        self.view.cursor = position;
+        let height = self.text_height();
+        self.view.offset.y = y.saturating_sub(height / 2);
+        self.scroll();
+    }
+
+    /// Takes the mark name from `args`, or reads it as the next key pressed.
+    fn mark_name(&mut self, args: &str, prompt: &str) -> Option<char> {
+        let name = if args.is_empty() {
+            match self.ask(prompt) {
+                Some(Key::Char(c)) => c,
+                _ => return None,
+            }
+        } else {
+            let mut chars = args.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => c,
+                _ => ' ',
+            }
+        };
+        if name.is_ascii_alphabetic() {
+            Some(name)
+        } else {
+            self.status_message = StatusMessage::from(tr!("Marks are named by a letter."));
+            None
+        }
+    }
+
+    /// Shows `question` and waits for the key that answers it.
+    fn ask(&mut self, question: &str) -> Option<Key> {
+        self.status_message = StatusMessage::from(question.to_string());
+        self.refresh_screen().ok()?;
+        let key = self.read_key().ok();
+        self.status_message = StatusMessage::from(String::new());
+        key
+    }
+
+    fn goto_mark(&mut self, name: char) {
+        let Some(position) = self.document.mark(name).cloned() else {
+            self.status_message = StatusMessage::from(tr!("Mark '{name}' is not set.", name));
+            return;
+        };
+        let jump = self.current_jump();
+        self.jumps.push(jump);
+        self.view.cursor = position;
+        self.clamp_cursor();
+        self.scroll();
+    }
+
+    /// Works out an arithmetic expression taken from `args`, prompting for it
+    /// if `args` is empty, and shows the result or with `insert` types it at
+    /// the cursor.
+    fn calculate(&mut self, args: &str, insert: bool) {
+        let expression = if args.is_empty() {
+            match self.prompt("= ", None, |_, _, _| {}) {
+                Ok(Some(expression)) => expression,
+                _ => return,
+            }
+        } else {
+            args.to_string()
+        };
+        let (number, base) = match calc::evaluate(&expression) {
+            Ok(result) => result,
+            Err(error) => {
+                self.status_message = StatusMessage::from(format!("ERR: {error}"));
+                return;
+            }
+        };
+        if !insert {
+            self.status_message =
+                StatusMessage::from(format!("{} = {}", expression.trim(), number.describe()));
+            return;
+        }
+        if self.document.is_read_only() {
+            self.status_message = StatusMessage::from(tr!("This buffer is read-only."));
+            return;
+        }
+        let result = number.format(base);
+        let cursor = self.document.insert_str(&self.view.cursor, &result);
+        self.place_cursor(cursor);
+    }
+
+    /// Replaces text as `/pattern/replacement/` in every line, the first
+    /// match on each or with a trailing `g` all of them. Any character may
+    /// stand in for the `/`.
+    fn substitute(&mut self, args: &str) {
+        let mut chars = args.chars();
+        let Some(delimiter) = chars.next() else {
+            self.status_message =
+                StatusMessage::from(tr!("Usage: substitute /pattern/replacement/[gir]"));
+            return;
+        };
+        let mut parts = chars.as_str().splitn(3, delimiter);
+        let (Some(pattern), Some(replacement)) = (parts.next(), parts.next()) else {
+            self.status_message =
+                StatusMessage::from(tr!("ERR: Missing replacement in {args}", args));
+            return;
+        };
+        let flags = parts.next().unwrap_or_default();
+        if let Some(flags) = flags.chars().find(|flag| !"gir".contains(*flag)) {
+            self.status_message = StatusMessage::from(tr!("ERR: Unknown flags: {flags}", flags));
+            return;
+        }
+        let options = ReplaceOptions {
+            regex: flags.contains('r'),
+            ignore_case: flags.contains('i'),
+            first_only: !flags.contains('g'),
+        };
+        self.replace_all(pattern, replacement, &options);
+    }
+
+    /// Replaces the matches of `pattern` throughout the document and says
+    /// how many there were.
+    fn replace_all(&mut self, pattern: &str, replacement: &str, options: &ReplaceOptions) {
+        if pattern.is_empty() {
+            self.status_message = StatusMessage::from(tr!("ERR: Empty pattern"));
+            return;
+        }
+        if self.document.is_read_only() {
+            self.status_message = StatusMessage::from(tr!("This buffer is read-only."));
+            return;
+        }
+        let report = match self.document.replace_all(pattern, replacement, options) {
+            Ok(report) => report,
+            Err(error) => {
+                self.status_message = StatusMessage::from(format!("ERR: {error}"));
+                return;
+            }
+        };
+        if report.count == 0 {
+            self.status_message = StatusMessage::from(tr!("Pattern not found: {pattern}", pattern));
+            return;
+        }
+        self.clamp_cursor();
+        self.scroll();
+        self.status_message = StatusMessage::from(tr!(
+            "Replaced {replaced} matches on {lines} lines.",
+            replaced = report.count,
+            lines = report.lines.len()
+        ));
+    }
+
+    /// Runs each line of `script` as a command line, skipping blank lines
+    /// and those starting with `#`, until one fails or quits the editor.
+    ///
+    /// # Errors
+    ///
+    /// Returns the number of the line that failed, counting from one, and
+    /// why.
+    pub fn run_script(&mut self, script: &str) -> Result<(), (usize, String)> {
+        for (index, line) in script.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            self.status_message = StatusMessage::from(String::new());
+            self.execute(line);
+            // Scripts expect a file saved once the line saving it is done.
+            self.wait_for_save();
+            let message = &self.status_message.text;
+            let name = line.split_whitespace().next().unwrap_or_default();
+            let unknown = command::parse(line).is_err() && !self.plugins.has_command(name);
+            if message.starts_with("ERR") || unknown {
+                return Err((index.saturating_add(1), message.clone()));
+            }
+            if self.should_quit {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Runs the `.lua` files in the `plugins` directory next to
+    /// `config.toml`.
+    fn load_plugins(&mut self) {
+        let Some(directory) = paths::config_dir().map(|directory| directory.join("plugins")) else {
+            return;
+        };
+        if let Err(errors) = self.plugins.load(&directory) {
+            self.status_message = StatusMessage::from(format!("ERR: {}", errors.join("; ")));
+        }
+    }
+
+    fn run_plugin_command(&mut self, name: &str, args: &str) {
+        let context = Context {
+            document: &self.document,
+            cursor: &self.view.cursor,
+        };
+        let result = self.plugins.run_command(name, args, &context);
+        self.apply_plugin_actions(result);
+    }
+
+    /// Tells plugins about `event`.
+    fn emit(&mut self, event: &str, argument: &str) {
+        let context = Context {
+            document: &self.document,
+            cursor: &self.view.cursor,
+        };
+        let result = self.plugins.emit(event, argument, &context);
+        self.apply_plugin_actions(result);
+    }
+
+    /// Does what a plugin asked for, or shows the error it raised.
+    fn apply_plugin_actions(&mut self, result: Result<Vec<Action>, String>) {
+        let actions = match result {
+            Ok(actions) => actions,
+            Err(error) => {
+                self.status_message = StatusMessage::from(format!("ERR: {error}"));
+                return;
+            }
+        };
+        for action in actions {
+            match action {
+                Action::Insert(text) => {
+                    if self.document.is_read_only() {
+                        self.status_message = StatusMessage::from(tr!("This buffer is read-only."));
+                        continue;
+                    }
+                    let cursor = self.document.insert_str(&self.view.cursor, &text);
+                    self.place_cursor(cursor);
+                }
+                Action::ReplaceLine(y, text) => {
+                    if self.document.is_read_only() {
+                        self.status_message = StatusMessage::from(tr!("This buffer is read-only."));
+                        continue;
+                    }
+                    self.document.patch_rows(y, y, &text);
+                    self.clamp_cursor();
+                }
+                Action::ReplaceAll(pattern, replacement, options) => {
+                    self.replace_all(&pattern, &replacement, &options);
+                }
+                Action::Message(text) => self.status_message = StatusMessage::from(text),
+                Action::Execute(line) => self.execute(&line),
+            }
+        }
+    }
+
+    /// Lists the plugins loaded and the commands they added.
+    fn show_plugins(&mut self) {
+        let mut text = String::from("Plugins:\n");
+        for name in self.plugins.loaded() {
+            text.push_str(&format!("  {name}\n"));
+        }
+        text.push_str("\nCommands:\n");
+        for (name, description) in self.plugins.commands() {
+            text.push_str(&format!("  {name:<24}{description}\n"));
+        }
+        self.show_read_only(Document::read_only("[Plugins]", &text));
+    }
+
+    /// Shows the log file in a read-only buffer, scrolled to its end.
+    fn show_log(&mut self) {
+        let contents = Logger::path().and_then(|path| fs::read_to_string(path).ok());
+        let Some(contents) = contents else {
+            self.status_message = StatusMessage::from(tr!("Nothing has been logged."));
+            return;
+        };
+        let log = Document::read_only("[Log]", &contents);
+        let last = log.len().saturating_sub(1);
+        self.show_read_only(log);
+        self.view.cursor = Position { x: 0, y: last };
+        self.scroll();
+    }
+
+    /// Lets the user pick a character by its name and inserts it at the
+    /// cursor. The ones picked lately come first.
+    fn insert_character(&mut self) {
+        if self.document.is_read_only() {
+            self.status_message = StatusMessage::from(tr!("This buffer is read-only."));
+            return;
+        }
+        let named = characters::named();
+        let mut choices: Vec<(char, &str)> = characters::recent()
+            .into_iter()
+            .filter_map(|c| {
+                let index = named.binary_search_by_key(&c, |(named, _)| *named).ok()?;
+                Some((c, named.get(index)?.1.as_str()))
+            })
+            .collect();
+        choices.extend(named.iter().map(|(c, name)| (*c, name.as_str())));
+        let items = choices
+            .iter()
+            .map(|(c, name)| format!("{c}  {name}  U+{:04X}", u32::from(*c)))
+            .collect();
+        let Some(c) = self
+            .pick(&tr!("Character: "), items)
+            .and_then(|index| choices.get(index))
+            .map(|(c, _)| *c)
+        else {
+            return;
+        };
+        let cursor = self.document.insert_str(&self.view.cursor, &c.to_string());
+        self.place_cursor(cursor);
+        characters::remember(c);
+    }
+
+    /// Tells what the grapheme under the cursor is made of, to make sense of
+    /// text in an unexpected encoding or with invisible characters.
+    fn inspect_character(&mut self) {
+        let Position { x, y } = self.view.cursor;
+        let grapheme = self
+            .document
+            .row(y)
+            .and_then(|row| row.as_str().graphemes(true).nth(x).map(str::to_string));
+        self.status_message = StatusMessage::from(match grapheme {
+            Some(grapheme) => {
+                let code_points = characters::code_points(&grapheme);
+                let bytes = characters::utf8_bytes(&grapheme);
+                let width = grapheme.width();
+                tr!(
+                    "{code_points} | bytes {bytes} | width {width}",
+                    code_points,
+                    bytes,
+                    width
+                )
+            }
+            None => tr!("No character under the cursor."),
+        });
+    }
+
+    /// Shows `document`, keeping the current one in the background.
+    fn push_buffer(&mut self, document: Document) {
+        self.remember_cursor();
+        let previous = Buffer {
+            document: std::mem::replace(&mut self.document, document),
+            view: std::mem::take(&mut self.view),
+        };
+        self.hidden_buffers.push(previous);
+        self.selection = None;
+        self.split = None;
+        self.refresh_git();
+    }
+
+    fn close_buffer(&mut self, force: bool) {
+        if self.document.is_dirty() && !force {
+            self.status_message =
+                StatusMessage::from(tr!("File has unsaved changes, add ! to discard them."));
+            return;
+        }
+        let Some(buffer) = self.hidden_buffers.pop() else {
+            self.status_message = StatusMessage::from(tr!("This is the only buffer."));
+            return;
+        };
+        self.remember_cursor();
+        self.remember_view();
+        self.document = buffer.document;
+        self.view = buffer.view;
+        self.selection = None;
+        self.split = None;
+        self.refresh_git();
+    }
+
+    /// Remembers where the cursor is in the current file, so it is put back
+    /// the next time the file is opened.
+    fn remember_cursor(&mut self) {
+        if self.document.is_read_only() {
+            return;
+        }
+        self.document
+            .set_mark(LAST_POSITION_MARK, &self.view.cursor);
+    }
+
+    /// Remembers the cursor in every open file, and how each file is shown,
+    /// as the editor quits.
+    fn remember_cursors(&mut self) {
+        self.remember_cursor();
+        for buffer in &mut self.hidden_buffers {
+            if !buffer.document.is_read_only() {
+                buffer
+                    .document
+                    .set_mark(LAST_POSITION_MARK, &buffer.view.cursor);
+            }
+        }
+        let buffers = self
+            .hidden_buffers
+            .iter()
+            .map(|buffer| (&buffer.document, &buffer.view))
+            .chain([(&self.document, &self.view)]);
+        views::store(buffers.filter_map(|(document, view)| {
+            let file_name = document.file_name.as_deref()?;
+            (!document.is_read_only()).then(|| (file_name, ViewState::from(view)))
+        }));
+    }
+
+    /// Remembers how the current file is shown, as its buffer is closed.
+    fn remember_view(&self) {
+        if let Some(file_name) = self.document.file_name.as_deref() {
+            if !self.document.is_read_only() {
+                views::store([(file_name, ViewState::from(&self.view))]);
+            }
+        }
+    }
+
+    /// Puts the cursor back where it was when the current file was last
+    /// left, and shows the file as it was then, or else centers the cursor's
+    /// line on the screen. The options saved with the view are set for this
+    /// buffer alone.
+    fn restore_cursor(&mut self) {
+        let view = self.document.file_name.as_deref().and_then(ViewState::load);
+        if let Some(view) = &view {
+            self.view.tab_width = (view.tab_width > 0).then_some(view.tab_width);
+            self.view.options = view
+                .options
+                .iter()
+                .filter(|(name, _)| views::OPTIONS.contains(&name.as_str()))
+                .map(|(name, value)| (name.clone(), *value))
+                .collect();
+        }
+        let position = view.as_ref().and_then(|view| view.cursor.clone());
+        let Some(position) = position.or_else(|| self.document.mark(LAST_POSITION_MARK).cloned())
+        else {
+            return;
+        };
+        self.view.cursor = position;
+        self.clamp_cursor();
+        self.view.offset = match view {
+            Some(view) => view.offset,
+            None => Position {
+                x: 0,
+                y: self.view.cursor.y.saturating_sub(self.text_height() / 2),
+            },
+        };
+        self.scroll();
+    }
+
+    /// Shows a generated read-only document, replacing the current one if it
+    /// was generated the same way.
+    fn show_read_only(&mut self, document: Document) {
+        if self.document.is_read_only() && self.document.name() == document.name() {
+            self.document = document;
+            self.view.cursor = Position::default();
+            self.view.offset = Position::default();
+        } else {
+            self.push_buffer(document);
+        }
+    }
+
+    /// Shows `message` and keeps it in the log shown by `messages`.
+    fn log(&mut self, message: String) {
+        self.message_log.push(message.clone());
+        if self.message_log.len() > MAX_LOGGED_MESSAGES {
+            self.message_log.remove(0);
+        }
+        self.status_message = StatusMessage::from(message);
+    }
+
+    /// Runs `command` in the shell and returns what it printed, logging what
+    /// it reported on stderr.
+    fn run_shell(&mut self, command: &str, input: Option<&str>) -> Option<String> {
+        if command.is_empty() {
+            self.status_message = StatusMessage::from(tr!("Usage: !<command>"));
+            return None;
+        }
+        let output = match shell::run(command, input) {
+            Ok(output) => output,
+            Err(error) => {
+                self.log(tr!("ERR: Could not run {command}: {error}", command, error));
+                return None;
+            }
+        };
+        for line in output.stderr.lines() {
+            self.log(format!("{command}: {line}"));
+        }
+        if !output.success {
+            self.log(tr!("ERR: {command} failed", command));
+        }
+        Some(output.stdout)
+    }
+
+    /// Inserts a file, or the output of a command given as `!command`, at the
+    /// cursor.
+    fn read(&mut self, args: &str) {
+        if self.document.is_read_only() {
+            self.status_message = StatusMessage::from(tr!("This buffer is read-only."));
+            return;
+        }
+        let text = if let Some(command) = args.strip_prefix('!') {
+            match self.run_shell(command.trim(), None) {
+                Some(text) => text,
+                None => return,
+            }
+        } else if args.is_empty() {
+            self.status_message = StatusMessage::from(tr!("Usage: read <file> | read !<command>"));
+            return;
+        } else {
+            match std::fs::read_to_string(args) {
+                Ok(text) => text,
+                Err(_) => {
+                    self.status_message =
+                        StatusMessage::from(tr!("ERR: Could not read file: {args}", args));
+                    return;
+                }
+            }
+        };
+        // The final newline would leave the rest of the line on its own.
+        let text = text.strip_suffix('\n').unwrap_or(&text);
+        self.view.cursor = self.document.insert_str(&self.view.cursor, text);
+        self.scroll();
+    }
+
+    /// Pipes the selected lines, or the current line, through `command` and
+    /// replaces them with its output. Nothing changes if the command fails.
+    fn filter(&mut self, command: &str) {
+        if self.document.is_read_only() {
+            self.status_message = StatusMessage::from(tr!("This buffer is read-only."));
+            return;
+        }
+        let command = if command.is_empty() {
+            match self.prompt(&tr!("Filter through: "), None, |_, _, _| {}) {
+                Ok(Some(command)) => command,
+                _ => return,
+            }
+        } else {
+            command.to_string()
+        };
+        let (first, last) = self.selected_rows();
+        if last >= self.document.len() {
+            return;
+        }
+        let input: String = (first..=last)
+            .filter_map(|y| self.document.row(y))
+            .map(|row| format!("{}\n", row.as_str()))
+            .collect();
+        let output = match shell::run(&command, Some(&input)) {
+            Ok(output) if output.success => output,
+            Ok(output) => {
+                for line in output.stderr.lines() {
+                    self.log(format!("{command}: {line}"));
+                }
+                self.log(tr!("ERR: {command} failed, nothing was changed", command));
+                return;
+            }
+            Err(error) => {
+                self.log(tr!("ERR: Could not run {command}: {error}", command, error));
+                return;
+            }
+        };
+        let text = output.stdout.strip_suffix('\n').unwrap_or(&output.stdout);
+        let count = self.document.replace_rows(first, last, text);
+        self.selection = None;
+        self.view.cursor = Position { x: 0, y: first };
+        self.clamp_cursor();
+        self.scroll();
+        self.status_message = StatusMessage::from(tr!(
+            "Filtered {lines} lines into {count}.",
+            lines = last.saturating_sub(first).saturating_add(1),
+            count
+        ));
+    }
+
+    /// Formats the selected lines, or the whole buffer if nothing is selected.
+    fn format(&mut self) {
+        if self.document.is_read_only() {
+            self.status_message = StatusMessage::from(tr!("This buffer is read-only."));
+            return;
+        }
+        let (first, last) = if self.selection.is_some() {
+            self.selected_rows()
+        } else {
+            (0, self.document.len().saturating_sub(1))
+        };
+        if self.run_formatter(first, last) {
+            self.status_message = StatusMessage::from(tr!("Formatted."));
+        }
+    }
+
+    /// Returns the formatter for the current file's type or extension.
+    fn formatter(&self) -> Option<&Formatter> {
+        self.for_file_type(&self.formatters)
+    }
+
+    /// Looks up the setting for the current file in `settings`, keyed by file
+    /// type, such as `rust`, or by extension, such as `py`.
+    fn for_file_type<'a, T>(&self, settings: &'a BTreeMap<String, T>) -> Option<&'a T> {
+        let file_type = self.document.file_type().to_lowercase();
+        let extension = self
+            .document
+            .file_name
+            .as_deref()
+            .and_then(|file_name| Path::new(file_name).extension())
+            .and_then(|extension| extension.to_str());
+        settings
+            .get(&file_type)
+            .or_else(|| settings.get(extension?))
+    }
+
+    /// Returns the diagnostics for the current file, in document order.
+    fn current_diagnostics(&self) -> Vec<&Diagnostic> {
+        if self.diagnostics.is_empty() {
+            return Vec::new();
+        }
+        let Some(path) = self.document.path() else {
+            return Vec::new();
+        };
+        self.diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.path == path)
+            .collect()
+    }
+
+    /// Returns the command of the language server for the current file.
+    fn language_server(&self) -> Option<String> {
+        if self.document.is_large() {
+            return None;
+        }
+        self.for_file_type(&self.language_servers).cloned()
+    }
+
+    /// Starts the language server for the current file if it is not running
+    /// yet, and sends it the text if it changed since it last saw it.
+    fn sync_language_server(&mut self) {
+        let Some(command) = self.language_server() else {
+            return;
+        };
+        let Some(path) = self.document.path().map(Path::to_path_buf) else {
+            return;
+        };
+        if !self.servers.contains_key(&command) {
+            let root = env::current_dir().unwrap_or_else(|_| PathBuf::from("/"));
+            let server = match LanguageServer::start(&command, &root) {
+                Ok(server) => Some(server),
+                Err(error) => {
+                    self.log(tr!(
+                        "ERR: Could not start {command}: {error}",
+                        command,
+                        error
+                    ));
+                    None
+                }
+            };
+            self.servers.insert(command.clone(), server);
+        }
+        let language_id = match self.document.file_type().as_str() {
+            "No filetype" => path
+                .extension()
+                .and_then(|extension| extension.to_str())
+                .unwrap_or_default()
+                .to_string(),
+            file_type => file_type.to_lowercase(),
+        };
+        let changed = self.rows_changed();
+        let document = &self.document;
+        let Some(Some(server)) = self.servers.get_mut(&command) else {
+            return;
+        };
+        if let Err(error) = server.sync(&path, &language_id, document.revision(), changed, || {
+            document.text()
+        }) {
+            self.log(tr!(
+                "ERR: Could not reach {command}: {error}",
+                command,
+                error
+            ));
+        }
+    }
+
+    /// Sends the document to the print command as PostScript, or writes it
+    /// to the file `args` as PostScript or, for a `.pdf` file, as PDF, with
+    /// the file name and page number atop each page.
+    fn print(&mut self, args: &str) {
+        if self.print_highlighting && !self.document.is_large() {
+            self.document.highlight_rows(0..self.document.len());
+        }
+        let format = if args.is_empty() {
+            Format::PostScript
+        } else {
+            Format::of(args)
+        };
+        let rows = (0..self.document.len()).filter_map(|y| self.document.row(y));
+        let (file, pages) = print::render(
+            format,
+            self.document.name(),
+            rows,
+            self.tab_width(),
+            self.print_highlighting,
+        );
+        let message = if args.is_empty() {
+            let command = &self.print_command;
+            match shell::run(command, Some(&String::from_utf8_lossy(&file))) {
+                Ok(output) if output.success => {
+                    tr!("Sent {pages} pages to {command}.", pages, command)
+                }
+                Ok(_) => tr!("ERR: {command} failed", command),
+                Err(error) => tr!("ERR: Could not run {command}: {error}", command, error),
+            }
+        } else {
+            match fs::write(args, file) {
+                Ok(()) => tr!(
+                    "Wrote {pages} pages to {file_name}.",
+                    pages,
+                    file_name = args
+                ),
+                Err(error) => tr!(
+                    "ERR: Could not write {file_name}: {error}",
+                    file_name = args,
+                    error
+                ),
+            }
+        };
+        self.status_message = StatusMessage::from(message);
+    }
+
+    /// Writes the open buffers, the options, the registers and the prompt
+    /// histories to the JSON file `file_name`.
+    fn export_state(&mut self, file_name: &str) {
+        if file_name.is_empty() {
+            self.status_message = StatusMessage::from(tr!("Usage: export_state <file>"));
+            return;
+        }
+        let buffer_state = |document: &Document, view: &View| BufferState {
+            name: document.name().to_string(),
+            file_name: document.file_name.clone(),
+            file_type: document.file_type(),
+            lines: document.len(),
+            modified: document.is_dirty(),
+            read_only: document.is_read_only(),
+            cursor: view.cursor.clone(),
+            offset: view.offset.clone(),
+        };
+        let state = State {
+            version: VERSION.to_string(),
+            buffers: self
+                .hidden_buffers
+                .iter()
+                .map(|buffer| buffer_state(&buffer.document, &buffer.view))
+                .chain([buffer_state(&self.document, &self.view)])
+                .collect(),
+            options: command::OPTIONS
+                .iter()
+                .filter_map(|name| Some(((*name).to_string(), self.flag(name)?)))
+                .collect(),
+            tab_width: self.tab_width,
+            mode: self.mode.name().to_string(),
+            registers: self
+                .registers
+                .iter()
+                .filter(|(name, _)| *name != registers::CLIPBOARD)
+                .map(|(name, register)| (name, register.clone()))
+                .collect(),
+            histories: self
+                .histories
+                .iter()
+                .map(|(prompt, history)| (prompt.clone(), history.entries().to_vec()))
+                .collect(),
+        };
+        let buffers = state.buffers.len();
+        let message = match state.write(file_name) {
+            Ok(()) => tr!(
+                "Exported the state of {buffers} buffers to {file_name}.",
+                buffers,
+                file_name
+            ),
+            Err(error) => tr!(
+                "ERR: Could not write {file_name}: {error}",
+                file_name,
+                error
+            ),
+        };
+        self.status_message = StatusMessage::from(message);
+    }
+
+    /// Restores what [`Editor::export_state`] wrote to `file_name`: buffers
+    /// showing a file are opened again with their cursors, and the options,
+    /// registers and histories are set as they were.
+    fn import_state(&mut self, file_name: &str) {
+        if file_name.is_empty() {
+            self.status_message = StatusMessage::from(tr!("Usage: import_state <file>"));
+            return;
+        }
+        let state = match State::read(file_name) {
+            Ok(state) => state,
+            Err(error) => {
+                self.status_message = StatusMessage::from(tr!(
+                    "ERR: Could not read the state in {file_name}: {error}",
+                    file_name,
+                    error
+                ));
+                return;
+            }
+        };
+        let mut restored = 0_usize;
+        let mut skipped = 0_usize;
+        for buffer in state.buffers {
+            match &buffer.file_name {
+                Some(path) if self.show_file(path, false) => {
+                    self.view.cursor = buffer.cursor;
+                    self.view.offset = Position {
+                        x: buffer.offset.x,
+                        y: cmp::min(buffer.offset.y, self.document.len()),
+                    };
+                    self.clamp_cursor();
+                    restored = restored.saturating_add(1);
+                }
+                _ => skipped = skipped.saturating_add(1),
+            }
+        }
+        for (name, value) in &state.options {
+            self.set_flag(name, *value);
+        }
+        if state.tab_width > 0 {
+            self.tab_width = state.tab_width;
+        }
+        self.set_mode(if state.mode == Mode::Replace.name() {
+            Mode::Replace
+        } else {
+            Mode::Insert
+        });
+        for (name, register) in state.registers {
+            self.registers.restore(name, register);
+        }
+        for (prompt, entries) in &state.histories {
+            let mut history = History::default();
+            for entry in entries {
+                history.push(entry);
+            }
+            self.histories.insert(prompt.clone(), history);
+        }
+        self.terminal.set_cursor_shape(self.mode.cursor_shape());
+        self.scroll();
+        self.status_message = StatusMessage::from(if skipped == 0 {
+            tr!(
+                "Restored {buffers} buffers from {file_name}.",
+                buffers = restored,
+                file_name
+            )
+        } else {
+            tr!(
+                "Restored {buffers} buffers from {file_name}, skipping {skipped} without a file.",
+                buffers = restored,
+                file_name,
+                skipped
+            )
+        });
+    }
+
+    /// Returns the rows of the document that changed since the last call, or
+    /// `None` if that is not known, as for a document not followed until
+    /// now, which is followed from then on.
+    fn rows_changed(&mut self) -> Option<RowsChanged> {
+        let revision = self.document.revision();
+        if let Some((file_name, since, changes)) = &mut self.server_changes {
+            if *file_name == self.document.file_name {
+                let mut span: Option<Span> = None;
+                let followed = loop {
+                    match changes.try_recv() {
+                        Ok(change) => {
+                            span =
+                                Some(span.map_or(change.span(), |span| span.then(change.span())));
+                        }
+                        Err(TryRecvError::Empty) => break true,
+                        // The document was read again, as a new one.
+                        Err(TryRecvError::Disconnected) => break false,
+                    }
+                };
+                if followed {
+                    let since = mem::replace(since, revision);
+                    return span.map(|span| RowsChanged {
+                        since,
+                        span,
+                        text: (span.start..span.new_end)
+                            .filter_map(|y| self.document.row(y))
+                            .map(|row| format!("{}\n", row.as_str()))
+                            .collect(),
+                    });
+                }
+            }
+        }
+        self.server_changes = Some((
+            self.document.file_name.clone(),
+            revision,
+            self.document.subscribe(),
+        ));
+        None
+    }
+
+    /// Asks the language server about the symbol at the cursor. The answer
+    /// is handled once it arrives.
+    fn ask_server(&mut self, request: Request, method: &str) {
+        let Some(command) = self.language_server() else {
+            self.status_message = StatusMessage::from(tr!(
+                "No language server for {file_type} files.",
+                file_type = self.document.file_type()
+            ));
+            return;
+        };
+        self.sync_language_server();
+        let Some(path) = self.document.path().map(Path::to_path_buf) else {
+            self.status_message =
+                StatusMessage::from(tr!("Save the file to use the language server."));
+            return;
+        };
+        let Position { x, y } = self.view.cursor;
+        let line = self.document.row(y).map_or("", Row::as_str);
+        let params = json!({
+            "textDocument": { "uri": lsp::uri(&path) },
+            "position": { "line": y, "character": lsp::utf16_column(line, x) },
+        });
+        let sent = match self.servers.get_mut(&command) {
+            Some(Some(server)) if server.is_initialized() => {
+                server.request(request, method, params)
+            }
+            Some(Some(_)) => {
+                self.status_message =
+                    StatusMessage::from(tr!("{command} is still starting.", command));
+                return;
+            }
+            _ => {
+                self.status_message =
+                    StatusMessage::from(tr!("{command} is not running.", command));
+                return;
+            }
+        };
+        if let Err(error) = sent {
+            self.log(tr!(
+                "ERR: Could not reach {command}: {error}",
+                command,
+                error
+            ));
+        }
+    }
+
+    /// Handles the messages from every language server. Returns whether
+    /// there were any.
+    fn update_servers(&mut self) -> bool {
+        let mut received = Vec::new();
+        for (command, server) in &mut self.servers {
+            if let Some(server) = server {
+                for message in server.poll() {
+                    received.push((command.clone(), message));
+                }
+            }
+        }
+        let any = !received.is_empty();
+        for (command, message) in received {
+            match message {
+                Message::Exited => {
+                    self.servers.insert(command.clone(), None);
+                    self.log(tr!("ERR: {command} exited", command));
+                }
+                Message::Response {
+                    result: Err(error), ..
+                } => self.log(format!("ERR: {command}: {error}")),
+                Message::Response {
+                    request,
+                    result: Ok(result),
+                } => match request {
+                    Request::Initialize => self.sync_language_server(),
+                    Request::Completion => self.complete(&result),
+                    Request::Hover => self.hover(&result),
+                    Request::Definition => self.goto_definition(&result),
+                },
+                Message::Notification { method, params } => {
+                    if method == "textDocument/publishDiagnostics" {
+                        self.receive_diagnostics(&params);
+                    }
+                }
+            }
+        }
+        any
+    }
+
+    /// Lets the user pick one of the completions a server offered, and
+    /// replaces the word before the cursor with it.
+    fn complete(&mut self, result: &Value) {
+        let items = result
+            .get("items")
+            .unwrap_or(result)
+            .as_array()
+            .cloned()
+            .unwrap_or_default();
+        let completions: Vec<(String, String)> = items
+            .iter()
+            .filter_map(|item| {
+                let label = item.get("label")?.as_str()?.to_string();
+                let text = item
+                    .pointer("/textEdit/newText")
+                    .or_else(|| item.get("insertText"))
+                    .and_then(Value::as_str)
+                    .unwrap_or(&label)
+                    .to_string();
+                Some((label, text))
+            })
+            .collect();
+        if completions.is_empty() {
+            self.status_message = StatusMessage::from(tr!("No completions."));
+            return;
+        }
+        let labels = completions.iter().map(|(label, _)| label.clone()).collect();
+        let Some((_, text)) = self
+            .pick(&tr!("Complete: "), labels)
+            .and_then(|index| completions.get(index))
+        else {
+            return;
+        };
+        let Position { mut x, y } = self.view.cursor;
+        let word_length = self.document.row(y).map_or(0, |row| {
+            let before: Vec<&str> = row.as_str().graphemes(true).take(x).collect();
+            before
+                .iter()
+                .rev()
+                .take_while(|grapheme| grapheme.chars().all(|c| c.is_alphanumeric() || c == '_'))
+                .count()
+        });
+        for _ in 0..word_length {
+            x = x.saturating_sub(1);
+            self.document.delete(&Position { x, y });
+        }
+        self.view.cursor = self.document.insert_str(&Position { x, y }, text);
+        self.scroll();
+    }
+
+    /// Completes the path before the cursor, such as the `../lib/` of an
+    /// include, from the files next to the document. Returns `false` if the
+    /// text before the cursor does not look like a path or no file matches
+    /// it, such as `a/b` in a division, for the language server to complete
+    /// it instead.
+    fn complete_path(&mut self) -> bool {
+        let Position { x, y } = self.view.cursor;
+        let partial = self.document.row(y).map_or_else(String::new, |row| {
+            let before: Vec<&str> = row.as_str().graphemes(true).take(x).collect();
+            let length = before
+                .iter()
+                .rev()
+                .take_while(|grapheme| {
+                    grapheme
+                        .chars()
+                        .all(|c| !c.is_whitespace() && !"\"'`<>()[]{},;=".contains(c))
+                })
+                .count();
+            before[before.len().saturating_sub(length)..].concat()
+        });
+        if !partial.contains('/') {
+            return false;
+        }
+        let base = self
+            .document
+            .file_name
+            .as_deref()
+            .and_then(|file_name| Path::new(file_name).parent())
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+        let candidates = command::complete_path_from(&base, &partial, &self.ignore);
+        if candidates.is_empty() {
+            return false;
+        }
+        let Some(rest) = self
+            .pick(&tr!("Complete: "), candidates.clone())
+            .and_then(|index| candidates.get(index))
+            .and_then(|path| path.get(partial.len()..))
+        else {
+            return true;
+        };
+        self.view.cursor = self.document.insert_str(&self.view.cursor, rest);
+        self.scroll();
+        true
+    }
+
+    fn hover(&mut self, result: &Value) {
+        let text = result.get("contents").map(hover_text).unwrap_or_default();
+        let text = text.trim();
+        if text.is_empty() {
+            self.status_message = StatusMessage::from(tr!("Nothing known about this."));
+        } else if text.lines().count() == 1 {
+            self.status_message = StatusMessage::from(text.to_string());
+        } else {
+            self.show_read_only(Document::read_only("[Hover]", text));
+        }
+    }
+
+    /// Jumps to the first definition a server pointed at.
+    fn goto_definition(&mut self, result: &Value) {
+        let location = match result {
+            Value::Array(locations) => locations.first(),
+            Value::Null => None,
+            location => Some(location),
+        };
+        let target = location.and_then(|location| {
+            let uri = location
+                .get("uri")
+                .or_else(|| location.get("targetUri"))?
+                .as_str()?;
+            let start = location
+                .pointer("/range/start")
+                .or_else(|| location.pointer("/targetSelectionRange/start"))?;
+            let line = start.get("line")?.as_u64()?;
+            let character = start.get("character")?.as_u64()?;
+            Some((
+                lsp::path(uri)?,
+                usize::try_from(line).ok()?,
+                usize::try_from(character).ok()?,
+            ))
+        });
+        let Some((path, y, character)) = target else {
+            self.status_message = StatusMessage::from(tr!("No definition found."));
+            return;
+        };
+        // Files under the working directory are named relative to it, as
+        // they usually were when opened.
+        let file_name = env::current_dir()
+            .ok()
+            .and_then(|dir| path.strip_prefix(dir).ok().map(Path::to_path_buf))
+            .unwrap_or(path);
+        let from = self.current_jump();
+        let jump = Jump {
+            file_name: Some(file_name.to_string_lossy().into_owned()),
+            position: Position { x: 0, y },
+        };
+        if self.jump_to(jump) {
+            self.jumps.push(from);
+            let line = self.document.row(y).map_or("", Row::as_str);
+            self.view.cursor.x = lsp::grapheme_column(line, character);
+            self.clamp_cursor();
+            self.scroll();
+        }
+    }
+
+    /// Replaces the diagnostics of the file a server reported on.
+    fn receive_diagnostics(&mut self, params: &Value) {
+        let Some(path) = params
+            .get("uri")
+            .and_then(Value::as_str)
+            .and_then(lsp::path)
+        else {
+            return;
+        };
+        let path = fs::canonicalize(&path).unwrap_or(path);
+        let current = self.document.path();
+        let received: Vec<Diagnostic> = params
+            .get("diagnostics")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(|diagnostic| {
+                let start = diagnostic.pointer("/range/start")?;
+                let y = usize::try_from(start.get("line")?.as_u64()?).ok()?;
+                let character = usize::try_from(start.get("character")?.as_u64()?).ok()?;
+                // Columns can only be converted for the document on screen.
+                let x = match (&current, self.document.row(y)) {
+                    (Some(current), Some(row)) if *current == path => {
+                        lsp::grapheme_column(row.as_str(), character)
+                    }
+                    _ => character,
+                };
+                let severity = match diagnostic.get("severity").and_then(Value::as_u64) {
+                    Some(1) => Severity::Error,
+                    _ => Severity::Warning,
+                };
+                Some(Diagnostic {
+                    path: path.clone(),
+                    position: Position { x, y },
+                    severity,
+                    message: diagnostic.get("message")?.as_str()?.to_string(),
+                })
+            })
+            .collect();
+        self.diagnostics
+            .retain(|diagnostic| diagnostic.path != path);
+        self.diagnostics.extend(received);
+        self.diagnostics
+            .sort_by_key(|diagnostic| (diagnostic.position.y, diagnostic.position.x));
+    }
+
+    /// Moves the cursor to the next or previous diagnostic in the buffer,
+    /// wrapping around at either end.
+    fn visit_diagnostic(&mut self, forward: bool) {
+        let diagnostics = self.current_diagnostics();
+        let cursor = (self.view.cursor.y, self.view.cursor.x);
+        let at = |diagnostic: &&Diagnostic| (diagnostic.position.y, diagnostic.position.x);
+        let target = if forward {
+            diagnostics
+                .iter()
+                .find(|diagnostic| at(diagnostic) > cursor)
+                .or_else(|| diagnostics.first())
+        } else {
+            diagnostics
+                .iter()
+                .rev()
+                .find(|diagnostic| at(diagnostic) < cursor)
+                .or_else(|| diagnostics.last())
+        };
+        let Some(diagnostic) = target else {
+            self.status_message = StatusMessage::from(tr!("The linter found no problems here."));
+            return;
+        };
+        let position = diagnostic.position.clone();
+        let message = diagnostic.message.clone();
+        let jump = self.current_jump();
+        self.jumps.push(jump);
+        self.view.cursor = position;
+        self.clamp_cursor();
+        self.scroll();
+        self.status_message = StatusMessage::from(message);
+    }
+
+    /// Finds the merge conflicts in the document again if it changed.
+    fn update_conflicts(&mut self) {
+        let revision = self.document.revision();
+        if revision == self.conflicts_revision {
+            return;
+        }
+        self.conflicts_revision = revision;
+        self.conflicts = if self.document.is_large() {
+            Vec::new()
+        } else {
+            conflicts::find(
+                (0..self.document.len())
+                    .filter_map(|y| self.document.row(y))
+                    .map(Row::as_str),
+            )
+        };
+    }
+
+    /// Returns the part of a merge conflict row `index` is in, if any.
+    fn conflict_section(&self, index: usize) -> Option<Section> {
+        self.conflicts
+            .iter()
+            .find_map(|conflict| conflict.section(index))
+    }
+
+    /// Moves to the start of the next merge conflict after the cursor, or of
+    /// the previous one before it, going around at either end.
+    fn visit_conflict(&mut self, forward: bool) {
+        self.update_conflicts();
+        let y = self.view.cursor.y;
+        let target = if forward {
+            self.conflicts
+                .iter()
+                .find(|conflict| conflict.start > y)
+                .or_else(|| self.conflicts.first())
+        } else {
+            self.conflicts
+                .iter()
+                .rev()
+                .find(|conflict| conflict.start < y)
+                .or_else(|| self.conflicts.last())
+        };
+        let Some(start) = target.map(|conflict| conflict.start) else {
+            self.status_message = StatusMessage::from(tr!("No merge conflicts."));
+            return;
+        };
+        let jump = self.current_jump();
+        self.jumps.push(jump);
+        self.view.cursor = Position { x: 0, y: start };
+        self.scroll();
+    }
+
+    /// Jumps to where the next section starts, or the previous one, as the
+    /// outline finds them.
+    fn visit_section(&mut self, forward: bool) {
+        if self.document.is_large() {
+            self.status_message = StatusMessage::from(tr!("The outline is off for large files."));
+            return;
+        }
+        let mut outline = Outline::default();
+        outline.update(&self.document);
+        let y = self.view.cursor.y;
+        let target = if forward {
+            outline.sections().find(|&line| line > y)
+        } else {
+            outline.sections().rev().find(|&line| line < y)
+        };
+        let Some(y) = target else {
+            self.status_message = StatusMessage::from(tr!("No more sections."));
+            return;
+        };
+        let jump = self.current_jump();
+        self.jumps.push(jump);
+        self.view.cursor = Position { x: 0, y };
+        self.scroll();
+    }
+
+    /// Replaces the merge conflict under the cursor with `ours`, `theirs`
+    /// or both, ours first, as a single edit.
+    fn resolve_conflict(&mut self, ours: bool, theirs: bool) {
+        if self.document.is_read_only() {
+            self.status_message = StatusMessage::from(tr!("This buffer is read-only."));
+            return;
+        }
+        self.update_conflicts();
+        let y = self.view.cursor.y;
+        let Some(conflict) = self
+            .conflicts
+            .iter()
+            .find(|conflict| conflict.contains(y))
+            .cloned()
+        else {
+            self.status_message = StatusMessage::from(tr!("No merge conflict at the cursor."));
+            return;
+        };
+        let mut kept = Vec::new();
+        if ours {
+            kept.extend(conflict.ours());
+        }
+        if theirs {
+            kept.extend(conflict.theirs());
+        }
+        let lines: Vec<&str> = kept
+            .into_iter()
+            .filter_map(|y| self.document.row(y))
+            .map(Row::as_str)
+            .collect();
+        let text = lines.join("\n");
+        self.document
+            .replace_rows(conflict.start, conflict.end, &text);
+        self.view.cursor = Position {
+            x: 0,
+            y: conflict.start,
+        };
+        self.clamp_cursor();
+        self.scroll();
+        self.update_conflicts();
+        self.status_message = StatusMessage::from(tr!(
+            "Conflict resolved, {count} left.",
+            count = self.conflicts.len()
+        ));
+    }
+
+    /// Pipes the rows from `first` through `last` through the formatter and
+    /// applies only the lines it changed, so the cursor and marks elsewhere
+    /// stay put. Returns whether the formatter succeeded.
+    fn run_formatter(&mut self, first: usize, last: usize) -> bool {
+        let Some(command) = self.formatter().map(|formatter| formatter.command.clone()) else {
+            self.status_message = StatusMessage::from(tr!(
+                "No formatter for {file_type} files.",
+                file_type = self.document.file_type()
+            ));
+            return false;
+        };
+        if last >= self.document.len() {
+            return true;
+        }
+        let input: String = (first..=last)
+            .filter_map(|y| self.document.row(y))
+            .map(|row| format!("{}\n", row.as_str()))
+            .collect();
+        let output = match shell::run(&command, Some(&input)) {
+            Ok(output) if output.success => output,
+            Ok(output) => {
+                for line in output.stderr.lines() {
+                    self.log(format!("{command}: {line}"));
+                }
+                self.log(tr!("ERR: {command} failed, nothing was changed", command));
+                return false;
+            }
+            Err(error) => {
+                self.log(tr!("ERR: Could not run {command}: {error}", command, error));
+                return false;
+            }
+        };
+        let text = output.stdout.strip_suffix('\n').unwrap_or(&output.stdout);
+        let Some((start, replaced, count)) = self.document.patch_rows(first, last, text) else {
+            return true;
+        };
+        let y = self.view.cursor.y;
+        if y >= start.saturating_add(replaced) {
+            self.view.cursor.y = y.saturating_sub(replaced).saturating_add(count);
+        } else if y >= start {
+            self.view.cursor.y = cmp::min(y, start.saturating_add(count).saturating_sub(1));
+        }
+        self.selection = None;
+        self.clamp_cursor();
+        self.scroll();
+        true
+    }
+
+    /// Returns the selection as its start and end, in document order.
+    fn selection_range(&self) -> Option<(Position, Position)> {
+        let anchor = self.selection.clone()?;
+        let cursor = self.view.cursor.clone();
+        if (anchor.y, anchor.x) <= (cursor.y, cursor.x) {
+            Some((anchor, cursor))
+        } else {
+            Some((cursor, anchor))
+        }
+    }
+
+    /// Returns the first and last rows touched by the selection, or the
+    /// current row if nothing is selected.
+    fn selected_rows(&self) -> (usize, usize) {
+        match self.selection_range() {
+            Some((start, end)) => (start.y, end.y),
+            None => (self.view.cursor.y, self.view.cursor.y),
+        }
+    }
+
+    fn extend_selection(&mut self, key: Key) {
+        if self.selection.is_none() {
+            self.selection = Some(self.view.cursor.clone());
+        }
+        self.move_cursor(key);
+    }
+
+    /// Runs a command and shows its output in a read-only buffer.
+    fn shell(&mut self, command: &str) {
+        if let Some(output) = self.run_shell(command, None) {
+            self.show_read_only(Document::read_only("[Output]", &output));
+        }
+    }
+
+    fn undo(&mut self, redo: bool) {
+        let cursor = if redo {
+            self.document.redo()
+        } else {
+            self.document.undo()
+        };
+        match cursor {
+            Some(cursor) => self.place_cursor(cursor),
+            None => {
+                let message = if redo {
+                    tr!("Already at the newest change.")
+                } else {
+                    tr!("Already at the oldest change.")
+                };
+                self.status_message = StatusMessage::from(message);
+            }
+        }
+    }
+
+    /// Lets the user pick a state from the document's undo tree and brings
+    /// the document to it.
+    fn pick_undo_state(&mut self) {
+        let tree = self.document.undo_tree();
+        let current = tree.current();
+        let (states, items): (Vec<usize>, Vec<String>) = tree.lines().into_iter().unzip();
+        let selected = states
+            .iter()
+            .position(|state| *state == current)
+            .unwrap_or(0);
+        let Some(state) = self
+            .pick_at(&tr!("Undo tree: "), items, selected)
+            .and_then(|index| states.get(index))
+        else {
+            return;
+        };
+        if let Some(cursor) = self.document.undo_to(*state) {
+            self.place_cursor(cursor);
+        }
+    }
+
+    /// Drops the undo states used least recently to keep within the limits
+    /// set. The buffers used least recently give up their history first.
+    fn trim_undo(&mut self) {
+        let limit = |limit: usize| if limit == 0 { usize::MAX } else { limit };
+        let (depth, memory) = (limit(self.undo_depth), limit(self.undo_memory));
+        let mut documents: Vec<&mut Document> = iter::once(&mut self.document)
+            .chain(
+                self.hidden_buffers
+                    .iter_mut()
+                    .map(|buffer| &mut buffer.document),
+            )
+            .collect();
+        let mut total: usize = documents
+            .iter()
+            .map(|document| document.undo_tree().memory())
+            .sum();
+        documents.sort_by_key(|document| document.undo_tree().last_used());
+        for document in documents {
+            let others = total.saturating_sub(document.undo_tree().memory());
+            document.trim_undo(depth, memory.saturating_sub(others));
+            total = others.saturating_add(document.undo_tree().memory());
+        }
+    }
+
+    fn show_undo_status(&mut self) {
+        let tree = self.document.undo_tree();
+        let states = tree.state_count();
+        let size = tree.memory() >> 10;
+        let total = iter::once(&self.document)
+            .chain(self.hidden_buffers.iter().map(|buffer| &buffer.document))
+            .map(|document| document.undo_tree().memory())
+            .sum::<usize>()
+            >> 10;
+        let message = if self.undo_memory == 0 {
+            tr!(
+                "Undo history: {states} states taking {size} KB here, {total} KB in all buffers.",
+                states,
+                size,
+                total
+            )
+        } else {
+            tr!(
+                "Undo history: {states} states taking {size} KB here, {total} of {limit} KB in all buffers.",
+                states,
+                size,
+                total,
+                limit = self.undo_memory >> 10
+            )
+        };
+        self.status_message = StatusMessage::from(message);
+    }
+
+    /// Returns the register named by `args`, or if there is none the one
+    /// named by the `register` command, or the default register.
+    fn take_register(&mut self, args: &str) -> Option<char> {
+        let named = self.register.take();
+        if args.is_empty() {
+            return Some(named.unwrap_or(registers::DEFAULT));
+        }
+        self.register_name(args, "")
+    }
+
+    /// Takes the register name from `args`, or reads it as the next key
+    /// pressed.
+    fn register_name(&mut self, args: &str, prompt: &str) -> Option<char> {
+        let name = if args.is_empty() {
+            match self.ask(prompt) {
+                Some(Key::Char(c)) => c,
+                _ => return None,
+            }
+        } else {
+            let mut chars = args.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => c,
+                _ => ' ',
+            }
+        };
+        if Registers::is_valid(name) {
+            Some(name)
+        } else {
+            self.status_message = StatusMessage::from(tr!(
+                "Registers are named by a letter, {default} or {clipboard}.",
+                default = registers::DEFAULT,
+                clipboard = registers::CLIPBOARD
+            ));
+            None
+        }
+    }
+
+    /// Copies the selection into a register, or the current line if nothing
+    /// is selected, and with `cut` removes it from the document.
+    fn yank(&mut self, args: &str, cut: bool) {
+        let Some(name) = self.take_register(args) else {
+            return;
+        };
+        if cut && self.document.is_read_only() {
+            self.status_message = StatusMessage::from(tr!("This buffer is read-only."));
+            return;
+        }
+        let register = match self.selection_range() {
+            Some((start, end)) => {
+                let text = self.document.text_range(&start, &end);
+                if cut {
+                    self.document.delete_range(&start, &end);
+                    self.place_cursor(start);
+                }
+                Register {
+                    text,
+                    linewise: false,
+                }
+            }
+            None => {
+                let y = self.view.cursor.y;
+                let Some(text) = self.document.row(y).map(|row| row.as_str().to_string()) else {
+                    return;
+                };
+                if cut {
+                    self.document.delete_row(y);
+                    self.clamp_cursor();
+                }
+                Register {
+                    text,
+                    linewise: true,
+                }
+            }
+        };
+        let lines = register.text.split('\n').count();
+        self.registers.set(name, register);
+        self.selection = None;
+        let message = match (cut, lines) {
+            (true, 1) => tr!("Cut 1 line into register '{name}'.", name),
+            (true, _) => tr!("Cut {lines} lines into register '{name}'.", lines, name),
+            (false, 1) => tr!("Yanked 1 line into register '{name}'.", name),
+            (false, _) => tr!("Yanked {lines} lines into register '{name}'.", lines, name),
+        };
+        self.status_message = StatusMessage::from(message);
+    }
+
+    /// Pastes a register at the cursor, replacing the selection, or below
+    /// the cursor's line if it holds whole lines.
+    fn paste(&mut self, args: &str) {
+        let Some(name) = self.take_register(args) else {
+            return;
+        };
+        if self.document.is_read_only() {
+            self.status_message = StatusMessage::from(tr!("This buffer is read-only."));
+            return;
+        }
+        let Some(register) = self.registers.get(name) else {
+            self.status_message = StatusMessage::from(tr!("Register '{name}' is empty.", name));
+            return;
+        };
+        self.put(register);
+    }
+
+    /// Lets the user pick one of the latest yanks and deletions, searching
+    /// and previewing them, and pastes it.
+    fn pick_yank(&mut self) {
+        if self.document.is_read_only() {
+            self.status_message = StatusMessage::from(tr!("This buffer is read-only."));
+            return;
+        }
+        let registers: Vec<Register> = self.registers.history().iter().rev().cloned().collect();
+        if registers.is_empty() {
+            self.status_message = StatusMessage::from(tr!("Nothing was yanked or deleted yet."));
+            return;
+        }
+        let items = registers
+            .iter()
+            .map(|register| {
+                let kind = if register.linewise { "lines" } else { "text" };
+                format!("{kind:<6}{}", register.text.replace('\n', "\\n"))
+            })
+            .collect();
+        let previews = registers
+            .iter()
+            .map(|register| register.text.clone())
+            .collect();
+        let picker = Picker::new(items).with_previews(previews);
+        if let Some(index) = self.run_picker(&tr!("Paste: "), picker) {
+            if let Some(register) = registers.get(index) {
+                self.put(register.clone());
+            }
+        }
+    }
+
+    /// Pastes `register` as [`Editor::paste`] does.
+    fn put(&mut self, register: Register) {
+        if register.linewise {
+            let y = self.view.cursor.y.saturating_add(1);
+            if y < self.document.len() {
+                self.document
+                    .insert_str(&Position { x: 0, y }, &format!("{}\n", register.text));
+            } else {
+                let last = self.document.len().saturating_sub(1);
+                let x = self.document.row(last).map_or(0, Row::len);
+                let text = if self.document.is_empty() {
+                    register.text
+                } else {
+                    format!("\n{}", register.text)
+                };
+                self.document.insert_str(&Position { x, y: last }, &text);
+            }
+            self.place_cursor(Position {
+                x: 0,
+                y: cmp::min(y, self.document.len().saturating_sub(1)),
+            });
+        } else {
+            if let Some((start, end)) = self.selection_range() {
+                self.document.delete_range(&start, &end);
+                self.view.cursor = start;
+            }
+            let cursor = self.document.insert_str(&self.view.cursor, &register.text);
+            self.place_cursor(cursor);
+        }
+    }
+
+    fn show_registers(&mut self) {
+        let lines: Vec<String> = self
+            .registers
+            .iter()
+            .map(|(name, register)| {
+                let text = register.text.replace('\n', "\\n");
+                let kind = if register.linewise { "lines" } else { "text" };
+                format!("{name}  {kind:<6}{text}")
+            })
+            .collect();
+        if lines.is_empty() {
+            self.status_message = StatusMessage::from(tr!("All registers are empty."));
+            return;
+        }
+        self.show_read_only(Document::read_only("[Registers]", &lines.join("\n")));
+    }
+
+    fn place_cursor(&mut self, cursor: Position) {
+        self.selection = None;
+        self.view.cursor = cursor;
+        self.clamp_cursor();
+        self.scroll();
+    }
+
+    /// Returns the change under the cursor between the document and its
+    /// version in `base`, telling the user if there is none.
+    fn hunk_at_cursor(&mut self, base: Base) -> Option<Hunk> {
+        let Some(file_name) = self.document.file_name.clone() else {
+            self.status_message = StatusMessage::from(tr!("This buffer has no file."));
+            return None;
+        };
+        let hunks = match git::hunks(&file_name, base, &self.document.text()) {
+            Ok(hunks) => hunks,
+            Err(error) => {
+                self.status_message = StatusMessage::from(error);
+                return None;
+            }
+        };
+        let y = self.view.cursor.y;
+        let hunk = hunks.into_iter().find(|hunk| hunk.contains(y));
+        if hunk.is_none() {
+            self.status_message = StatusMessage::from(tr!("No change under the cursor."));
+        }
+        hunk
+    }
+
+    fn stage_hunk(&mut self) {
+        let Some(hunk) = self.hunk_at_cursor(Base::Index) else {
+            return;
+        };
+        let file_name = self.document.file_name.clone().unwrap_or_default();
+        match git::stage(&file_name, &hunk) {
+            Ok(()) => {
+                self.refresh_git();
+                self.status_message = StatusMessage::from(tr!("Change staged."));
+            }
+            Err(error) => self.status_message = StatusMessage::from(error),
+        }
+    }
+
+    /// Puts back the rows the change under the cursor replaced, as they were
+    /// in the last commit.
+    fn revert_hunk(&mut self) {
+        if self.document.is_read_only() {
+            self.status_message = StatusMessage::from(tr!("This buffer is read-only."));
+            return;
+        }
+        let Some(hunk) = self.hunk_at_cursor(Base::Head) else {
+            return;
+        };
+        let start = hunk.new_start;
+        if hunk.new_count > 0 {
+            self.document.replace_rows(
+                start,
+                start.saturating_add(hunk.new_count).saturating_sub(1),
+                "",
+            );
+        }
+        let old = hunk.old_lines();
+        if !old.is_empty() {
+            let mut text = old.join("\n");
+            if start < self.document.len() {
+                text.push('\n');
+            }
+            self.document
+                .insert_str(&Position { x: 0, y: start }, &text);
+        }
+        self.view.cursor = Position { x: 0, y: start };
+        self.clamp_cursor();
+        self.scroll();
+        self.status_message = StatusMessage::from(tr!("Change reverted."));
+    }
+
+    /// Returns the identifier the cursor is on or right after.
+    fn word_at_cursor(&self) -> Option<String> {
+        let Position { x, y } = self.view.cursor;
+        let graphemes: Vec<&str> = self.document.row(y)?.as_str().graphemes(true).collect();
+        let is_word = |grapheme: &&str| grapheme.chars().all(|c| c.is_alphanumeric() || c == '_');
+        let start = graphemes
+            .get(..x)?
+            .iter()
+            .rposition(|grapheme| !is_word(grapheme))
+            .map_or(0, |index| index.saturating_add(1));
+        let end = graphemes
+            .get(x..)?
+            .iter()
+            .position(|grapheme| !is_word(grapheme))
+            .map_or(graphemes.len(), |index| index.saturating_add(x));
+        let word = graphemes.get(start..end)?.concat();
+        if word.is_empty() {
+            None
+        } else {
+            Some(word)
+        }
+    }
+
+    /// Reads the tags file closest to the current file.
+    fn load_tags(&mut self) -> Option<Vec<Tag>> {
+        let dir = self
+            .document
+            .path()
+            .and_then(Path::parent)
+            .map(Path::to_path_buf)
+            .or_else(|| env::current_dir().ok())?;
+        match tags::load(&dir) {
+            Ok(tags) => Some(tags),
+            Err(error) => {
+                self.status_message = StatusMessage::from(error);
+                None
+            }
+        }
+    }
+
+    /// Jumps to the definition of `name`, or of the word at the cursor,
+    /// letting the user choose if there are several.
+    fn tag(&mut self, name: &str) {
+        let name = if name.is_empty() {
+            match self.word_at_cursor() {
+                Some(word) => word,
+                None => {
+                    self.status_message =
+                        StatusMessage::from(tr!("No identifier under the cursor."));
+                    return;
+                }
+            }
+        } else {
+            name.to_string()
+        };
+        let Some(tags) = self.load_tags() else {
+            return;
+        };
+        let mut matches: Vec<Tag> = tags.into_iter().filter(|tag| tag.name == name).collect();
+        let index = match matches.len() {
+            0 => {
+                self.status_message = StatusMessage::from(tr!("Tag not found: {name}", name));
+                return;
+            }
+            1 => 0,
+            _ => {
+                let items = matches.iter().map(describe_tag).collect();
+                match self.pick(&format!("{name}: "), items) {
+                    Some(index) => index,
+                    None => return,
+                }
+            }
+        };
+        if index < matches.len() {
+            let tag = matches.swap_remove(index);
+            self.go_to_tag(&tag);
+        }
+    }
+
+    fn search_tags(&mut self) {
+        let Some(mut tags) = self.load_tags() else {
+            return;
+        };
+        let items = tags
+            .iter()
+            .map(|tag| format!("{}  {}", tag.name, describe_tag(tag)))
+            .collect();
+        if let Some(index) = self.pick(&tr!("Tag: "), items) {
+            if index < tags.len() {
+                let tag = tags.swap_remove(index);
+                self.go_to_tag(&tag);
+            }
+        }
+    }
+
+    /// Opens the file of `tag` at its definition, remembering where the jump
+    /// started on the tag stack.
+    fn go_to_tag(&mut self, tag: &Tag) {
+        let from = self.current_jump();
+        let line = match &tag.address {
+            Address::Line(line) => *line,
+            Address::Pattern(_) => 0,
+        };
+        let jump = Jump {
+            file_name: Some(tag.file_name.clone()),
+            position: Position { x: 0, y: line },
+        };
+        if !self.jump_to(jump) {
+            return;
+        }
+        if let Address::Pattern(pattern) = &tag.address {
+            let found = (0..self.document.len()).find(|y| {
+                self.document
+                    .row(*y)
+                    .is_some_and(|row| row.as_str().starts_with(pattern.as_str()))
+            });
+            match found {
+                Some(y) => self.view.cursor = Position { x: 0, y },
+                None => {
+                    self.status_message = StatusMessage::from(tr!(
+                        "The definition of {name} moved, the tags file is out of date.",
+                        name = tag.name
+                    ));
+                }
+            }
+        }
+        // Put the cursor on the name, as the definition line often starts
+        // with keywords.
+        if let Some(x) = self.document.row(self.view.cursor.y).and_then(|row| {
+            row.as_str()
+                .find(tag.name.as_str())
+                .map(|index| row.as_str()[..index].graphemes(true).count())
+        }) {
+            self.view.cursor.x = x;
+        }
+        self.clamp_cursor();
+        let height = self.text_height();
+        self.view.offset.y = self.view.cursor.y.saturating_sub(height / 2);
+        self.scroll();
+        self.jumps.push(from.clone());
+        self.tag_stack.push(from);
+        if self.tag_stack.len() > MAX_TAG_DEPTH {
+            self.tag_stack.remove(0);
+        }
+    }
+
+    /// Runs the build command with `args` added, collecting the errors it
+    /// reports, and jumps to the first one.
+    fn make(&mut self, args: &str) {
+        let command = if args.is_empty() {
+            self.make.clone()
+        } else {
+            format!("{} {args}", self.make)
+        };
+        // The build blocks the editor, so say what it is waiting for.
+        self.status_message = StatusMessage::from(tr!("Running {command}...", command));
+        let _ = self.refresh_screen();
+        let output = match shell::run(&command, None) {
+            Ok(output) => output,
+            Err(error) => {
+                self.log(tr!("ERR: Could not run {command}: {error}", command, error));
+                return;
+            }
+        };
+        let text = format!("{}{}", output.stdout, output.stderr);
+        self.locations = LocationList::new(locations::parse(&text));
+        if !self.locations.is_empty() {
+            self.visit_error(true);
+        } else if output.success {
+            self.log(tr!("{command} succeeded.", command));
+        } else {
+            self.show_read_only(Document::read_only("[Make]", &text));
+            self.log(tr!("ERR: {command} failed", command));
+        }
+    }
+
+    /// Starts searching the project for tasks, to be listed once found.
+    fn scan_tasks(&mut self) {
+        if self.task_scan.is_some() {
+            return;
+        }
+        self.task_scan = Some(Scan::start(self.ignore.clone()));
+        self.status_message = StatusMessage::from(tr!("Searching for tasks..."));
+    }
+
+    /// Lists the tasks found once the search has finished, in place of the
+    /// last list. Returns whether there is anything new to show.
+    fn update_tasks(&mut self) -> bool {
+        let Some(result) = self.task_scan.as_ref().and_then(Scan::poll) else {
+            return false;
+        };
+        self.task_scan = None;
+        let found = match result {
+            Ok(found) => found,
+            Err(error) => {
+                self.log(format!("ERR: {error}"));
+                return true;
+            }
+        };
+        let count = found.len();
+        let (text, rows) = tasks::list(found);
+        self.tasks = rows;
+        if self.document.name() != TASKS_NAME {
+            if let Some(index) = self
+                .hidden_buffers
+                .iter()
+                .position(|buffer| buffer.document.name() == TASKS_NAME)
+            {
+                self.switch_to(index);
+            }
+        }
+        self.show_read_only(Document::read_only(TASKS_NAME, &text));
+        self.status_message = StatusMessage::from(tr!("Found {count} tasks.", count));
+        true
+    }
+
+    /// Jumps to the task on the cursor's row of the task list.
+    fn visit_task(&mut self) {
+        let Some(Some(task)) = self.tasks.get(self.view.cursor.y) else {
+            return;
+        };
+        let jump = Jump {
+            file_name: Some(task.file_name.clone()),
+            position: task.position.clone(),
+        };
+        let from = self.current_jump();
+        if self.jump_to(jump) {
+            self.jumps.push(from);
+        }
+    }
+
+    /// Jumps to the next or previous error reported by the last build.
+    fn visit_error(&mut self, forward: bool) {
+        let count = self.locations.len();
+        let location = if forward {
+            self.locations.next()
+        } else {
+            self.locations.previous()
+        };
+        let Some((index, location)) = location else {
+            self.status_message = StatusMessage::from(tr!("The last build reported no errors."));
+            return;
+        };
+        let jump = Jump {
+            file_name: Some(location.file_name.clone()),
+            position: location.position.clone(),
+        };
+        let message = format!(
+            "({}/{}) {}",
+            index.saturating_add(1),
+            count,
+            location.message
+        );
+        let from = self.current_jump();
+        if self.jump_to(jump) {
+            self.jumps.push(from);
+            self.status_message = StatusMessage::from(message);
+        }
+    }
+
+    /// Lets the user pick a buffer, most recently used first.
+    fn pick_buffer(&mut self) {
+        let describe = |document: &Document| {
+            let modified = if document.is_dirty() { " [+]" } else { "" };
+            format!("{}{modified}", document.name())
+        };
+        let mut items: Vec<String> = self
+            .hidden_buffers
+            .iter()
+            .rev()
+            .map(|buffer| describe(&buffer.document))
+            .collect();
+        items.push(tr!("{name} (current)", name = describe(&self.document)));
+        if let Some(index) = self.pick(&tr!("Buffer: "), items) {
+            let hidden = self.hidden_buffers.len();
+            if index < hidden {
+                self.switch_to(hidden.saturating_sub(1).saturating_sub(index));
+            }
+        }
+    }
+
+    /// Brings the background buffer at `index` to the front.
+    fn switch_to(&mut self, index: usize) {
+        if index >= self.hidden_buffers.len() {
+            return;
+        }
+        let buffer = self.hidden_buffers.remove(index);
+        self.push_buffer(buffer.document);
+        self.view = buffer.view;
+    }
+
+    fn current_jump(&self) -> Jump {
+        Jump {
+            file_name: self.document.file_name.clone(),
+            position: self.view.cursor.clone(),
+        }
+    }
+
+    /// Moves the cursor to `jump`, opening its file if it is not the current
+    /// one. Returns `false` if the file could not be opened.
+    fn jump_to(&mut self, jump: Jump) -> bool {
+        if jump.file_name != self.document.file_name {
+            let Some(file_name) = &jump.file_name else {
+                self.status_message = StatusMessage::from(tr!("Cannot jump to an unnamed buffer."));
+                return false;
+            };
+            if !self.show_file(file_name, false) {
+                return false;
+            }
+        }
+        self.view.cursor = jump.position;
+        self.clamp_cursor();
+        self.scroll();
+        true
+    }
+
+    fn open(&mut self, file_name: &str, force: bool) {
+        if file_name.is_empty() && force {
+            self.revert();
+            return;
+        }
+        if file_name.is_empty() {
+            self.status_message = StatusMessage::from(tr!("Usage: open <file>"));
+            return;
+        }
+        let jump = self.current_jump();
+        if !glob::is_pattern(file_name) {
+            if self.show_file(file_name, force) {
+                self.jumps.push(jump);
+            }
+            return;
+        }
+        let files = glob::files(file_name);
+        if files.is_empty() {
+            self.status_message =
+                StatusMessage::from(tr!("No files match {pattern}", pattern = file_name));
+            return;
+        }
+        // Each is opened in turn, then the first is made current again.
+        for file in files.iter().rev() {
+            self.show_file(file, force);
+        }
+        self.jumps.push(jump);
+        if files.len() > 1 {
+            self.status_message =
+                StatusMessage::from(tr!("Opened {count} files.", count = files.len()));
+        }
+    }
+
+    /// Reads the current file again, dropping its unsaved changes and the
+    /// undo history made since it was saved. The cursor stays where it was,
+    /// or as near as the file allows.
+    fn revert(&mut self) {
+        let Some(file_name) = self.document.file_name.clone() else {
+            self.status_message = StatusMessage::from(tr!("This buffer has no file."));
+            return;
+        };
+        if self.show_file(&file_name, true) {
+            self.status_message = StatusMessage::from(tr!("Reverted to the file on disk."));
+        }
+    }
+
+    /// Makes the buffer for `file_name` current, opening the file if it is not
+    /// open yet. With `reload`, the file is read again and unsaved changes to
+    /// it are lost, keeping the cursor where it was. Returns `false` if the
+    /// file could not be opened.
+    fn show_file(&mut self, file_name: &str, reload: bool) -> bool {
+        if self.document.file_name.as_deref() != Some(file_name) {
+            if let Some(index) = self
+                .hidden_buffers
+                .iter()
+                .position(|buffer| buffer.document.file_name.as_deref() == Some(file_name))
+            {
+                self.switch_to(index);
+            }
+        }
+        let is_current = self.document.file_name.as_deref() == Some(file_name);
+        if is_current && !reload {
+            return true;
+        }
+        if !self.confirm_large_file(file_name) {
+            return false;
+        }
+        match self.open_document(file_name) {
+            Ok(document) => {
+                // An untouched empty buffer, like the one hecto starts with
+                // when given no file, is not worth keeping around.
+                let untouched = self.document.file_name.is_none()
+                    && !self.document.is_scratch()
+                    && self.document.is_empty()
+                    && !self.document.is_dirty()
+                    && !self.document.is_read_only();
+                if is_current {
+                    self.document = document;
+                    self.selection = None;
+                    self.clamp_cursor();
+                    self.scroll();
+                    self.refresh_git();
+                } else if untouched {
+                    self.document = document;
+                    self.view.cursor = Position::default();
+                    self.view.offset = Position::default();
+                    self.refresh_git();
+                } else {
+                    self.push_buffer(document);
+                }
+                if !is_current {
+                    self.restore_cursor();
+                }
+                self.offer_to_strip_ansi();
+                self.publish(&EditorEvent::BufferOpened(file_name.to_string()));
+                true
+            }
+            Err(_) => {
+                self.status_message =
+                    StatusMessage::from(tr!("ERR: Could not open file: {file_name}", file_name));
+                false
+            }
+        }
+    }
+
+    /// Returns the size of `file_name` if it is too big to open as usual.
+    fn large_file(&self, file_name: &str) -> Option<u64> {
+        let size = fs::metadata(file_name).ok()?.len();
+        (self.large_file_size > 0 && size > self.large_file_size).then_some(size)
+    }
+
+    /// Opens `file_name`, as a large document if it is too big to highlight.
+    fn open_document(&self, file_name: &str) -> Result<Document, std::io::Error> {
+        if self.large_file(file_name).is_some() {
+            Document::open_large(file_name)
+        } else {
+            Document::open(file_name)
+        }
+    }
+
+    /// Asks whether to go on with `file_name` if it is too big to open as
+    /// usual. Returns `true` for files that are not.
+    fn confirm_large_file(&mut self, file_name: &str) -> bool {
+        let Some(size) = self.large_file(file_name) else {
+            return true;
+        };
+        let question = tr!(
+            "{file_name} is {size} MB. Open it with slow features off? (y/n)",
+            file_name,
+            size = size >> 20
+        );
+        if self.ask(&question) == Some(Key::Char('y')) {
+            self.status_message = StatusMessage::from(tr!(
+                "{file_name} is large: highlighting, outline, blame and language server are off.",
+                file_name
+            ));
+            true
+        } else {
+            self.status_message = StatusMessage::from(tr!("Did not open {file_name}.", file_name));
+            false
+        }
+    }
+
+    /// Asks whether to strip the ANSI escape codes from a document just
+    /// opened that has some, as captured output does, or keep them raw.
+    fn offer_to_strip_ansi(&mut self) {
+        if self.document.is_large()
+            || self.document.is_read_only()
+            || !self.document.has_ansi_codes()
+        {
+            return;
+        }
+        let file_name = self.document.name().to_string();
+        let question = tr!(
+            "{file_name} contains ANSI escape codes. Strip them? (y/n)",
+            file_name
+        );
+        if self.ask(&question) == Some(Key::Char('y')) {
+            self.strip_ansi();
+        } else {
+            self.status_message =
+                StatusMessage::from(tr!("Kept the escape codes in {file_name}.", file_name));
+        }
+    }
+
+    /// Removes the ANSI escape codes from the document, as one step to undo.
+    fn strip_ansi(&mut self) {
+        if self.document.is_read_only() {
+            self.status_message = StatusMessage::from(tr!("This buffer is read-only."));
+            return;
+        }
+        let before = self.view.cursor.clone();
+        let lines = self.document.strip_ansi_codes();
+        if lines == 0 {
+            self.status_message = StatusMessage::from(tr!("There are no escape codes to strip."));
+            return;
+        }
+        self.clamp_cursor();
+        self.document
+            .end_undo_group(before, self.view.cursor.clone(), None);
+        self.status_message = StatusMessage::from(tr!(
+            "Stripped escape codes from {lines} lines; undo brings them back.",
+            lines
+        ));
+    }
+
+    /// Picks the variant of the theme for a `light` or `dark` background, or
+    /// with `auto` the one for the background the terminal reports.
+    fn set_background(&mut self, name: &str) {
+        match name {
+            "" => {}
+            "auto" => {
+                self.background_setting = None;
+                self.terminal.query_background();
+            }
+            name => match Background::parse(name) {
+                Some(background) => self.background_setting = Some(background),
+                None => {
+                    self.status_message =
+                        StatusMessage::from(tr!("Usage: background [auto|light|dark]"));
+                    return;
+                }
+            },
+        }
+        self.update_theme();
+        let background = match (self.background_setting, self.background) {
+            (Some(background), _) => background.name().to_string(),
+            (None, Some(background)) => format!("auto ({})", background.name()),
+            (None, None) => "auto".to_string(),
+        };
+        self.status_message = StatusMessage::from(tr!(
+            "Background: {background}, theme {theme}",
+            background,
+            theme = self.theme.name
+        ));
+    }
+
+    /// Draws with the variant of the configured theme for the background.
+    fn update_theme(&mut self) {
+        self.theme = match self.background_setting.or(self.background) {
+            Some(background) => self.configured_theme.variant(background),
+            None => self.configured_theme,
+        };
+    }
+
+    fn set_option(&mut self, option: &str) {
+        let (name, value) = match option.strip_prefix("no") {
+            Some(name) => (name, false),
+            None => (option, true),
+        };
+        if !self.set_flag(name, value) {
+            self.status_message = StatusMessage::from(tr!("Unknown option: {option}", option));
+        } else if views::OPTIONS.contains(&name) {
+            // Remembered with the file's view, to be set again for it alone.
+            self.view.options.insert(name.to_string(), value);
+        }
+    }
+
+    /// Returns whether one of the [`views::OPTIONS`] is on for the current
+    /// buffer: as set for it, or else as set for the editor.
+    fn view_flag(&self, name: &str) -> bool {
+        self.view
+            .options
+            .get(name)
+            .copied()
+            .or_else(|| self.flag(name))
+            .unwrap_or(false)
+    }
+
+    /// Returns the tab width of the current buffer: as set for it, or else
+    /// as set for the editor.
+    fn tab_width(&self) -> usize {
+        self.view.tab_width.unwrap_or(self.tab_width).max(1)
+    }
+
+    /// Returns whether one of the [`command::OPTIONS`] is on.
+    fn flag(&self, name: &str) -> Option<bool> {
+        Some(match name {
+            "autosave" => self.autosave,
+            "number" => self.line_numbers,
+            "sync" => self.synchronized_output,
+            "blame" => self.blame_inline,
+            "highlight" => self.syntax_highlighting,
+            "zen" => self.zen,
+            "subword" => self.subword,
+            "autoindent" => self.auto_indent,
+            "accessible" => self.accessible,
+            "perf" => self.perf_overlay,
+            "title" => self.terminal_title,
+            _ => return None,
+        })
+    }
+
+    /// Turns one of the [`command::OPTIONS`] on or off, returning `false` if
+    /// there is no such option.
+    fn set_flag(&mut self, name: &str, value: bool) -> bool {
+        match name {
+            "autosave" => self.autosave = value,
+            "number" => self.line_numbers = value,
+            "sync" => self.synchronized_output = value,
+            "blame" => self.blame_inline = value,
+            "highlight" => self.syntax_highlighting = value,
+            "zen" => self.zen = value,
+            "subword" => self.subword = value,
+            "autoindent" => self.auto_indent = value,
+            "accessible" => {
+                self.terminal.set_plain(value);
+                self.accessible = value;
+            }
+            "perf" => {
+                perf::set_enabled(value);
+                self.perf_overlay = value;
+            }
+            "title" => {
+                if !value && !self.title.is_empty() {
+                    self.terminal.restore_title();
+                    self.title.clear();
+                }
+                self.terminal_title = value;
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    fn set_mode(&mut self, mode: Mode) {
+        self.mode = mode;
+        self.publish(&EditorEvent::ModeChanged(mode.name()));
+    }
+
+    /// Returns the screen column of the cursor within the text, which differs
+    /// from its position in the row once tabs are expanded.
+    fn cursor_render_x(&self) -> usize {
+        let Position { x, y } = self.view.cursor;
+        self.document
+            .row(y)
+            .map_or(x, |row| row.render_x(x, self.tab_width()))
+    }
+
+    fn scroll(&mut self) {
+        let x = self.cursor_render_x();
+        let width = self.text_width();
+        let height = self.text_height();
+        scroll_to(&mut self.view, x, width, height);
+        self.follow_split();
+    }
+
+    /// Brings the other view up to date with the edits made in this one,
+    /// keeping its cursor on the document and in sight.
+    fn follow_split(&mut self) {
+        let (top, bottom) = self.split_heights();
+        let width = self.text_width();
+        let tab_width = self.tab_width();
+        let Some(split) = &mut self.split else {
+            return;
+        };
+        if !split.catch_up() {
+            // The document was read again, as a new one.
+            *split = Split::new(&mut self.document, split.view.clone());
+        }
+        let view = &mut split.view;
+        let y = cmp::min(view.cursor.y, self.document.len());
+        let row = self.document.row(y);
+        view.cursor = Position {
+            x: cmp::min(view.cursor.x, row.map_or(0, Row::len)),
+            y,
+        };
+        let x = row.map_or(view.cursor.x, |row| row.render_x(view.cursor.x, tab_width));
+        let height = if split.above { top } else { bottom };
+        scroll_to(view, x, width, height);
+    }
+
+    /// Shows the document in a second view, or moves focus to the other
+    /// view.
+    fn split_view(&mut self) {
+        match &mut self.split {
+            Some(split) => {
+                split.catch_up();
+                mem::swap(&mut self.view, &mut split.view);
+                split.above = !split.above;
+            }
+            None => self.split = Some(Split::new(&mut self.document, self.view.clone())),
+        }
+        self.selection = None;
+        self.scroll();
+    }
+
+    fn clamp_cursor(&mut self) {
+        let Position { x, y } = self.view.cursor;
+        let y = cmp::min(y, self.document.len());
+        let width = self.document.row(y).map_or(0, Row::len);
+        self.view.cursor = Position {
+            x: cmp::min(x, width),
+            y,
+        };
+    }
+
+    /// Checks, in debug builds, that the cursor is on a row of the document
+    /// or just past the last, and no further right than the end of its row.
+    fn check_cursor(&self) {
+        let Position { x, y } = self.view.cursor;
+        debug_assert!(
+            y <= self.document.len() && x <= self.document.row(y).map_or(0, Row::len),
+            "cursor at column {x} of row {y} is outside the document"
+        );
+    }
+
+    fn move_cursor(&mut self, key: Key) {
+        let Position { mut y, mut x } = self.view.cursor;
+        let height = self.document.len();
+        let mut width = if let Some(row) = self.document.row(y) {
+            row.len()
+        } else {
+            0
+        };
+        match key {
+            Key::Up => {
+                self.move_to_row(y.saturating_sub(1));
+                return;
+            }
+            Key::Down if y < height => {
+                self.move_to_row(y.saturating_add(1));
+                return;
+            }
+            Key::Left => {
+                if x > 0 {
+                    x -= 1;
+                } else if y > 0 {
+                    y -= 1;
+                    if let Some(row) = self.document.row(y) {
+                        x = row.len();
+                    } else {
+                        x = 0;
+                    }
+                }
+            }
+            Key::Right => {
+                if x < width {
+                    x += 1;
+                } else if y < height {
+                    y += 1;
+                    x = 0;
+                }
+            }
+            Key::Home => x = 0,
+            Key::End => x = width,
+            _ => (),
+        }
+        width = if let Some(row) = self.document.row(y) {
+            row.len()
+        } else {
+            0
+        };
+        if x > width {
+            x = width;
+        }
+        self.view.cursor = Position { x, y }
+    }
+
+    /// Scrolls down by `rows`, or up, and moves the cursor as far, so it
+    /// stays on the same row of the screen. Near the ends of the document,
+    /// where the view cannot scroll as far, the cursor still moves.
+    fn scroll_page(&mut self, rows: usize, down: bool) {
+        let height = self.text_height();
+        let y = self.view.cursor.y;
+        let offset = self.view.offset.y;
+        let (y, offset) = if down {
+            let last = self.document.len().saturating_sub(1).max(y);
+            let last_offset = self.document.len().saturating_sub(height).max(offset);
+            (
+                cmp::min(y.saturating_add(rows), last),
+                cmp::min(offset.saturating_add(rows), last_offset),
+            )
+        } else {
+            (y.saturating_sub(rows), offset.saturating_sub(rows))
+        };
+        self.view.offset.y = offset;
+        self.move_to_row(y);
+    }
+
+    /// Moves the cursor to row `y`, to the grapheme drawn at the column it
+    /// was on before the moves up and down that led here.
+    fn move_to_row(&mut self, y: usize) {
+        let goal = match &self.view.goal {
+            Some((at, goal)) if *at == self.view.cursor => *goal,
+            _ => self.cursor_render_x(),
+        };
+        let x = self
+            .document
+            .row(y)
+            .map_or(0, |row| row.x_at(goal, self.tab_width()));
+        self.view.cursor = Position { x, y };
+        self.view.goal = Some((self.view.cursor.clone(), goal));
+    }
+
+    fn draw_welcome_message(&self) {
+        let mut welcome_message = tr!("Hector editor -- version {version}", version = VERSION);
+        let width = self.terminal.size().width as usize;
+        let len = welcome_message.chars().count();
+        let padding = width.saturating_sub(len) / 2;
+        let spaces = " ".repeat(padding.saturating_sub(1));
+        welcome_message = format!("~{spaces}{welcome_message}");
+        welcome_message = welcome_message.chars().take(width).collect();
+        self.terminal.writeln(&welcome_message);
+    }
+
+    pub fn draw_row(&self, index: usize, row: &Row) {
+        let diagnostics = self.current_diagnostics();
+        self.draw_row_with(index, row, &diagnostics, &self.view);
+    }
+
+    /// Draws a row as shown in `view`, along with the sign and message of
+    /// the first of `diagnostics` on it, errors first. Without one, the
+    /// cursor's row is followed by who last changed it while blame is shown.
+    fn draw_row_with(&self, index: usize, row: &Row, diagnostics: &[&Diagnostic], view: &View) {
+        let width = self.text_width();
+        let start = view.offset.x;
+        let end = view.offset.x + width;
+        let focused = std::ptr::eq(view, &self.view);
+        let selected = match self.selection_range().filter(|_| focused) {
+            Some((first, last)) if (first.y..=last.y).contains(&index) => {
+                let from = if index == first.y { first.x } else { 0 };
+                let to = if index == last.y { last.x } else { usize::MAX };
+                from..to
+            }
+            _ => 0..0,
+        };
+        let shown = row
+            .render_x(row.len(), self.tab_width())
+            .saturating_sub(start)
+            .min(width);
+        let diagnostic = diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.position.y == index)
+            .min_by_key(|diagnostic| diagnostic.severity != Severity::Error);
+        let color = diagnostic.map(|diagnostic| match diagnostic.severity {
+            Severity::Error => self.theme.error,
+            Severity::Warning => self.theme.warning,
+        });
+        if !self.zen && self.breakpoint_width() > 0 {
+            if self.document.breakpoints().contains(index) {
+                self.terminal.set_fg_color(self.theme.error);
+                self.terminal.write("● ");
+                self.terminal.reset_fg_color();
+            } else {
+                self.terminal.write("  ");
+            }
+        }
+        if self.zen {
+            self.terminal.write(&" ".repeat(self.gutter_width()));
+        } else if !diagnostics.is_empty() {
+            match (diagnostic, color) {
+                (Some(diagnostic), Some(color)) => {
+                    self.terminal.set_fg_color(color);
+                    self.terminal.write(match diagnostic.severity {
+                        Severity::Error => "E ",
+                        Severity::Warning => "W ",
+                    });
+                    self.terminal.reset_fg_color();
+                }
+                _ => self.terminal.write("  "),
+            }
+        }
+        let number_width = self.number_width();
+        if number_width > 0 && !self.zen {
+            self.terminal.set_fg_color(self.theme.line_number);
+            let number = index.saturating_add(1);
+            self.terminal
+                .write(&format!("{number:>0$} ", number_width.saturating_sub(1)));
+            self.terminal.reset_fg_color();
+        }
+        let cursor_line = focused && index == view.cursor.y && !self.zen && !self.hurried();
+        if cursor_line {
+            self.terminal.set_bg_color(if self.has_focus {
+                self.theme.cursor_line_bg
+            } else {
+                self.theme.unfocused_cursor_line_bg
+            });
+        }
+        self.draw_text(index, row, start..end, &selected);
+        let mut column = shown;
+        for (ruler, ruler_color) in self.rulers() {
+            let Some(x) = ruler.checked_sub(start) else {
+                continue;
+            };
+            if x < column || x >= width {
+                continue;
+            }
+            self.terminal.write(&" ".repeat(x.saturating_sub(column)));
+            self.terminal.set_fg_color(ruler_color);
+            self.terminal.write("│");
+            self.terminal.reset_fg_color();
+            column = x.saturating_add(1);
+        }
+        let virtual_text = match (diagnostic, color) {
+            _ if self.zen => None,
+            (Some(diagnostic), Some(color)) => {
+                let message = diagnostic.message.lines().next().unwrap_or_default();
+                Some((message.to_string(), color))
+            }
+            _ if self.blame_inline && index == view.cursor.y => self
+                .annotation(index)
+                .map(|annotation| (annotation.describe(), self.theme.line_number)),
+            _ => None,
+        };
+        if let Some((message, color)) = virtual_text {
+            let room = width
+                .saturating_sub(column)
+                .saturating_sub(VIRTUAL_TEXT_GAP.len());
+            let message: String = message.chars().take(room).collect();
+            if !message.is_empty() {
+                self.terminal.set_fg_color(color);
+                self.terminal.write(&format!("{VIRTUAL_TEXT_GAP}{message}"));
+                self.terminal.reset_fg_color();
+                column = column
+                    .saturating_add(VIRTUAL_TEXT_GAP.len())
+                    .saturating_add(message.chars().count());
+            }
+        }
+        if cursor_line {
+            self.terminal
+                .write(&" ".repeat(width.saturating_sub(column)));
+            self.terminal.reset_bg_color();
+        }
+        self.terminal.writeln("");
+    }
+
+    /// Draws the `columns` of a row that fit on screen. In a commit message,
+    /// comments are dimmed and the part of the summary past
+    /// `COMMIT_SUMMARY_WIDTH` stands out.
+    fn draw_text(&self, index: usize, row: &Row, columns: Range<usize>, selected: &Range<usize>) {
+        let Range { start, end } = columns;
+        // Zen mode leaves only search matches colored.
+        let syntax = self.view_flag("highlight") && !self.zen;
+        let matches = !self.hurried();
+        if self.is_commit_message() {
+            if row.as_str().starts_with('#') {
+                self.terminal.set_fg_color(self.theme.line_number);
+                self.terminal.write(&row.render(
+                    start,
+                    end,
+                    self.tab_width(),
+                    self.theme,
+                    selected,
+                    syntax,
+                    matches,
+                ));
+                self.terminal.reset_fg_color();
+                return;
+            }
+            if index == 0 && COMMIT_SUMMARY_WIDTH < end {
+                let limit = cmp::max(COMMIT_SUMMARY_WIDTH, start);
+                self.terminal.write(&row.render(
+                    start,
+                    limit,
+                    self.tab_width(),
+                    self.theme,
+                    selected,
+                    syntax,
+                    matches,
+                ));
+                self.terminal.set_fg_color(self.theme.warning);
+                self.terminal.write(&row.render(
+                    limit,
+                    end,
+                    self.tab_width(),
+                    self.theme,
+                    selected,
+                    syntax,
+                    matches,
+                ));
+                self.terminal.reset_fg_color();
+                return;
+            }
+        }
+        let background = match self.conflict_section(index) {
+            Some(Section::Marker) => {
+                self.terminal.set_fg_color(self.theme.status_fg);
+                self.terminal.set_bg_color(self.theme.status_bg);
+                self.terminal.write(&row.render(
+                    start,
+                    end,
+                    self.tab_width(),
+                    self.theme,
+                    selected,
+                    false,
+                    matches,
+                ));
+                self.terminal.reset_fg_color();
+                self.terminal.reset_bg_color();
+                return;
+            }
+            Some(Section::Ours) => Some(self.theme.ours_bg),
+            Some(Section::Base) => Some(self.theme.base_bg),
+            Some(Section::Theirs) => Some(self.theme.theirs_bg),
+            None => None,
+        };
+        if let Some(background) = background {
+            self.terminal.set_bg_color(background);
+        }
+        self.terminal.write(&row.render(
+            start,
+            end,
+            self.tab_width(),
+            self.theme,
+            selected,
+            syntax,
+            matches,
+        ));
+        if background.is_some() {
+            self.terminal.reset_bg_color();
+        }
+    }
+
+    /// Returns the number of columns in front of the text, taken by
+    /// diagnostic signs and line numbers, or in zen mode by the margin that
+    /// centers the text.
+    fn gutter_width(&self) -> usize {
+        if self.zen {
+            let width = (self.terminal.size().width as usize).saturating_sub(self.outline_width());
+            return width.saturating_sub(self.zen_width) / 2;
+        }
+        let signs: usize = if self.current_diagnostics().is_empty() {
+            0
+        } else {
+            2
+        };
+        signs
+            .saturating_add(self.breakpoint_width())
+            .saturating_add(self.number_width())
+    }
+
+    /// Returns the number of columns taken by breakpoint signs, which are
+    /// only shown in documents that have some.
+    fn breakpoint_width(&self) -> usize {
+        if self.document.breakpoints().is_empty() {
+            0
+        } else {
+            2
+        }
+    }
+
+    /// Returns the number of columns taken by line numbers, including the
+    /// space separating them from the text.
+    fn number_width(&self) -> usize {
+        if !self.view_flag("number") {
+            return 0;
+        }
+        let digits = cmp::max(self.document.len(), 1).to_string().len();
+        digits.saturating_add(1)
+    }
+
+    fn text_width(&self) -> usize {
+        let width = (self.terminal.size().width as usize)
+            .saturating_sub(self.gutter_width())
+            .saturating_sub(self.outline_width());
+        if self.zen {
+            width.min(self.zen_width)
+        } else {
+            width
+        }
+    }
+
+    /// Columns taken by the outline, including its border.
+    fn outline_width(&self) -> usize {
+        if self.outline.is_none() {
+            return 0;
+        }
+        OUTLINE_WIDTH.min(self.terminal.size().width as usize / 2)
+    }
+
+    fn draw_rows(&self) {
+        if let Some(split) = self.split.as_ref().filter(|split| split.above) {
+            self.draw_split(split);
+        }
+        let height = self.text_height();
+        let diagnostics = self.current_diagnostics();
+        let hints = match &self.picker {
+            Some(picker) => picker.lines(height),
+            None if !self.prompt_hints.is_empty() => self.prompt_hints.clone(),
+            None => self.key_hints(),
+        };
+        let hints_start = height.saturating_sub(hints.len());
+        let outline_offset = self.outline_offset(height.saturating_sub(1));
+        for terminal_row in 0..height {
+            self.terminal.clear_current_line();
+            if terminal_row < hints_start {
+                self.draw_outline_row(terminal_row, outline_offset);
+            }
+            if let Some(hint) = hints.get(terminal_row.wrapping_sub(hints_start)) {
+                self.draw_hint(hint);
+            } else if let Some(row) = self.document.row(terminal_row + self.view.offset.y) {
+                self.draw_row_with(
+                    terminal_row + self.view.offset.y,
+                    row,
+                    &diagnostics,
+                    &self.view,
+                );
+            } else if self.document.is_empty() && terminal_row == height / 3 {
+                self.draw_welcome_message();
+            } else if self.zen || self.accessible {
+                self.terminal.writeln("");
+            } else {
+                self.terminal.writeln("~");
+            }
+        }
+        if let Some(split) = self.split.as_ref().filter(|split| !split.above) {
+            self.draw_split(split);
+        }
+        self.draw_pane();
+    }
+
+    /// Draws the view without focus, and the line between it and the other,
+    /// which tells where its cursor is.
+    fn draw_split(&self, split: &Split) {
+        let (top, bottom) = self.split_heights();
+        let height = if split.above { top } else { bottom };
+        let name: String = self.document.name().chars().take(20).collect();
+        let line = format!(
+            " {name} | {}/{}",
+            split.view.cursor.y.saturating_add(1),
+            self.document.len()
+        );
+        if !split.above {
+            self.terminal.clear_current_line();
+            self.draw_hint(&line);
+        }
+        let diagnostics = self.current_diagnostics();
+        for terminal_row in 0..height {
+            self.terminal.clear_current_line();
+            let index = terminal_row.saturating_add(split.view.offset.y);
+            if let Some(row) = self.document.row(index) {
+                self.draw_row_with(index, row, &diagnostics, &split.view);
+            } else if self.zen || self.accessible {
+                self.terminal.writeln("");
+            } else {
+                self.terminal.writeln("~");
+            }
+        }
+        if split.above {
+            self.terminal.clear_current_line();
+            self.draw_hint(&line);
+        }
+    }
+
+    /// Shows the timings of the last frame in the top right corner.
+    fn draw_perf_overlay(&self) {
+        let text = format!(" {} ", self.last_frame.describe());
+        let width = self.terminal.size().width as usize;
+        let text: String = text.chars().take(width).collect();
+        self.terminal.cursor_position(&Position {
+            x: width.saturating_sub(text.chars().count()),
+            y: 0,
+        });
+        self.terminal.set_bg_color(self.theme.status_bg);
+        self.terminal.set_fg_color(self.theme.status_fg);
+        self.terminal.write(&text);
+        self.terminal.reset_fg_color();
+        self.terminal.reset_bg_color();
+    }
+
+    /// Returns the outline entry to highlight: the one chosen while it has
+    /// focus, otherwise the symbol the cursor is in.
+    fn outline_highlight(&self) -> Option<usize> {
+        let outline = self.outline.as_ref()?;
+        if self.outline_focused {
+            Some(outline.selected)
+        } else {
+            outline.active(self.view.cursor.y)
+        }
+    }
+
+    /// Returns the first outline entry to show in `rows` rows, so that the
+    /// highlighted one stays near the middle.
+    fn outline_offset(&self, rows: usize) -> usize {
+        let count = self
+            .outline
+            .as_ref()
+            .map_or(0, |outline| outline.symbols().len());
+        self.outline_highlight()
+            .map_or(0, |index| index.saturating_sub(rows / 2))
+            .min(count.saturating_sub(rows))
+    }
+
+    /// Draws one row of the outline at the right edge, leaving the cursor at
+    /// the start of the row for the text.
+    fn draw_outline_row(&self, terminal_row: usize, offset: usize) {
+        let Some(outline) = &self.outline else {
+            return;
+        };
+        let width = self.outline_width();
+        let x = (self.terminal.size().width as usize).saturating_sub(width);
+        let y = terminal_row.saturating_add(self.text_top());
+        self.terminal.cursor_position(&Position { x, y });
+        let index = offset.saturating_add(terminal_row).wrapping_sub(1);
+        let entry = if terminal_row == 0 {
+            Some(tr!(" Outline"))
+        } else if let Some(symbol) = outline.symbols().get(index) {
+            let indent = "  ".repeat(symbol.depth);
+            if symbol.kind.starts_with('#') {
+                Some(format!("│ {indent}{}", symbol.name))
+            } else {
+                Some(format!("│ {indent}{} {}", symbol.kind, symbol.name))
+            }
+        } else if terminal_row == 1 && outline.symbols().is_empty() {
+            Some(format!("│ {}", tr!("No symbols found")))
+        } else {
+            None
+        };
+        let entry = entry.unwrap_or_else(|| "│".to_string());
+        let entry: String = format!("{entry:<width$}").chars().take(width).collect();
+        if terminal_row == 0 || self.outline_highlight() == Some(index) {
+            self.terminal.set_bg_color(self.theme.status_bg);
+            self.terminal.set_fg_color(self.theme.status_fg);
+            self.terminal.write(&entry);
+            self.terminal.reset_fg_color();
+            self.terminal.reset_bg_color();
+        } else {
+            self.terminal.write(&entry);
+        }
+        self.terminal.cursor_position(&Position { x: 0, y });
+    }
+
+    fn draw_pane(&self) {
+        let Some(pane) = &self.pane else {
+            self.draw_commit_summary();
+            return;
+        };
+        let height = self.pane_height().saturating_sub(1);
+        let toggle = self
+            .keymap
+            .bindings()
+            .find(|(_, command)| *command == "terminal")
+            .map_or_else(
+                || "terminal".to_string(),
+                |(keys, _)| {
+                    let keys: Vec<String> = keys.iter().copied().map(keymap::describe).collect();
+                    keys.join(" ")
+                },
+            );
+        let shell = pty::shell();
+        let header = if self.pane_focused {
+            tr!(
+                " {shell} - {toggle} to switch to the document",
+                shell,
+                toggle
+            )
+        } else {
+            tr!(" {shell} - {toggle} to switch to the shell", shell, toggle)
+        };
+        self.terminal.clear_current_line();
+        self.draw_hint(&header);
+        let width = self.terminal.size().width as usize;
+        let lines = pane.lines(height);
+        for index in 0..height {
+            self.terminal.clear_current_line();
+            let line = lines.get(index).map_or("", String::as_str);
+            let line: String = line.chars().take(width).collect();
+            self.terminal.writeln(&line);
+        }
+    }
+
+    fn draw_commit_summary(&self) {
+        let Some(summary) = &self.commit_summary else {
+            return;
+        };
+        self.terminal.clear_current_line();
+        self.draw_hint(&tr!(" Staged changes"));
+        let width = self.terminal.size().width as usize;
+        for index in 0..self.pane_height().saturating_sub(1) {
+            self.terminal.clear_current_line();
+            let line = summary.get(index).map_or("", String::as_str);
+            let line: String = line.chars().take(width).collect();
+            self.terminal.writeln(&line);
+        }
+    }
+
+    /// Returns the columns marked by a line beside the text, in order, each
+    /// with the color it is drawn in.
+    fn rulers(&self) -> Vec<(usize, Rgb)> {
+        if self.accessible {
+            return Vec::new();
+        }
+        self.for_file_type(&self.file_type_rulers)
+            .unwrap_or(&self.rulers)
+            .iter()
+            .enumerate()
+            .map(|(index, (column, color))| {
+                (*column, color.unwrap_or_else(|| self.theme.ruler(index)))
+            })
+            .collect()
+    }
+
+    /// Lists the keys that complete the chord typed so far, one per line.
+    fn key_hints(&self) -> Vec<String> {
+        let pending = self.dispatcher.pending();
+        if pending.is_empty() {
+            return Vec::new();
+        }
+        let prefix: Vec<String> = pending.iter().copied().map(keymap::describe).collect();
+        let prefix = prefix.join(" ");
+        self.keymap
+            .continuations(pending)
+            .into_iter()
+            .map(|(key, command)| {
+                format!(
+                    " {prefix} {:<9} {}",
+                    keymap::describe(key),
+                    command.map_or("+prefix", command::describe)
+                )
+            })
+            .collect()
+    }
+
+    /// Lists what the path at the end of `text` could go on to, by name, if
+    /// it is in a directory, as `completer` finds them.
+    fn path_hints(&self, completer: fn(&str, &Ignore) -> Vec<String>, text: &str) -> Vec<String> {
+        if !text.contains('/') {
+            return Vec::new();
+        }
+        let candidates = completer(text, &self.ignore);
+        let mut hints: Vec<String> = candidates
+            .iter()
+            .take(MAX_PATH_HINTS)
+            .map(|candidate| {
+                let (path, slash) = match candidate.strip_suffix('/') {
+                    Some(directory) => (directory, "/"),
+                    None => (candidate.as_str(), ""),
+                };
+                let name = path.rsplit('/').next().unwrap_or(path);
+                format!(" {name}{slash}")
+            })
+            .collect();
+        if candidates.len() > MAX_PATH_HINTS {
+            hints.pop();
+            let count = candidates.len().saturating_sub(hints.len());
+            hints.push(format!(" {}", tr!("… {count} more", count)));
+        }
+        hints
+    }
+
+    fn draw_hint(&self, hint: &str) {
+        let width = self.terminal.size().width as usize;
+        let hint: String = format!("{hint:<width$}").chars().take(width).collect();
+        self.terminal.set_bg_color(self.theme.status_bg);
+        self.terminal.set_fg_color(self.theme.status_fg);
+        self.terminal.writeln(&hint);
+        self.terminal.reset_fg_color();
+        self.terminal.reset_bg_color();
+    }
+
+    /// Reads the next key as remapped, in the current mode and overlays.
+    fn read_key(&self) -> Result<Key, std::io::Error> {
+        let key = self.terminal.read_key()?;
+        Ok(self.remapper.translate(key, self.mode.name()))
+    }
+
+    /// Shows `items` above the message bar and lets the user narrow them down
+    /// by typing, returning the index of the chosen one.
+    fn pick(&mut self, title: &str, items: Vec<String>) -> Option<usize> {
+        self.pick_at(title, items, 0)
+    }
+
+    /// Like [`Editor::pick`], starting with the item at `selected` chosen.
+    fn pick_at(&mut self, title: &str, items: Vec<String>, selected: usize) -> Option<usize> {
+        let mut picker = Picker::new(items);
+        picker.select(selected);
+        self.run_picker(title, picker)
+    }
+
+    /// Shows `picker` until the user picks one of its items.
+    fn run_picker(&mut self, title: &str, picker: Picker) -> Option<usize> {
+        self.picker = Some(picker);
+        self.remapper.open_overlay("picker");
+        self.terminal.set_cursor_shape(CursorShape::Block);
+        let picked = loop {
+            let Some(picker) = &self.picker else {
+                break Picked::Cancelled;
+            };
+            let query = picker.query();
+            self.status_message = StatusMessage::from(format!("{title}{}", query.text()));
+            self.prompt_cursor = Some(title.chars().count().saturating_add(query.cursor()));
+            if self.refresh_screen().is_err() {
+                break Picked::Cancelled;
+            }
+            let Ok(key) = self.read_key() else {
+                break Picked::Cancelled;
+            };
+            if let Some(picked) = self.picker.as_mut().and_then(|picker| picker.handle(key)) {
+                break picked;
+            }
+        };
+        self.remapper.close_overlay();
+        self.picker = None;
+        self.prompt_cursor = None;
+        self.terminal.set_cursor_shape(self.mode.cursor_shape());
+        self.status_message = StatusMessage::from(String::new());
+        match picked {
+            Picked::Item(index) => Some(index),
+            Picked::Cancelled => None,
+        }
+    }
+
+    fn prompt<C>(
+        &mut self,
+        prompt: &str,
+        completer: Option<fn(&str, &Ignore) -> Vec<String>>,
+        mut callback: C,
+    ) -> Result<Option<String>, std::io::Error>
+    where
+        C: FnMut(&mut Self, Key, &str),
+    {
+        let mut line = LineEditor::default();
+        let mut completions: Vec<String> = Vec::new();
+        let mut completion_index = 0;
+        // Up and Down walk through earlier answers to the same prompt; the
+        // index equals the history's length while on the line being typed.
+        let mut history_index = self.histories.get(prompt).map_or(0, History::len);
+        let mut typed = String::new();
+        self.terminal.set_cursor_shape(CursorShape::Block);
+        loop {
+            self.status_message = StatusMessage::from(format!("{prompt}{}", line.text()));
+            self.prompt_cursor = Some(prompt.chars().count().saturating_add(line.cursor()));
+            self.refresh_screen()?;
+            self.remapper.open_overlay("prompt");
+            let key = self.read_key();
+            self.remapper.close_overlay();
+            let key = key?;
+            if key != Key::Char('\t') {
+                completions.clear();
+            }
             match key {
-                Key::Backspace => {
-                    if !result.is_empty() {
-                        result.truncate(result.len() - 1);
+                // Repeated tabs cycle through the candidates for what was
+                // typed before the first one.
+                Key::Char('\t') => {
+                    if let Some(completer) = completer {
+                        if completions.is_empty() {
+                            completions = completer(line.text(), &self.ignore);
+                            completion_index = 0;
+                        }
+                        if let Some(completion) = completions.get(completion_index) {
+                            line.set_text(completion);
+                            completion_index = (completion_index + 1) % completions.len();
+                        }
+                        if completions.len() == 1 {
+                            completions.clear();
+                        }
                     }
                 }
-                Key::Char('\n') => break,
-                Key::Char(c) => {
-                    if !c.is_control() {
-                        result.push(c);
+                Key::Up | Key::Down => {
+                    let history = self.histories.entry(prompt.to_string()).or_default();
+                    if history_index == history.len() {
+                        typed = line.text().to_string();
                     }
+                    history_index = if key == Key::Up {
+                        history_index.saturating_sub(1)
+                    } else {
+                        cmp::min(history_index.saturating_add(1), history.len())
+                    };
+                    line.set_text(history.get(history_index).unwrap_or(&typed));
                 }
+                Key::Char('\n') => break,
                 Key::Esc => {
-                    result.truncate(0);
+                    line.set_text("");
                     break;
                 }
-                _ => (),
+                key => {
+                    line.edit(key);
+                }
+            }
+            // The listing stays that of what was typed while cycling.
+            if completions.is_empty() {
+                self.prompt_hints = completer
+                    .map(|completer| self.path_hints(completer, line.text()))
+                    .unwrap_or_default();
             }
-            callback(self, key, &result);
+            callback(self, key, line.text());
         }
+        self.prompt_hints.clear();
+        self.prompt_cursor = None;
+        self.terminal.set_cursor_shape(self.mode.cursor_shape());
         self.status_message = StatusMessage::from(String::new());
-        if result.is_empty() {
+        if line.text().is_empty() {
             return Ok(None);
         }
-        Ok(Some(result))
+        self.histories
+            .entry(prompt.to_string())
+            .or_default()
+            .push(line.text());
+        Ok(Some(line.text().to_string()))
     }
 }
 
-impl Default for Editor {
-    fn default() -> Self {
-        let args: Vec<String> = env::args().collect();
-        let mut initial_status =
-            String::from("HELP: Ctrl-F = find | Ctrl-S = save | Ctrl-Q = quit");
-        let document = if args.len() > 1 {
-            let file_name = &args[1];
-            let doc = Document::open(file_name);
-
-            if let Ok(document) = doc {
-                document
-            } else {
-                initial_status = format!("ERR: Could not open file: {file_name}");
-                Document::default()
+/// Returns the columns of `rulers` in order, with the colors they were
+/// given, adding a message to `errors` for each color that cannot be read.
+fn resolve_rulers(rulers: &[Ruler], errors: &mut Vec<String>) -> Vec<(usize, Option<Rgb>)> {
+    let mut resolved: Vec<(usize, Option<Rgb>)> = rulers
+        .iter()
+        .map(|ruler| match ruler {
+            Ruler::Column(column) => (*column, None),
+            Ruler::Colored { column, color } => {
+                let parsed = theme::parse_color(color);
+                if parsed.is_none() {
+                    errors.push(tr!("Unknown color: {color}", color = color));
+                }
+                (*column, parsed)
             }
-        } else {
-            Document::default()
-        };
+        })
+        .collect();
+    resolved.sort_by_key(|(column, _)| *column);
+    resolved
+}
 
-        Self {
-            should_quit: false,
-            terminal: Terminal::default(),
-            document,
-            cursor_position: Position::default(),
-            offset: Position::default(),
-            status_message: StatusMessage::from(initial_status),
-            quit_times: QUIT_TIMES,
-        }
+/// Scrolls `view` so that the screen column `x` of its cursor is in sight,
+/// showing `width` columns and `height` rows.
+fn scroll_to(view: &mut View, x: usize, width: usize, height: usize) {
+    let y = view.cursor.y;
+    let offset = &mut view.offset;
+    if y < offset.y {
+        offset.y = y;
+    } else if y >= offset.y.saturating_add(height) {
+        offset.y = y.saturating_sub(height).saturating_add(1);
+    }
+    if x < offset.x {
+        offset.x = x;
+    } else if x >= offset.x.saturating_add(width) {
+        offset.x = x.saturating_sub(width).saturating_add(1);
     }
 }
 
-fn die(e: &std::io::Error) {
-    Terminal::clear_screen();
-    panic!("{}", e);
+/// Flattens hover contents, which servers send as a string, a marked string
+/// with a language, markup content or a list of those.
+fn hover_text(contents: &Value) -> String {
+    match contents {
+        Value::String(text) => text.clone(),
+        Value::Array(parts) => parts
+            .iter()
+            .map(hover_text)
+            .collect::<Vec<_>>()
+            .join("\n\n"),
+        Value::Object(part) => part
+            .get("value")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string(),
+        _ => String::new(),
+    }
+}
+
+fn describe_tag(tag: &Tag) -> String {
+    match &tag.kind {
+        Some(kind) => format!("{} ({kind})", tag.file_name),
+        None => tag.file_name.clone(),
+    }
 }
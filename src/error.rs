@@ -0,0 +1,36 @@
+use std::fmt;
+use std::io;
+
+/// What can stop the editor as a whole. Failures of a single command, such
+/// as a file that cannot be saved, are shown in the message bar instead.
+#[derive(Debug)]
+pub enum Error {
+    /// Standard input or output is not a terminal, or could not be put in
+    /// raw mode.
+    NoTerminal(io::Error),
+    /// Reading input or drawing the screen failed.
+    Io(io::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoTerminal(error) => write!(f, "could not set up the terminal: {error}"),
+            Self::Io(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::NoTerminal(error) | Self::Io(error) => Some(error),
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
@@ -0,0 +1,128 @@
+use crate::Position;
+
+/// Something that happened in the editor, published to whatever subscribed
+/// to its [`Kind`].
+pub enum EditorEvent {
+    /// A file was opened into a buffer, named by its file name.
+    BufferOpened(String),
+    /// The file is about to be written, while the document can still be
+    /// changed.
+    BeforeSave(String),
+    /// The file was written.
+    AfterSave(String),
+    /// The cursor ended up somewhere else after an event was handled.
+    CursorMoved(Position),
+    /// Typing switched between inserting and replacing, named by the new mode.
+    ModeChanged(&'static str),
+    /// A key was pressed, described as in the `keymap` setting.
+    KeyPressed(String),
+    /// Breakpoints were set or removed in the file.
+    BreakpointsChanged(String),
+}
+
+/// The kinds of [`EditorEvent`], which subscriptions are made to.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Kind {
+    BufferOpened,
+    BeforeSave,
+    AfterSave,
+    CursorMoved,
+    ModeChanged,
+    KeyPressed,
+    BreakpointsChanged,
+}
+
+/// The parts of the editor that react to events.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Subscriber {
+    /// Formats the document, if its formatter asks to be run on save.
+    Formatter,
+    /// Stores the undo history next to the file.
+    UndoHistory,
+    /// Keeps a snapshot of the saved file.
+    FileHistory,
+    /// Checks the file with the linter for its type.
+    Linter,
+    /// Looks up the file's git status again.
+    Git,
+    /// Shapes the cursor after the mode.
+    CursorShape,
+    /// Passes the event on to plugin handlers.
+    Plugins,
+}
+
+/// Which subscriber reacts to which kind of event, in the order they are
+/// told.
+const SUBSCRIPTIONS: &[(Kind, Subscriber)] = &[
+    (Kind::BufferOpened, Subscriber::Plugins),
+    (Kind::BeforeSave, Subscriber::Formatter),
+    (Kind::BeforeSave, Subscriber::Plugins),
+    (Kind::AfterSave, Subscriber::UndoHistory),
+    (Kind::AfterSave, Subscriber::FileHistory),
+    (Kind::AfterSave, Subscriber::Plugins),
+    (Kind::AfterSave, Subscriber::Linter),
+    (Kind::AfterSave, Subscriber::Git),
+    (Kind::CursorMoved, Subscriber::Plugins),
+    (Kind::ModeChanged, Subscriber::CursorShape),
+    (Kind::ModeChanged, Subscriber::Plugins),
+    (Kind::KeyPressed, Subscriber::Plugins),
+    (Kind::BreakpointsChanged, Subscriber::Plugins),
+];
+
+impl EditorEvent {
+    #[must_use]
+    pub fn kind(&self) -> Kind {
+        match self {
+            Self::BufferOpened(_) => Kind::BufferOpened,
+            Self::BeforeSave(_) => Kind::BeforeSave,
+            Self::AfterSave(_) => Kind::AfterSave,
+            Self::CursorMoved(_) => Kind::CursorMoved,
+            Self::ModeChanged(_) => Kind::ModeChanged,
+            Self::KeyPressed(_) => Kind::KeyPressed,
+            Self::BreakpointsChanged(_) => Kind::BreakpointsChanged,
+        }
+    }
+
+    /// Returns what plugin handlers are passed: the file name, the key, the
+    /// mode, or the cursor as `line:column` counting from one.
+    #[must_use]
+    pub fn argument(&self) -> String {
+        match self {
+            Self::BufferOpened(file_name)
+            | Self::BeforeSave(file_name)
+            | Self::AfterSave(file_name)
+            | Self::BreakpointsChanged(file_name) => file_name.clone(),
+            Self::CursorMoved(position) => format!(
+                "{}:{}",
+                position.y.saturating_add(1),
+                position.x.saturating_add(1)
+            ),
+            Self::ModeChanged(mode) => (*mode).to_string(),
+            Self::KeyPressed(key) => key.clone(),
+        }
+    }
+}
+
+impl Kind {
+    /// Returns the name plugins register handlers under with `hecto.on`.
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::BufferOpened => "open",
+            Self::BeforeSave => "before_save",
+            Self::AfterSave => "save",
+            Self::CursorMoved => "cursor",
+            Self::ModeChanged => "mode",
+            Self::KeyPressed => "key",
+            Self::BreakpointsChanged => "breakpoints",
+        }
+    }
+}
+
+/// Returns who subscribed to events of `kind`.
+pub fn subscribers(kind: Kind) -> impl Iterator<Item = Subscriber> {
+    SUBSCRIPTIONS
+        .iter()
+        .filter(move |(subscribed, _)| *subscribed == kind)
+        .map(|(_, subscriber)| *subscriber)
+}
@@ -1,3 +1,6 @@
+/// The file type of the message git asks for when committing.
+pub const COMMIT_MESSAGE: &str = "Git commit";
+
 pub struct FileType {
     name: String,
     hl_opts: HighlightingOptions,
@@ -39,6 +42,12 @@ impl FileType {
                 },
             };
         }
+        if file_name.ends_with("COMMIT_EDITMSG") {
+            return Self {
+                name: String::from(COMMIT_MESSAGE),
+                hl_opts: HighlightingOptions::default(),
+            };
+        }
         Self::default()
     }
 }
@@ -0,0 +1,284 @@
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// The state of the git repository a file belongs to, as shown in the status
+/// bar.
+pub struct GitStatus {
+    pub branch: String,
+    /// Whether tracked files have uncommitted changes.
+    pub dirty: bool,
+}
+
+impl GitStatus {
+    /// Looks up the repository containing `file_name`, returning `None` if
+    /// there is none.
+    #[must_use]
+    pub fn of(file_name: &str) -> Option<Self> {
+        let path = fs::canonicalize(file_name).ok()?;
+        let work_tree = path.ancestors().find(|dir| dir.join(".git").exists())?;
+        let branch = branch(&git_dir(work_tree)?)?;
+        Some(Self {
+            branch,
+            dirty: has_changes(work_tree),
+        })
+    }
+}
+
+/// The version of a file that changes are compared with.
+#[derive(Clone, Copy)]
+pub enum Base {
+    /// What is staged for the next commit.
+    Index,
+    /// What was last committed.
+    Head,
+}
+
+/// A run of changed lines between a file's text and its staged or committed
+/// version.
+pub struct Hunk {
+    /// The first row the change covers in the text, or for a deletion the row
+    /// the deleted rows were in front of.
+    pub new_start: usize,
+    /// How many rows of the text the change covers.
+    pub new_count: usize,
+    header: String,
+    /// The diff lines, each starting with `-`, `+` or `\`.
+    lines: Vec<String>,
+}
+
+impl Hunk {
+    /// Returns whether the change covers row `y` of the text, or is right
+    /// next to it if it only deleted rows.
+    #[must_use]
+    pub fn contains(&self, y: usize) -> bool {
+        if self.new_count == 0 {
+            y == self.new_start || y.saturating_add(1) == self.new_start
+        } else {
+            (self.new_start..self.new_start.saturating_add(self.new_count)).contains(&y)
+        }
+    }
+
+    /// Returns the rows the change replaced.
+    #[must_use]
+    pub fn old_lines(&self) -> Vec<&str> {
+        self.lines
+            .iter()
+            .filter_map(|line| line.strip_prefix('-'))
+            .collect()
+    }
+}
+
+/// Compares `text`, the contents of `file_name` in the editor, with the
+/// version of the file in `base`.
+///
+/// # Errors
+///
+/// Returns a message for the user if the file is not tracked by git or git
+/// failed.
+pub fn hunks(file_name: &str, base: Base, text: &str) -> Result<Vec<Hunk>, String> {
+    let (work_tree, path) = locate(file_name)?;
+    let revision = match base {
+        Base::Index => format!(":{path}"),
+        Base::Head => format!("HEAD:{path}"),
+    };
+    run(&work_tree, &["cat-file", "-e", &revision], None)
+        .map_err(|_| format!("{file_name} is not in {}", describe(base)))?;
+    // The text is stored as a blob, so git can compare it without it being
+    // saved.
+    let blob = run(&work_tree, &["hash-object", "-w", "--stdin"], Some(text))?;
+    let diff = run(
+        &work_tree,
+        &[
+            "diff",
+            "--no-color",
+            "--no-ext-diff",
+            "-U0",
+            &revision,
+            blob.trim(),
+        ],
+        None,
+    )?;
+    let mut hunks: Vec<Hunk> = Vec::new();
+    for line in diff.lines() {
+        if let Some(hunk) = parse_header(line) {
+            hunks.push(hunk);
+        } else if let Some(hunk) = hunks.last_mut() {
+            if line.starts_with(['-', '+', '\\']) {
+                hunk.lines.push(line.to_string());
+            }
+        }
+    }
+    Ok(hunks)
+}
+
+/// Adds the change in `hunk`, found by comparing with [`Base::Index`], to
+/// the index of the repository `file_name` is in.
+///
+/// # Errors
+///
+/// Returns a message for the user if git could not apply the change.
+pub fn stage(file_name: &str, hunk: &Hunk) -> Result<(), String> {
+    let (work_tree, path) = locate(file_name)?;
+    let mut patch = format!(
+        "diff --git a/{path} b/{path}\n--- a/{path}\n+++ b/{path}\n{}\n",
+        hunk.header
+    );
+    for line in &hunk.lines {
+        patch.push_str(line);
+        patch.push('\n');
+    }
+    run(
+        &work_tree,
+        &["apply", "--cached", "--unidiff-zero", "-"],
+        Some(&patch),
+    )
+    .map(|_| ())
+}
+
+fn describe(base: Base) -> &'static str {
+    match base {
+        Base::Index => "the index",
+        Base::Head => "the last commit",
+    }
+}
+
+/// Parses a hunk header such as `@@ -3,2 +3,0 @@`.
+fn parse_header(line: &str) -> Option<Hunk> {
+    let ranges = line.strip_prefix("@@ -")?;
+    let (ranges, _) = ranges.split_once(" @@")?;
+    let (_, new) = ranges.split_once(" +")?;
+    let (start, count) = new.split_once(',').unwrap_or((new, "1"));
+    let start: usize = start.parse().ok()?;
+    let new_count: usize = count.parse().ok()?;
+    // Git numbers rows from one, and an empty range by the row before it.
+    let new_start = if new_count == 0 {
+        start
+    } else {
+        start.saturating_sub(1)
+    };
+    Some(Hunk {
+        new_start,
+        new_count,
+        header: line.to_string(),
+        lines: Vec::new(),
+    })
+}
+
+/// Summarizes the changes staged for the commit whose message is written in
+/// `message_file`, usually `.git/COMMIT_EDITMSG`.
+///
+/// # Errors
+///
+/// Returns what git printed to its standard error if it failed.
+pub fn staged_summary(message_file: &str) -> Result<String, String> {
+    let path =
+        fs::canonicalize(message_file).map_err(|error| format!("{message_file}: {error}"))?;
+    // Git starts the editor at the top of the work tree, which is also where
+    // the message is unless the repository is a worktree or submodule.
+    let work_tree = path
+        .parent()
+        .filter(|dir| dir.ends_with(".git"))
+        .and_then(Path::parent)
+        .map_or_else(|| PathBuf::from("."), Path::to_path_buf);
+    run(&work_tree, &["diff", "--cached", "--stat"], None)
+}
+
+/// Returns the work tree containing `file_name` and the file's path in it.
+///
+/// # Errors
+///
+/// Returns a message for the user if the file is not in a repository.
+pub fn locate(file_name: &str) -> Result<(PathBuf, String), String> {
+    let not_in_repository = || format!("{file_name} is not in a git repository");
+    let path = fs::canonicalize(file_name).map_err(|error| format!("{file_name}: {error}"))?;
+    let work_tree = path
+        .ancestors()
+        .find(|dir| dir.join(".git").exists())
+        .ok_or_else(not_in_repository)?;
+    let relative = path
+        .strip_prefix(work_tree)
+        .map_err(|_| not_in_repository())?;
+    let components: Vec<String> = relative
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy().into_owned())
+        .collect();
+    Ok((work_tree.to_path_buf(), components.join("/")))
+}
+
+/// Runs git in `work_tree`, feeding it `input`, and returns what it printed.
+///
+/// # Errors
+///
+/// Returns what git printed to its standard error if it failed.
+pub fn run(work_tree: &Path, args: &[&str], input: Option<&str>) -> Result<String, String> {
+    let mut child = Command::new("git")
+        .arg("-C")
+        .arg(work_tree)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|error| format!("Could not run git: {error}"))?;
+    // Every command used here reads all of its input before it writes much.
+    if let (Some(mut stdin), Some(input)) = (child.stdin.take(), input) {
+        stdin
+            .write_all(input.as_bytes())
+            .map_err(|error| format!("Could not write to git: {error}"))?;
+    }
+    let output = child
+        .wait_with_output()
+        .map_err(|error| format!("Could not run git: {error}"))?;
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    } else {
+        let error = String::from_utf8_lossy(&output.stderr);
+        Err(format!(
+            "git {}: {}",
+            args.first().unwrap_or(&""),
+            error.trim()
+        ))
+    }
+}
+
+/// Finds the git directory, following the `gitdir:` file that worktrees and
+/// submodules have in place of a directory.
+fn git_dir(work_tree: &Path) -> Option<PathBuf> {
+    let dot_git = work_tree.join(".git");
+    if dot_git.is_dir() {
+        return Some(dot_git);
+    }
+    let contents = fs::read_to_string(&dot_git).ok()?;
+    let dir = contents.strip_prefix("gitdir:")?.trim();
+    Some(work_tree.join(dir))
+}
+
+/// Reads the checked out branch, or the abbreviated commit when detached.
+fn branch(git_dir: &Path) -> Option<String> {
+    let head = fs::read_to_string(git_dir.join("HEAD")).ok()?;
+    let head = head.trim();
+    match head.strip_prefix("ref: ") {
+        Some(reference) => Some(
+            reference
+                .strip_prefix("refs/heads/")
+                .unwrap_or(reference)
+                .to_string(),
+        ),
+        None => Some(head.chars().take(7).collect()),
+    }
+}
+
+/// Asks git whether tracked files were modified. Without git installed, the
+/// repository is reported as clean.
+fn has_changes(work_tree: &Path) -> bool {
+    Command::new("git")
+        .arg("-C")
+        .arg(work_tree)
+        .args(["status", "--porcelain", "--untracked-files=no"])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .is_ok_and(|output| output.status.success() && !output.stdout.is_empty())
+}
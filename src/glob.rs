@@ -0,0 +1,122 @@
+use std::fs;
+use std::path::Path;
+
+/// Returns whether `text` holds any of the wildcards [`matches`] knows.
+#[must_use]
+pub fn is_pattern(text: &str) -> bool {
+    text.contains(['*', '?'])
+}
+
+/// Returns the files whose path matches `pattern`, in order. Names starting
+/// with a dot are only matched by a part of the pattern that does too.
+#[must_use]
+pub fn files(pattern: &str) -> Vec<String> {
+    // Paths matching the pattern so far, without a trailing `/`, but for
+    // the root.
+    let mut matched = vec![if pattern.starts_with('/') {
+        "/".to_string()
+    } else {
+        String::new()
+    }];
+    for part in pattern.split('/').filter(|part| !part.is_empty()) {
+        matched = if part == "**" {
+            matched.iter().flat_map(|path| directories(path)).collect()
+        } else if is_pattern(part) {
+            matched
+                .iter()
+                .flat_map(|path| {
+                    entries(path)
+                        .into_iter()
+                        .filter(|name| !name.starts_with('.') || part.starts_with('.'))
+                        .filter(|name| matches(part, name))
+                        .map(move |name| join(path, &name))
+                })
+                .collect()
+        } else {
+            matched.iter().map(|path| join(path, part)).collect()
+        };
+    }
+    let mut files: Vec<String> = matched
+        .into_iter()
+        .filter(|path| Path::new(path).is_file())
+        .collect();
+    files.sort();
+    files.dedup();
+    files
+}
+
+fn join(directory: &str, name: &str) -> String {
+    if directory.is_empty() || directory.ends_with('/') {
+        format!("{directory}{name}")
+    } else {
+        format!("{directory}/{name}")
+    }
+}
+
+/// Returns the names in `directory`, the working directory if it is empty.
+fn entries(directory: &str) -> Vec<String> {
+    let directory = if directory.is_empty() { "." } else { directory };
+    fs::read_dir(directory)
+        .map(|entries| {
+            entries
+                .filter_map(Result::ok)
+                .filter_map(|entry| entry.file_name().into_string().ok())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Returns `directory` and those below it, but for hidden ones and links.
+fn directories(directory: &str) -> Vec<String> {
+    let mut found = vec![directory.to_string()];
+    let mut index = 0;
+    while let Some(path) = found.get(index).cloned() {
+        index = index.saturating_add(1);
+        let listed = if path.is_empty() { "." } else { path.as_str() };
+        let Ok(entries) = fs::read_dir(listed) else {
+            continue;
+        };
+        for entry in entries.filter_map(Result::ok) {
+            let is_directory = entry.file_type().is_ok_and(|kind| kind.is_dir());
+            match entry.file_name().into_string() {
+                Ok(name) if is_directory && !name.starts_with('.') => {
+                    found.push(join(&path, &name));
+                }
+                _ => {}
+            }
+        }
+    }
+    found
+}
+
+/// Returns whether all of `text` matches `glob`, where `*` stands for any run
+/// of characters but `/`, `**` for any run at all and `?` for one character.
+#[must_use]
+pub fn matches(glob: &str, text: &str) -> bool {
+    let glob: Vec<char> = glob.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    matches_from(&glob, &text)
+}
+
+fn matches_from(glob: &[char], text: &[char]) -> bool {
+    match glob {
+        [] => text.is_empty(),
+        // `**/` also matches no directories at all.
+        ['*', '*', '/', rest @ ..] => (0..=text.len())
+            .filter(|&start| start == 0 || text.get(start.saturating_sub(1)) == Some(&'/'))
+            .any(|start| matches_from(rest, text.get(start..).unwrap_or_default())),
+        ['*', '*', rest @ ..] => {
+            (0..=text.len()).any(|start| matches_from(rest, text.get(start..).unwrap_or_default()))
+        }
+        ['*', rest @ ..] => (0..=text.len())
+            .take_while(|&start| start == 0 || text.get(start.saturating_sub(1)) != Some(&'/'))
+            .any(|start| matches_from(rest, text.get(start..).unwrap_or_default())),
+        ['?', rest @ ..] => {
+            matches!(text.first(), Some(c) if *c != '/')
+                && matches_from(rest, text.get(1..).unwrap_or_default())
+        }
+        [c, rest @ ..] => {
+            text.first() == Some(c) && matches_from(rest, text.get(1..).unwrap_or_default())
+        }
+    }
+}
@@ -0,0 +1,186 @@
+use crate::backend::{Backend, Event, Size};
+use crate::output::Output;
+use std::cell::{Cell, RefCell};
+use std::io::{Error, ErrorKind};
+use std::str::Chars;
+use std::time::Duration;
+
+struct Screen {
+    lines: Vec<Vec<char>>,
+    cursor: (usize, usize),
+}
+
+impl Screen {
+    fn put(&mut self, c: char) {
+        let (x, y) = self.cursor;
+        if let Some(cell) = self.lines.get_mut(y).and_then(|line| line.get_mut(x)) {
+            *cell = c;
+        }
+        self.cursor.0 = x.saturating_add(1);
+    }
+
+    fn clear_line(&mut self) {
+        if let Some(line) = self.lines.get_mut(self.cursor.1) {
+            line.fill(' ');
+        }
+    }
+
+    fn clear(&mut self) {
+        for line in &mut self.lines {
+            line.fill(' ');
+        }
+    }
+
+    fn csi(&mut self, chars: &mut Chars) {
+        let mut params = String::new();
+        for c in chars.by_ref() {
+            if ('\x40'..='\x7e').contains(&c) {
+                match c {
+                    'H' => {
+                        let mut coordinates = params.split(';').map(|n| n.parse().unwrap_or(1));
+                        let y: usize = coordinates.next().unwrap_or(1);
+                        let x: usize = coordinates.next().unwrap_or(1);
+                        self.cursor = (x.saturating_sub(1), y.saturating_sub(1));
+                    }
+                    'J' => self.clear(),
+                    'K' => self.clear_line(),
+                    _ => (),
+                }
+                return;
+            }
+            params.push(c);
+        }
+    }
+
+    /// Makes the screen `width` by `height` cells, keeping what fits.
+    fn resize(&mut self, width: usize, height: usize) {
+        self.lines.resize_with(height, Vec::new);
+        for line in &mut self.lines {
+            line.resize(width, ' ');
+        }
+    }
+
+    fn lines(&self) -> Vec<String> {
+        self.lines
+            .iter()
+            .map(|line| line.iter().collect::<String>().trim_end().to_string())
+            .collect()
+    }
+}
+
+/// A [`Backend`] that keeps the screen in memory instead of drawing to a TTY.
+///
+/// Input is taken from an iterator of events, such as a script, and every
+/// flushed frame is recorded as plain text lines with escape sequences
+/// stripped, so editor behaviour can be asserted without a terminal, while
+/// the text itself is kept as a terminal would have been sent it. The
+/// editor waits for nothing: [`Event::Tick`] stands for time passing, and
+/// [`Event::Resize`] resizes the screen.
+pub struct Headless {
+    size: Cell<Size>,
+    events: RefCell<Box<dyn Iterator<Item = Event>>>,
+    screen: RefCell<Screen>,
+    frames: RefCell<Vec<Vec<String>>>,
+    output: Output<Vec<u8>>,
+}
+
+impl Headless {
+    /// Creates a screen of `width` by `height` cells, including the two lines
+    /// used by the status and message bars.
+    #[must_use]
+    pub fn new<I>(width: u16, height: u16, events: I) -> Self
+    where
+        I: IntoIterator<Item = Event>,
+        I::IntoIter: 'static,
+    {
+        Self {
+            size: Cell::new(Size {
+                width,
+                height: height.saturating_sub(2),
+            }),
+            events: RefCell::new(Box::new(events.into_iter())),
+            screen: RefCell::new(Screen {
+                lines: vec![vec![' '; width as usize]; height as usize],
+                cursor: (0, 0),
+            }),
+            frames: RefCell::new(Vec::new()),
+            output: Output::new(Vec::new()),
+        }
+    }
+
+    /// Returns every frame flushed so far, oldest first.
+    #[must_use]
+    pub fn frames(&self) -> Vec<Vec<String>> {
+        self.frames.borrow().clone()
+    }
+
+    /// Returns the text flushed so far, escape sequences and all.
+    #[must_use]
+    pub fn output(&self) -> String {
+        String::from_utf8_lossy(&self.output.writer()).into_owned()
+    }
+
+    #[must_use]
+    pub fn last_frame(&self) -> Option<Vec<String>> {
+        self.frames.borrow().last().cloned()
+    }
+
+    /// Returns the zero-based `(x, y)` cell the cursor was left on.
+    #[must_use]
+    pub fn cursor(&self) -> (usize, usize) {
+        self.screen.borrow().cursor
+    }
+
+    fn next_event(&self) -> Option<Event> {
+        let event = self.events.borrow_mut().next()?;
+        if let Event::Resize { width, height } = event {
+            self.size.set(Size {
+                width,
+                height: height.saturating_sub(2),
+            });
+            self.screen
+                .borrow_mut()
+                .resize(width as usize, height as usize);
+        }
+        Some(event)
+    }
+}
+
+impl Backend for Headless {
+    fn size(&self) -> Size {
+        self.size.get()
+    }
+
+    fn write(&self, text: &str) {
+        self.output.write(text);
+        let mut screen = self.screen.borrow_mut();
+        let mut chars = text.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '\x1b' => match chars.next() {
+                    Some('[') => screen.csi(&mut chars),
+                    Some(']') => for _ in chars.by_ref().take_while(|c| *c != '\x07') {},
+                    _ => (),
+                },
+                '\r' => screen.cursor.0 = 0,
+                '\n' => screen.cursor.1 = screen.cursor.1.saturating_add(1),
+                c => screen.put(c),
+            }
+        }
+    }
+
+    fn flush(&self) -> Result<(), Error> {
+        let lines = self.screen.borrow().lines();
+        self.frames.borrow_mut().push(lines);
+        self.output.flush()
+    }
+
+    fn read_event(&self) -> Result<Event, Error> {
+        self.next_event()
+            .ok_or_else(|| ErrorKind::UnexpectedEof.into())
+    }
+
+    fn poll_event(&self, _timeout: Duration) -> Result<Option<Event>, Error> {
+        Ok(self.next_event())
+    }
+}
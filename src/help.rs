@@ -0,0 +1,53 @@
+use crate::command::{self, Argument, COMMANDS};
+use crate::keymap::{self, Keymap};
+
+/// Builds the text of the help screen from the bindings and commands that are
+/// actually in effect, so it never goes out of date.
+#[must_use]
+pub fn text(keymap: &Keymap) -> String {
+    let mut lines = vec![
+        "hecto help. Search it with Ctrl-F, run `close` to go back.".to_string(),
+        String::new(),
+        "KEY BINDINGS".to_string(),
+    ];
+    let mut bindings: Vec<(String, &str)> = keymap
+        .bindings()
+        .map(|(keys, command)| {
+            let keys: Vec<String> = keys.iter().copied().map(keymap::describe).collect();
+            (keys.join(" "), command)
+        })
+        .collect();
+    bindings.sort();
+    for (keys, command) in bindings {
+        lines.push(format!(
+            "  {keys:<14}{command:<16}{}",
+            command::describe(command)
+        ));
+    }
+    lines.push(String::new());
+    lines.push("COMMANDS".to_string());
+    for command in COMMANDS {
+        let mut usage = command.name.to_string();
+        match command.argument {
+            Argument::None => (),
+            Argument::Path => usage.push_str(" [file]"),
+            Argument::Option => usage.push_str(" <option>"),
+            Argument::Line => usage.push_str(" [line[:column]|percent%]"),
+            Argument::Mark => usage.push_str(" [letter]"),
+            Argument::Shell => usage.push_str(" <command>"),
+            Argument::Arguments => usage.push_str(" [arguments]"),
+            Argument::Symbol => usage.push_str(" [name]"),
+            Argument::Register => usage.push_str(" [register]"),
+            Argument::Expression => usage.push_str(" [expression]"),
+            Argument::Substitution => usage.push_str(" /pattern/replacement/[g]"),
+            Argument::Choice(choices) => usage.push_str(&format!(" [{}]", choices.join("|"))),
+        }
+        if !command.aliases.is_empty() {
+            usage = format!("{usage} ({})", command.aliases.join(", "));
+        }
+        lines.push(format!("  {usage:<30}{}", command.description));
+    }
+    lines.push(String::new());
+    lines.push(format!("OPTIONS: {}", command::OPTIONS.join(", ")));
+    lines.join("\n")
+}
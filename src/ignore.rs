@@ -0,0 +1,51 @@
+use crate::glob::matches;
+use std::env;
+use std::path::{Path, PathBuf};
+
+/// Paths left out of completion and of the search for tasks, given as globs,
+/// as [`matches`] reads them. A glob without a `/` matches any file or
+/// directory of that name, and one with a `/` matches paths from the `root`
+/// on, such as `docs/*.html`.
+#[derive(Clone, Default)]
+pub struct Ignore {
+    root: PathBuf,
+    globs: Vec<String>,
+}
+
+impl Ignore {
+    #[must_use]
+    pub fn new(root: PathBuf, globs: Vec<String>) -> Self {
+        Self { root, globs }
+    }
+
+    /// Returns the directory globs with a `/` start from.
+    #[must_use]
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Returns whether `path`, relative to the working directory unless it
+    /// is absolute, matches one of the globs.
+    #[must_use]
+    pub fn is_ignored(&self, path: &Path) -> bool {
+        if self.globs.is_empty() {
+            return false;
+        }
+        let absolute = if path.is_absolute() {
+            path.to_path_buf()
+        } else {
+            env::current_dir().unwrap_or_default().join(path)
+        };
+        let relative = absolute.strip_prefix(&self.root).unwrap_or(path);
+        self.globs.iter().any(|glob| {
+            let glob = glob.trim_matches('/');
+            if glob.contains('/') {
+                matches(glob, &relative.to_string_lossy())
+            } else {
+                relative
+                    .iter()
+                    .any(|name| matches(glob, &name.to_string_lossy()))
+            }
+        })
+    }
+}
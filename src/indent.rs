@@ -0,0 +1,83 @@
+use std::collections::BTreeMap;
+
+/// The widths an indentation of spaces is guessed among.
+const WIDTHS: std::ops::RangeInclusive<usize> = 2..=8;
+
+/// How a file indents its lines.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Indent {
+    Tabs,
+    /// Spaces, this many a level.
+    Spaces(usize),
+}
+
+impl Indent {
+    /// Returns the text that indents a line starting at column `x` to the
+    /// next level.
+    #[must_use]
+    pub fn step(self, x: usize) -> String {
+        match self {
+            Self::Tabs => "\t".to_string(),
+            Self::Spaces(width) => {
+                let width = width.max(1);
+                " ".repeat(width.saturating_sub(x % width))
+            }
+        }
+    }
+
+    /// Returns one level of indentation.
+    #[must_use]
+    pub fn unit(self) -> String {
+        self.step(0)
+    }
+}
+
+/// Guesses how `lines` are indented from their leading whitespace: with tabs
+/// if more lines start with a tab than with spaces, or else with as many
+/// spaces as a line most often goes in from the one above. Returns `None`
+/// if no line is indented.
+pub fn detect<'a>(lines: impl Iterator<Item = &'a str>) -> Option<Indent> {
+    let mut tabs = 0_usize;
+    let mut spaces = 0_usize;
+    // How often a line is indented by so many more spaces than the one above.
+    let mut steps: BTreeMap<usize, usize> = BTreeMap::new();
+    let mut previous = 0;
+    for line in lines {
+        let text = line.trim_start();
+        if text.is_empty() {
+            continue;
+        }
+        let leading = &line[..line.len().saturating_sub(text.len())];
+        if leading.starts_with('\t') {
+            tabs = tabs.saturating_add(1);
+            continue;
+        }
+        // The ` * ` lines of block comments are one space off the level.
+        if text.starts_with('*') {
+            continue;
+        }
+        let width = leading.chars().take_while(|&c| c == ' ').count();
+        if width > 0 {
+            spaces = spaces.saturating_add(1);
+        }
+        if width > previous && WIDTHS.contains(&width.saturating_sub(previous)) {
+            let count = steps.entry(width.saturating_sub(previous)).or_default();
+            *count = count.saturating_add(1);
+        }
+        previous = width;
+    }
+    if tabs == 0 && spaces == 0 {
+        None
+    } else if tabs > spaces {
+        Some(Indent::Tabs)
+    } else {
+        // The narrower width wins a tie, as the wider is often two levels.
+        let width = steps
+            .into_iter()
+            .max_by(|(width, count), (other, other_count)| {
+                count.cmp(other_count).then(other.cmp(width))
+            })
+            .map_or(4, |(width, _)| width);
+        Some(Indent::Spaces(width))
+    }
+}
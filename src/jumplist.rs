@@ -0,0 +1,58 @@
+use crate::Position;
+
+const MAX_JUMPS: usize = 100;
+
+#[derive(Clone, PartialEq)]
+pub struct Jump {
+    pub file_name: Option<String>,
+    pub position: Position,
+}
+
+/// Locations the cursor jumped away from, so the user can retrace them like a
+/// browser history.
+#[derive(Default)]
+pub struct JumpList {
+    jumps: Vec<Jump>,
+    /// Index of the entry the user is at, or `jumps.len()` when they are not
+    /// walking through the list.
+    current: usize,
+}
+
+impl JumpList {
+    /// Records the location the cursor is about to leave. Entries ahead of the
+    /// current one are dropped, as they would be in a browser.
+    pub fn push(&mut self, jump: Jump) {
+        self.jumps.truncate(self.current);
+        if self.jumps.last() != Some(&jump) {
+            self.jumps.push(jump);
+        }
+        if self.jumps.len() > MAX_JUMPS {
+            self.jumps.remove(0);
+        }
+        self.current = self.jumps.len();
+    }
+
+    /// Steps back, given the location the cursor is at so that
+    /// [`JumpList::forward`] can return to it.
+    pub fn back(&mut self, from: Jump) -> Option<&Jump> {
+        if self.current == self.jumps.len() {
+            if self.jumps.last() != Some(&from) {
+                self.jumps.push(from);
+            }
+            self.current = self.jumps.len().saturating_sub(1);
+        }
+        if self.current == 0 {
+            return None;
+        }
+        self.current = self.current.saturating_sub(1);
+        self.jumps.get(self.current)
+    }
+
+    pub fn forward(&mut self) -> Option<&Jump> {
+        if self.current.saturating_add(1) >= self.jumps.len() {
+            return None;
+        }
+        self.current = self.current.saturating_add(1);
+        self.jumps.get(self.current)
+    }
+}
@@ -0,0 +1,357 @@
+use crate::command;
+use std::time::{Duration, Instant};
+use termion::event::Key;
+
+const CHORD_TIMEOUT: Duration = Duration::from_millis(1500);
+
+struct Binding {
+    keys: Vec<Key>,
+    command: String,
+}
+
+pub struct Keymap {
+    bindings: Vec<Binding>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let mut keymap = Self {
+            bindings: Vec::new(),
+        };
+        keymap.bind(&[Key::Ctrl('q')], "quit_all");
+        keymap.bind(&[Key::Ctrl('s')], "save");
+        keymap.bind(&[Key::Ctrl('f')], "find");
+        // Terminals send Shift-F3 in too many ways to tell apart here, so the
+        // search goes back on Alt-Shift-N, as `N` does in vi.
+        keymap.bind(&[Key::F(3)], "find_next");
+        keymap.bind(&[Key::Alt('n')], "find_next");
+        keymap.bind(&[Key::Alt('N')], "find_previous");
+        keymap.bind(&[Key::Ctrl('p')], "command_line");
+        keymap.bind(&[Key::Ctrl('g')], "goto");
+        keymap.bind(&[Key::F(1)], "help");
+        keymap.bind(&[Key::Ctrl('b')], "buffers");
+        // Ctrl-^ arrives as Ctrl-6, as in vi.
+        keymap.bind(&[Key::Ctrl('6')], "buffer_previous");
+        keymap.bind(&[Key::Alt('t')], "terminal");
+        keymap.bind(&[Key::F(4)], "next_error");
+        keymap.bind(&[Key::F(8)], "next_diagnostic");
+        keymap.bind(&[Key::F(9)], "breakpoint");
+        keymap.bind(&[Key::F(12)], "goto_definition");
+        keymap.bind(&[Key::Alt('/')], "complete");
+        // Terminals send Ctrl-] as the same byte as Ctrl-5.
+        keymap.bind(&[Key::Ctrl('5')], "tag");
+        keymap.bind(&[Key::Ctrl('t')], "tag_pop");
+        keymap.bind(&[Key::Ctrl('z')], "undo");
+        keymap.bind(&[Key::Ctrl('y')], "redo");
+        keymap.bind(&[Key::Ctrl('c')], "yank");
+        keymap.bind(&[Key::Ctrl('x')], "cut");
+        keymap.bind(&[Key::Ctrl('v')], "paste");
+        keymap.bind(&[Key::Ctrl('o')], "jump_back");
+        // Terminals send Ctrl-I as a tab, so it cannot be bound here.
+        keymap.bind(&[Key::Alt('i')], "jump_forward");
+        keymap.bind(&[Key::Delete], "delete");
+        keymap.bind(&[Key::Backspace], "backspace");
+        keymap.bind(&[Key::Up], "move_up");
+        keymap.bind(&[Key::Down], "move_down");
+        keymap.bind(&[Key::Left], "move_left");
+        keymap.bind(&[Key::Right], "move_right");
+        keymap.bind(&[Key::PageUp], "page_up");
+        keymap.bind(&[Key::PageDown], "page_down");
+        keymap.bind(&[Key::Ctrl('u')], "half_page_up");
+        keymap.bind(&[Key::Ctrl('d')], "half_page_down");
+        keymap.bind(&[Key::Home], "line_start");
+        keymap.bind(&[Key::End], "line_end");
+        keymap.bind(&[Key::CtrlHome], "document_start");
+        keymap.bind(&[Key::CtrlEnd], "document_end");
+        keymap.bind(&[Key::CtrlLeft], "previous_word");
+        keymap.bind(&[Key::CtrlRight], "next_word");
+        keymap.bind(&[Key::CtrlUp], "previous_paragraph");
+        keymap.bind(&[Key::CtrlDown], "next_paragraph");
+        keymap.bind(&[Key::Alt('(')], "previous_sentence");
+        keymap.bind(&[Key::Alt(')')], "next_sentence");
+        keymap.bind(&[Key::Alt('{')], "previous_section");
+        keymap.bind(&[Key::Alt('}')], "next_section");
+        keymap.bind(&[Key::Insert], "toggle_replace");
+        // Ctrl-Space arrives as a null byte.
+        keymap.bind(&[Key::Null], "select");
+        keymap.bind(&[Key::Esc], "deselect");
+        keymap.bind(&[Key::ShiftUp], "select_up");
+        keymap.bind(&[Key::ShiftDown], "select_down");
+        keymap.bind(&[Key::ShiftLeft], "select_left");
+        keymap.bind(&[Key::ShiftRight], "select_right");
+        keymap.bind(&[Key::AltUp], "move_line_up");
+        keymap.bind(&[Key::AltDown], "move_line_down");
+        keymap.bind(&[Key::Ctrl('k'), Key::Char('d')], "delete_line");
+        keymap.bind(&[Key::Ctrl('k'), Key::Char('s')], "save");
+        keymap.bind(&[Key::Ctrl('k'), Key::Char('q')], "quit");
+        keymap.bind(&[Key::Ctrl('k'), Key::Char('m')], "mark");
+        keymap.bind(&[Key::Ctrl('k'), Key::Char('|')], "filter");
+        keymap.bind(&[Key::Ctrl('k'), Key::Char('f')], "format");
+        keymap.bind(&[Key::Ctrl('k'), Key::Char('n')], "next_error");
+        keymap.bind(&[Key::Ctrl('k'), Key::Char('p')], "previous_error");
+        keymap.bind(&[Key::Ctrl('k'), Key::Char(']')], "next_diagnostic");
+        keymap.bind(&[Key::Ctrl('k'), Key::Char('[')], "previous_diagnostic");
+        keymap.bind(&[Key::Ctrl('k'), Key::Char('h')], "hover");
+        keymap.bind(&[Key::Ctrl('k'), Key::Char('t')], "tags");
+        keymap.bind(&[Key::Ctrl('k'), Key::Char('o')], "outline");
+        keymap.bind(&[Key::Ctrl('k'), Key::Char('T')], "tasks");
+        keymap.bind(&[Key::Ctrl('k'), Key::Char('w')], "split");
+        keymap.bind(&[Key::Ctrl('k'), Key::Char('a')], "stage_hunk");
+        keymap.bind(&[Key::Ctrl('k'), Key::Char('r')], "revert_hunk");
+        keymap.bind(&[Key::Ctrl('k'), Key::Char('c')], "next_conflict");
+        keymap.bind(&[Key::Ctrl('k'), Key::Char('C')], "previous_conflict");
+        keymap.bind(&[Key::Ctrl('k'), Key::Char('b')], "blame");
+        keymap.bind(&[Key::Ctrl('k'), Key::Char('u')], "undo_tree");
+        keymap.bind(&[Key::Ctrl('k'), Key::Char('\'')], "goto_mark");
+        keymap.bind(&[Key::Ctrl('k'), Key::Char('"')], "register");
+        keymap.bind(&[Key::Ctrl('k'), Key::Char('y')], "yank_history");
+        keymap
+    }
+}
+
+impl Keymap {
+    /// Binds the key sequence `keys` to `command`, replacing any previous
+    /// binding for the same sequence.
+    pub fn bind(&mut self, keys: &[Key], command: &str) {
+        self.bindings.retain(|binding| binding.keys != keys);
+        self.bindings.push(Binding {
+            keys: keys.to_vec(),
+            command: command.to_string(),
+        });
+    }
+
+    pub fn unbind(&mut self, keys: &[Key]) {
+        self.bindings.retain(|binding| binding.keys != keys);
+    }
+
+    /// Applies user bindings such as `"ctrl-k d" = "delete_line"` on top of
+    /// the current ones. An empty command removes the binding.
+    ///
+    /// # Errors
+    ///
+    /// Returns one message per binding that could not be applied; the other
+    /// bindings are still applied.
+    pub fn apply<'a>(
+        &mut self,
+        bindings: impl IntoIterator<Item = (&'a String, &'a String)>,
+    ) -> Result<(), Vec<String>> {
+        let mut errors = Vec::new();
+        for (description, command) in bindings {
+            let keys = match parse_keys(description) {
+                Ok(keys) => keys,
+                Err(error) => {
+                    errors.push(error);
+                    continue;
+                }
+            };
+            if command.trim().is_empty() {
+                self.unbind(&keys);
+            } else if let Err(error) = command::parse(command) {
+                errors.push(format!("{description}: {error}"));
+            } else {
+                self.bind(&keys, command);
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Lists every binding as the keys and the command line they run.
+    pub fn bindings(&self) -> impl Iterator<Item = (&[Key], &str)> {
+        self.bindings
+            .iter()
+            .map(|binding| (binding.keys.as_slice(), binding.command.as_str()))
+    }
+
+    pub fn command(&self, keys: &[Key]) -> Option<&str> {
+        self.bindings
+            .iter()
+            .find(|binding| binding.keys == keys)
+            .map(|binding| binding.command.as_str())
+    }
+
+    fn is_prefix(&self, keys: &[Key]) -> bool {
+        self.bindings
+            .iter()
+            .any(|binding| binding.keys.len() > keys.len() && binding.keys.starts_with(keys))
+    }
+
+    /// Returns the keys that can follow `prefix`, each with the command it runs
+    /// or `None` if it leads to a longer chord.
+    #[must_use]
+    pub fn continuations(&self, prefix: &[Key]) -> Vec<(Key, Option<&str>)> {
+        let mut result: Vec<(Key, Option<&str>)> = Vec::new();
+        for binding in &self.bindings {
+            if binding.keys.len() <= prefix.len() || !binding.keys.starts_with(prefix) {
+                continue;
+            }
+            let next = binding.keys[prefix.len()];
+            if result.iter().any(|(key, _)| *key == next) {
+                continue;
+            }
+            if binding.keys.len() == prefix.len().saturating_add(1) {
+                result.push((next, Some(binding.command.as_str())));
+            } else {
+                result.push((next, None));
+            }
+        }
+        result
+    }
+}
+
+pub enum Dispatch {
+    Command(String),
+    Pending,
+    Unbound(Vec<Key>),
+}
+
+/// Collects keys until they form a complete binding of a [`Keymap`].
+pub struct Dispatcher {
+    pending: Vec<Key>,
+    last_key: Instant,
+}
+
+impl Default for Dispatcher {
+    fn default() -> Self {
+        Self {
+            pending: Vec::new(),
+            last_key: Instant::now(),
+        }
+    }
+}
+
+impl Dispatcher {
+    pub fn feed(&mut self, keymap: &Keymap, key: Key) -> Dispatch {
+        if self.last_key.elapsed() > CHORD_TIMEOUT {
+            self.pending.clear();
+        }
+        self.last_key = Instant::now();
+        self.pending.push(key);
+        if let Some(command) = keymap.command(&self.pending) {
+            self.pending.clear();
+            return Dispatch::Command(command.to_string());
+        }
+        if keymap.is_prefix(&self.pending) {
+            return Dispatch::Pending;
+        }
+        Dispatch::Unbound(std::mem::take(&mut self.pending))
+    }
+
+    /// Gives up on the keys typed so far once no other key has come for a
+    /// while. Returns whether there were any.
+    pub fn expire(&mut self) -> bool {
+        if self.pending.is_empty() || self.last_key.elapsed() <= CHORD_TIMEOUT {
+            return false;
+        }
+        self.pending.clear();
+        true
+    }
+
+    #[must_use]
+    pub fn pending(&self) -> &[Key] {
+        &self.pending
+    }
+}
+
+const NAMED_KEYS: &[(&str, Key)] = &[
+    ("up", Key::Up),
+    ("down", Key::Down),
+    ("left", Key::Left),
+    ("right", Key::Right),
+    ("shift-up", Key::ShiftUp),
+    ("shift-down", Key::ShiftDown),
+    ("shift-left", Key::ShiftLeft),
+    ("shift-right", Key::ShiftRight),
+    ("alt-up", Key::AltUp),
+    ("alt-down", Key::AltDown),
+    ("alt-left", Key::AltLeft),
+    ("alt-right", Key::AltRight),
+    ("ctrl-up", Key::CtrlUp),
+    ("ctrl-down", Key::CtrlDown),
+    ("ctrl-left", Key::CtrlLeft),
+    ("ctrl-right", Key::CtrlRight),
+    ("home", Key::Home),
+    ("end", Key::End),
+    ("ctrl-home", Key::CtrlHome),
+    ("ctrl-end", Key::CtrlEnd),
+    ("pageup", Key::PageUp),
+    ("pagedown", Key::PageDown),
+    ("backspace", Key::Backspace),
+    ("delete", Key::Delete),
+    ("insert", Key::Insert),
+    ("esc", Key::Esc),
+    ("enter", Key::Char('\n')),
+    ("tab", Key::Char('\t')),
+    ("shift-tab", Key::BackTab),
+    ("space", Key::Char(' ')),
+    ("ctrl-space", Key::Null),
+];
+
+/// Parses a description such as `ctrl-s`, `alt-up` or `f3`. A chord is
+/// written as several descriptions separated by spaces, e.g. `ctrl-k d`.
+///
+/// # Errors
+///
+/// Returns a message for the user naming the key that was not understood.
+pub fn parse_keys(description: &str) -> Result<Vec<Key>, String> {
+    let keys: Result<Vec<Key>, String> = description.split_whitespace().map(parse_key).collect();
+    match keys {
+        Ok(keys) if keys.is_empty() => Err("Empty key description".to_string()),
+        keys => keys,
+    }
+}
+
+fn parse_key(description: &str) -> Result<Key, String> {
+    let lowercase = description.to_ascii_lowercase();
+    if let Some((_, key)) = NAMED_KEYS.iter().find(|(name, _)| *name == lowercase) {
+        return Ok(*key);
+    }
+    if let Some(n) = lowercase.strip_prefix('f').and_then(|n| n.parse().ok()) {
+        return Ok(Key::F(n));
+    }
+    let single = |rest: &str| {
+        let mut chars = rest.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Some(c),
+            _ => None,
+        }
+    };
+    let key = if let Some(c) = lowercase.strip_prefix("ctrl-").and_then(single) {
+        Some(Key::Ctrl(c))
+    } else if lowercase.starts_with("alt-") {
+        description.get(4..).and_then(single).map(Key::Alt)
+    } else {
+        single(description).map(Key::Char)
+    };
+    key.ok_or_else(|| format!("Unknown key: {description}"))
+}
+
+#[must_use]
+pub fn describe(key: Key) -> String {
+    match key {
+        Key::Ctrl(c) => format!("Ctrl-{}", c.to_ascii_uppercase()),
+        Key::Alt(c) => format!("Alt-{c}"),
+        Key::Char('\n') => "Enter".to_string(),
+        Key::Char('\t') => "Tab".to_string(),
+        Key::Char(' ') => "Space".to_string(),
+        Key::Char(c) => c.to_string(),
+        Key::F(n) => format!("F{n}"),
+        Key::PageUp => "PageUp".to_string(),
+        Key::PageDown => "PageDown".to_string(),
+        Key::BackTab => "Shift-Tab".to_string(),
+        Key::Esc => "Esc".to_string(),
+        Key::Null => "Ctrl-Space".to_string(),
+        other => {
+            let name = format!("{other:?}");
+            for modifier in ["Shift", "Alt", "Ctrl"] {
+                if let Some(rest) = name.strip_prefix(modifier) {
+                    return format!("{modifier}-{rest}");
+                }
+            }
+            name
+        }
+    }
+}
@@ -0,0 +1,112 @@
+//! The core of the hecto text editor.
+//!
+//! The `hecto` binary only parses its arguments and hands them to
+//! [`Editor::with_options`]; everything else lives here, so it can be reused
+//! on its own:
+//!
+//! - [`Document`] holds the text of a file as [`Row`]s, with undo history and
+//!   marks, and reads and writes it. It sends each [`Change`] to the text to
+//!   whoever subscribed to it.
+//! - [`FileType`] tells from a file name how its rows are highlighted, and
+//!   [`Row::highlight`] marks up a row as a sequence of [`Highlight`]s that a
+//!   [`Theme`] gives colors to.
+//! - [`Backend`] is what the editor draws on and reads input from.
+//!   [`Terminal`] drives a real terminal, while [`Headless`] keeps the screen
+//!   in memory and replays scripted [`Event`]s, for tests and for running the
+//!   editor inside other tools.
+//! - [`Editor`] ties these together, and [`Config`] and [`Options`] are the
+//!   settings it reads from `config.toml` and the command line.
+//!   [`run_batch`] drives it from a script instead of the keyboard, and
+//!   [`Server`] lets other programs edit documents over JSON-RPC.
+
+mod ansi;
+mod appearance;
+mod backend;
+mod batch;
+mod blame;
+mod breakpoints;
+mod calc;
+mod changes;
+mod characters;
+mod cli;
+mod command;
+mod comparison;
+mod config;
+mod conflicts;
+mod diff;
+mod document;
+mod editor;
+mod error;
+mod events;
+mod filetype;
+mod git;
+mod glob;
+mod headless;
+mod help;
+mod highlighting;
+mod ignore;
+mod indent;
+mod jumplist;
+mod keymap;
+mod lineedit;
+mod lint;
+mod locale;
+mod locations;
+mod logger;
+mod lsp;
+mod marks;
+mod motion;
+mod outline;
+mod output;
+mod paging;
+mod pane;
+mod paths;
+mod perf;
+mod picker;
+mod plugins;
+mod print;
+mod pty;
+mod recovery;
+mod registers;
+mod remap;
+mod replace;
+mod row;
+mod save;
+mod screen;
+mod server;
+mod shell;
+mod snapshots;
+mod state;
+mod tags;
+mod tasks;
+mod terminal;
+mod theme;
+mod undo;
+mod view;
+mod views;
+mod wasm;
+
+pub use appearance::Background;
+pub use backend::{Backend, CursorShape, Event, Size};
+pub use batch::run as run_batch;
+pub use breakpoints::SourceBreakpoints;
+pub use changes::{Change, Span};
+pub use cli::{Options, USAGE};
+pub use config::Config;
+pub use document::Document;
+pub use editor::Editor;
+pub use editor::Position;
+pub use editor::SearchDirection;
+pub use error::Error;
+pub use filetype::FileType;
+pub use filetype::HighlightingOptions;
+pub use headless::Headless;
+pub use highlighting::Type as Highlight;
+pub use indent::Indent;
+pub use logger::Logger;
+pub use perf::CountingAllocator;
+pub use replace::{ReplaceOptions, ReplaceReport};
+pub use row::Row;
+pub use server::Server;
+pub use terminal::Terminal;
+pub use theme::Theme;
@@ -0,0 +1,138 @@
+use termion::event::Key;
+use unicode_segmentation::UnicodeSegmentation;
+
+const MAX_HISTORY: usize = 100;
+
+/// A single line of text being edited, such as the answer to a prompt.
+#[derive(Default)]
+pub struct LineEditor {
+    text: String,
+    /// Position of the cursor, in graphemes.
+    cursor: usize,
+}
+
+impl LineEditor {
+    #[must_use]
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    #[must_use]
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    /// Replaces the text, leaving the cursor at its end.
+    pub fn set_text(&mut self, text: &str) {
+        self.text = text.to_string();
+        self.cursor = self.len();
+    }
+
+    /// Applies an editing key, returning `false` if `key` does not edit the
+    /// line so the caller can handle it.
+    pub fn edit(&mut self, key: Key) -> bool {
+        match key {
+            Key::Char(c) if !c.is_control() => {
+                let index = self.byte_index(self.cursor);
+                self.text.insert(index, c);
+                self.cursor = self.cursor.saturating_add(1);
+            }
+            Key::Backspace if self.cursor > 0 => {
+                self.delete(self.cursor.saturating_sub(1), self.cursor);
+            }
+            Key::Delete => self.delete(self.cursor, self.cursor.saturating_add(1)),
+            Key::Left => self.cursor = self.cursor.saturating_sub(1),
+            Key::Right => self.cursor = self.len().min(self.cursor.saturating_add(1)),
+            Key::Home | Key::Ctrl('a') => self.cursor = 0,
+            Key::End | Key::Ctrl('e') => self.cursor = self.len(),
+            Key::CtrlLeft | Key::Alt('b') => self.cursor = self.previous_word(),
+            Key::CtrlRight | Key::Alt('f') => self.cursor = self.next_word(),
+            Key::Ctrl('w') | Key::Alt('\x7f') => self.delete(self.previous_word(), self.cursor),
+            Key::Alt('d') => self.delete(self.cursor, self.next_word()),
+            Key::Ctrl('u') => self.delete(0, self.cursor),
+            Key::Ctrl('k') => self.delete(self.cursor, self.len()),
+            _ => return false,
+        }
+        true
+    }
+
+    fn len(&self) -> usize {
+        self.text.graphemes(true).count()
+    }
+
+    fn byte_index(&self, grapheme: usize) -> usize {
+        self.text
+            .grapheme_indices(true)
+            .nth(grapheme)
+            .map_or(self.text.len(), |(index, _)| index)
+    }
+
+    /// Removes the graphemes from `start` up to `end` and leaves the cursor
+    /// where they were.
+    fn delete(&mut self, start: usize, end: usize) {
+        let range = self.byte_index(start)..self.byte_index(end);
+        self.text.replace_range(range, "");
+        self.cursor = start;
+    }
+
+    fn previous_word(&self) -> usize {
+        let before: Vec<&str> = self.text.graphemes(true).take(self.cursor).collect();
+        let mut graphemes = before.into_iter().rev().peekable();
+        let mut index = self.cursor;
+        while graphemes.next_if(|grapheme| !is_word(grapheme)).is_some() {
+            index = index.saturating_sub(1);
+        }
+        while graphemes.next_if(|grapheme| is_word(grapheme)).is_some() {
+            index = index.saturating_sub(1);
+        }
+        index
+    }
+
+    fn next_word(&self) -> usize {
+        let mut graphemes = self.text.graphemes(true).skip(self.cursor).peekable();
+        let mut index = self.cursor;
+        while graphemes.next_if(|grapheme| !is_word(grapheme)).is_some() {
+            index = index.saturating_add(1);
+        }
+        while graphemes.next_if(|grapheme| is_word(grapheme)).is_some() {
+            index = index.saturating_add(1);
+        }
+        index
+    }
+}
+
+fn is_word(grapheme: &str) -> bool {
+    grapheme.chars().all(|c| c.is_alphanumeric() || c == '_')
+}
+
+/// Lines entered at a prompt, oldest first.
+#[derive(Default)]
+pub struct History {
+    entries: Vec<String>,
+}
+
+impl History {
+    pub fn push(&mut self, entry: &str) {
+        self.entries.retain(|existing| existing != entry);
+        self.entries.push(entry.to_string());
+        if self.entries.len() > MAX_HISTORY {
+            self.entries.remove(0);
+        }
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    #[must_use]
+    pub fn get(&self, index: usize) -> Option<&str> {
+        self.entries.get(index).map(String::as_str)
+    }
+
+    /// Returns the entries, oldest first.
+    #[must_use]
+    pub fn entries(&self) -> &[String] {
+        &self.entries
+    }
+}
@@ -0,0 +1,148 @@
+use crate::locations;
+use crate::shell;
+use crate::Position;
+use serde::Deserialize;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// A problem a linter found in a file.
+pub struct Diagnostic {
+    /// The file, canonicalized so it can be matched with open documents.
+    pub path: PathBuf,
+    pub position: Position,
+    pub severity: Severity,
+    pub message: String,
+}
+
+/// A linter running in the background.
+pub struct Lint {
+    command: String,
+    result: Receiver<Result<Vec<Diagnostic>, String>>,
+}
+
+impl Lint {
+    /// Starts `command` on its own thread.
+    #[must_use]
+    pub fn start(command: &str) -> Self {
+        let (sender, result) = mpsc::channel();
+        let owned = command.to_string();
+        thread::spawn(move || {
+            let diagnostics = shell::run(&owned, None)
+                .map(|output| parse(&format!("{}{}", output.stdout, output.stderr)))
+                .map_err(|error| format!("Could not run {owned}: {error}"));
+            let _ = sender.send(diagnostics);
+        });
+        Self {
+            command: command.to_string(),
+            result,
+        }
+    }
+
+    /// Returns what the linter found once it has finished.
+    pub fn poll(&self) -> Option<Result<Vec<Diagnostic>, String>> {
+        match self.result.try_recv() {
+            Ok(result) => Some(result),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => {
+                Some(Err(format!("{} stopped unexpectedly", self.command)))
+            }
+        }
+    }
+}
+
+/// Reads the diagnostics in a linter's output: either the JSON messages of
+/// `cargo clippy --message-format=json`, or lines such as
+/// `file:line:column: warning: message` that most other linters print.
+#[must_use]
+pub fn parse(output: &str) -> Vec<Diagnostic> {
+    let mut json = false;
+    let mut diagnostics = Vec::new();
+    for line in output.lines() {
+        let Ok(message) = serde_json::from_str::<CargoMessage>(line) else {
+            continue;
+        };
+        json = true;
+        if let Some(diagnostic) = message.message.and_then(CompilerMessage::diagnostic) {
+            diagnostics.push(diagnostic);
+        }
+    }
+    if !json {
+        diagnostics = locations::parse(output)
+            .into_iter()
+            .filter_map(|location| {
+                let severity = if location.message.starts_with("warning") {
+                    Severity::Warning
+                } else {
+                    Severity::Error
+                };
+                Some(Diagnostic {
+                    path: fs::canonicalize(&location.file_name).ok()?,
+                    position: location.position,
+                    severity,
+                    message: location.message,
+                })
+            })
+            .collect();
+    }
+    diagnostics.sort_by(|a, b| {
+        (&a.path, a.position.y, a.position.x, &a.message).cmp(&(
+            &b.path,
+            b.position.y,
+            b.position.x,
+            &b.message,
+        ))
+    });
+    // Cargo reports the same problem once for every target the file is part
+    // of.
+    diagnostics
+        .dedup_by(|a, b| a.path == b.path && a.position == b.position && a.message == b.message);
+    diagnostics
+}
+
+#[derive(Deserialize)]
+struct CargoMessage {
+    message: Option<CompilerMessage>,
+}
+
+#[derive(Deserialize)]
+struct CompilerMessage {
+    message: String,
+    level: String,
+    spans: Vec<Span>,
+}
+
+#[derive(Deserialize)]
+struct Span {
+    file_name: String,
+    line_start: usize,
+    column_start: usize,
+    is_primary: bool,
+}
+
+impl CompilerMessage {
+    fn diagnostic(self) -> Option<Diagnostic> {
+        let severity = match self.level.as_str() {
+            "warning" => Severity::Warning,
+            level if level.starts_with("error") => Severity::Error,
+            _ => return None,
+        };
+        let span = self.spans.into_iter().find(|span| span.is_primary)?;
+        Some(Diagnostic {
+            path: fs::canonicalize(&span.file_name).ok()?,
+            position: Position {
+                x: span.column_start.saturating_sub(1),
+                y: span.line_start.saturating_sub(1),
+            },
+            severity,
+            message: self.message,
+        })
+    }
+}
@@ -0,0 +1,339 @@
+use std::env;
+use std::fmt::Display;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+static LANGUAGE: AtomicU8 = AtomicU8::new(Language::English as u8);
+
+/// A language the interface has a message catalog for.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Language {
+    English,
+    Spanish,
+}
+
+impl Language {
+    /// Parses a locale name such as `es`, `es_AR` or `es_ES.UTF-8`, going by
+    /// its language alone.
+    #[must_use]
+    pub fn parse(name: &str) -> Option<Self> {
+        let language = name.split(['_', '.', '@', '-']).next().unwrap_or_default();
+        match language.to_ascii_lowercase().as_str() {
+            "en" | "c" | "posix" => Some(Self::English),
+            "es" => Some(Self::Spanish),
+            _ => None,
+        }
+    }
+
+    /// Takes the language from `$LC_ALL`, `$LC_MESSAGES` or `$LANG`, the
+    /// first of them that is set, falling back to English.
+    #[must_use]
+    pub fn from_env() -> Self {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|variable| env::var(variable).ok())
+            .find(|name| !name.is_empty())
+            .and_then(|name| Self::parse(&name))
+            .unwrap_or(Self::English)
+    }
+}
+
+/// Sets the language messages are shown in from now on.
+pub fn set_language(language: Language) {
+    LANGUAGE.store(language as u8, Ordering::Relaxed);
+}
+
+#[must_use]
+pub fn language() -> Language {
+    if LANGUAGE.load(Ordering::Relaxed) == Language::Spanish as u8 {
+        Language::Spanish
+    } else {
+        Language::English
+    }
+}
+
+/// Looks `message`, as written in English, up in the catalog of the current
+/// language. Messages missing from it are shown in English.
+#[must_use]
+pub fn translate(message: &'static str) -> &'static str {
+    match language() {
+        Language::English => message,
+        Language::Spanish => spanish(message).unwrap_or(message),
+    }
+}
+
+/// Replaces each `{name}` in `template` with its value in `values`.
+#[must_use]
+pub fn fill(template: &str, values: &[(&str, &dyn Display)]) -> String {
+    let mut text = template.to_string();
+    for (name, value) in values {
+        text = text.replace(&format!("{{{name}}}"), &value.to_string());
+    }
+    text
+}
+
+/// Translates an English message, filling in its `{name}` placeholders from
+/// `name = value` pairs, or from variables of the same name.
+macro_rules! tr {
+    (@value $name:ident) => {
+        $name
+    };
+    (@value $name:ident, $value:expr) => {
+        $value
+    };
+    ($message:literal) => {
+        $crate::locale::translate($message).to_string()
+    };
+    ($message:literal, $($name:ident $(= $value:expr)?),+ $(,)?) => {
+        $crate::locale::fill(
+            $crate::locale::translate($message),
+            &[$((
+                stringify!($name),
+                &($crate::locale::tr!(@value $name $(, $value)?)) as &dyn std::fmt::Display,
+            )),+],
+        )
+    };
+}
+
+pub(crate) use tr;
+
+/// The Spanish catalog.
+#[allow(clippy::too_many_lines)]
+fn spanish(message: &str) -> Option<&'static str> {
+    let translation = match message {
+        "HELP: F1 = help | Ctrl-F = find | Ctrl-S = save | Ctrl-P = command | Ctrl-Q = quit" => {
+            "AYUDA: F1 = ayuda | Ctrl-F = buscar | Ctrl-S = guardar | Ctrl-P = comando | Ctrl-Q = salir"
+        }
+        "Hector editor -- version {version}" => "Editor Hector -- versión {version}",
+        "{file_name} - {lines} lines" => "{file_name} - {lines} líneas",
+        " (modified)" => " (modificado)",
+        "REPLACE | " => "REEMPLAZAR | ",
+        "Search (ESC to cancel, Ctrl-N/Ctrl-P to navigate): " => {
+            "Buscar (ESC para cancelar, Ctrl-N/Ctrl-P para navegar): "
+        }
+        "Save as: " => "Guardar como: ",
+        "Go to line: " => "Ir a la línea: ",
+        "Filter through: " => "Filtrar con: ",
+        "Mark: " => "Marca: ",
+        "Go to mark: " => "Ir a la marca: ",
+        "Register: " => "Registro: ",
+        "Buffer: " => "Búfer: ",
+        "Tag: " => "Etiqueta: ",
+        "Character: " => "Carácter: ",
+        "Complete: " => "Completar: ",
+        "Undo tree: " => "Árbol de deshacer: ",
+        "{name} (current)" => "{name} (actual)",
+        "ERR: Could not open file: {file_name}" => "ERR: No se pudo abrir el archivo: {file_name}",
+        "This buffer is read-only." => "Este búfer es de solo lectura.",
+        "Save aborted." => "Guardado cancelado.",
+        "ERR: Still saving {file_name}." => "ERR: Todavía se está guardando {file_name}.",
+        "Saving {file_name}: {percent}%" => "Guardando {file_name}: {percent}%",
+        "File saved successfully." => "Archivo guardado.",
+        "Error writing file!" => "¡Error al escribir el archivo!",
+        "WARNING! File changed on disk, saving will overwrite it." => {
+            "¡ATENCIÓN! El archivo cambió en el disco; guardarlo lo sobrescribirá."
+        }
+        "File reloaded from disk." => "Archivo recargado desde el disco.",
+        "This file is not in a git repository." => "Este archivo no está en un repositorio git.",
+        "Linter found {errors} errors and {warnings} warnings." => {
+            "El linter encontró {errors} errores y {warnings} advertencias."
+        }
+        "The shell exited." => "La shell terminó.",
+        "Could not start shell: {error}" => "No se pudo iniciar la shell: {error}",
+        "The outline is off for large files." => {
+            "El esquema está desactivado en los archivos grandes."
+        }
+        "Could not write to shell: {error}" => "No se pudo escribir en la shell: {error}",
+        "{keys} is not bound" => "{keys} no está asignado",
+        "Count: {count}" => "Cuenta: {count}",
+        "File has unsaved changes, use quit! to discard them." => {
+            "El archivo tiene cambios sin guardar; usa quit! para descartarlos."
+        }
+        "Unsaved changes in {names}. Save all (s), discard them (d) or cancel (Esc)?" => {
+            "Cambios sin guardar en {names}. ¿Guardar todo (s), descartarlos (d) o cancelar (Esc)?"
+        }
+        "Quit cancelled." => "Salida cancelada.",
+        "The tag stack is empty." => "La pila de etiquetas está vacía.",
+        "Register '{name}' selected." => "Registro '{name}' seleccionado.",
+        "This is the only buffer." => "Este es el único búfer.",
+        "Mark '{name}' set." => "Marca '{name}' puesta.",
+        "Not implemented: {name}" => "No implementado: {name}",
+        "Not a line number: {args}" => "No es un número de línea: {args}",
+        "Marks are named by a letter." => "Las marcas se nombran con una letra.",
+        "Mark '{name}' is not set." => "La marca '{name}' no está puesta.",
+        "Usage: substitute /pattern/replacement/[gir]" => {
+            "Uso: substitute /patrón/reemplazo/[gir]"
+        }
+        "ERR: Missing replacement in {args}" => "ERR: Falta el reemplazo en {args}",
+        "ERR: Unknown flags: {flags}" => "ERR: Opciones desconocidas: {flags}",
+        "ERR: Empty pattern" => "ERR: Patrón vacío",
+        "Pattern not found: {pattern}" => "Patrón no encontrado: {pattern}",
+        "Replaced {replaced} matches on {lines} lines." => {
+            "Se reemplazaron {replaced} coincidencias en {lines} líneas."
+        }
+        "Nothing has been logged." => "No se ha registrado nada.",
+        "File has unsaved changes, add ! to discard them." => {
+            "El archivo tiene cambios sin guardar; añade ! para descartarlos."
+        }
+        "Usage: !<command>" => "Uso: !<comando>",
+        "ERR: Could not run {command}: {error}" => "ERR: No se pudo ejecutar {command}: {error}",
+        "ERR: {command} failed" => "ERR: {command} falló",
+        "Usage: read <file> | read !<command>" => "Uso: read <archivo> | read !<comando>",
+        "ERR: Could not read file: {args}" => "ERR: No se pudo leer el archivo: {args}",
+        "ERR: {command} failed, nothing was changed" => "ERR: {command} falló, no se cambió nada",
+        "Filtered {lines} lines into {count}." => "Se filtraron {lines} líneas en {count}.",
+        "Formatted." => "Formateado.",
+        "ERR: Could not start {command}: {error}" => "ERR: No se pudo iniciar {command}: {error}",
+        "ERR: Could not reach {command}: {error}" => "ERR: No se pudo contactar con {command}: {error}",
+        "No language server for {file_type} files." => {
+            "No hay servidor de lenguaje para los archivos {file_type}."
+        }
+        "Save the file to use the language server." => {
+            "Guarda el archivo para usar el servidor de lenguaje."
+        }
+        "{command} is still starting." => "{command} todavía se está iniciando.",
+        "{command} is not running." => "{command} no se está ejecutando.",
+        "ERR: {command} exited" => "ERR: {command} terminó",
+        "No completions." => "No hay sugerencias.",
+        "Nothing known about this." => "No se sabe nada de esto.",
+        "No definition found." => "No se encontró la definición.",
+        "The linter found no problems here." => "El linter no encontró problemas aquí.",
+        "No formatter for {file_type} files." => "No hay formateador para los archivos {file_type}.",
+        "Already at the newest change." => "Ya está en el cambio más reciente.",
+        "Already at the oldest change." => "Ya está en el cambio más antiguo.",
+        "Undo history: {states} states taking {size} KB here, {total} KB in all buffers." => {
+            "Historial de deshacer: {states} estados que ocupan {size} KB aquí, {total} KB en todos los búferes."
+        }
+        "Undo history: {states} states taking {size} KB here, {total} of {limit} KB in all buffers." => {
+            "Historial de deshacer: {states} estados que ocupan {size} KB aquí, {total} de {limit} KB en todos los búferes."
+        }
+        "Registers are named by a letter, {default} or {clipboard}." => {
+            "Los registros se nombran con una letra, {default} o {clipboard}."
+        }
+        "Reverted to the file on disk." => "Se volvió al archivo en disco.",
+        "No more sections." => "No hay más secciones.",
+        "tabs" => "tabulaciones",
+        "{width} spaces" => "{width} espacios",
+        "1 line, 1 character selected" => "1 línea, 1 carácter seleccionado",
+        "1 line, {characters} characters selected" => {
+            "1 línea, {characters} caracteres seleccionados"
+        }
+        "{lines} lines, {characters} characters selected" => {
+            "{lines} líneas, {characters} caracteres seleccionados"
+        }
+        "{code_points} | bytes {bytes} | width {width}" => {
+            "{code_points} | bytes {bytes} | ancho {width}"
+        }
+        "No character under the cursor." => "No hay ningún carácter bajo el cursor.",
+        "No previous search." => "No hay búsqueda anterior.",
+        "No match for '{query}'." => "No hay coincidencias para '{query}'.",
+        "Match {index} of {total}." => "Coincidencia {index} de {total}.",
+        "Match {index} of {total}, wrapped around." => {
+            "Coincidencia {index} de {total}, dando la vuelta."
+        }
+        "Yanked 1 line into register '{name}'." => "Se copió 1 línea al registro '{name}'.",
+        "Yanked {lines} lines into register '{name}'." => {
+            "Se copiaron {lines} líneas al registro '{name}'."
+        }
+        "Cut 1 line into register '{name}'." => "Se cortó 1 línea al registro '{name}'.",
+        "Cut {lines} lines into register '{name}'." => {
+            "Se cortaron {lines} líneas al registro '{name}'."
+        }
+        "Register '{name}' is empty." => "El registro '{name}' está vacío.",
+        "All registers are empty." => "Todos los registros están vacíos.",
+        "This buffer has no file." => "Este búfer no tiene archivo.",
+        "No change under the cursor." => "No hay ningún cambio bajo el cursor.",
+        "Change staged." => "Cambio añadido al índice.",
+        "Change reverted." => "Cambio revertido.",
+        "No identifier under the cursor." => "No hay ningún identificador bajo el cursor.",
+        "Tag not found: {name}" => "Etiqueta no encontrada: {name}",
+        "The definition of {name} moved, the tags file is out of date." => {
+            "La definición de {name} se movió; el archivo de etiquetas está desactualizado."
+        }
+        "Running {command}..." => "Ejecutando {command}...",
+        "{command} succeeded." => "{command} terminó bien.",
+        "The last build reported no errors." => "La última compilación no dio errores.",
+        "Searching for tasks..." => "Buscando tareas...",
+        "Found {count} tasks." => "Se encontraron {count} tareas.",
+        "Cannot jump to an unnamed buffer." => "No se puede saltar a un búfer sin nombre.",
+        "Usage: open <file>" => "Uso: open <archivo>",
+        "{file_name} is {size} MB. Open it with slow features off? (y/n)" => {
+            "{file_name} ocupa {size} MB. ¿Abrirlo con las funciones lentas desactivadas? (y/n)"
+        }
+        "{file_name} is large: highlighting, outline, blame and language server are off." => {
+            "{file_name} es grande: el resaltado, el esquema, blame y el servidor de lenguaje están desactivados."
+        }
+        "Did not open {file_name}." => "No se abrió {file_name}.",
+        "Unknown option: {option}" => "Opción desconocida: {option}",
+        "Unknown theme: {theme}" => "Tema desconocido: {theme}",
+        "Unknown background: {background}" => "Fondo desconocido: {background}",
+        "Usage: background [auto|light|dark]" => "Uso: background [auto|light|dark]",
+        "Background: {background}, theme {theme}" => "Fondo: {background}, tema {theme}",
+        "Unknown color: {color}" => "Color desconocido: {color}",
+        "No files match {pattern}" => "Ningún archivo coincide con {pattern}",
+        "Opened {count} files." => "Se abrieron {count} archivos.",
+        "… {count} more" => "… {count} más",
+        "No differences" => "Sin diferencias",
+        "Difference {index}/{count}" => "Diferencia {index}/{count}",
+        "n = next difference | p = previous | q = edit {file_name}" => "n = siguiente diferencia | p = anterior | q = editar {file_name}",
+        "The files are the same." => "Los archivos son iguales.",
+        "No more differences." => "No hay más diferencias.",
+        "No merge conflicts." => "No hay conflictos de fusión.",
+        "No merge conflict at the cursor." => "No hay un conflicto de fusión en el cursor.",
+        "Conflict resolved, {count} left." => "Conflicto resuelto, quedan {count}.",
+        "Unknown language: {language}" => "Idioma desconocido: {language}",
+        "Sent {pages} pages to {command}." => "Se enviaron {pages} páginas a {command}.",
+        "Wrote {pages} pages to {file_name}." => "Se escribieron {pages} páginas en {file_name}.",
+        "{file_name} contains ANSI escape codes. Strip them? (y/n)" => {
+            "{file_name} contiene códigos de escape ANSI. ¿Quitarlos? (y/n)"
+        }
+        "Kept the escape codes in {file_name}." => {
+            "Se conservaron los códigos de escape en {file_name}."
+        }
+        "There are no escape codes to strip." => "No hay códigos de escape que quitar.",
+        "Stripped escape codes from {lines} lines; undo brings them back." => {
+            "Se quitaron los códigos de escape de {lines} líneas; deshacer los recupera."
+        }
+        "No earlier versions of this file were kept." => {
+            "No se guardaron versiones anteriores de este archivo."
+        }
+        "{age}, {lines} lines" => "{age}, {lines} líneas",
+        "{age}, {lines} lines, as in the buffer" => "{age}, {lines} líneas, igual que el buffer",
+        "Version: " => "Versión: ",
+        "Restored the version from {age}." => "Se restauró la versión de {age}.",
+        "The version from {age} is the same as the buffer." => {
+            "La versión de {age} es igual al buffer."
+        }
+        "Nothing was yanked or deleted yet." => "Aún no se copió ni borró nada.",
+        "Paste: " => "Pegar: ",
+        "Breakpoint set on line {line}." => "Punto de interrupción en la línea {line}.",
+        "Breakpoint removed from line {line}." => {
+            "Se quitó el punto de interrupción de la línea {line}."
+        }
+        "Usage: export_state <file>" => "Uso: export_state <archivo>",
+        "Usage: import_state <file>" => "Uso: import_state <archivo>",
+        "Exported the state of {buffers} buffers to {file_name}." => {
+            "Se exportó el estado de {buffers} buffers a {file_name}."
+        }
+        "ERR: Could not read the state in {file_name}: {error}" => {
+            "ERR: No se pudo leer el estado en {file_name}: {error}"
+        }
+        "Restored {buffers} buffers from {file_name}." => {
+            "Se restauraron {buffers} buffers desde {file_name}."
+        }
+        "Restored {buffers} buffers from {file_name}, skipping {skipped} without a file." => {
+            "Se restauraron {buffers} buffers desde {file_name}, omitiendo {skipped} sin archivo."
+        }
+        "ERR: Could not write {file_name}: {error}" => {
+            "ERR: No se pudo escribir {file_name}: {error}"
+        }
+        " Outline" => " Esquema",
+        "No symbols found" => "No se encontraron símbolos",
+        " {shell} - {toggle} to switch to the document" => {
+            " {shell} - {toggle} para pasar al documento"
+        }
+        " {shell} - {toggle} to switch to the shell" => " {shell} - {toggle} para pasar a la shell",
+        " Staged changes" => " Cambios en el índice",
+        _ => return None,
+    };
+    Some(translation)
+}
@@ -0,0 +1,123 @@
+use crate::Position;
+use std::path::Path;
+
+/// A place in a file that a build pointed at, such as a compiler error, or a
+/// task left in a comment.
+pub struct Location {
+    pub file_name: String,
+    pub position: Position,
+    pub message: String,
+}
+
+/// The locations found in the output of the last build, visited in turn.
+#[derive(Default)]
+pub struct LocationList {
+    locations: Vec<Location>,
+    /// Index of the location visited last, if any was.
+    current: Option<usize>,
+}
+
+impl LocationList {
+    #[must_use]
+    pub fn new(locations: Vec<Location>) -> Self {
+        Self {
+            locations,
+            current: None,
+        }
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.locations.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.locations.is_empty()
+    }
+
+    /// Steps to the next location, returning it with its index. Stays on the
+    /// last one once reached.
+    pub fn next(&mut self) -> Option<(usize, &Location)> {
+        let index = match self.current {
+            Some(current) => current.saturating_add(1),
+            None => 0,
+        };
+        self.visit(index)
+    }
+
+    /// Steps to the previous location, returning it with its index. Stays on
+    /// the first one once reached.
+    pub fn previous(&mut self) -> Option<(usize, &Location)> {
+        let index = self.current.map_or(0, |current| current.saturating_sub(1));
+        self.visit(index)
+    }
+
+    fn visit(&mut self, index: usize) -> Option<(usize, &Location)> {
+        let index = index.min(self.locations.len().checked_sub(1)?);
+        self.current = Some(index);
+        Some((index, self.locations.get(index)?))
+    }
+}
+
+/// Finds the locations in a build's output. Both the `file:line:column:
+/// message` lines printed by most compilers and linters and the `--> file:line:column`
+/// lines rustc prints below its message are understood. References to files
+/// that do not exist are skipped, as they are more likely to be times or
+/// addresses than paths.
+#[must_use]
+pub fn parse(output: &str) -> Vec<Location> {
+    let mut locations = Vec::new();
+    let mut message = "";
+    for line in output.lines() {
+        let trimmed = line.trim_start();
+        if let Some(reference) = trimmed.strip_prefix("--> ") {
+            if let Some((file_name, position, _)) = split_reference(reference) {
+                locations.push(Location {
+                    file_name: file_name.to_string(),
+                    position,
+                    message: message.to_string(),
+                });
+            }
+        } else if let Some((file_name, position, text)) = split_reference(trimmed) {
+            locations.push(Location {
+                file_name: file_name.to_string(),
+                position,
+                message: text.to_string(),
+            });
+        } else if !trimmed.is_empty() && trimmed.len() == line.len() {
+            message = trimmed;
+        }
+    }
+    locations.retain(|location| Path::new(&location.file_name).is_file());
+    locations
+}
+
+/// Splits `file:line[:column][: message]` into its parts.
+fn split_reference(text: &str) -> Option<(&str, Position, &str)> {
+    let (file_name, rest) = text.split_once(':')?;
+    if file_name.is_empty() || file_name.contains(char::is_whitespace) {
+        return None;
+    }
+    let (line, rest) = leading_number(rest)?;
+    let (column, rest) = match rest.strip_prefix(':').and_then(leading_number) {
+        Some((column, rest)) => (column, rest),
+        None => (1, rest),
+    };
+    if !rest.is_empty() && !rest.starts_with(':') {
+        return None;
+    }
+    let position = Position {
+        x: column.saturating_sub(1),
+        y: line.saturating_sub(1),
+    };
+    Some((file_name, position, rest.trim_start_matches(':').trim()))
+}
+
+fn leading_number(text: &str) -> Option<(usize, &str)> {
+    let digits = text
+        .len()
+        .saturating_sub(text.trim_start_matches(|c: char| c.is_ascii_digit()).len());
+    let (number, rest) = text.split_at(digits);
+    Some((number.parse().ok()?, rest))
+}
@@ -0,0 +1,111 @@
+use crate::paths;
+use log::{Level, Log, Metadata, Record};
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Writes the `log` records of the editor to `hecto.log` in the user's state
+/// directory, so problems can be looked into after the fact.
+pub struct Logger {
+    file: Mutex<File>,
+    level: Level,
+}
+
+impl Logger {
+    /// Sends log records to the log file from now on: warnings and general
+    /// information, and with `verbose` also debugging details such as the
+    /// traffic with language servers. Failing to open the file leaves
+    /// logging off, which is not worth stopping the editor for.
+    pub fn install(verbose: bool) {
+        let level = if verbose { Level::Debug } else { Level::Info };
+        let Some(path) = Self::path() else {
+            return;
+        };
+        if let Some(directory) = path.parent() {
+            let _ = fs::create_dir_all(directory);
+        }
+        let Ok(file) = OpenOptions::new().create(true).append(true).open(path) else {
+            return;
+        };
+        let logger = Self {
+            file: Mutex::new(file),
+            level,
+        };
+        if log::set_boxed_logger(Box::new(logger)).is_ok() {
+            log::set_max_level(level.to_level_filter());
+        }
+    }
+
+    /// Returns `hecto.log` in the state directory.
+    #[must_use]
+    pub fn path() -> Option<PathBuf> {
+        Some(paths::state_dir()?.join("hecto.log"))
+    }
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        if let Ok(mut file) = self.file.lock() {
+            let _ = writeln!(
+                file,
+                "{} {:<5} {}: {}",
+                timestamp(),
+                record.level(),
+                record.target(),
+                record.args()
+            );
+        }
+    }
+
+    fn flush(&self) {
+        if let Ok(mut file) = self.file.lock() {
+            let _ = file.flush();
+        }
+    }
+}
+
+/// Returns the current time in UTC, as `YYYY-MM-DD HH:MM:SS.mmm`.
+fn timestamp() -> String {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    let days = now.as_secs() / (24 * 60 * 60);
+    let seconds = now.as_secs() % (24 * 60 * 60);
+    let (year, month, day) = civil_date(days);
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}:{:02}.{:03}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60,
+        now.subsec_millis()
+    )
+}
+
+/// Turns days since 1970-01-01 into a year, month and day, using Howard
+/// Hinnant's `civil_from_days`.
+fn civil_date(days: u64) -> (u64, u64, u64) {
+    let days = days.saturating_add(719_468);
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+    (year, month, day)
+}
@@ -0,0 +1,371 @@
+use crate::changes::Span;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Error, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// What a request to a server asked for, so its response can be handled.
+#[derive(Clone, Copy)]
+pub enum Request {
+    Initialize,
+    Completion,
+    Hover,
+    Definition,
+}
+
+pub enum Message {
+    /// The server answered a request, or said why it could not.
+    Response {
+        request: Request,
+        result: Result<Value, String>,
+    },
+    Notification {
+        method: String,
+        params: Value,
+    },
+    /// The server exited.
+    Exited,
+}
+
+/// A language server speaking JSON-RPC on its standard input and output.
+pub struct LanguageServer {
+    child: Child,
+    stdin: ChildStdin,
+    messages: Receiver<Value>,
+    next_id: u64,
+    pending: HashMap<u64, Request>,
+    /// Whether the server answered the `initialize` request. Until then it
+    /// must not be told about documents.
+    initialized: bool,
+    /// The revision of each document last sent, keyed by URI.
+    documents: HashMap<String, u64>,
+    /// Whether the server takes the rows that changed instead of the whole
+    /// text of a document.
+    incremental: bool,
+}
+
+/// The rows of a document that changed since revision `since`: those in
+/// `span`, which now read `text`.
+pub struct RowsChanged {
+    pub since: u64,
+    pub span: Span,
+    pub text: String,
+}
+
+impl LanguageServer {
+    /// Starts `command` with `sh -c` and asks it to initialize for the project
+    /// in `root`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the server could not be started.
+    pub fn start(command: &str, root: &Path) -> Result<Self, Error> {
+        log::info!("Starting {command} in {}", root.display());
+        let mut child = Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let (Some(stdin), Some(stdout)) = (child.stdin.take(), child.stdout.take()) else {
+            return Err(Error::new(ErrorKind::BrokenPipe, "no pipe to the server"));
+        };
+        let mut server = Self {
+            child,
+            stdin,
+            messages: spawn_reader(stdout),
+            next_id: 0,
+            pending: HashMap::new(),
+            initialized: false,
+            documents: HashMap::new(),
+            incremental: false,
+        };
+        server.request(
+            Request::Initialize,
+            "initialize",
+            json!({
+                "processId": std::process::id(),
+                "rootUri": uri(root),
+                "capabilities": {
+                    "textDocument": {
+                        "hover": { "contentFormat": ["plaintext"] },
+                        "completion": { "completionItem": { "snippetSupport": false } },
+                        "publishDiagnostics": {},
+                    },
+                },
+            }),
+        )?;
+        Ok(server)
+    }
+
+    /// Returns whether the server is ready to be asked about documents.
+    #[must_use]
+    pub fn is_initialized(&self) -> bool {
+        self.initialized
+    }
+
+    /// # Errors
+    ///
+    /// Returns an error if the server is no longer reading its input.
+    pub fn request(&mut self, request: Request, method: &str, params: Value) -> Result<(), Error> {
+        self.next_id = self.next_id.wrapping_add(1);
+        let id = self.next_id;
+        self.pending.insert(id, request);
+        self.send(&json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }))
+    }
+
+    /// # Errors
+    ///
+    /// Returns an error if the server is no longer reading its input.
+    pub fn notify(&mut self, method: &str, params: Value) -> Result<(), Error> {
+        self.send(&json!({ "jsonrpc": "2.0", "method": method, "params": params }))
+    }
+
+    /// Sends the text of a document unless the server already has this
+    /// revision of it. Only the rows that `changed` are sent if the server
+    /// takes them and has the revision they changed from. `text` is only
+    /// called when it is needed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the server is no longer reading its input.
+    pub fn sync<F>(
+        &mut self,
+        path: &Path,
+        language_id: &str,
+        revision: u64,
+        changed: Option<RowsChanged>,
+        text: F,
+    ) -> Result<(), Error>
+    where
+        F: FnOnce() -> String,
+    {
+        if !self.initialized {
+            return Ok(());
+        }
+        let uri = uri(path);
+        match self.documents.get(&uri) {
+            Some(sent) if *sent == revision => return Ok(()),
+            Some(sent) => {
+                let change = match changed {
+                    // Whole rows are replaced, so columns never need to be
+                    // counted in UTF-16.
+                    Some(changed) if self.incremental && changed.since == *sent => json!({
+                        "range": {
+                            "start": { "line": changed.span.start, "character": 0 },
+                            "end": { "line": changed.span.old_end, "character": 0 },
+                        },
+                        "text": changed.text,
+                    }),
+                    // Every server takes the whole text.
+                    _ => json!({ "text": text() }),
+                };
+                self.notify(
+                    "textDocument/didChange",
+                    json!({
+                        "textDocument": { "uri": uri, "version": revision },
+                        "contentChanges": [change],
+                    }),
+                )?;
+            }
+            None => self.notify(
+                "textDocument/didOpen",
+                json!({
+                    "textDocument": {
+                        "uri": uri,
+                        "languageId": language_id,
+                        "version": revision,
+                        "text": text(),
+                    },
+                }),
+            )?,
+        }
+        self.documents.insert(uri, revision);
+        Ok(())
+    }
+
+    /// Returns the messages that arrived since the last call.
+    pub fn poll(&mut self) -> Vec<Message> {
+        let mut messages = Vec::new();
+        loop {
+            match self.messages.try_recv() {
+                Ok(value) => messages.extend(self.receive(value)),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    messages.push(Message::Exited);
+                    break;
+                }
+            }
+        }
+        messages
+    }
+
+    fn receive(&mut self, value: Value) -> Option<Message> {
+        let id = value.get("id").cloned();
+        if let Some(method) = value.get("method").and_then(Value::as_str) {
+            if let Some(id) = id {
+                // Requests from the server, such as progress reports, are
+                // acknowledged without being acted on.
+                let _ = self.send(&json!({ "jsonrpc": "2.0", "id": id, "result": null }));
+                return None;
+            }
+            return Some(Message::Notification {
+                method: method.to_string(),
+                params: value.get("params").cloned().unwrap_or(Value::Null),
+            });
+        }
+        let request = self.pending.remove(&id?.as_u64()?)?;
+        let result = match value.get("error") {
+            Some(error) => Err(error
+                .get("message")
+                .and_then(Value::as_str)
+                .unwrap_or("unknown error")
+                .to_string()),
+            None => Ok(value.get("result").cloned().unwrap_or(Value::Null)),
+        };
+        if let (Request::Initialize, Ok(result)) = (request, &result) {
+            // The kind of sync is a number, or the `change` of an object.
+            let sync = result.pointer("/capabilities/textDocumentSync");
+            let kind = sync
+                .and_then(|sync| sync.get("change"))
+                .or(sync)
+                .and_then(Value::as_u64);
+            self.incremental = kind == Some(2);
+            self.initialized = true;
+            let _ = self.notify("initialized", json!({}));
+        }
+        Some(Message::Response { request, result })
+    }
+
+    fn send(&mut self, message: &Value) -> Result<(), Error> {
+        let body = message.to_string();
+        log::debug!("-> {body}");
+        write!(self.stdin, "Content-Length: {}\r\n\r\n{body}", body.len())?;
+        self.stdin.flush()
+    }
+}
+
+impl Drop for LanguageServer {
+    fn drop(&mut self) {
+        let _ = self.notify("exit", Value::Null);
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Reads the server's messages on their own thread, so the editor can check
+/// for them without blocking.
+fn spawn_reader(stdout: ChildStdout) -> Receiver<Value> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let mut reader = BufReader::new(stdout);
+        while let Some(message) = read_message(&mut reader) {
+            log::debug!("<- {message}");
+            if sender.send(message).is_err() {
+                break;
+            }
+        }
+    });
+    receiver
+}
+
+/// Reads one message framed by a `Content-Length` header, returning `None`
+/// once the server closed its output.
+fn read_message(reader: &mut impl BufRead) -> Option<Value> {
+    loop {
+        let mut length = None;
+        loop {
+            let mut header = String::new();
+            if reader.read_line(&mut header).ok()? == 0 {
+                return None;
+            }
+            let header = header.trim_end();
+            if header.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = header.split_once(':') {
+                if name.eq_ignore_ascii_case("content-length") {
+                    length = value.trim().parse::<usize>().ok();
+                }
+            }
+        }
+        let Some(length) = length else {
+            continue;
+        };
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body).ok()?;
+        if let Ok(message) = serde_json::from_slice(&body) {
+            return Some(message);
+        }
+    }
+}
+
+/// Returns the `file://` URI of `path`.
+#[must_use]
+pub fn uri(path: &Path) -> String {
+    let mut uri = "file://".to_string();
+    for byte in path.to_string_lossy().bytes() {
+        if byte.is_ascii_alphanumeric() || b"/-._~".contains(&byte) {
+            uri.push(char::from(byte));
+        } else {
+            uri.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    uri
+}
+
+/// Returns the path of a `file://` URI.
+#[must_use]
+pub fn path(uri: &str) -> Option<PathBuf> {
+    let encoded = uri.strip_prefix("file://")?.as_bytes();
+    let mut bytes = Vec::with_capacity(encoded.len());
+    let mut index = 0;
+    while let Some(byte) = encoded.get(index) {
+        let escaped = encoded
+            .get(index.saturating_add(1)..index.saturating_add(3))
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (byte, escaped) {
+            (b'%', Some(decoded)) => {
+                bytes.push(decoded);
+                index = index.saturating_add(3);
+            }
+            _ => {
+                bytes.push(*byte);
+                index = index.saturating_add(1);
+            }
+        }
+    }
+    Some(PathBuf::from(String::from_utf8(bytes).ok()?))
+}
+
+/// Converts a column counted in graphemes, as the editor does, to one
+/// counted in UTF-16 code units, as servers do.
+#[must_use]
+pub fn utf16_column(line: &str, x: usize) -> usize {
+    line.graphemes(true)
+        .take(x)
+        .map(|grapheme| grapheme.encode_utf16().count())
+        .sum()
+}
+
+/// Converts a column counted in UTF-16 code units to one counted in
+/// graphemes.
+#[must_use]
+pub fn grapheme_column(line: &str, utf16: usize) -> usize {
+    let mut units: usize = 0;
+    let mut x: usize = 0;
+    for grapheme in line.graphemes(true) {
+        if units >= utf16 {
+            break;
+        }
+        units = units.saturating_add(grapheme.encode_utf16().count());
+        x = x.saturating_add(1);
+    }
+    x
+}
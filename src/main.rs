@@ -1,19 +1,79 @@
-mod document;
-mod editor;
-mod filetype;
-mod highlighting;
-mod row;
-mod terminal;
+use hecto::{run_batch, CountingAllocator, Editor, Logger, Options, Server, Terminal, USAGE};
+use std::env;
+use std::fs;
+use std::io;
+use std::panic::{self, AssertUnwindSafe};
+use std::process;
 
-pub use document::Document;
-use editor::Editor;
-pub use editor::Position;
-pub use editor::SearchDirection;
-pub use filetype::FileType;
-pub use filetype::HighlightingOptions;
-pub use row::Row;
-pub use terminal::Terminal;
+// Counts allocations for the `perf` overlay.
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
 
 fn main() {
-    Editor::default().run();
+    let options = match Options::parse(env::args().skip(1)) {
+        Ok(options) => options,
+        Err(error) => {
+            eprint!("hecto: {error}\n\n{USAGE}");
+            process::exit(2);
+        }
+    };
+    if options.help {
+        print!("{USAGE}");
+        return;
+    }
+    if options.version {
+        println!("hecto {}", env!("CARGO_PKG_VERSION"));
+        return;
+    }
+    Logger::install(options.verbose);
+    if let Some(script) = &options.batch {
+        let result = fs::read_to_string(script)
+            .map_err(|error| format!("{}: {error}", script.display()))
+            .and_then(|script| run_batch(&script, &options.files));
+        if let Err(error) = result {
+            eprintln!("hecto: {error}");
+            process::exit(1);
+        }
+        return;
+    }
+    if options.serve {
+        let mut server = Server::default();
+        let result = match &options.socket {
+            Some(path) => server.listen(path),
+            None => server.serve(io::stdin().lock(), io::stdout().lock()),
+        };
+        if let Err(error) = result {
+            eprintln!("hecto: {error}");
+            process::exit(1);
+        }
+        return;
+    }
+    Terminal::install_panic_hook();
+    let terminal = match Terminal::new() {
+        Ok(terminal) => terminal,
+        Err(error) => {
+            eprintln!("hecto: {error}");
+            process::exit(1);
+        }
+    };
+    let mut editor = Editor::with_options(Box::new(terminal), options);
+    let Ok(result) = panic::catch_unwind(AssertUnwindSafe(|| editor.run())) else {
+        // The panic hook already reported the panic itself.
+        let recovered = editor.recover();
+        drop(editor);
+        match recovered {
+            Ok(directory) => eprintln!(
+                "Unsaved work and a crash report were saved to {}",
+                directory.display()
+            ),
+            Err(error) => eprintln!("hecto: could not save unsaved work: {error}"),
+        }
+        process::exit(101);
+    };
+    if let Err(error) = result {
+        log::error!("{error}");
+        Terminal::restore();
+        eprintln!("hecto: {error}");
+        process::exit(1);
+    }
 }
@@ -0,0 +1,135 @@
+use crate::paths;
+use crate::Position;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+type Store = BTreeMap<String, BTreeMap<String, (usize, usize)>>;
+
+/// Named positions in a document, kept in place as rows are inserted and
+/// removed around them.
+#[derive(Default)]
+pub struct Marks {
+    marks: BTreeMap<char, Position>,
+}
+
+impl Marks {
+    pub fn set(&mut self, name: char, position: Position) {
+        self.marks.insert(name, position);
+    }
+
+    #[must_use]
+    pub fn get(&self, name: char) -> Option<&Position> {
+        self.marks.get(&name)
+    }
+
+    /// Adjusts for `count` rows having been inserted after row `at.y`, with
+    /// the text that followed `at` moved to column `new_x` of the last of
+    /// them.
+    pub fn rows_inserted(&mut self, at: &Position, count: usize, new_x: usize) {
+        for position in self.marks.values_mut() {
+            if position.y > at.y {
+                position.y = position.y.saturating_add(count);
+            } else if position.y == at.y && position.x >= at.x {
+                position.y = position.y.saturating_add(count);
+                position.x = position.x.saturating_sub(at.x).saturating_add(new_x);
+            }
+        }
+    }
+
+    /// Adjusts for `count` rows having been inserted before row `y`.
+    pub fn rows_added(&mut self, y: usize, count: usize) {
+        for position in self.marks.values_mut() {
+            if position.y >= y {
+                position.y = position.y.saturating_add(count);
+            }
+        }
+    }
+
+    /// Adjusts for row `y` having been removed. Marks on it are dropped.
+    pub fn row_removed(&mut self, y: usize) {
+        self.marks.retain(|_, position| position.y != y);
+        for position in self.marks.values_mut() {
+            if position.y > y {
+                position.y = position.y.saturating_sub(1);
+            }
+        }
+    }
+
+    /// Adjusts for row `y + 1` having been appended to row `y`, which was
+    /// `len` graphemes long.
+    pub fn rows_joined(&mut self, y: usize, len: usize) {
+        for position in self.marks.values_mut() {
+            if position.y == y.saturating_add(1) {
+                position.y = y;
+                position.x = position.x.saturating_add(len);
+            } else if position.y > y {
+                position.y = position.y.saturating_sub(1);
+            }
+        }
+    }
+
+    pub fn rows_swapped(&mut self, a: usize, b: usize) {
+        for position in self.marks.values_mut() {
+            if position.y == a {
+                position.y = b;
+            } else if position.y == b {
+                position.y = a;
+            }
+        }
+    }
+
+    /// Reads the marks saved for `file_name`.
+    #[must_use]
+    pub fn load(file_name: &str) -> Self {
+        let marks = read_store()
+            .remove(&key(file_name))
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|(name, (x, y))| Some((name.chars().next()?, Position { x, y })))
+            .collect();
+        Self { marks }
+    }
+
+    /// Saves the marks for `file_name`, replacing the ones saved before.
+    /// Failing to do so is not worth bothering the user with, so errors are
+    /// ignored.
+    pub fn store(&self, file_name: &str) {
+        let Some(path) = store_path() else {
+            return;
+        };
+        let mut store = read_store();
+        let marks = self
+            .marks
+            .iter()
+            .map(|(name, position)| (name.to_string(), (position.x, position.y)))
+            .collect();
+        store.insert(key(file_name), marks);
+        store.retain(|_, marks| !marks.is_empty());
+        if let (Some(directory), Ok(contents)) = (path.parent(), toml::to_string(&store)) {
+            let _ = fs::create_dir_all(directory);
+            let _ = fs::write(path, contents);
+        }
+    }
+}
+
+/// Returns `marks.toml` in the state directory.
+fn store_path() -> Option<PathBuf> {
+    Some(paths::state_dir()?.join("marks.toml"))
+}
+
+fn read_store() -> Store {
+    store_path()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|contents| toml::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+/// Files are remembered by their absolute path, so marks survive opening the
+/// same file from another directory.
+fn key(file_name: &str) -> String {
+    fs::canonicalize(file_name)
+        .unwrap_or_else(|_| Path::new(file_name).to_path_buf())
+        .to_string_lossy()
+        .into_owned()
+}
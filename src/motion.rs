@@ -0,0 +1,247 @@
+use crate::{Document, Position};
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Returns the blank line after the paragraph the cursor is in, or after
+/// the next one if the cursor is on a blank line. Returns the end of the
+/// document if there is none.
+#[must_use]
+pub fn next_paragraph(document: &Document, from: &Position) -> Position {
+    let len = document.len();
+    let start = if is_blank(document, from.y) {
+        (from.y..len)
+            .find(|&y| !is_blank(document, y))
+            .unwrap_or(len)
+    } else {
+        from.y
+    };
+    match (start..len).find(|&y| is_blank(document, y)) {
+        Some(y) => Position { x: 0, y },
+        None => end(document),
+    }
+}
+
+/// Returns the blank line before the paragraph the cursor is in, or before
+/// the one above if the cursor is on a blank line. Returns the start of the
+/// document if there is none.
+#[must_use]
+pub fn previous_paragraph(document: &Document, from: &Position) -> Position {
+    let start = if is_blank(document, from.y) {
+        (0..from.y).rev().find(|&y| !is_blank(document, y))
+    } else {
+        Some(from.y)
+    };
+    let blank = start.and_then(|start| (0..start).rev().find(|&y| is_blank(document, y)));
+    Position {
+        x: 0,
+        y: blank.unwrap_or(0),
+    }
+}
+
+/// Returns where the next sentence starts: after a `.`, `!` or `?` and any
+/// closing quotes or brackets, once a space or the end of the line follows,
+/// or at the first of some blank lines. Returns the end of the document if
+/// there is none.
+#[must_use]
+pub fn next_sentence(document: &Document, from: &Position) -> Position {
+    // Whether the text read so far ends a sentence, and whether a space has
+    // come after it.
+    let mut ended = false;
+    let mut spaced = false;
+    for y in from.y..document.len() {
+        if is_blank(document, y) {
+            if y > from.y && !is_blank(document, y.saturating_sub(1)) {
+                return Position { x: 0, y };
+            }
+            ended = true;
+            spaced = true;
+            continue;
+        }
+        let Some(row) = document.row(y) else {
+            break;
+        };
+        // The line is read from its start, so a sentence ending just before
+        // the cursor is seen.
+        for (x, grapheme) in row.as_str().graphemes(true).enumerate() {
+            let c = grapheme.chars().next().unwrap_or(' ');
+            if c.is_whitespace() {
+                spaced = ended;
+                continue;
+            }
+            if spaced && (y > from.y || x > from.x) {
+                return Position { x, y };
+            }
+            if matches!(c, '.' | '!' | '?') {
+                ended = true;
+            } else if !ended || spaced || !matches!(c, ')' | ']' | '"' | '\'') {
+                ended = false;
+            }
+            spaced = false;
+        }
+        // The end of a line counts as a space.
+        spaced = ended;
+    }
+    end(document)
+}
+
+/// Returns where the sentence the cursor is in starts, or the one before if
+/// the cursor is at its start. Blank lines count as sentences of their own.
+#[must_use]
+pub fn previous_sentence(document: &Document, from: &Position) -> Position {
+    let start = (0..from.y)
+        .rev()
+        .find(|&y| is_blank(document, y))
+        .map_or_else(Position::default, |y| Position { x: 0, y });
+    let mut previous = start.clone();
+    let mut at = start;
+    loop {
+        let next = next_sentence(document, &at);
+        if !is_before(&next, from) || next == at {
+            return previous;
+        }
+        previous = next.clone();
+        at = next;
+    }
+}
+
+fn is_before(position: &Position, other: &Position) -> bool {
+    (position.y, position.x) < (other.y, other.x)
+}
+
+fn is_blank(document: &Document, y: usize) -> bool {
+    document
+        .row(y)
+        .is_some_and(|row| row.as_str().trim().is_empty())
+}
+
+/// Returns the end of the last line.
+fn end(document: &Document) -> Position {
+    let y = document.len().saturating_sub(1);
+    Position {
+        x: document.row(y).map_or(0, |row| row.len()),
+        y,
+    }
+}
+
+/// Returns where the next word starts, at the start of a line or after a
+/// space or a change between word characters and punctuation. With
+/// `subword`, the parts of `camelCase` and `snake_case` names count as words
+/// of their own. Empty lines count as words too.
+#[must_use]
+pub fn next_word(document: &Document, from: &Position, subword: bool) -> Position {
+    for y in from.y..document.len() {
+        let chars = row_chars(document, y);
+        if y > from.y && chars.is_empty() {
+            return Position { x: 0, y };
+        }
+        let first = if y == from.y {
+            from.x.saturating_add(1)
+        } else {
+            0
+        };
+        if let Some(x) = (first..chars.len()).find(|&x| starts_word(&chars, x, subword)) {
+            return Position { x, y };
+        }
+    }
+    end(document)
+}
+
+/// Returns where the word the cursor is in starts, or the word before if the
+/// cursor is at its start, as [`next_word`] tells words apart.
+#[must_use]
+pub fn previous_word(document: &Document, from: &Position, subword: bool) -> Position {
+    for y in (0..=from.y.min(document.len())).rev() {
+        let chars = row_chars(document, y);
+        if y < from.y && chars.is_empty() {
+            return Position { x: 0, y };
+        }
+        let last = if y == from.y { from.x } else { chars.len() };
+        if let Some(x) = (0..last.min(chars.len()))
+            .rev()
+            .find(|&x| starts_word(&chars, x, subword))
+        {
+            return Position { x, y };
+        }
+    }
+    Position::default()
+}
+
+/// Returns the last character of the word the cursor is in, or of the next
+/// word if the cursor is at its end, as [`next_word`] tells words apart.
+#[must_use]
+pub fn word_end(document: &Document, from: &Position, subword: bool) -> Position {
+    for y in from.y..document.len() {
+        let chars = row_chars(document, y);
+        let first = if y == from.y {
+            from.x.saturating_add(1)
+        } else {
+            0
+        };
+        if let Some(x) = (first..chars.len()).find(|&x| ends_word(&chars, x, subword)) {
+            return Position { x, y };
+        }
+    }
+    end(document)
+}
+
+#[derive(PartialEq)]
+enum Class {
+    Space,
+    Word,
+    Punctuation,
+}
+
+fn class(c: char, subword: bool) -> Class {
+    if c.is_whitespace() || (subword && c == '_') {
+        Class::Space
+    } else if c.is_alphanumeric() || c == '_' {
+        Class::Word
+    } else {
+        Class::Punctuation
+    }
+}
+
+/// Returns whether a word starts at `chars[x]`.
+fn starts_word(chars: &[char], x: usize, subword: bool) -> bool {
+    let Some(&c) = chars.get(x) else {
+        return false;
+    };
+    let current = class(c, subword);
+    if current == Class::Space {
+        return false;
+    }
+    let Some(&before) = x.checked_sub(1).and_then(|before| chars.get(before)) else {
+        return true;
+    };
+    current != class(before, subword)
+        || (subword && is_hump(before, c, chars.get(x.saturating_add(1)).copied()))
+}
+
+/// Returns whether a word ends at `chars[x]`.
+fn ends_word(chars: &[char], x: usize, subword: bool) -> bool {
+    let Some(&c) = chars.get(x) else {
+        return false;
+    };
+    let next = x.saturating_add(1);
+    class(c, subword) != Class::Space
+        && chars.get(next).is_none_or(|&after| {
+            class(after, subword) == Class::Space || starts_word(chars, next, subword)
+        })
+}
+
+/// Returns whether `c`, coming after `before` and followed by `after`, starts
+/// a part of a `camelCase` name, as in `camel|Case` or `HTTP|Server`.
+fn is_hump(before: char, c: char, after: Option<char>) -> bool {
+    c.is_uppercase()
+        && (before.is_lowercase()
+            || (before.is_uppercase() && after.is_some_and(char::is_lowercase)))
+}
+
+/// Returns the first character of each grapheme of row `y`.
+fn row_chars(document: &Document, y: usize) -> Vec<char> {
+    document.row(y).map_or_else(Vec::new, |row| {
+        row.as_str()
+            .graphemes(true)
+            .map(|grapheme| grapheme.chars().next().unwrap_or(' '))
+            .collect()
+    })
+}
@@ -0,0 +1,211 @@
+use crate::Document;
+
+/// Words that start a definition worth listing, in the languages people
+/// commonly edit.
+const KEYWORDS: &[&str] = &[
+    "fn",
+    "struct",
+    "enum",
+    "union",
+    "trait",
+    "impl",
+    "mod",
+    "type",
+    "macro_rules!",
+    "def",
+    "class",
+    "interface",
+    "function",
+    "func",
+];
+
+/// Words that may come before a keyword without changing what is defined.
+const MODIFIERS: &[&str] = &[
+    "pub",
+    "async",
+    "unsafe",
+    "const",
+    "extern",
+    "export",
+    "default",
+    "static",
+    "abstract",
+    "public",
+    "private",
+    "protected",
+];
+
+/// A definition or heading found in a document.
+pub struct Symbol {
+    pub name: String,
+    /// The keyword that introduced it, such as `fn`, or `#` for a heading.
+    pub kind: String,
+    pub line: usize,
+    /// How many symbols it is nested in.
+    pub depth: usize,
+}
+
+/// The symbols of a document, found again whenever it changes.
+#[derive(Default)]
+pub struct Outline {
+    symbols: Vec<Symbol>,
+    /// The document revision the symbols were found in.
+    revision: Option<u64>,
+    /// The entry chosen while the outline has focus.
+    pub selected: usize,
+}
+
+impl Outline {
+    /// Finds the symbols of `document` again unless they are up to date.
+    pub fn update(&mut self, document: &Document) {
+        if self.revision == Some(document.revision()) {
+            return;
+        }
+        let lines = (0..document.len()).filter_map(|y| document.row(y).map(|row| row.as_str()));
+        let headings = document.file_name.as_deref().is_some_and(|file_name| {
+            file_name.ends_with(".md") || file_name.ends_with(".markdown")
+        });
+        self.symbols = if headings {
+            parse_headings(lines)
+        } else {
+            parse_definitions(lines)
+        };
+        self.revision = Some(document.revision());
+        self.selected = self.selected.min(self.symbols.len().saturating_sub(1));
+    }
+
+    #[must_use]
+    pub fn symbols(&self) -> &[Symbol] {
+        &self.symbols
+    }
+
+    /// Returns the lines sections start at, to skim a document by: those of
+    /// its top-level definitions, or of its headings at any level.
+    pub fn sections(&self) -> impl DoubleEndedIterator<Item = usize> + '_ {
+        self.symbols
+            .iter()
+            .filter(|symbol| symbol.depth == 0 || symbol.kind.starts_with('#'))
+            .map(|symbol| symbol.line)
+    }
+
+    /// Returns the index of the symbol `line` is in, that is the last one
+    /// starting at or above it.
+    #[must_use]
+    pub fn active(&self, line: usize) -> Option<usize> {
+        self.symbols.iter().rposition(|symbol| symbol.line <= line)
+    }
+}
+
+/// Finds definitions by the keyword they start with, nesting them by their
+/// indentation.
+fn parse_definitions<'a>(lines: impl Iterator<Item = &'a str>) -> Vec<Symbol> {
+    let mut symbols = Vec::new();
+    let mut indents: Vec<usize> = Vec::new();
+    for (line, text) in lines.enumerate() {
+        let trimmed = text.trim_start();
+        if ["//", "/*", "*", "#"]
+            .iter()
+            .any(|comment| trimmed.starts_with(comment))
+        {
+            continue;
+        }
+        let Some((kind, name)) = definition(trimmed) else {
+            continue;
+        };
+        let indent = text.len().saturating_sub(trimmed.len());
+        while indents.last().is_some_and(|outer| *outer >= indent) {
+            indents.pop();
+        }
+        symbols.push(Symbol {
+            name,
+            kind: kind.to_string(),
+            line,
+            depth: indents.len(),
+        });
+        indents.push(indent);
+    }
+    symbols
+}
+
+/// Returns the keyword and name of the definition `text` starts, if any.
+fn definition(text: &str) -> Option<(&'static str, String)> {
+    let mut rest = text;
+    loop {
+        let (word, after) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+        let after = after.trim_start();
+        let modifier = MODIFIERS.contains(&word)
+            || word.starts_with("pub(")
+            || (word.starts_with('"') && word.ends_with('"') && word.len() > 1);
+        if modifier {
+            rest = after;
+            continue;
+        }
+        let keyword = KEYWORDS.iter().find(|keyword| {
+            word == **keyword
+                || word
+                    .strip_prefix(**keyword)
+                    .is_some_and(|generics| generics.starts_with('<'))
+        })?;
+        let name = if *keyword == "impl" {
+            let end = after.find(['{', ';']).unwrap_or(after.len());
+            let head = after.get(..end).unwrap_or(after);
+            head.split(" where")
+                .next()
+                .unwrap_or(head)
+                .trim()
+                .to_string()
+        } else {
+            // Go methods name their receiver first: `func (s *Server) Run()`.
+            let after = match after.strip_prefix('(') {
+                Some(receiver) => receiver
+                    .split_once(')')
+                    .map_or("", |(_, name)| name.trim_start()),
+                None => after,
+            };
+            after
+                .chars()
+                .take_while(|c| c.is_alphanumeric() || *c == '_' || *c == '$')
+                .collect()
+        };
+        if name.is_empty() {
+            return None;
+        }
+        return Some((keyword, name));
+    }
+}
+
+/// Finds Markdown headings, nesting them by their level.
+fn parse_headings<'a>(lines: impl Iterator<Item = &'a str>) -> Vec<Symbol> {
+    let mut symbols = Vec::new();
+    let mut levels: Vec<usize> = Vec::new();
+    let mut in_code = false;
+    for (line, text) in lines.enumerate() {
+        if text.trim_start().starts_with("```") {
+            in_code = !in_code;
+            continue;
+        }
+        if in_code {
+            continue;
+        }
+        let level = text
+            .len()
+            .saturating_sub(text.trim_start_matches('#').len());
+        let Some(title) = text.get(level..).and_then(|rest| rest.strip_prefix(' ')) else {
+            continue;
+        };
+        if level == 0 || level > 6 {
+            continue;
+        }
+        while levels.last().is_some_and(|outer| *outer >= level) {
+            levels.pop();
+        }
+        symbols.push(Symbol {
+            name: title.trim().trim_end_matches('#').trim_end().to_string(),
+            kind: "#".repeat(level),
+            line,
+            depth: levels.len(),
+        });
+        levels.push(level);
+    }
+    symbols
+}
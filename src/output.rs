@@ -0,0 +1,41 @@
+use std::cell::{Ref, RefCell};
+use std::io::{self, Write};
+use std::mem;
+
+/// Text written to a backend, kept until it is flushed and then handed to
+/// `W` in one write, so a frame reaches the terminal whole rather than a
+/// line or an escape sequence at a time.
+pub struct Output<W: Write> {
+    pending: RefCell<Vec<u8>>,
+    writer: RefCell<W>,
+}
+
+impl<W: Write> Output<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            pending: RefCell::new(Vec::new()),
+            writer: RefCell::new(writer),
+        }
+    }
+
+    pub fn write(&self, text: &str) {
+        self.pending.borrow_mut().extend_from_slice(text.as_bytes());
+    }
+
+    /// Sends what was written since the last flush.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the writer fails to take it.
+    pub fn flush(&self) -> io::Result<()> {
+        let pending = mem::take(&mut *self.pending.borrow_mut());
+        let mut writer = self.writer.borrow_mut();
+        writer.write_all(&pending)?;
+        writer.flush()
+    }
+
+    /// Returns the writer, to look at what it was sent.
+    pub fn writer(&self) -> Ref<'_, W> {
+        self.writer.borrow()
+    }
+}
@@ -0,0 +1,71 @@
+use std::fs::{self, File, OpenOptions};
+use std::io;
+use std::os::unix::fs::FileExt;
+use std::process;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+static FILES: AtomicU64 = AtomicU64::new(0);
+
+/// Where a piece of text was written in a [`PageFile`].
+#[derive(Clone, Copy)]
+pub struct Page {
+    offset: u64,
+    len: usize,
+}
+
+/// A temporary file that the text of rows is paged out to when a document
+/// takes more memory than it may. Nothing is ever removed from it; it goes
+/// away when the last row paged out to it does.
+pub struct PageFile {
+    file: File,
+    len: AtomicU64,
+}
+
+impl PageFile {
+    /// Creates the file in the temporary directory. It is removed from there
+    /// at once, so it is never left behind.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the file cannot be created.
+    pub fn create() -> io::Result<Self> {
+        let path = std::env::temp_dir().join(format!(
+            "hecto-{}-{}.pages",
+            process::id(),
+            FILES.fetch_add(1, Ordering::Relaxed)
+        ));
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&path)?;
+        fs::remove_file(&path)?;
+        Ok(Self {
+            file,
+            len: AtomicU64::new(0),
+        })
+    }
+
+    /// # Errors
+    ///
+    /// Will return `Err` if the text cannot be written.
+    pub fn write(&self, text: &str) -> io::Result<Page> {
+        let offset = self.len.fetch_add(text.len() as u64, Ordering::Relaxed);
+        self.file.write_all_at(text.as_bytes(), offset)?;
+        Ok(Page {
+            offset,
+            len: text.len(),
+        })
+    }
+
+    /// Reads back the text written to `page`.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the text cannot be read.
+    pub fn read(&self, page: &Page) -> io::Result<String> {
+        let mut bytes = vec![0; page.len];
+        self.file.read_exact_at(&mut bytes, page.offset)?;
+        String::from_utf8(bytes).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+    }
+}
@@ -0,0 +1,172 @@
+use crate::pty::{self, Pty};
+use std::io::Error;
+use termion::event::Key;
+
+const MAX_LINES: usize = 1000;
+
+/// A shell shown below the document. Its output is kept as plain lines: the
+/// shell runs with `TERM=dumb`, and the few escape sequences that still come
+/// through are dropped rather than interpreted.
+pub struct Pane {
+    pty: Pty,
+    lines: Vec<String>,
+    /// Column of the cursor in the last line, in characters.
+    column: usize,
+    escape: Escape,
+}
+
+#[derive(PartialEq)]
+enum Escape {
+    None,
+    Started,
+    /// Inside a control sequence, `ESC [ ...`, until its final byte.
+    Csi,
+    /// Inside an operating system command, `ESC ] ...`, until BEL or `ESC \`.
+    Osc,
+    /// An `ESC` inside an operating system command, which starts `ESC \`.
+    OscEnding,
+}
+
+impl Pane {
+    /// Starts the user's shell in a pane of the given size.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the shell could not be started.
+    pub fn open(width: u16, height: u16) -> Result<Self, Error> {
+        Ok(Self {
+            pty: Pty::spawn(&pty::shell(), width, height)?,
+            lines: vec![String::new()],
+            column: 0,
+            escape: Escape::None,
+        })
+    }
+
+    /// Takes in what the shell printed, returning whether anything did, or
+    /// `None` once the shell has exited.
+    pub fn update(&mut self) -> Option<bool> {
+        let bytes = self.pty.read()?;
+        let changed = !bytes.is_empty();
+        for c in String::from_utf8_lossy(&bytes).chars() {
+            self.put(c);
+        }
+        Some(changed)
+    }
+
+    /// Sends a key to the shell.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the shell is no longer reading its input.
+    pub fn send(&mut self, key: Key) -> Result<(), Error> {
+        let bytes: Vec<u8> = match key {
+            Key::Char(c) => c.to_string().into_bytes(),
+            Key::Ctrl(c) => vec![(c as u8) & 0x1f],
+            Key::Alt(c) => format!("\x1b{c}").into_bytes(),
+            Key::Backspace => vec![0x7f],
+            Key::Esc => vec![0x1b],
+            Key::Null => vec![0],
+            Key::Up => b"\x1b[A".to_vec(),
+            Key::Down => b"\x1b[B".to_vec(),
+            Key::Right => b"\x1b[C".to_vec(),
+            Key::Left => b"\x1b[D".to_vec(),
+            Key::Home => b"\x1b[H".to_vec(),
+            Key::End => b"\x1b[F".to_vec(),
+            Key::Delete => b"\x1b[3~".to_vec(),
+            _ => return Ok(()),
+        };
+        self.pty.write(&bytes)
+    }
+
+    /// Returns the last `height` lines, the last one holding the cursor.
+    #[must_use]
+    pub fn lines(&self, height: usize) -> &[String] {
+        let first = self.lines.len().saturating_sub(height);
+        self.lines.get(first..).unwrap_or_default()
+    }
+
+    #[must_use]
+    pub fn cursor_column(&self) -> usize {
+        self.column
+    }
+
+    fn put(&mut self, c: char) {
+        match self.escape {
+            Escape::Started => {
+                self.escape = match c {
+                    '[' => Escape::Csi,
+                    ']' => Escape::Osc,
+                    _ => Escape::None,
+                };
+                return;
+            }
+            Escape::Csi => {
+                if ('\x40'..='\x7e').contains(&c) {
+                    self.escape = Escape::None;
+                    if c == 'K' {
+                        self.truncate_line();
+                    }
+                }
+                return;
+            }
+            Escape::Osc => {
+                match c {
+                    '\x07' => self.escape = Escape::None,
+                    '\x1b' => self.escape = Escape::OscEnding,
+                    _ => (),
+                }
+                return;
+            }
+            Escape::OscEnding => {
+                self.escape = Escape::None;
+                return;
+            }
+            Escape::None => (),
+        }
+        match c {
+            '\x1b' => self.escape = Escape::Started,
+            '\n' => {
+                self.lines.push(String::new());
+                self.column = 0;
+                if self.lines.len() > MAX_LINES {
+                    self.lines.remove(0);
+                }
+            }
+            '\r' => self.column = 0,
+            '\x08' => self.column = self.column.saturating_sub(1),
+            '\t' => {
+                for _ in 0..8_usize.saturating_sub(self.column % 8) {
+                    self.overwrite(' ');
+                }
+            }
+            c if c.is_control() => (),
+            c => self.overwrite(c),
+        }
+    }
+
+    /// Writes `c` at the cursor in the last line, replacing what was there.
+    fn overwrite(&mut self, c: char) {
+        let column = self.column;
+        if let Some(line) = self.lines.last_mut() {
+            let mut chars: Vec<char> = line.chars().collect();
+            while chars.len() < column {
+                chars.push(' ');
+            }
+            if let Some(existing) = chars.get_mut(column) {
+                *existing = c;
+            } else {
+                chars.push(c);
+            }
+            *line = chars.into_iter().collect();
+        }
+        self.column = column.saturating_add(1);
+    }
+
+    /// Erases the last line from the cursor on.
+    fn truncate_line(&mut self) {
+        let column = self.column;
+        if let Some(line) = self.lines.last_mut() {
+            *line = line.chars().take(column).collect();
+        }
+    }
+}
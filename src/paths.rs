@@ -0,0 +1,106 @@
+use std::env;
+use std::path::PathBuf;
+
+/// Returns the directory of the user's settings and plugins:
+/// `$XDG_CONFIG_HOME/hecto`, falling back to `~/.config/hecto`, or to
+/// `~/Library/Application Support/hecto` on macOS and `%APPDATA%\hecto` on
+/// Windows.
+#[must_use]
+pub fn config_dir() -> Option<PathBuf> {
+    let base = match xdg("XDG_CONFIG_HOME") {
+        Some(dir) => dir,
+        None if cfg!(target_os = "macos") => application_support()?,
+        None if cfg!(windows) => PathBuf::from(env::var_os("APPDATA")?),
+        None => home()?.join(".config"),
+    };
+    Some(base.join("hecto"))
+}
+
+/// Returns the directory of what is kept about files across sessions, such
+/// as their undo history: `$XDG_DATA_HOME/hecto`, falling back to
+/// `~/.local/share/hecto`, or to `~/Library/Application Support/hecto` on
+/// macOS and `%LOCALAPPDATA%\hecto` on Windows.
+#[must_use]
+pub fn data_dir() -> Option<PathBuf> {
+    let base = match xdg("XDG_DATA_HOME") {
+        Some(dir) => dir,
+        None if cfg!(target_os = "macos") => application_support()?,
+        None if cfg!(windows) => PathBuf::from(env::var_os("LOCALAPPDATA")?),
+        None => home()?.join(".local/share"),
+    };
+    Some(base.join("hecto"))
+}
+
+/// Returns the directory of what is kept about the editor itself, such as
+/// histories, marks and the log: `$XDG_STATE_HOME/hecto`, falling back to
+/// `~/.local/state/hecto`, or to a `state` directory inside [`data_dir`] on
+/// macOS and Windows.
+#[must_use]
+pub fn state_dir() -> Option<PathBuf> {
+    match xdg("XDG_STATE_HOME") {
+        Some(dir) => Some(dir.join("hecto")),
+        None if cfg!(any(target_os = "macos", windows)) => Some(data_dir()?.join("state")),
+        None => Some(home()?.join(".local/state").join("hecto")),
+    }
+}
+
+/// Returns `text` with a leading `~` standing for the home directory, and
+/// `$NAME` or `${NAME}` for the environment variable of that name. Those
+/// that are not set are left as they are.
+#[must_use]
+pub fn expand(text: &str) -> String {
+    let mut expanded = String::with_capacity(text.len());
+    let mut rest = text;
+    if let Some(after) = text.strip_prefix('~') {
+        if let Some(home) = home().filter(|_| after.is_empty() || after.starts_with('/')) {
+            expanded.push_str(&home.to_string_lossy());
+            rest = after;
+        }
+    }
+    while let Some(start) = rest.find('$') {
+        expanded.push_str(&rest[..start]);
+        let after = &rest[start.saturating_add(1)..];
+        let (name, length) = match after.strip_prefix('{') {
+            Some(braced) => match braced.find('}') {
+                Some(end) => (&braced[..end], end.saturating_add(2)),
+                None => ("", 0),
+            },
+            None => {
+                let end = after
+                    .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                    .unwrap_or(after.len());
+                (&after[..end], end)
+            }
+        };
+        match env::var(name).ok().filter(|_| !name.is_empty()) {
+            Some(value) => {
+                expanded.push_str(&value);
+                rest = &after[length..];
+            }
+            None => {
+                expanded.push('$');
+                rest = after;
+            }
+        }
+    }
+    expanded.push_str(rest);
+    expanded
+}
+
+/// Returns the directory in `variable`, which only counts if it is set to an
+/// absolute path, as the XDG base directory specification has it.
+fn xdg(variable: &str) -> Option<PathBuf> {
+    let dir = PathBuf::from(env::var_os(variable)?);
+    dir.is_absolute().then_some(dir)
+}
+
+fn application_support() -> Option<PathBuf> {
+    Some(home()?.join("Library").join("Application Support"))
+}
+
+fn home() -> Option<PathBuf> {
+    env::var_os("HOME")
+        .or_else(|| env::var_os("USERPROFILE"))
+        .filter(|home| !home.is_empty())
+        .map(PathBuf::from)
+}
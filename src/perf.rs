@@ -0,0 +1,96 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+/// Nanoseconds spent highlighting since the last frame was measured.
+static HIGHLIGHTING: AtomicU64 = AtomicU64::new(0);
+
+/// The system allocator, counting allocations for the performance overlay.
+/// Allocations are only counted in programs that install it with
+/// `#[global_allocator]`, as hecto does.
+pub struct CountingAllocator;
+
+// SAFETY: every call is passed on to the system allocator unchanged.
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc_zeroed(layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+    }
+}
+
+/// Where the time of one frame went: handling the input that came in, the
+/// part of that spent highlighting rows, drawing the screen, and sending it
+/// to the terminal.
+#[derive(Clone, Copy, Default)]
+pub struct Frame {
+    pub input: Duration,
+    pub highlighting: Duration,
+    pub render: Duration,
+    pub output: Duration,
+    pub allocations: u64,
+}
+
+impl Frame {
+    #[must_use]
+    pub fn describe(&self) -> String {
+        format!(
+            "input {:.2}ms  highlight {:.2}ms  render {:.2}ms  output {:.2}ms  {} allocs",
+            milliseconds(self.input),
+            milliseconds(self.highlighting),
+            milliseconds(self.render),
+            milliseconds(self.output),
+            self.allocations,
+        )
+    }
+}
+
+fn milliseconds(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+/// Turns measuring on or off. Highlighting is only timed while it is on, so
+/// it costs nothing otherwise.
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Returns when highlighting started, if it is being timed.
+#[must_use]
+pub fn start_highlighting() -> Option<Instant> {
+    ENABLED.load(Ordering::Relaxed).then(Instant::now)
+}
+
+pub fn end_highlighting(started: Option<Instant>) {
+    if let Some(started) = started {
+        let nanoseconds = u64::try_from(started.elapsed().as_nanos()).unwrap_or(u64::MAX);
+        HIGHLIGHTING.fetch_add(nanoseconds, Ordering::Relaxed);
+    }
+}
+
+/// Returns the time spent highlighting since this was last called.
+#[must_use]
+pub fn take_highlighting() -> Duration {
+    Duration::from_nanos(HIGHLIGHTING.swap(0, Ordering::Relaxed))
+}
+
+/// Returns how many allocations were made so far.
+#[must_use]
+pub fn allocations() -> u64 {
+    ALLOCATIONS.load(Ordering::Relaxed)
+}
@@ -0,0 +1,157 @@
+use crate::lineedit::LineEditor;
+use termion::event::Key;
+
+const MAX_LINES: usize = 10;
+/// Lines of the selected item's preview shown above the list.
+const PREVIEW_LINES: usize = 5;
+
+pub enum Picked {
+    /// The index of the chosen item in the list the picker was built from.
+    Item(usize),
+    Cancelled,
+}
+
+/// A list of items narrowed down by fuzzy matching what the user types.
+pub struct Picker {
+    items: Vec<String>,
+    /// Longer text shown for each item while it is selected, if any.
+    previews: Vec<String>,
+    /// Indices of the items matching the query, best match first.
+    matches: Vec<usize>,
+    selected: usize,
+    query: LineEditor,
+}
+
+impl Picker {
+    #[must_use]
+    pub fn new(items: Vec<String>) -> Self {
+        let matches = (0..items.len()).collect();
+        Self {
+            items,
+            previews: Vec::new(),
+            matches,
+            selected: 0,
+            query: LineEditor::default(),
+        }
+    }
+
+    /// Shows `previews`, one for each item, above the list while their item
+    /// is selected.
+    #[must_use]
+    pub fn with_previews(mut self, previews: Vec<String>) -> Self {
+        self.previews = previews;
+        self
+    }
+
+    /// Moves the selection to the item at `index`, while nothing is typed.
+    pub fn select(&mut self, index: usize) {
+        if index < self.matches.len() {
+            self.selected = index;
+        }
+    }
+
+    #[must_use]
+    pub fn query(&self) -> &LineEditor {
+        &self.query
+    }
+
+    /// Handles a key, returning what was picked once the user is done.
+    pub fn handle(&mut self, key: Key) -> Option<Picked> {
+        match key {
+            Key::Char('\n') => {
+                return Some(
+                    self.matches
+                        .get(self.selected)
+                        .map_or(Picked::Cancelled, |index| Picked::Item(*index)),
+                );
+            }
+            Key::Esc => return Some(Picked::Cancelled),
+            Key::Up | Key::Ctrl('p') => self.selected = self.selected.saturating_sub(1),
+            Key::Down | Key::Ctrl('n') => {
+                if self.selected.saturating_add(1) < self.matches.len() {
+                    self.selected = self.selected.saturating_add(1);
+                }
+            }
+            key => {
+                if self.query.edit(key) {
+                    self.filter();
+                }
+            }
+        }
+        None
+    }
+
+    /// Returns the lines to show, with the selected item marked.
+    #[must_use]
+    pub fn lines(&self, height: usize) -> Vec<String> {
+        let preview: Vec<String> = self
+            .matches
+            .get(self.selected)
+            .and_then(|index| self.previews.get(*index))
+            .map(|preview| {
+                preview
+                    .lines()
+                    .take(PREVIEW_LINES.min(height.saturating_sub(1)))
+                    .map(|line| format!("  │ {line}"))
+                    .collect()
+            })
+            .unwrap_or_default();
+        let height = height.saturating_sub(preview.len()).min(MAX_LINES);
+        let first = self.selected.saturating_add(1).saturating_sub(height);
+        let mut lines: Vec<String> = self
+            .matches
+            .iter()
+            .enumerate()
+            .skip(first)
+            .take(height)
+            .filter_map(|(position, index)| {
+                let marker = if position == self.selected { ">" } else { " " };
+                Some(format!("{marker} {}", self.items.get(*index)?))
+            })
+            .collect();
+        if lines.is_empty() {
+            lines.push("  (no matches)".to_string());
+        }
+        preview.into_iter().chain(lines).collect()
+    }
+
+    fn filter(&mut self) {
+        let query = self.query.text();
+        let mut scored: Vec<(usize, usize)> = self
+            .items
+            .iter()
+            .enumerate()
+            .filter_map(|(index, item)| Some((fuzzy_score(query, item)?, index)))
+            .collect();
+        // The sort is stable, so equally good matches keep their order.
+        scored.sort_by_key(|(score, _)| *score);
+        self.matches = scored.into_iter().map(|(_, index)| index).collect();
+        self.selected = 0;
+    }
+}
+
+/// Scores how well `candidate` contains the characters of `query` in order,
+/// ignoring case. Lower is better: matches that start early and are tightly
+/// packed win. Returns `None` if `candidate` does not match.
+#[must_use]
+pub fn fuzzy_score(query: &str, candidate: &str) -> Option<usize> {
+    let mut query = query.chars().flat_map(char::to_lowercase).peekable();
+    let mut start = None;
+    let mut end = 0;
+    for (index, c) in candidate.chars().flat_map(char::to_lowercase).enumerate() {
+        let Some(wanted) = query.peek() else {
+            break;
+        };
+        if c == *wanted {
+            query.next();
+            start.get_or_insert(index);
+            end = index;
+        }
+    }
+    if query.peek().is_some() {
+        return None;
+    }
+    let start = start.unwrap_or(0);
+    let span = end.saturating_sub(start);
+    Some(span.saturating_mul(2).saturating_add(start))
+}
@@ -0,0 +1,459 @@
+use crate::wasm::WasmPlugin;
+use crate::{Document, Position, ReplaceOptions};
+use mlua::{Function, Lua, MultiValue, RegistryKey, Table};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::rc::Rc;
+
+/// Events plugins can register handlers for with `hecto.on`.
+pub const EVENTS: &[&str] = &[
+    "open",
+    "before_save",
+    "save",
+    "cursor",
+    "mode",
+    "key",
+    "breakpoints",
+];
+
+/// Something a plugin asked the editor to do, carried out once the plugin
+/// has returned.
+pub enum Action {
+    /// Type the text at the cursor.
+    Insert(String),
+    /// Replace the text of the line, counting from zero.
+    ReplaceLine(usize, String),
+    /// Replace the matches of a pattern throughout the document.
+    ReplaceAll(String, String, ReplaceOptions),
+    /// Show the text in the message bar.
+    Message(String),
+    /// Run the command line.
+    Execute(String),
+}
+
+/// What plugins can see of the editor while they run.
+pub struct Context<'a> {
+    pub document: &'a Document,
+    pub cursor: &'a Position,
+}
+
+/// What plugins registered through the `hecto` table.
+#[derive(Default)]
+struct Registry {
+    commands: BTreeMap<String, (String, RegistryKey)>,
+    bindings: Vec<(String, String)>,
+    segments: Vec<RegistryKey>,
+    handlers: BTreeMap<String, Vec<RegistryKey>>,
+    actions: Vec<Action>,
+}
+
+/// Plugins from the `plugins` directory next to `config.toml`.
+///
+/// Lua scripts can add commands, key bindings, status bar segments and event
+/// handlers through the global `hecto` table:
+///
+/// - `hecto.command(name, description, function(args))`
+/// - `hecto.bind(keys, command_line)`, with keys as in the `keymap` setting
+/// - `hecto.status(function())`, returning text for the status bar
+/// - `hecto.on(event, function(argument))`, for one of the [`EVENTS`], with
+///   the argument described at [`EditorEvent::argument`](crate::events::EditorEvent::argument)
+///
+/// While one of those functions runs, it can read the document with
+/// `hecto.text()`, `hecto.line(n)`, `hecto.line_count()`, `hecto.cursor()`
+/// and `hecto.file_name()`, counting lines and columns from one, and act
+/// with `hecto.insert(text)`, `hecto.replace_line(n, text)`,
+/// `hecto.replace_all(pattern, replacement, options)`, `hecto.message(text)`
+/// and `hecto.execute(command_line)`. The options of `replace_all` are an
+/// optional table of the [`ReplaceOptions`] flags, such as
+/// `{ regex = true }`.
+///
+/// WebAssembly modules can add commands, as described at [`WasmPlugin`].
+pub struct Plugins {
+    lua: Lua,
+    registry: Rc<RefCell<Registry>>,
+    wasm: Vec<WasmPlugin>,
+    loaded: Vec<String>,
+}
+
+impl Default for Plugins {
+    fn default() -> Self {
+        let plugins = Self {
+            lua: Lua::new(),
+            registry: Rc::default(),
+            wasm: Vec::new(),
+            loaded: Vec::new(),
+        };
+        if let Err(error) = plugins.install_api() {
+            log::error!("Could not set up the plugin API: {error}");
+        }
+        plugins
+    }
+}
+
+impl Plugins {
+    /// Runs every `.lua` file and loads every `.wasm` module in `directory`,
+    /// in order of their names.
+    ///
+    /// # Errors
+    ///
+    /// Returns a message for each plugin that failed to load; the others are
+    /// still loaded.
+    pub fn load(&mut self, directory: &Path) -> Result<(), Vec<String>> {
+        let Ok(entries) = fs::read_dir(directory) else {
+            return Ok(());
+        };
+        let mut paths: Vec<_> = entries
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.extension()
+                    .is_some_and(|extension| extension == "lua" || extension == "wasm")
+            })
+            .collect();
+        paths.sort();
+        let mut errors = Vec::new();
+        for path in paths {
+            let name = path
+                .file_stem()
+                .map_or_else(String::new, |name| name.to_string_lossy().into_owned());
+            let loaded = if path
+                .extension()
+                .is_some_and(|extension| extension == "wasm")
+            {
+                WasmPlugin::load(&path).map(|plugin| self.wasm.push(plugin))
+            } else {
+                fs::read_to_string(&path)
+                    .map_err(|error| error.to_string())
+                    .and_then(|source| {
+                        self.lua
+                            .load(source)
+                            .set_name(name.as_str())
+                            .exec()
+                            .map_err(|error| error.to_string())
+                    })
+            };
+            match loaded {
+                Ok(()) => {
+                    log::info!("Loaded plugin {}", path.display());
+                    self.loaded.push(name);
+                }
+                Err(error) => {
+                    log::warn!("Could not load plugin {}: {error}", path.display());
+                    errors.push(format!("{name}: {error}"));
+                }
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Returns the names of the plugins loaded.
+    #[must_use]
+    pub fn loaded(&self) -> &[String] {
+        &self.loaded
+    }
+
+    #[must_use]
+    pub fn has_command(&self, name: &str) -> bool {
+        self.registry.borrow().commands.contains_key(name) || self.wasm_plugin(name).is_some()
+    }
+
+    /// Returns the commands plugins added, with their descriptions.
+    #[must_use]
+    pub fn commands(&self) -> Vec<(String, String)> {
+        let mut commands: Vec<_> = self
+            .registry
+            .borrow()
+            .commands
+            .iter()
+            .map(|(name, (description, _))| (name.clone(), description.clone()))
+            .collect();
+        for plugin in &self.wasm {
+            commands.extend_from_slice(plugin.commands());
+        }
+        commands.sort();
+        commands
+    }
+
+    /// Returns the keys plugins bound, with the command lines they run.
+    #[must_use]
+    pub fn bindings(&self) -> Vec<(String, String)> {
+        self.registry.borrow().bindings.clone()
+    }
+
+    /// Runs the plugin command `name` with `args`.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error the plugin raised.
+    pub fn run_command(
+        &mut self,
+        name: &str,
+        args: &str,
+        context: &Context,
+    ) -> Result<Vec<Action>, String> {
+        if !self.registry.borrow().commands.contains_key(name) {
+            return match self.wasm_plugin(name) {
+                Some(index) => self.wasm[index].run_command(name, args, context),
+                None => Ok(Vec::new()),
+            };
+        }
+        let function = {
+            let registry = self.registry.borrow();
+            let Some((_, key)) = registry.commands.get(name) else {
+                return Ok(Vec::new());
+            };
+            self.function(key)?
+        };
+        self.call(&[function], args, context)?;
+        Ok(self.take_actions())
+    }
+
+    /// Tells the handlers of `event` about it, passing them `argument`.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error a handler raised.
+    pub fn emit(
+        &self,
+        event: &str,
+        argument: &str,
+        context: &Context,
+    ) -> Result<Vec<Action>, String> {
+        let functions = {
+            let registry = self.registry.borrow();
+            let Some(keys) = registry.handlers.get(event) else {
+                return Ok(Vec::new());
+            };
+            keys.iter()
+                .map(|key| self.function(key))
+                .collect::<Result<Vec<_>, _>>()?
+        };
+        self.call(&functions, argument, context)?;
+        Ok(self.take_actions())
+    }
+
+    /// Returns the text of the status bar segments. Segments that fail are
+    /// left out.
+    #[must_use]
+    pub fn segments(&self, context: &Context) -> Vec<String> {
+        let functions: Vec<Function> = {
+            let registry = self.registry.borrow();
+            registry
+                .segments
+                .iter()
+                .filter_map(|key| self.function(key).ok())
+                .collect()
+        };
+        if functions.is_empty() {
+            return Vec::new();
+        }
+        let segments = self.lua.scope(|scope| {
+            self.expose(scope, context)?;
+            Ok(functions
+                .iter()
+                .filter_map(|function| function.call::<_, String>(()).ok())
+                .filter(|segment| !segment.is_empty())
+                .collect())
+        });
+        // Whatever a segment asked for while drawing is not carried out.
+        self.take_actions();
+        segments.unwrap_or_default()
+    }
+
+    /// Returns which WebAssembly plugin added the command `name`.
+    fn wasm_plugin(&self, name: &str) -> Option<usize> {
+        self.wasm
+            .iter()
+            .position(|plugin| plugin.commands().iter().any(|(command, _)| command == name))
+    }
+
+    fn function(&self, key: &RegistryKey) -> Result<Function<'_>, String> {
+        self.lua
+            .registry_value(key)
+            .map_err(|error| error.to_string())
+    }
+
+    fn take_actions(&self) -> Vec<Action> {
+        std::mem::take(&mut self.registry.borrow_mut().actions)
+    }
+
+    /// Calls each of `functions` with `argument`, with the document readable.
+    fn call(
+        &self,
+        functions: &[Function],
+        argument: &str,
+        context: &Context,
+    ) -> Result<(), String> {
+        self.lua
+            .scope(|scope| {
+                self.expose(scope, context)?;
+                for function in functions {
+                    function.call::<_, MultiValue>(argument)?;
+                }
+                Ok(())
+            })
+            .map_err(|error| {
+                log::warn!("Plugin error: {error}");
+                // The traceback that follows is left to the log.
+                let error = error.to_string();
+                error.lines().next().unwrap_or_default().to_string()
+            })
+    }
+
+    /// Adds the functions reading `context` to the `hecto` table, for as long
+    /// as `scope` lasts.
+    fn expose<'lua, 'scope>(
+        &'lua self,
+        scope: &mlua::Scope<'lua, 'scope>,
+        context: &'scope Context,
+    ) -> mlua::Result<()>
+    where
+        'lua: 'scope,
+    {
+        let hecto: Table = self.lua.globals().get("hecto")?;
+        let document = context.document;
+        let cursor = context.cursor;
+        hecto.set(
+            "text",
+            scope.create_function(move |_, ()| Ok(document.text()))?,
+        )?;
+        hecto.set(
+            "line",
+            scope.create_function(move |_, line: usize| {
+                Ok(line
+                    .checked_sub(1)
+                    .and_then(|y| document.row(y))
+                    .map(|row| row.as_str().to_string()))
+            })?,
+        )?;
+        hecto.set(
+            "line_count",
+            scope.create_function(move |_, ()| Ok(document.len()))?,
+        )?;
+        hecto.set(
+            "cursor",
+            scope.create_function(move |_, ()| {
+                Ok((cursor.y.saturating_add(1), cursor.x.saturating_add(1)))
+            })?,
+        )?;
+        hecto.set(
+            "file_name",
+            scope.create_function(move |_, ()| Ok(document.file_name.clone()))?,
+        )?;
+        Ok(())
+    }
+
+    /// Creates the `hecto` table with the functions plugins register with and
+    /// act through.
+    fn install_api(&self) -> mlua::Result<()> {
+        let lua = &self.lua;
+        let hecto = lua.create_table()?;
+        let registry = Rc::clone(&self.registry);
+        hecto.set(
+            "command",
+            lua.create_function(
+                move |lua, (name, description, function): (String, String, Function)| {
+                    let key = lua.create_registry_value(function)?;
+                    registry
+                        .borrow_mut()
+                        .commands
+                        .insert(name, (description, key));
+                    Ok(())
+                },
+            )?,
+        )?;
+        let registry = Rc::clone(&self.registry);
+        hecto.set(
+            "bind",
+            lua.create_function(move |_, (keys, command): (String, String)| {
+                registry.borrow_mut().bindings.push((keys, command));
+                Ok(())
+            })?,
+        )?;
+        let registry = Rc::clone(&self.registry);
+        hecto.set(
+            "status",
+            lua.create_function(move |lua, function: Function| {
+                let key = lua.create_registry_value(function)?;
+                registry.borrow_mut().segments.push(key);
+                Ok(())
+            })?,
+        )?;
+        let registry = Rc::clone(&self.registry);
+        hecto.set(
+            "on",
+            lua.create_function(move |lua, (event, function): (String, Function)| {
+                if !EVENTS.contains(&event.as_str()) {
+                    return Err(mlua::Error::runtime(format!("Unknown event: {event}")));
+                }
+                let key = lua.create_registry_value(function)?;
+                registry
+                    .borrow_mut()
+                    .handlers
+                    .entry(event)
+                    .or_default()
+                    .push(key);
+                Ok(())
+            })?,
+        )?;
+        for (name, action) in [
+            ("insert", Action::Insert as fn(String) -> Action),
+            ("message", Action::Message),
+            ("execute", Action::Execute),
+        ] {
+            let registry = Rc::clone(&self.registry);
+            hecto.set(
+                name,
+                lua.create_function(move |_, text: String| {
+                    registry.borrow_mut().actions.push(action(text));
+                    Ok(())
+                })?,
+            )?;
+        }
+        let registry = Rc::clone(&self.registry);
+        hecto.set(
+            "replace_line",
+            lua.create_function(move |_, (line, text): (usize, String)| {
+                if let Some(y) = line.checked_sub(1) {
+                    registry
+                        .borrow_mut()
+                        .actions
+                        .push(Action::ReplaceLine(y, text));
+                }
+                Ok(())
+            })?,
+        )?;
+        let registry = Rc::clone(&self.registry);
+        hecto.set(
+            "replace_all",
+            lua.create_function(
+                move |_, (pattern, replacement, options): (String, String, Option<Table>)| {
+                    let flag = |name: &str| -> mlua::Result<bool> {
+                        Ok(match &options {
+                            Some(options) => options.get::<_, Option<bool>>(name)?.unwrap_or(false),
+                            None => false,
+                        })
+                    };
+                    let options = ReplaceOptions {
+                        regex: flag("regex")?,
+                        ignore_case: flag("ignore_case")?,
+                        first_only: flag("first_only")?,
+                    };
+                    registry.borrow_mut().actions.push(Action::ReplaceAll(
+                        pattern,
+                        replacement,
+                        options,
+                    ));
+                    Ok(())
+                },
+            )?,
+        )?;
+        lua.globals().set("hecto", hecto)
+    }
+}
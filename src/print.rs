@@ -0,0 +1,304 @@
+use crate::highlighting::Type;
+use crate::Row;
+use std::fmt::Write;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// A4, in points.
+const PAGE_WIDTH: usize = 595;
+const PAGE_HEIGHT: usize = 842;
+const MARGIN: usize = 50;
+const FONT_SIZE: usize = 10;
+const LINE_HEIGHT: usize = 12;
+/// Courier is six points wide at ten points high.
+const COLUMNS: usize = (PAGE_WIDTH - 2 * MARGIN) / 6;
+/// Lines below the header on each page.
+const LINES: usize = (PAGE_HEIGHT - 2 * MARGIN) / LINE_HEIGHT - 2;
+
+/// What a document is printed as.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    PostScript,
+    Pdf,
+}
+
+impl Format {
+    /// Goes by the extension of `file_name`, taking anything but `.pdf` as
+    /// PostScript.
+    #[must_use]
+    pub fn of(file_name: &str) -> Self {
+        if file_name.to_ascii_lowercase().ends_with(".pdf") {
+            Self::Pdf
+        } else {
+            Self::PostScript
+        }
+    }
+}
+
+/// Colors for highlighted text that read well on paper.
+fn color(highlight: &Type) -> (u8, u8, u8) {
+    match highlight {
+        Type::None | Type::Match => (0, 0, 0),
+        Type::Number => (160, 40, 40),
+        Type::String => (30, 120, 40),
+        Type::Character => (70, 70, 170),
+    }
+}
+
+/// A run of text in one color.
+type Span = (String, (u8, u8, u8));
+
+/// Lays `rows` out in lines that fit the page, with tabs expanded to
+/// `tab_width` and long rows wrapped. Highlighted rows are colored if
+/// `highlighting` is set.
+fn lines<'a>(
+    rows: impl Iterator<Item = &'a Row>,
+    tab_width: usize,
+    highlighting: bool,
+) -> Vec<Vec<Span>> {
+    let mut lines = Vec::new();
+    for row in rows {
+        let colors = row.highlighting();
+        let mut line: Vec<Span> = Vec::new();
+        let mut column = 0;
+        for (index, grapheme) in row.as_str().graphemes(true).enumerate() {
+            let color = match colors.get(index) {
+                Some(highlight) if highlighting && row.is_highlighted() => color(highlight),
+                _ => (0, 0, 0),
+            };
+            let text = if grapheme == "\t" {
+                " ".repeat(tab_width.saturating_sub(column % tab_width.max(1)).max(1))
+            } else {
+                grapheme.to_string()
+            };
+            for c in text.chars() {
+                if column == COLUMNS {
+                    lines.push(std::mem::take(&mut line));
+                    column = 0;
+                }
+                match line.last_mut() {
+                    Some((text, last)) if *last == color => text.push(c),
+                    _ => line.push((c.to_string(), color)),
+                }
+                column = column.saturating_add(1);
+            }
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+/// Escapes `text` for a string in PostScript or PDF, in Latin-1, which
+/// both fonts are encoded in. Other characters are printed as `?`.
+fn escape(text: &str) -> String {
+    let mut escaped = String::new();
+    for c in text.chars() {
+        match c {
+            '(' | ')' | '\\' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            ' '..='~' => escaped.push(c),
+            '\u{a0}'..='\u{ff}' => {
+                let _ = write!(escaped, "\\{:03o}", u32::from(c));
+            }
+            _ => escaped.push('?'),
+        }
+    }
+    escaped
+}
+
+/// Returns the header of page `page` of `pages`: the title on the left and
+/// the page number on the right.
+fn header(title: &str, page: usize, pages: usize) -> (String, String) {
+    let number = format!("Page {page} of {pages}");
+    let room = COLUMNS.saturating_sub(number.len()).saturating_sub(2);
+    (title.chars().take(room).collect(), number)
+}
+
+/// The height of the header line and of line `index` below it, from the
+/// bottom of the page.
+fn baseline(index: Option<usize>) -> usize {
+    let top = PAGE_HEIGHT - MARGIN - FONT_SIZE;
+    index.map_or(top, |index| {
+        top.saturating_sub(LINE_HEIGHT * 2)
+            .saturating_sub(LINE_HEIGHT.saturating_mul(index))
+    })
+}
+
+/// Renders `rows` as pages headed by `title`, returning the file and how
+/// many pages it has.
+#[must_use]
+pub fn render<'a>(
+    format: Format,
+    title: &str,
+    rows: impl Iterator<Item = &'a Row>,
+    tab_width: usize,
+    highlighting: bool,
+) -> (Vec<u8>, usize) {
+    let lines = lines(rows, tab_width, highlighting);
+    let pages: Vec<&[Vec<Span>]> = if lines.is_empty() {
+        vec![&[]]
+    } else {
+        lines.chunks(LINES).collect()
+    };
+    let contents: Vec<String> = pages
+        .iter()
+        .enumerate()
+        .map(|(index, lines)| page(format, title, index.saturating_add(1), pages.len(), lines))
+        .collect();
+    let file = match format {
+        Format::PostScript => postscript(title, &contents),
+        Format::Pdf => pdf(&contents),
+    };
+    (file, pages.len())
+}
+
+/// Returns the drawing of one page, in PostScript or a PDF content stream.
+fn page(format: Format, title: &str, page: usize, pages: usize, lines: &[Vec<Span>]) -> String {
+    let (title, number) = header(title, page, pages);
+    let number_x = (PAGE_WIDTH - MARGIN).saturating_sub(number.len().saturating_mul(6));
+    let mut drawing = String::new();
+    match format {
+        Format::PostScript => {
+            let _ = writeln!(drawing, "0 setgray /Header {FONT_SIZE} selectfont");
+            let _ = writeln!(
+                drawing,
+                "{MARGIN} {} moveto ({}) show",
+                baseline(None),
+                escape(&title)
+            );
+            let _ = writeln!(
+                drawing,
+                "{number_x} {} moveto ({number}) show",
+                baseline(None)
+            );
+            let _ = writeln!(drawing, "/Text {FONT_SIZE} selectfont");
+            for (index, line) in lines.iter().enumerate() {
+                let _ = writeln!(drawing, "{MARGIN} {} moveto", baseline(Some(index)));
+                for (text, (r, g, b)) in line {
+                    let _ = writeln!(
+                        drawing,
+                        "{} setrgbcolor ({}) show",
+                        rgb(*r, *g, *b),
+                        escape(text)
+                    );
+                }
+            }
+        }
+        Format::Pdf => {
+            let _ = writeln!(drawing, "BT 0 g /F2 {FONT_SIZE} Tf");
+            let _ = writeln!(
+                drawing,
+                "1 0 0 1 {MARGIN} {} Tm ({}) Tj",
+                baseline(None),
+                escape(&title)
+            );
+            let _ = writeln!(
+                drawing,
+                "1 0 0 1 {number_x} {} Tm ({number}) Tj",
+                baseline(None)
+            );
+            let _ = writeln!(drawing, "/F1 {FONT_SIZE} Tf");
+            for (index, line) in lines.iter().enumerate() {
+                let _ = writeln!(drawing, "1 0 0 1 {MARGIN} {} Tm", baseline(Some(index)));
+                for (text, (r, g, b)) in line {
+                    let _ = writeln!(drawing, "{} rg ({}) Tj", rgb(*r, *g, *b), escape(text));
+                }
+            }
+            drawing.push_str("ET\n");
+        }
+    }
+    drawing
+}
+
+/// Writes a color as the fractions PostScript and PDF take.
+fn rgb(r: u8, g: u8, b: u8) -> String {
+    let fraction = |value: u8| {
+        let value = format!("{:.3}", f32::from(value) / 255.0);
+        value
+            .trim_end_matches('0')
+            .trim_end_matches('.')
+            .to_string()
+    };
+    format!("{} {} {}", fraction(r), fraction(g), fraction(b))
+}
+
+fn postscript(title: &str, pages: &[String]) -> Vec<u8> {
+    let mut file = String::new();
+    let _ = writeln!(file, "%!PS-Adobe-3.0");
+    let _ = writeln!(file, "%%Title: ({})", escape(title));
+    let _ = writeln!(file, "%%Creator: hecto");
+    let _ = writeln!(file, "%%BoundingBox: 0 0 {PAGE_WIDTH} {PAGE_HEIGHT}");
+    let _ = writeln!(file, "%%Pages: {}", pages.len());
+    file.push_str("%%EndComments\n%%BeginProlog\n");
+    // Courier in Latin-1 rather than its standard encoding.
+    for (name, font) in [("Text", "Courier"), ("Header", "Courier-Bold")] {
+        let _ = writeln!(
+            file,
+            "/{font} findfont dup length dict begin {{1 index /FID ne {{def}} {{pop pop}} ifelse}} forall \
+             /Encoding ISOLatin1Encoding def currentdict end /{name} exch definefont pop"
+        );
+    }
+    file.push_str("%%EndProlog\n");
+    for (index, page) in pages.iter().enumerate() {
+        let number = index.saturating_add(1);
+        let _ = writeln!(file, "%%Page: {number} {number}");
+        file.push_str(page);
+        file.push_str("showpage\n");
+    }
+    file.push_str("%%EOF\n");
+    file.into_bytes()
+}
+
+fn pdf(pages: &[String]) -> Vec<u8> {
+    // The catalog, the page tree and two fonts come first, then each page
+    // and its contents.
+    let first_page = 5;
+    let kids: Vec<String> = (0..pages.len())
+        .map(|index| format!("{} 0 R", first_page + index.saturating_mul(2)))
+        .collect();
+    let mut objects = vec![
+        "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+        format!(
+            "<< /Type /Pages /Kids [{}] /Count {} /MediaBox [0 0 {PAGE_WIDTH} {PAGE_HEIGHT}] >>",
+            kids.join(" "),
+            pages.len()
+        ),
+        "<< /Type /Font /Subtype /Type1 /BaseFont /Courier /Encoding /WinAnsiEncoding >>"
+            .to_string(),
+        "<< /Type /Font /Subtype /Type1 /BaseFont /Courier-Bold /Encoding /WinAnsiEncoding >>"
+            .to_string(),
+    ];
+    for (index, page) in pages.iter().enumerate() {
+        objects.push(format!(
+            "<< /Type /Page /Parent 2 0 R /Resources << /Font << /F1 3 0 R /F2 4 0 R >> >> \
+             /Contents {} 0 R >>",
+            index.saturating_mul(2).saturating_add(first_page + 1)
+        ));
+        objects.push(format!(
+            "<< /Length {} >>\nstream\n{page}endstream",
+            page.len()
+        ));
+    }
+    let mut file = String::from("%PDF-1.4\n");
+    let mut offsets = Vec::new();
+    for (index, object) in objects.iter().enumerate() {
+        offsets.push(file.len());
+        let _ = write!(
+            file,
+            "{} 0 obj\n{object}\nendobj\n",
+            index.saturating_add(1)
+        );
+    }
+    let xref = file.len();
+    let size = objects.len().saturating_add(1);
+    let _ = write!(file, "xref\n0 {size}\n0000000000 65535 f \n");
+    for offset in offsets {
+        let _ = writeln!(file, "{offset:010} 00000 n ");
+    }
+    let _ = write!(
+        file,
+        "trailer\n<< /Size {size} /Root 1 0 R >>\nstartxref\n{xref}\n%%EOF\n"
+    );
+    file.into_bytes()
+}
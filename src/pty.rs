@@ -0,0 +1,127 @@
+use std::fs::File;
+use std::io::{Error, Read, Write};
+use std::os::unix::io::FromRawFd;
+use std::os::unix::process::CommandExt;
+use std::process::{Child, Command};
+use std::ptr;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+
+/// A program running on its own pseudo-terminal.
+pub struct Pty {
+    master: File,
+    child: Child,
+    output: Receiver<Vec<u8>>,
+}
+
+impl Pty {
+    /// Starts `program` on a new pseudo-terminal of the given size.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no pseudo-terminal is available or the program
+    /// could not be started.
+    pub fn spawn(program: &str, width: u16, height: u16) -> Result<Self, Error> {
+        let mut master = 0;
+        let mut slave = 0;
+        let size = window_size(width, height);
+        // SAFETY: every pointer is either null, which openpty accepts, or
+        // points to a live local for the duration of the call.
+        let result =
+            unsafe { libc::openpty(&mut master, &mut slave, ptr::null_mut(), ptr::null(), &size) };
+        if result != 0 {
+            return Err(Error::last_os_error());
+        }
+        // SAFETY: openpty succeeded, so both descriptors are open and nothing
+        // else owns them.
+        let (master, slave) = unsafe { (File::from_raw_fd(master), File::from_raw_fd(slave)) };
+        let mut command = Command::new(program);
+        command
+            .stdin(slave.try_clone()?)
+            .stdout(slave.try_clone()?)
+            .stderr(slave)
+            .env("TERM", "dumb");
+        // SAFETY: the closure only makes async-signal-safe system calls, as
+        // required between fork and exec.
+        unsafe {
+            command.pre_exec(|| {
+                // Become the session leader so the pseudo-terminal can be the
+                // controlling terminal, which job control and ^C rely on.
+                if libc::setsid() == -1 || libc::ioctl(0, libc::TIOCSCTTY, 0) == -1 {
+                    return Err(Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+        let child = command.spawn()?;
+        Ok(Self {
+            output: spawn_reader(master.try_clone()?),
+            master,
+            child,
+        })
+    }
+
+    /// # Errors
+    ///
+    /// Returns an error if the program is no longer reading its input.
+    pub fn write(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        self.master.write_all(bytes)
+    }
+
+    /// Returns what the program printed since the last call, or `None` once
+    /// it has exited and everything it printed was read.
+    pub fn read(&mut self) -> Option<Vec<u8>> {
+        let mut bytes = Vec::new();
+        loop {
+            match self.output.try_recv() {
+                Ok(chunk) => bytes.extend(chunk),
+                Err(TryRecvError::Empty) => return Some(bytes),
+                Err(TryRecvError::Disconnected) if bytes.is_empty() => return None,
+                Err(TryRecvError::Disconnected) => return Some(bytes),
+            }
+        }
+    }
+}
+
+impl Drop for Pty {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+fn window_size(width: u16, height: u16) -> libc::winsize {
+    libc::winsize {
+        ws_row: height,
+        ws_col: width,
+        ws_xpixel: 0,
+        ws_ypixel: 0,
+    }
+}
+
+/// Reads the program's output on its own thread, so the editor can check for
+/// it without blocking.
+fn spawn_reader(mut master: File) -> Receiver<Vec<u8>> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let mut buffer = [0; 4096];
+        loop {
+            match master.read(&mut buffer) {
+                // Linux reports EIO once the program has exited.
+                Ok(0) | Err(_) => break,
+                Ok(count) => {
+                    if sender.send(buffer[..count].to_vec()).is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+    });
+    receiver
+}
+
+/// Returns the user's shell.
+#[must_use]
+pub fn shell() -> String {
+    std::env::var("SHELL").unwrap_or_else(|_| "/bin/sh".to_string())
+}
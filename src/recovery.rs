@@ -0,0 +1,60 @@
+use crate::paths;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The report of the last panic, kept by the panic hook for the crash report.
+static PANIC: Mutex<Option<String>> = Mutex::new(None);
+
+/// Keeps `report`, describing a panic, for [`write`].
+pub fn record_panic(report: &str) {
+    if let Ok(mut panic) = PANIC.lock() {
+        *panic = Some(report.to_string());
+    }
+}
+
+/// Writes the text of each `(name, text)` document to a new directory under
+/// `recovery` in the state directory, with `crash.txt` describing the last
+/// panic and the `commands` run before it. Returns the directory.
+///
+/// # Errors
+///
+/// Will return `Err` if the directory or a file in it cannot be written.
+pub fn write(documents: &[(String, String)], commands: &[String]) -> io::Result<PathBuf> {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs());
+    let directory = recovery_path()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no state directory"))?
+        .join(format!("{seconds}-{}", std::process::id()));
+    fs::create_dir_all(&directory)?;
+    for (index, (name, text)) in documents.iter().enumerate() {
+        // Named like the file, with its slashes turned into percent signs.
+        let file_name = format!("{}-{}", index.saturating_add(1), name.replace('/', "%"));
+        fs::write(directory.join(file_name), text)?;
+    }
+    let panic = PANIC
+        .lock()
+        .ok()
+        .and_then(|mut panic| panic.take())
+        .unwrap_or_else(|| "hecto crashed.\n".to_string());
+    let mut report = format!("hecto {}\n\n{panic}", env!("CARGO_PKG_VERSION"));
+    report.push_str("\nRecent commands:\n");
+    for command in commands {
+        report.push_str(&format!("  {command}\n"));
+    }
+    report.push_str("\nRecovered files:\n");
+    for (index, (name, _)) in documents.iter().enumerate() {
+        report.push_str(&format!("  {}: {name}\n", index.saturating_add(1)));
+    }
+    fs::write(directory.join("crash.txt"), report)?;
+    Ok(directory)
+}
+
+/// Returns `recovery` in the state directory.
+fn recovery_path() -> Option<PathBuf> {
+    Some(paths::state_dir()?.join("recovery"))
+}
@@ -0,0 +1,219 @@
+use crate::paths;
+use crate::shell;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+/// The register yanks and deletes always go to, and pastes come from when no
+/// other is named.
+pub const DEFAULT: char = '"';
+/// The register backed by the system clipboard.
+pub const CLIPBOARD: char = '+';
+
+/// Programs that put their input on the clipboard, tried in turn.
+const COPY_COMMANDS: &[&str] = &[
+    "wl-copy",
+    "xclip -selection clipboard",
+    "xsel --clipboard --input",
+    "pbcopy",
+];
+
+/// Programs that print the clipboard, tried in turn.
+const PASTE_COMMANDS: &[&str] = &[
+    "wl-paste --no-newline",
+    "xclip -selection clipboard -o",
+    "xsel --clipboard --output",
+    "pbpaste",
+];
+
+/// Text kept in a register.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct Register {
+    pub text: String,
+    /// Whether the text is whole lines, pasted below the cursor's line rather
+    /// than at the cursor.
+    pub linewise: bool,
+}
+
+/// Slots for yanked and deleted text, named by a character as in Vim.
+#[derive(Default)]
+pub struct Registers {
+    registers: BTreeMap<char, Register>,
+    /// What was yanked or deleted into any register, newest last.
+    history: VecDeque<Register>,
+    history_size: usize,
+}
+
+impl Registers {
+    /// Returns whether `name` is a register: a letter, the default register
+    /// or the clipboard.
+    #[must_use]
+    pub fn is_valid(name: char) -> bool {
+        name.is_ascii_alphabetic() || name == DEFAULT || name == CLIPBOARD
+    }
+
+    /// Puts `register` into `name` and into the default register. A capital
+    /// letter adds to the end of its lowercase register instead.
+    pub fn set(&mut self, name: char, register: Register) {
+        self.remember(register.clone());
+        let register = match self.registers.get(&name.to_ascii_lowercase()) {
+            Some(existing) if name.is_ascii_uppercase() => append(existing, &register),
+            _ => register,
+        };
+        if name == CLIPBOARD {
+            if register.linewise {
+                copy_to_clipboard(&format!("{}\n", register.text));
+            } else {
+                copy_to_clipboard(&register.text);
+            }
+        }
+        self.registers.insert(DEFAULT, register.clone());
+        self.registers.insert(name.to_ascii_lowercase(), register);
+    }
+
+    /// Puts `register` back into `name` as it was saved, leaving the default
+    /// register and the clipboard alone.
+    pub fn restore(&mut self, name: char, register: Register) {
+        if Self::is_valid(name) && name != CLIPBOARD {
+            self.registers.insert(name, register);
+        }
+    }
+
+    /// Returns the text in `name`. The clipboard is read from the system when
+    /// possible, falling back to what was last put into it here.
+    #[must_use]
+    pub fn get(&self, name: char) -> Option<Register> {
+        if name == CLIPBOARD {
+            if let Some(text) = read_clipboard() {
+                return Some(match text.strip_suffix('\n') {
+                    Some(lines) => Register {
+                        text: lines.to_string(),
+                        linewise: true,
+                    },
+                    None => Register {
+                        text,
+                        linewise: false,
+                    },
+                });
+            }
+        }
+        self.registers.get(&name.to_ascii_lowercase()).cloned()
+    }
+
+    /// Keeps up to `size` of the latest yanks and deletions, forgetting the
+    /// oldest beyond that.
+    pub fn set_history_size(&mut self, size: usize) {
+        self.history_size = size;
+        while self.history.len() > size {
+            self.history.pop_front();
+        }
+    }
+
+    /// Returns the latest yanks and deletions, newest last.
+    #[must_use]
+    pub fn history(&self) -> &VecDeque<Register> {
+        &self.history
+    }
+
+    /// Adds `register` to the history, moving it to the end if the same
+    /// text is there already.
+    fn remember(&mut self, register: Register) {
+        if self.history_size == 0 {
+            return;
+        }
+        self.history.retain(|existing| *existing != register);
+        self.history.push_back(register);
+        if self.history.len() > self.history_size {
+            self.history.pop_front();
+        }
+    }
+
+    /// Reads the history saved by [`Registers::store_history`], under what
+    /// is remembered already.
+    pub fn load_history(&mut self) {
+        let saved: Vec<Register> = history_path()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default();
+        let latest = std::mem::take(&mut self.history);
+        for register in saved.into_iter().chain(latest) {
+            self.remember(register);
+        }
+    }
+
+    /// Saves the history for the next session. Failing to do so is not worth
+    /// bothering the user with, so errors are ignored.
+    pub fn store_history(&self) {
+        let Some(path) = history_path() else {
+            return;
+        };
+        if let (Some(directory), Ok(contents)) =
+            (path.parent(), serde_json::to_string(&self.history))
+        {
+            let _ = fs::create_dir_all(directory);
+            let _ = fs::write(path, contents);
+        }
+    }
+
+    /// Returns the registers holding text, in order of their names.
+    pub fn iter(&self) -> impl Iterator<Item = (char, &Register)> {
+        self.registers
+            .iter()
+            .map(|(name, register)| (*name, register))
+    }
+}
+
+/// Returns `yank_history.json` in the state directory.
+fn history_path() -> Option<PathBuf> {
+    Some(paths::state_dir()?.join("yank_history.json"))
+}
+
+fn append(existing: &Register, added: &Register) -> Register {
+    if existing.linewise || added.linewise {
+        Register {
+            text: format!("{}\n{}", existing.text, added.text),
+            linewise: true,
+        }
+    } else {
+        Register {
+            text: format!("{}{}", existing.text, added.text),
+            linewise: false,
+        }
+    }
+}
+
+/// Hands `text` to the first clipboard program that accepts it. Their
+/// output is not waited for, since some of them stay around to serve the
+/// clipboard.
+fn copy_to_clipboard(text: &str) {
+    for command in COPY_COMMANDS {
+        let child = Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn();
+        let Ok(mut child) = child else {
+            continue;
+        };
+        if let Some(mut stdin) = child.stdin.take() {
+            let _ = stdin.write_all(text.as_bytes());
+        }
+        if child.wait().is_ok_and(|status| status.success()) {
+            return;
+        }
+    }
+}
+
+fn read_clipboard() -> Option<String> {
+    PASTE_COMMANDS.iter().find_map(|command| {
+        shell::run(command, None)
+            .ok()
+            .filter(|output| output.success)
+            .map(|output| output.stdout)
+    })
+}
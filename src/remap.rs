@@ -0,0 +1,112 @@
+use crate::keymap;
+use std::collections::{BTreeMap, HashMap};
+use termion::event::Key;
+
+/// Modes and overlays that can have keys of their own.
+const LAYERS: &[&str] = &["insert", "replace", "picker", "prompt"];
+
+/// Single keys translated into others.
+type Layer = HashMap<Key, Key>;
+
+/// Translates each key before it is looked up in the keymap. A key is looked
+/// up in the overlays open at the moment, the newest first, then in the
+/// user's remaps, those of the current mode and the defaults; the first that
+/// has it decides what it becomes.
+pub struct Remapper {
+    user: Layer,
+    /// The keys of each mode and overlay, keyed by its name.
+    layers: HashMap<&'static str, Layer>,
+    defaults: Layer,
+    overlays: Vec<&'static str>,
+}
+
+impl Default for Remapper {
+    fn default() -> Self {
+        let mut picker = Layer::new();
+        picker.insert(Key::Ctrl('j'), Key::Down);
+        picker.insert(Key::Ctrl('k'), Key::Up);
+        let mut defaults = Layer::new();
+        // Terminals set to send Ctrl-H for Backspace.
+        defaults.insert(Key::Ctrl('h'), Key::Backspace);
+        Self {
+            user: Layer::new(),
+            layers: HashMap::from([("picker", picker)]),
+            defaults,
+            overlays: Vec::new(),
+        }
+    }
+}
+
+impl Remapper {
+    /// Returns what `key` becomes in `mode`.
+    #[must_use]
+    pub fn translate(&self, key: Key, mode: &str) -> Key {
+        self.overlays
+            .iter()
+            .rev()
+            .filter_map(|overlay| self.layers.get(overlay))
+            .chain([&self.user])
+            .chain(self.layers.get(mode))
+            .chain([&self.defaults])
+            .find_map(|layer| layer.get(&key))
+            .copied()
+            .unwrap_or(key)
+    }
+
+    /// Opens the overlay `name`, such as `picker`, until it is closed.
+    pub fn open_overlay(&mut self, name: &'static str) {
+        self.overlays.push(name);
+    }
+
+    /// Closes the overlay opened last.
+    pub fn close_overlay(&mut self) {
+        self.overlays.pop();
+    }
+
+    /// Applies remaps such as `"alt-h" = "left"`, in every mode, and those
+    /// keyed by the mode or overlay they are for, on top of the current ones.
+    ///
+    /// # Errors
+    ///
+    /// Returns one message per remap that could not be applied; the others
+    /// are still applied.
+    pub fn apply(
+        &mut self,
+        remaps: &BTreeMap<String, String>,
+        mode_remaps: &BTreeMap<String, BTreeMap<String, String>>,
+    ) -> Result<(), Vec<String>> {
+        let mut errors = Vec::new();
+        add(&mut self.user, remaps, &mut errors);
+        for (name, remaps) in mode_remaps {
+            match LAYERS.iter().find(|layer| *layer == name) {
+                Some(layer) => add(self.layers.entry(layer).or_default(), remaps, &mut errors),
+                None => errors.push(format!("Unknown mode: {name}")),
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// Adds `remaps` to `layer`, keeping a message for each that is not one key
+/// remapped to another.
+fn add(layer: &mut Layer, remaps: &BTreeMap<String, String>, errors: &mut Vec<String>) {
+    for (from, to) in remaps {
+        match (single_key(from), single_key(to)) {
+            (Ok(from), Ok(to)) => {
+                layer.insert(from, to);
+            }
+            (Err(error), _) | (_, Err(error)) => errors.push(error),
+        }
+    }
+}
+
+fn single_key(description: &str) -> Result<Key, String> {
+    match keymap::parse_keys(description)?[..] {
+        [key] => Ok(key),
+        _ => Err(format!("Only single keys can be remapped: {description}")),
+    }
+}
@@ -0,0 +1,86 @@
+use regex::{NoExpand, Regex, RegexBuilder};
+
+/// How [`crate::Document::replace_all`] finds what to replace.
+#[derive(Clone, Copy, Default)]
+pub struct ReplaceOptions {
+    /// Take the pattern as a regular expression, whose groups the
+    /// replacement can refer to as `$1` or `${name}`. Otherwise both are
+    /// taken literally.
+    pub regex: bool,
+    pub ignore_case: bool,
+    /// Replace only the first match on each line.
+    pub first_only: bool,
+}
+
+/// What [`crate::Document::replace_all`] did.
+#[derive(Default, Debug)]
+pub struct ReplaceReport {
+    /// How many matches were replaced.
+    pub count: usize,
+    /// The lines that changed, counting from zero.
+    pub lines: Vec<usize>,
+}
+
+/// A pattern ready to replace matches in one line at a time.
+pub struct Replacer<'a> {
+    regex: Regex,
+    replacement: &'a str,
+    options: ReplaceOptions,
+}
+
+impl<'a> Replacer<'a> {
+    /// # Errors
+    ///
+    /// Returns a message for the user if `pattern` is empty or, in regex
+    /// mode, is not a valid regular expression.
+    pub fn new(
+        pattern: &str,
+        replacement: &'a str,
+        options: ReplaceOptions,
+    ) -> Result<Self, String> {
+        if pattern.is_empty() {
+            return Err("Empty pattern".to_string());
+        }
+        let pattern = if options.regex {
+            pattern.to_string()
+        } else {
+            regex::escape(pattern)
+        };
+        let regex = RegexBuilder::new(&pattern)
+            .case_insensitive(options.ignore_case)
+            .build()
+            .map_err(|error| {
+                // Syntax errors quote the pattern over several lines, ending
+                // with what is wrong with it.
+                let error = error.to_string();
+                let reason = error.lines().last().unwrap_or_default();
+                format!("Invalid pattern: {}", reason.trim_start_matches("error: "))
+            })?;
+        Ok(Self {
+            regex,
+            replacement,
+            options,
+        })
+    }
+
+    /// Returns `line` with the matches replaced and how many there were, or
+    /// `None` if nothing matched.
+    #[must_use]
+    pub fn replace(&self, line: &str) -> Option<(String, usize)> {
+        let mut count = self.regex.find_iter(line).count();
+        if count == 0 {
+            return None;
+        }
+        // Zero replaces every match.
+        let limit = usize::from(self.options.first_only);
+        if self.options.first_only {
+            count = 1;
+        }
+        let replaced = if self.options.regex {
+            self.regex.replacen(line, limit, self.replacement)
+        } else {
+            self.regex.replacen(line, limit, NoExpand(self.replacement))
+        };
+        Some((replaced.into_owned(), count))
+    }
+}
@@ -1,15 +1,38 @@
 use crate::highlighting;
+use crate::paging::{Page, PageFile};
+use crate::perf;
+use crate::theme::Theme;
 use crate::HighlightingOptions;
 use crate::SearchDirection;
 use std::cmp;
-use termion::color;
+use std::mem;
+use std::ops::Range;
+use std::sync::{Arc, OnceLock};
+use termion::{color, style};
 use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
 
 #[derive(Default)]
 pub struct Row {
     string: String,
     highlighting: Vec<highlighting::Type>,
     len: usize,
+    /// Whether each byte of the text is a grapheme of its own, as in most
+    /// ASCII text, so graphemes are found by their index without going over
+    /// the row. Very long lines, such as minified code, are usually so.
+    plain: bool,
+    /// The byte offset at which each grapheme starts, worked out the first
+    /// time it is needed after a change, for rows that are not plain.
+    boundaries: OnceLock<Vec<usize>>,
+    paged: Option<Paged>,
+}
+
+/// The text of a row paged out to disk, and the text read back from there
+/// until the row is paged out again.
+struct Paged {
+    file: Arc<PageFile>,
+    page: Page,
+    text: OnceLock<String>,
 }
 
 impl From<&str> for Row {
@@ -18,6 +41,9 @@ impl From<&str> for Row {
             string: String::from(slice),
             highlighting: Vec::new(),
             len: 0,
+            plain: false,
+            boundaries: OnceLock::new(),
+            paged: None,
         };
         row.update_len();
         row
@@ -25,40 +51,96 @@ impl From<&str> for Row {
 }
 
 impl Row {
+    /// Renders the screen columns from `start` up to `end`, expanding tabs to
+    /// the next multiple of `tab_width` and showing the graphemes in
+    /// `selected` in reverse video. Without `syntax`, only search matches are
+    /// colored, and without `matches` not even those.
     #[must_use]
-    pub fn render(&self, start: usize, end: usize) -> String {
-        let end = cmp::min(end, self.string.len());
-        let start = cmp::min(start, end);
+    #[allow(clippy::too_many_arguments)]
+    pub fn render(
+        &self,
+        start: usize,
+        end: usize,
+        tab_width: usize,
+        theme: &Theme,
+        selected: &Range<usize>,
+        syntax: bool,
+        matches: bool,
+    ) -> String {
         let mut result = String::new();
         let mut current_highlight = &highlighting::Type::None;
-        for (index, grapheme) in self.string[..]
-            .graphemes(true)
-            .enumerate()
-            .skip(start)
-            .take(end - start)
-        {
-            if let Some(c) = grapheme.chars().next() {
-                let highlighting_type = self
-                    .highlighting
-                    .get(index)
-                    .unwrap_or(&highlighting::Type::None);
-                if highlighting_type != current_highlight {
-                    current_highlight = highlighting_type;
-                    let start_highlight =
-                        format!("{}", termion::color::Fg(highlighting_type.to_color()));
-                    result.push_str(&start_highlight[..]);
+        let mut in_selection = false;
+        let mut column = 0;
+        for (index, grapheme) in self.graphemes().enumerate() {
+            if column >= end {
+                break;
+            }
+            let next_column = next_column(column, grapheme, tab_width);
+            let visible = cmp::min(next_column, end).saturating_sub(cmp::max(column, start));
+            column = next_column;
+            if visible == 0 {
+                continue;
+            }
+            let highlighting_type = match self.highlighting.get(index) {
+                Some(&highlighting::Type::Match) if matches => &highlighting::Type::Match,
+                Some(highlighting_type)
+                    if syntax && *highlighting_type != highlighting::Type::Match =>
+                {
+                    highlighting_type
                 }
-                if c == '\t' {
-                    result.push_str(" ");
+                _ => &highlighting::Type::None,
+            };
+            if highlighting_type != current_highlight {
+                current_highlight = highlighting_type;
+                let start_highlight = format!("{}", color::Fg(theme.color(highlighting_type)));
+                result.push_str(&start_highlight[..]);
+            }
+            if selected.contains(&index) != in_selection {
+                in_selection = !in_selection;
+                if in_selection {
+                    result.push_str(style::Invert.as_ref());
                 } else {
-                    result.push(c);
+                    result.push_str(style::NoInvert.as_ref());
                 }
             }
+            // Tabs, and wide graphemes cut by the edge, are drawn as spaces.
+            if grapheme == "\t" || visible < grapheme.width() {
+                result.push_str(&" ".repeat(visible));
+            } else {
+                result.push_str(grapheme);
+            }
+        }
+        if in_selection {
+            result.push_str(style::NoInvert.as_ref());
         }
-        let end_highlight = format!("{}", termion::color::Fg(color::Reset));
+        let end_highlight = format!("{}", color::Fg(color::Reset));
         result.push_str(&end_highlight[..]);
         result
     }
+
+    /// Returns the grapheme drawn at screen column `column`, or the end of
+    /// the row if it is not that wide.
+    #[must_use]
+    pub fn x_at(&self, column: usize, tab_width: usize) -> usize {
+        let mut start = 0;
+        for (x, grapheme) in self.graphemes().enumerate() {
+            let end = next_column(start, grapheme, tab_width);
+            if end > column {
+                return x;
+            }
+            start = end;
+        }
+        self.len
+    }
+
+    /// Returns the screen column at which the grapheme at `x` is drawn.
+    #[must_use]
+    pub fn render_x(&self, x: usize, tab_width: usize) -> usize {
+        self.graphemes().take(x).fold(0, |column, grapheme| {
+            next_column(column, grapheme, tab_width)
+        })
+    }
+
     #[must_use]
     pub fn len(&self) -> usize {
         self.len
@@ -67,67 +149,246 @@ impl Row {
     pub fn is_empty(&self) -> bool {
         self.len == 0
     }
+    /// Checks, in debug builds, that the length kept for the row is its
+    /// number of graphemes and that its highlighting, if it was highlighted,
+    /// covers each of them.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if either is out of step with the text.
+    pub fn check_invariants(&self) {
+        debug_assert_eq!(
+            self.len,
+            self.as_str().graphemes(true).count(),
+            "length of row {:?}",
+            self.as_str()
+        );
+        if let Some(boundaries) = self.boundaries.get() {
+            debug_assert!(
+                boundaries
+                    .iter()
+                    .copied()
+                    .eq(self.as_str().grapheme_indices(true).map(|(index, _)| index)),
+                "grapheme boundaries of row {:?}",
+                self.as_str()
+            );
+        }
+        debug_assert!(
+            self.highlighting.is_empty() || self.highlighting.len() == self.len,
+            "{} highlights for row {:?}",
+            self.highlighting.len(),
+            self.as_str()
+        );
+    }
+
     fn update_len(&mut self) {
-        self.len = self.string[..].graphemes(true).count();
+        self.boundaries = OnceLock::new();
+        // A `\r\n` is the only pair of ASCII characters that is one grapheme.
+        self.plain = self.string.is_ascii() && !self.string.contains("\r\n");
+        self.len = if self.plain {
+            self.string.len()
+        } else {
+            self.string.graphemes(true).count()
+        };
+    }
+
+    /// Returns the byte offset of the grapheme at `at`, or the length of the
+    /// text if the row is not that long.
+    fn byte_index(&self, at: usize) -> usize {
+        let text = self.as_str();
+        if self.plain {
+            return at.min(text.len());
+        }
+        self.boundaries().get(at).copied().unwrap_or(text.len())
+    }
+
+    /// Returns the grapheme that starts at byte `index`, if one does.
+    fn grapheme_at(&self, index: usize) -> Option<usize> {
+        if self.plain {
+            return (index < self.as_str().len()).then_some(index);
+        }
+        self.boundaries().binary_search(&index).ok()
+    }
+
+    fn boundaries(&self) -> &[usize] {
+        if let Some(boundaries) = self.boundaries.get() {
+            return boundaries;
+        }
+        // Nothing is kept for a row that cannot be read back yet.
+        let Ok(text) = self.text() else {
+            return &[];
+        };
+        self.boundaries.get_or_init(|| {
+            text.grapheme_indices(true)
+                .map(|(index, _)| index)
+                .collect()
+        })
+    }
+
+    /// Returns the graphemes of the row, cut at the boundaries found before
+    /// rather than by going over the text again.
+    fn graphemes(&self) -> impl Iterator<Item = &str> {
+        let text = self.as_str();
+        (0..self.len).map(move |x| &text[self.byte_index(x)..self.byte_index(x.saturating_add(1))])
     }
 
     pub fn insert(&mut self, at: usize, c: char) {
-        if at >= self.len() {
-            self.string.push(c);
-        } else {
-            let mut result: String = self.string[..].graphemes(true).take(at).collect();
-            let remainder: String = self.string[..].graphemes(true).skip(at).collect();
-            result.push(c);
-            result.push_str(&remainder);
-            self.string = result;
+        if !self.page_in() {
+            return;
         }
+        let index = self.byte_index(at);
+        self.string.insert(index, c);
         self.update_len();
     }
 
     pub fn delete(&mut self, at: usize) {
-        if at >= self.len() {
+        if !self.page_in() || at >= self.len() {
             return;
         }
-
-        let mut result: String = self.string[..].graphemes(true).take(at).collect();
-        let remainder: String = self.string[..].graphemes(true).skip(at + 1).collect();
-        result.push_str(&remainder);
-        self.string = result;
+        let start = self.byte_index(at);
+        let end = self.byte_index(at.saturating_add(1));
+        self.string.replace_range(start..end, "");
         self.update_len();
     }
 
     pub fn append(&mut self, new: &Self) {
-        self.string = format!("{}{}", self.string, new.string);
+        if !self.page_in() {
+            return;
+        }
+        match new.text() {
+            Ok(text) => self.string.push_str(text),
+            Err(error) => log::error!("Could not read a paged out row: {error}"),
+        }
         self.update_len();
     }
 
     #[must_use]
     pub fn split(&mut self, at: usize) -> Self {
-        let mut row: String = String::new();
-        let mut length = 0;
-        let mut splitted_row: String = String::new();
-        let mut splitted_length = 0;
-        for (index, grapheme) in self.string[..].graphemes(true).enumerate() {
-            if index < at {
-                length += 1;
-                row.push_str(grapheme);
-            } else {
-                splitted_length += 1;
-                splitted_row.push_str(grapheme);
-            }
+        if !self.page_in() {
+            return Self::default();
         }
-        self.string = row;
-        self.len = length;
-        Self {
-            string: splitted_row,
-            len: splitted_length,
+        let index = self.byte_index(at);
+        let mut splitted = Self {
+            string: self.string.split_off(index),
             highlighting: Vec::new(),
-        }
+            len: 0,
+            plain: false,
+            boundaries: OnceLock::new(),
+            paged: None,
+        };
+        self.update_len();
+        splitted.update_len();
+        splitted
     }
 
     #[must_use]
     pub fn as_bytes(&self) -> &[u8] {
-        self.string.as_bytes()
+        self.as_str().as_bytes()
+    }
+
+    /// Returns the text of the row, reading it back if it was paged out. A
+    /// row that cannot be read back reads as empty, and [`Row::text`] tells
+    /// why.
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        self.text().unwrap_or_else(|error| {
+            log::error!("Could not read a paged out row: {error}");
+            ""
+        })
+    }
+
+    /// Returns the text of the row, reading it back if it was paged out.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the row was paged out and cannot be read back.
+    /// It is read again the next time.
+    pub fn text(&self) -> std::io::Result<&str> {
+        match &self.paged {
+            Some(paged) => {
+                if let Some(text) = paged.text.get() {
+                    return Ok(text);
+                }
+                let text = paged.file.read(&paged.page)?;
+                Ok(paged.text.get_or_init(|| text))
+            }
+            None => Ok(&self.string),
+        }
+    }
+
+    /// Returns roughly how many bytes the row takes in memory.
+    pub fn memory(&self) -> usize {
+        let paged = self
+            .paged
+            .as_ref()
+            .and_then(|paged| paged.text.get())
+            .map_or(0, String::capacity);
+        self.string
+            .capacity()
+            .saturating_add(self.highlighting.capacity() * mem::size_of::<highlighting::Type>())
+            .saturating_add(paged)
+            .saturating_add(self.boundaries.get().map_or(0, |boundaries| {
+                boundaries.capacity() * mem::size_of::<usize>()
+            }))
+    }
+
+    /// Returns `true` if the row has a highlight for each grapheme, which it
+    /// lacks until [`Row::highlight`] is called or after
+    /// [`Row::drop_highlighting`].
+    #[must_use]
+    pub fn is_highlighted(&self) -> bool {
+        self.highlighting.len() == self.len
+    }
+
+    /// Frees the highlighting of the row, to be computed again when needed.
+    pub fn drop_highlighting(&mut self) {
+        self.highlighting = Vec::new();
+    }
+
+    /// Frees the text of the row, writing it to `file` to be read back when
+    /// needed. A row already paged out only frees the text read back.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the text cannot be written.
+    pub fn page_out(&mut self, file: &Arc<PageFile>) -> std::io::Result<()> {
+        self.boundaries = OnceLock::new();
+        if let Some(paged) = &mut self.paged {
+            paged.text = OnceLock::new();
+            return Ok(());
+        }
+        let page = file.write(&self.string)?;
+        self.string = String::new();
+        self.paged = Some(Paged {
+            file: Arc::clone(file),
+            page,
+            text: OnceLock::new(),
+        });
+        Ok(())
+    }
+
+    /// Brings the text of the row back into memory to change it. Returns
+    /// `false` if it cannot be read back, leaving the row as it is rather
+    /// than changing what reads as empty.
+    fn page_in(&mut self) -> bool {
+        if self.paged.is_some() {
+            match self.text() {
+                Ok(text) => self.string = text.to_string(),
+                Err(error) => {
+                    log::error!("Could not change a paged out row: {error}");
+                    return false;
+                }
+            }
+            self.paged = None;
+        }
+        true
+    }
+
+    /// Returns what each character was marked as by the last call to
+    /// [`Row::highlight`].
+    #[must_use]
+    pub fn highlighting(&self) -> &[highlighting::Type] {
+        &self.highlighting
     }
 
     pub fn find(&self, query: &str, at: usize, direction: SearchDirection) -> Option<usize> {
@@ -144,55 +405,48 @@ impl Row {
         } else {
             at
         };
-        #[allow(clippy::integer_arithmetic)]
-        let substring: String = self.string[..]
-            .graphemes(true)
-            .skip(start)
-            .take(end - start)
-            .collect();
+        let start = self.byte_index(start);
+        let substring = &self.as_str()[start..self.byte_index(end)];
         let matching_byte_index = if direction == SearchDirection::Forward {
             substring.find(query)
         } else {
             substring.rfind(query)
         };
-        if let Some(matching_byte_index) = matching_byte_index {
-            for (grapheme_index, (byte_index, _)) in
-                substring[..].grapheme_indices(true).enumerate()
-            {
-                if matching_byte_index == byte_index {
-                    #[allow(clippy::integer_arithmetic)]
-                    return Some(start + grapheme_index);
-                }
-            }
+        matching_byte_index.and_then(|index| self.grapheme_at(start.saturating_add(index)))
+    }
+
+    /// Returns the graphemes at which `word` starts in the row, without
+    /// overlaps, found in one pass so a long row is not searched again from
+    /// each match.
+    fn word_matches(&self, word: &str) -> Vec<usize> {
+        if word.is_empty() {
+            return Vec::new();
         }
-        None
+        // Matches that start inside a grapheme are not shown.
+        self.as_str()
+            .match_indices(word)
+            .filter_map(|(index, _)| self.grapheme_at(index))
+            .collect()
     }
 
     pub fn highlight(&mut self, opts: HighlightingOptions, word: Option<&str>) {
+        let started = perf::start_highlighting();
         let mut highlighting = Vec::new();
 
-        let chars: Vec<char> = self.string.chars().collect();
-        let mut matches = Vec::new();
-        let mut search_index = 0;
-
-        if let Some(word) = word {
-            while let Some(search_match) = self.find(word, search_index, SearchDirection::Forward) {
-                matches.push(search_match);
-                if let Some(next_index) = search_match.checked_add(word[..].graphemes(true).count())
-                {
-                    search_index = next_index;
-                } else {
-                    break;
-                }
-            }
-        }
+        // One highlight per grapheme, as rows are drawn, each told apart by
+        // its first character.
+        let chars: Vec<char> = self
+            .graphemes()
+            .filter_map(|grapheme| grapheme.chars().next())
+            .collect();
+        let matches = word.map_or_else(Vec::new, |word| self.word_matches(word));
 
         let mut prev_is_separator = true;
         let mut in_string = false;
         let mut index = 0;
         while let Some(c) = chars.get(index) {
             if let Some(word) = word {
-                if matches.contains(&index) {
+                if matches.binary_search(&index).is_ok() {
                     for _ in word[..].graphemes(true) {
                         index += 1;
                         highlighting.push(highlighting::Type::Match);
@@ -201,7 +455,7 @@ impl Row {
                 }
             }
             let previous_highlight = if index > 0 {
-                #[allow(clippy::integer_arithmetic)]
+                #[allow(clippy::arithmetic_side_effects)]
                 highlighting
                     .get(index - 1)
                     .unwrap_or(&highlighting::Type::None)
@@ -224,7 +478,7 @@ impl Row {
                             }
                             continue;
                         }
-                    } 
+                    }
                 };
                 highlighting.push(highlighting::Type::None);
                 index += 1;
@@ -270,5 +524,17 @@ impl Row {
             index += 1;
         }
         self.highlighting = highlighting;
+        perf::end_highlighting(started);
+    }
+}
+
+fn next_column(column: usize, grapheme: &str, tab_width: usize) -> usize {
+    if grapheme == "\t" {
+        let tab_width = cmp::max(tab_width, 1);
+        column
+            .saturating_add(tab_width)
+            .saturating_sub(column % tab_width)
+    } else {
+        column.saturating_add(grapheme.width().max(1))
     }
 }
@@ -0,0 +1,89 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::Arc;
+use std::thread;
+
+/// A document being written to its file in the background.
+pub struct Save {
+    pub file_name: String,
+    /// The document revision being written.
+    pub revision: u64,
+    /// The percentage last reported to the user.
+    pub reported: u64,
+    total: u64,
+    written: Arc<AtomicU64>,
+    result: Receiver<io::Result<()>>,
+}
+
+impl Save {
+    /// Starts writing `lines`, the text of the document at `revision`, to
+    /// `file_name` on its own thread.
+    #[must_use]
+    pub fn start(file_name: &str, lines: Vec<String>, revision: u64) -> Self {
+        let (sender, result) = mpsc::channel();
+        let total = lines
+            .iter()
+            .map(|line| line.len().saturating_add(1) as u64)
+            .sum();
+        let written = Arc::new(AtomicU64::new(0));
+        let progress = Arc::clone(&written);
+        let path = file_name.to_string();
+        thread::spawn(move || {
+            let result = write(&path, &lines, &progress);
+            if let Err(error) = &result {
+                log::warn!("Could not save {path}: {error}");
+            }
+            let _ = sender.send(result);
+        });
+        Self {
+            file_name: file_name.to_string(),
+            revision,
+            reported: 0,
+            total,
+            written,
+            result,
+        }
+    }
+
+    /// Returns how much has been written so far, in percent.
+    #[must_use]
+    pub fn percent(&self) -> u64 {
+        let written = self.written.load(Ordering::Relaxed);
+        written
+            .saturating_mul(100)
+            .checked_div(self.total)
+            .unwrap_or(100)
+    }
+
+    /// Returns the outcome once the file has been written.
+    pub fn poll(&self) -> Option<io::Result<()>> {
+        match self.result.try_recv() {
+            Ok(result) => Some(result),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(Err(io::Error::other("saving stopped"))),
+        }
+    }
+
+    /// Blocks until the file has been written.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error writing failed with.
+    pub fn wait(&self) -> io::Result<()> {
+        self.result
+            .recv()
+            .unwrap_or_else(|_| Err(io::Error::other("saving stopped")))
+    }
+}
+
+fn write(file_name: &str, lines: &[String], written: &AtomicU64) -> io::Result<()> {
+    let mut file = BufWriter::new(File::create(file_name)?);
+    for line in lines {
+        file.write_all(line.as_bytes())?;
+        file.write_all(b"\n")?;
+        written.fetch_add(line.len().saturating_add(1) as u64, Ordering::Relaxed);
+    }
+    file.flush()
+}
@@ -0,0 +1,179 @@
+use crate::ansi;
+use crate::backend::{Backend, Event, Size};
+use crate::Position;
+use std::cell::{Cell, RefCell};
+use std::io::Error;
+use std::time::Duration;
+
+/// What was drawn during a frame, line by line.
+#[derive(Default)]
+struct Frame {
+    lines: Vec<String>,
+    /// The line being drawn.
+    y: usize,
+    /// Where the cursor was last placed, until something is written there.
+    cursor: Option<Position>,
+    cleared: bool,
+}
+
+impl Frame {
+    fn line(&mut self) -> &mut String {
+        if self.lines.len() <= self.y {
+            self.lines
+                .resize_with(self.y.saturating_add(1), String::new);
+        }
+        &mut self.lines[self.y]
+    }
+}
+
+/// Wraps the backend the editor draws on, so that each frame only sends the
+/// lines that look different from the frame before.
+///
+/// Between [`Screen::begin_frame`] and [`Screen::end_frame`], output is kept
+/// per line instead of being written. Ending the frame writes the lines that
+/// changed, then moves the cursor to where it was placed last.
+pub struct Screen {
+    backend: Box<dyn Backend>,
+    frame: RefCell<Option<Frame>>,
+    /// The lines as the last frame drew them.
+    shown: RefCell<Vec<String>>,
+    /// The size of the backend when the last frame was drawn. Once it
+    /// changes, the whole screen is drawn again.
+    shown_size: Cell<Option<Size>>,
+    /// Whether colors and other attributes are left out.
+    plain: Cell<bool>,
+}
+
+impl Screen {
+    #[must_use]
+    pub fn new(backend: Box<dyn Backend>) -> Self {
+        Self {
+            backend,
+            frame: RefCell::new(None),
+            shown: RefCell::new(Vec::new()),
+            shown_size: Cell::new(None),
+            plain: Cell::new(false),
+        }
+    }
+
+    /// Leaves colors and other attributes out of what is drawn from now on,
+    /// or puts them back.
+    pub fn set_plain(&self, plain: bool) {
+        self.plain.set(plain);
+    }
+
+    pub fn begin_frame(&self) {
+        *self.frame.borrow_mut() = Some(Frame::default());
+    }
+
+    /// Writes the lines that changed during the frame, as a synchronized
+    /// update if `synchronized`.
+    pub fn end_frame(&self, synchronized: bool) {
+        let Some(frame) = self.frame.borrow_mut().take() else {
+            return;
+        };
+        let mut shown = self.shown.borrow_mut();
+        if synchronized {
+            self.backend.begin_synchronized_update();
+        }
+        self.backend.cursor_hide();
+        let mut output = String::new();
+        let resized =
+            self.shown_size.replace(Some(self.backend.size())) != Some(self.backend.size());
+        if frame.cleared || resized {
+            output.push_str(termion::clear::All.as_ref());
+            shown.clear();
+        }
+        for (y, line) in frame.lines.iter().enumerate() {
+            if shown.get(y) != Some(line) {
+                // Colors left over from the line above may never have been
+                // reset, if that line was not drawn again.
+                output.push_str(termion::style::Reset.as_ref());
+                output.push_str(&goto(&Position { x: 0, y }));
+                output.push_str(line);
+            }
+        }
+        *shown = frame.lines;
+        if let Some(cursor) = &frame.cursor {
+            output.push_str(&goto(cursor));
+        }
+        self.backend.write(&output);
+        self.backend.cursor_show();
+        if synchronized {
+            self.backend.end_synchronized_update();
+        }
+    }
+}
+
+fn goto(position: &Position) -> String {
+    let x = u16::try_from(position.x.saturating_add(1)).unwrap_or(u16::MAX);
+    let y = u16::try_from(position.y.saturating_add(1)).unwrap_or(u16::MAX);
+    termion::cursor::Goto(x, y).to_string()
+}
+
+impl Backend for Screen {
+    fn size(&self) -> Size {
+        self.backend.size()
+    }
+
+    fn write(&self, text: &str) {
+        let plain;
+        let text = if self.plain.get() {
+            plain = ansi::strip_styles(text);
+            &plain
+        } else {
+            text
+        };
+        let mut frame = self.frame.borrow_mut();
+        let Some(frame) = frame.as_mut() else {
+            self.backend.write(text);
+            return;
+        };
+        if let Some(cursor) = frame.cursor.take() {
+            frame.y = cursor.y;
+            frame.line().push_str(&goto(&cursor));
+        }
+        frame.line().push_str(text);
+    }
+
+    fn writeln(&self, text: &str) {
+        self.write(text);
+        self.write("\r\n");
+        if let Some(frame) = self.frame.borrow_mut().as_mut() {
+            frame.y = frame.y.saturating_add(1);
+        }
+    }
+
+    fn clear_screen(&self) {
+        match self.frame.borrow_mut().as_mut() {
+            Some(frame) => {
+                frame.lines.clear();
+                frame.cleared = true;
+            }
+            None => self.backend.clear_screen(),
+        }
+    }
+
+    fn cursor_position(&self, position: &Position) {
+        match self.frame.borrow_mut().as_mut() {
+            Some(frame) => frame.cursor = Some(position.clone()),
+            None => self.backend.cursor_position(position),
+        }
+    }
+
+    fn flush(&self) -> Result<(), Error> {
+        self.backend.flush()
+    }
+
+    fn read_event(&self) -> Result<Event, Error> {
+        self.backend.read_event()
+    }
+
+    fn poll_event(&self, timeout: Duration) -> Result<Option<Event>, Error> {
+        self.backend.poll_event(timeout)
+    }
+
+    fn query_background(&self) {
+        self.backend.query_background();
+    }
+}
@@ -0,0 +1,282 @@
+use crate::highlighting;
+use crate::{Document, Position, ReplaceOptions, Row, SearchDirection};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::os::unix::net::UnixListener;
+use std::path::Path;
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+/// The request was understood but could not be carried out, such as a file
+/// that cannot be read.
+const FAILED: i64 = -32000;
+
+/// Why a request failed, as a JSON-RPC error code and message.
+type Failure = (i64, String);
+
+/// Serves documents to other programs over JSON-RPC 2.0, one message per
+/// line, so they can open, edit, search, highlight and save them without
+/// the editor's screen.
+///
+/// Documents are named by the number `open` returns. Positions are objects
+/// with a zero-based `line` and a `column` counted in graphemes.
+#[derive(Default)]
+pub struct Server {
+    documents: HashMap<u64, Document>,
+    next_id: u64,
+}
+
+impl Server {
+    /// Answers requests read from `input` on `output` until `input` ends.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if reading or writing fails.
+    pub fn serve(&mut self, input: impl BufRead, mut output: impl Write) -> io::Result<()> {
+        for line in input.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            if let Some(response) = self.handle_line(&line) {
+                writeln!(output, "{response}")?;
+                output.flush()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Listens on a Unix socket at `path`, serving one connection at a time.
+    /// Documents stay open from one connection to the next.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the socket cannot be created.
+    pub fn listen(&mut self, path: &Path) -> io::Result<()> {
+        // A socket left behind by an earlier run would make binding fail.
+        if fs::metadata(path).is_ok() {
+            fs::remove_file(path)?;
+        }
+        let listener = UnixListener::bind(path)?;
+        for stream in listener.incoming() {
+            let stream = stream?;
+            let reader = BufReader::new(stream.try_clone()?);
+            if let Err(error) = self.serve(reader, BufWriter::new(stream)) {
+                log::warn!("Connection ended: {error}");
+            }
+        }
+        Ok(())
+    }
+
+    /// Answers one line of input, or returns `None` for a notification.
+    fn handle_line(&mut self, line: &str) -> Option<Value> {
+        let message: Value = match serde_json::from_str(line) {
+            Ok(message) => message,
+            Err(error) => return Some(error_response(&Value::Null, PARSE_ERROR, &error)),
+        };
+        let id = message.get("id").cloned();
+        let Some(method) = message.get("method").and_then(Value::as_str) else {
+            let id = id.unwrap_or(Value::Null);
+            return Some(error_response(&id, INVALID_REQUEST, &"No method"));
+        };
+        let params = message.get("params").cloned().unwrap_or(Value::Null);
+        log::debug!("Serving {method}");
+        let result = self.call(method, &params);
+        let id = id?;
+        Some(match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err((code, message)) => error_response(&id, code, &message),
+        })
+    }
+
+    fn call(&mut self, method: &str, params: &Value) -> Result<Value, Failure> {
+        match method {
+            "open" => {
+                let path = string(params, "path")?;
+                let document = Document::open(path).map_err(|error| (FAILED, error.to_string()))?;
+                self.next_id = self.next_id.wrapping_add(1);
+                let lines = document.len();
+                self.documents.insert(self.next_id, document);
+                Ok(json!({ "document": self.next_id, "lines": lines }))
+            }
+            "close" => {
+                let id = document_id(params)?;
+                self.documents.remove(&id);
+                Ok(Value::Null)
+            }
+            "text" => Ok(Value::from(self.document(params)?.text())),
+            "insert" => {
+                let at = position(params, "at")?;
+                let text = string(params, "text")?.to_string();
+                let document = self.document_mut(params)?;
+                check_position(document, &at)?;
+                let end = document.insert_str(&at, &text);
+                document.end_undo_group(at, end.clone(), None);
+                Ok(position_json(&end))
+            }
+            "delete" => {
+                let start = position(params, "start")?;
+                let end = position(params, "end")?;
+                let document = self.document_mut(params)?;
+                check_position(document, &start)?;
+                check_position(document, &end)?;
+                if (end.y, end.x) < (start.y, start.x) {
+                    return Err((INVALID_PARAMS, "The end comes before the start".to_string()));
+                }
+                document.delete_range(&start, &end);
+                document.end_undo_group(end, start, None);
+                Ok(Value::Null)
+            }
+            "undo" => Ok(self
+                .document_mut(params)?
+                .undo()
+                .map_or(Value::Null, |at| position_json(&at))),
+            "redo" => Ok(self
+                .document_mut(params)?
+                .redo()
+                .map_or(Value::Null, |at| position_json(&at))),
+            "search" => {
+                let query = string(params, "query")?.to_string();
+                let at = position(params, "from").unwrap_or_default();
+                let direction = match params.get("backward").and_then(Value::as_bool) {
+                    Some(true) => SearchDirection::Backward,
+                    _ => SearchDirection::Forward,
+                };
+                let found = self.document(params)?.find(&query, &at, direction);
+                Ok(found.map_or(Value::Null, |at| position_json(&at)))
+            }
+            "replace_all" => {
+                let pattern = string(params, "pattern")?.to_string();
+                let replacement = string(params, "replacement")?.to_string();
+                let flag = |name: &str| params.get(name).and_then(Value::as_bool) == Some(true);
+                let options = ReplaceOptions {
+                    regex: flag("regex"),
+                    ignore_case: flag("ignore_case"),
+                    first_only: flag("first_only"),
+                };
+                let document = self.document_mut(params)?;
+                let report = document
+                    .replace_all(&pattern, &replacement, &options)
+                    .map_err(|error| (INVALID_PARAMS, error))?;
+                let at = Position::default();
+                document.end_undo_group(at.clone(), at, None);
+                Ok(json!({ "count": report.count, "lines": report.lines }))
+            }
+            "highlight" => {
+                let line = index(params, "line")?;
+                let document = self.document_mut(params)?;
+                document.highlight_rows(line..line.saturating_add(1));
+                let row = document
+                    .row(line)
+                    .ok_or_else(|| (INVALID_PARAMS, format!("No line {line}")))?;
+                let kinds: Vec<&str> = row.highlighting().iter().map(kind).collect();
+                Ok(json!(kinds))
+            }
+            "save" => {
+                let path = params
+                    .get("path")
+                    .and_then(Value::as_str)
+                    .map(str::to_string);
+                let document = self.document_mut(params)?;
+                if let Some(path) = path {
+                    document.file_name = Some(path);
+                }
+                document
+                    .save()
+                    .map_err(|error| (FAILED, error.to_string()))?;
+                Ok(Value::Null)
+            }
+            _ => Err((METHOD_NOT_FOUND, format!("Unknown method: {method}"))),
+        }
+    }
+
+    fn document(&self, params: &Value) -> Result<&Document, Failure> {
+        let id = document_id(params)?;
+        self.documents
+            .get(&id)
+            .ok_or_else(|| (INVALID_PARAMS, format!("No document {id}")))
+    }
+
+    fn document_mut(&mut self, params: &Value) -> Result<&mut Document, Failure> {
+        let id = document_id(params)?;
+        self.documents
+            .get_mut(&id)
+            .ok_or_else(|| (INVALID_PARAMS, format!("No document {id}")))
+    }
+}
+
+fn error_response(id: &Value, code: i64, message: &impl ToString) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": code, "message": message.to_string() },
+    })
+}
+
+fn string<'a>(params: &'a Value, name: &str) -> Result<&'a str, Failure> {
+    params
+        .get(name)
+        .and_then(Value::as_str)
+        .ok_or_else(|| (INVALID_PARAMS, format!("Missing string {name}")))
+}
+
+fn index(params: &Value, name: &str) -> Result<usize, Failure> {
+    params
+        .get(name)
+        .and_then(Value::as_u64)
+        .and_then(|value| usize::try_from(value).ok())
+        .ok_or_else(|| (INVALID_PARAMS, format!("Missing number {name}")))
+}
+
+fn document_id(params: &Value) -> Result<u64, Failure> {
+    params
+        .get("document")
+        .and_then(Value::as_u64)
+        .ok_or_else(|| (INVALID_PARAMS, "Missing document".to_string()))
+}
+
+fn position(params: &Value, name: &str) -> Result<Position, Failure> {
+    let value = params
+        .get(name)
+        .ok_or_else(|| (INVALID_PARAMS, format!("Missing position {name}")))?;
+    Ok(Position {
+        x: index(value, "column")?,
+        y: index(value, "line")?,
+    })
+}
+
+/// Checks that `at` is in `document`: on one of its lines and no further
+/// than its end, or at the start of the line after the last.
+fn check_position(document: &Document, at: &Position) -> Result<(), Failure> {
+    let len = if at.y == document.len() {
+        Some(0)
+    } else {
+        document.row(at.y).map(Row::len)
+    };
+    match len {
+        Some(len) if at.x <= len => Ok(()),
+        Some(_) => Err((
+            INVALID_PARAMS,
+            format!("No column {} in line {}", at.x, at.y),
+        )),
+        None => Err((INVALID_PARAMS, format!("No line {}", at.y))),
+    }
+}
+
+fn position_json(position: &Position) -> Value {
+    json!({ "line": position.y, "column": position.x })
+}
+
+fn kind(highlight: &highlighting::Type) -> &'static str {
+    match highlight {
+        highlighting::Type::None => "none",
+        highlighting::Type::Number => "number",
+        highlighting::Type::Match => "match",
+        highlighting::Type::String => "string",
+        highlighting::Type::Character => "character",
+    }
+}
@@ -0,0 +1,49 @@
+use std::io::{Error, Write};
+use std::process::{Command, Stdio};
+use std::thread;
+
+pub struct Output {
+    pub stdout: String,
+    pub stderr: String,
+    pub success: bool,
+}
+
+/// Runs `command` with `sh -c`, feeding it `input` on stdin, and waits for
+/// it to finish.
+///
+/// # Errors
+///
+/// Returns an error if the shell could not be started.
+pub fn run(command: &str, input: Option<&str>) -> Result<Output, Error> {
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stdin(if input.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    // Feed stdin from another thread, so a command writing a lot before it
+    // has read all of its input cannot deadlock with us.
+    let writer = match (child.stdin.take(), input) {
+        (Some(mut stdin), Some(input)) => {
+            let input = input.to_string();
+            Some(thread::spawn(move || {
+                let _ = stdin.write_all(input.as_bytes());
+            }))
+        }
+        _ => None,
+    };
+    let output = child.wait_with_output()?;
+    if let Some(writer) = writer {
+        let _ = writer.join();
+    }
+    Ok(Output {
+        stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+        stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        success: output.status.success(),
+    })
+}
@@ -0,0 +1,96 @@
+use crate::paths;
+use crate::undo;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A copy of a file's text as it was at some point, kept so that files
+/// outside version control have a history too.
+pub struct Snapshot {
+    /// When it was taken, in milliseconds since the Unix epoch.
+    taken: u128,
+    path: PathBuf,
+}
+
+impl Snapshot {
+    /// Returns when it was taken, in seconds since the Unix epoch.
+    #[must_use]
+    pub fn time(&self) -> u64 {
+        u64::try_from(self.taken / 1000).unwrap_or(u64::MAX)
+    }
+
+    /// # Errors
+    ///
+    /// Will return `Err` if the snapshot cannot be read.
+    pub fn read(&self) -> io::Result<String> {
+        fs::read_to_string(&self.path)
+    }
+}
+
+/// Keeps `text` as the latest version of `file_name`, unless it is the same
+/// as the one kept last, and forgets the oldest beyond `keep`. Returns
+/// whether a snapshot was taken.
+///
+/// # Errors
+///
+/// Will return `Err` if the snapshot cannot be written.
+pub fn take(file_name: &str, text: &str, keep: usize) -> io::Result<bool> {
+    let Some(directory) = directory(file_name) else {
+        return Ok(false);
+    };
+    let snapshots = list(file_name);
+    if keep == 0
+        || snapshots
+            .first()
+            .is_some_and(|latest| latest.read().is_ok_and(|latest| latest == text))
+    {
+        return Ok(false);
+    }
+    fs::create_dir_all(&directory)?;
+    let taken = now();
+    fs::write(directory.join(format!("{taken}.txt")), text)?;
+    // One taken in the same millisecond was just replaced.
+    let older = snapshots.iter().filter(|snapshot| snapshot.taken != taken);
+    for old in older.skip(keep.saturating_sub(1)) {
+        let _ = fs::remove_file(&old.path);
+    }
+    Ok(true)
+}
+
+/// Returns the snapshots kept of `file_name`, the latest first.
+#[must_use]
+pub fn list(file_name: &str) -> Vec<Snapshot> {
+    let Some(entries) = directory(file_name).and_then(|directory| fs::read_dir(directory).ok())
+    else {
+        return Vec::new();
+    };
+    let mut snapshots: Vec<Snapshot> = entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let path = entry.path();
+            let taken = path.file_stem()?.to_str()?.parse().ok()?;
+            Some(Snapshot { taken, path })
+        })
+        .collect();
+    snapshots.sort_by_key(|snapshot| std::cmp::Reverse(snapshot.taken));
+    snapshots
+}
+
+/// Returns where the snapshots of `file_name` are kept: `snapshots/` in the
+/// data directory, in a directory named after a checksum of its absolute
+/// path.
+fn directory(file_name: &str) -> Option<PathBuf> {
+    let path = fs::canonicalize(file_name).unwrap_or_else(|_| Path::new(file_name).to_path_buf());
+    let name = format!(
+        "{:016x}",
+        undo::checksum([path.to_string_lossy().as_ref()].into_iter())
+    );
+    Some(paths::data_dir()?.join("snapshots").join(name))
+}
+
+fn now() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_millis())
+}
@@ -0,0 +1,62 @@
+use crate::registers::Register;
+use crate::Position;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+
+/// What the editor holds apart from the text of its documents, written by
+/// `export_state` and read back by `import_state`, to report a problem or to
+/// carry on elsewhere.
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct State {
+    /// The version of hecto that wrote it.
+    pub version: String,
+    /// The open buffers, the current one last.
+    pub buffers: Vec<BufferState>,
+    /// Each of the options `set` turns on and off.
+    pub options: BTreeMap<String, bool>,
+    pub tab_width: usize,
+    pub mode: String,
+    /// The registers holding text, but the clipboard.
+    pub registers: BTreeMap<char, Register>,
+    /// Earlier answers to each prompt, oldest first, keyed by the prompt.
+    pub histories: BTreeMap<String, Vec<String>>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct BufferState {
+    pub name: String,
+    /// The file it shows, if any. Other buffers cannot be restored.
+    pub file_name: Option<String>,
+    pub file_type: String,
+    pub lines: usize,
+    pub modified: bool,
+    pub read_only: bool,
+    pub cursor: Position,
+    /// The first row and column shown.
+    pub offset: Position,
+}
+
+impl State {
+    /// Reads a state written by [`State::write`].
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if `file_name` cannot be read or does not hold a
+    /// state.
+    pub fn read(file_name: &str) -> io::Result<Self> {
+        let text = fs::read_to_string(file_name)?;
+        serde_json::from_str(&text)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+    }
+
+    /// # Errors
+    ///
+    /// Will return `Err` if `file_name` cannot be written.
+    pub fn write(&self, file_name: &str) -> io::Result<()> {
+        let text = serde_json::to_string_pretty(self)?;
+        fs::write(file_name, format!("{text}\n"))
+    }
+}
@@ -0,0 +1,101 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Where in its file a tag is defined.
+pub enum Address {
+    /// A zero-based line number.
+    Line(usize),
+    /// The text the defining line starts with.
+    Pattern(String),
+}
+
+/// A symbol definition listed in a `tags` file.
+pub struct Tag {
+    pub name: String,
+    pub file_name: String,
+    pub address: Address,
+    /// The kind of symbol, such as `f` for a function, if the file says.
+    pub kind: Option<String>,
+}
+
+/// Reads the `tags` file closest to `dir`, looking in it and then in each of
+/// its parents. File names in the tags are made relative to the working
+/// directory when they are below it, the way files are usually opened.
+///
+/// # Errors
+///
+/// Returns a message for the user if no tags file exists.
+pub fn load(dir: &Path) -> Result<Vec<Tag>, String> {
+    let Some(path) = dir
+        .ancestors()
+        .map(|dir| dir.join("tags"))
+        .find(|path| path.is_file())
+    else {
+        return Err("No tags file found, run ctags -R to create one.".to_string());
+    };
+    let contents =
+        fs::read_to_string(&path).map_err(|error| format!("{}: {error}", path.display()))?;
+    let base = path.parent().map(Path::to_path_buf).unwrap_or_default();
+    let cwd = std::env::current_dir().unwrap_or_default();
+    Ok(contents
+        .lines()
+        .filter(|line| !line.starts_with("!_TAG_"))
+        .filter_map(|line| parse_line(line, &base, &cwd))
+        .collect())
+}
+
+/// Parses `name<TAB>file<TAB>address;"<TAB>kind...`.
+fn parse_line(line: &str, base: &Path, cwd: &Path) -> Option<Tag> {
+    let mut fields = line.splitn(3, '\t');
+    let name = fields.next()?;
+    let file = fields.next()?;
+    let rest = fields.next()?;
+    let (address, extensions) = match rest.split_once(";\"") {
+        Some((address, extensions)) => (address, extensions),
+        None => (rest, ""),
+    };
+    let address = if let Ok(line) = address.trim().parse::<usize>() {
+        Address::Line(line.saturating_sub(1))
+    } else {
+        Address::Pattern(pattern(address)?)
+    };
+    let kind = extensions
+        .split('\t')
+        .map(|field| field.strip_prefix("kind:").unwrap_or(field))
+        .find(|field| !field.is_empty() && !field.contains(':'))
+        .map(str::to_string);
+    let path: PathBuf = base.join(file);
+    let file_name = path.strip_prefix(cwd).unwrap_or(&path);
+    Some(Tag {
+        name: name.to_string(),
+        file_name: file_name.to_string_lossy().into_owned(),
+        address,
+        kind,
+    })
+}
+
+/// Turns a search command such as `/^fn main() {$/` into the text it looks
+/// for. Only the anchors and escapes ctags writes are understood.
+fn pattern(address: &str) -> Option<String> {
+    let address = address.trim();
+    let inner = address
+        .strip_prefix('/')
+        .and_then(|rest| rest.strip_suffix('/'))
+        .or_else(|| {
+            address
+                .strip_prefix('?')
+                .and_then(|rest| rest.strip_suffix('?'))
+        })?;
+    let inner = inner.strip_prefix('^').unwrap_or(inner);
+    let inner = inner.strip_suffix('$').unwrap_or(inner);
+    let mut text = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            text.extend(chars.next());
+        } else {
+            text.push(c);
+        }
+    }
+    Some(text)
+}
@@ -0,0 +1,153 @@
+use crate::ignore::Ignore;
+use crate::locations::Location;
+use crate::{git, Position};
+use regex::Regex;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Files larger than this are not searched for tasks.
+const MAX_FILE_SIZE: u64 = 1024 * 1024;
+
+/// A search of the project for `TODO` and `FIXME` comments, running in the
+/// background.
+pub struct Scan {
+    result: Receiver<Vec<Location>>,
+}
+
+impl Scan {
+    /// Starts searching the files under the root of `ignore` on its own
+    /// thread, leaving out those git or `ignore` leaves out.
+    #[must_use]
+    pub fn start(ignore: Ignore) -> Self {
+        let (sender, result) = mpsc::channel();
+        thread::spawn(move || {
+            let pattern = Regex::new(r"\b(TODO|FIXME)\b").expect("the pattern is valid");
+            let root = ignore.root();
+            let current_dir = env::current_dir().unwrap_or_default();
+            let tasks = files(root)
+                .into_iter()
+                .map(|file| root.join(file))
+                .filter(|path| !ignore.is_ignored(path))
+                .flat_map(|path| {
+                    let file_name = path.strip_prefix(&current_dir).unwrap_or(&path);
+                    find(&file_name.to_string_lossy(), &pattern)
+                })
+                .collect();
+            let _ = sender.send(tasks);
+        });
+        Self { result }
+    }
+
+    /// Returns the tasks found once the search has finished.
+    pub fn poll(&self) -> Option<Result<Vec<Location>, String>> {
+        match self.result.try_recv() {
+            Ok(tasks) => Some(Ok(tasks)),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => {
+                Some(Err("The search for tasks stopped unexpectedly".to_string()))
+            }
+        }
+    }
+}
+
+/// Lays out `tasks` grouped by file, a line naming each file followed by a
+/// line for each of its tasks. Returns the text with the task on each line,
+/// if there is one.
+#[must_use]
+pub fn list(tasks: Vec<Location>) -> (String, Vec<Option<Location>>) {
+    let mut lines = Vec::new();
+    let mut rows = Vec::new();
+    let mut file_name = None;
+    for task in tasks {
+        if file_name.as_ref() != Some(&task.file_name) {
+            if !lines.is_empty() {
+                lines.push(String::new());
+                rows.push(None);
+            }
+            lines.push(task.file_name.clone());
+            rows.push(None);
+            file_name = Some(task.file_name.clone());
+        }
+        lines.push(format!(
+            "  {}:{} {}",
+            task.position.y.saturating_add(1),
+            task.position.x.saturating_add(1),
+            task.message
+        ));
+        rows.push(Some(task));
+    }
+    (lines.join("\n"), rows)
+}
+
+/// Returns the files under `root` to search, relative to it: those git knows
+/// of or would, inside a repository, or else every file not in a hidden
+/// directory.
+fn files(root: &Path) -> Vec<PathBuf> {
+    let in_repository = root.ancestors().any(|dir| dir.join(".git").exists());
+    if in_repository {
+        if let Ok(output) = git::run(
+            root,
+            &["ls-files", "--cached", "--others", "--exclude-standard"],
+            None,
+        ) {
+            return output.lines().map(PathBuf::from).collect();
+        }
+    }
+    let mut files = Vec::new();
+    walk(root, PathBuf::new(), &mut files);
+    files.sort();
+    files
+}
+
+/// Adds the files in `dir`, a directory under `root`, and in the directories
+/// in it to `files`.
+fn walk(root: &Path, dir: PathBuf, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(root.join(&dir)) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        if name.to_string_lossy().starts_with('.') {
+            continue;
+        }
+        let path = dir.join(&name);
+        match entry.file_type() {
+            Ok(kind) if kind.is_dir() => walk(root, path, files),
+            Ok(kind) if kind.is_file() => files.push(path),
+            _ => {}
+        }
+    }
+}
+
+/// Returns the tasks in `file_name`, none if it is large or not text.
+fn find(file_name: &str, pattern: &Regex) -> Vec<Location> {
+    let small = fs::metadata(file_name).is_ok_and(|metadata| metadata.len() <= MAX_FILE_SIZE);
+    let Some(text) = small
+        .then(|| fs::read(file_name).ok())
+        .flatten()
+        .and_then(|bytes| String::from_utf8(bytes).ok())
+    else {
+        return Vec::new();
+    };
+    if text.contains('\0') {
+        return Vec::new();
+    }
+    text.lines()
+        .enumerate()
+        .filter_map(|(y, line)| {
+            let found = pattern.find(line)?;
+            Some(Location {
+                file_name: file_name.to_string(),
+                position: Position {
+                    x: line[..found.start()].graphemes(true).count(),
+                    y,
+                },
+                message: line[found.start()..].trim_end().to_string(),
+            })
+        })
+        .collect()
+}
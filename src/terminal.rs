@@ -1,95 +1,307 @@
-use std::io::{self, stdout, Write};
+use crate::appearance::{self, Background};
+use crate::recovery;
+use std::backtrace::{Backtrace, BacktraceStatus};
+use std::cell::Cell;
+use std::io::{self, stdout, ErrorKind, Read, Stdout, Write};
+use std::panic;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
 use termion::{
-    color,
     event::Key,
     input::TermRead,
     raw::{IntoRawMode, RawTerminal},
 };
 
-use crate::editor::Position;
+use crate::backend::{Backend, Event, Size};
+use crate::error::Error;
+use crate::output::Output;
 
-pub struct Size {
-    pub width: u16,
-    pub height: u16,
-}
+/// How often the size of the terminal is checked.
+const RESIZE_POLL_INTERVAL: Duration = Duration::from_millis(250);
+/// How long the terminal has to report its background color before the
+/// environment is asked instead.
+const BACKGROUND_QUERY_TIMEOUT: Duration = Duration::from_millis(200);
+/// How long after asking an `Alt-]` is taken to start the terminal's answer
+/// rather than to be typed.
+const BACKGROUND_REPLY_WINDOW: Duration = Duration::from_secs(2);
+
+type Events = Sender<Result<Event, std::io::Error>>;
 
 pub struct Terminal {
-    size: Size,
+    size: Cell<Size>,
     _stdout: RawTerminal<std::io::Stdout>,
+    events: Receiver<Result<Event, std::io::Error>>,
+    sender: Events,
+    /// When the background color was last asked for, until it is reported.
+    background_query: Arc<Mutex<Option<Instant>>>,
+    output: Output<Stdout>,
 }
 
-impl Terminal {
-    #[must_use]
-    pub fn size(&self) -> &Size {
-        &self.size
+impl Backend for Terminal {
+    fn size(&self) -> Size {
+        self.size.get()
     }
-    pub fn clear_screen() {
-        print!("{}", termion::clear::All);
+
+    fn write(&self, text: &str) {
+        self.output.write(text);
     }
-    pub fn clear_current_line() {
-        print!("{}", termion::clear::CurrentLine);
+
+    fn flush(&self) -> Result<(), std::io::Error> {
+        self.output.flush()
     }
-    pub fn set_bg_color(color: color::Rgb) {
-        print!("{}", color::Bg(color));
+
+    fn read_event(&self) -> Result<Event, std::io::Error> {
+        self.events
+            .recv()
+            .unwrap_or_else(|_| Err(ErrorKind::UnexpectedEof.into()))
+            .inspect(|event| self.resize(event))
     }
-    pub fn reset_bg_color() {
-        print!("{}", color::Bg(color::Reset));
+
+    fn poll_event(&self, timeout: Duration) -> Result<Option<Event>, std::io::Error> {
+        match self.events.recv_timeout(timeout) {
+            Ok(event) => event.inspect(|event| self.resize(event)).map(Some),
+            Err(RecvTimeoutError::Timeout) => Ok(None),
+            Err(RecvTimeoutError::Disconnected) => Err(ErrorKind::UnexpectedEof.into()),
+        }
     }
-    pub fn set_fg_color(color: color::Rgb) {
-        print!("{}", color::Fg(color));
+
+    /// Sends `OSC 11`, which most terminals answer with their background
+    /// color. If none comes in time, the environment is asked instead.
+    fn query_background(&self) {
+        if let Ok(mut query) = self.background_query.lock() {
+            *query = Some(Instant::now());
+        }
+        self.write("\x1b]11;?\x07");
+        let _ = self.flush();
+        let query = Arc::clone(&self.background_query);
+        let sender = self.sender.clone();
+        thread::spawn(move || {
+            thread::sleep(BACKGROUND_QUERY_TIMEOUT);
+            let answered = query.lock().map_or(true, |query| query.is_none());
+            if answered {
+                return;
+            }
+            if let Some(background) = appearance::hint() {
+                let _ = sender.send(Ok(Event::Background(background)));
+            }
+        });
     }
-    pub fn reset_fg_color() {
-        print!("{}", color::Fg(color::Reset));
+}
+
+impl Terminal {
+    /// Reads stdin on its own thread, so the editor can wait for input with a
+    /// timeout instead of blocking until the next key.
+    fn spawn_input_reader(
+        background_query: Arc<Mutex<Option<Instant>>>,
+    ) -> (Events, Receiver<Result<Event, std::io::Error>>) {
+        let (sender, receiver) = mpsc::channel();
+        Self::spawn_resize_watcher(sender.clone());
+        let events = sender.clone();
+        thread::spawn(move || loop {
+            let event = Self::read_stdin_event(&background_query);
+            let failed = event.is_err();
+            if sender.send(event).is_err() || failed {
+                break;
+            }
+        });
+        (events, receiver)
     }
-    pub fn cursor_position(position: &Position) {
-        let Position { mut x, mut y } = position;
-        x = x.saturating_add(1);
-        y = y.saturating_add(1);
-        print!(
-            "{}",
-            termion::cursor::Goto(
-                x.try_into().expect("Unexpected u16 overflow in Goto"),
-                y.try_into().expect("Unexpected u16 overflow in Goto")
-            )
-        );
+
+    /// Checks the size of the terminal on its own thread, sending a resize
+    /// event whenever it changes, until the editor stops listening.
+    fn spawn_resize_watcher(sender: Events) {
+        thread::spawn(move || {
+            let mut last = termion::terminal_size().ok();
+            loop {
+                thread::sleep(RESIZE_POLL_INTERVAL);
+                let size = termion::terminal_size().ok();
+                if size == last {
+                    continue;
+                }
+                last = size;
+                if let Some((width, height)) = size {
+                    if sender.send(Ok(Event::Resize { width, height })).is_err() {
+                        break;
+                    }
+                }
+            }
+        });
     }
 
-    /// # Errors
-    ///
-    /// It is considered an error if not all bytes could be written due to I/O errors or EOF being reached.
-    pub fn flush() -> Result<(), std::io::Error> {
-        io::stdout().flush()
+    /// Reports the new size from now on if `event` is a resize.
+    fn resize(&self, event: &Event) {
+        if let Event::Resize { width, height } = *event {
+            self.size.set(Size {
+                width,
+                height: height.saturating_sub(2),
+            });
+        }
     }
 
-    /// # Errors
-    ///
-    /// It is considered an error if not all bytes could be written due to I/O errors or EOF being reached.
-    pub fn read_key() -> Result<Key, std::io::Error> {
+    fn read_stdin_event(
+        background_query: &Mutex<Option<Instant>>,
+    ) -> Result<Event, std::io::Error> {
         loop {
-            if let Some(key) = io::stdin().lock().keys().next() {
-                return key;
+            let event = match io::stdin().lock().events().next() {
+                Some(event) => event?,
+                None => return Err(ErrorKind::UnexpectedEof.into()),
+            };
+            match event {
+                termion::event::Event::Key(Key::Alt(']'))
+                    if Self::answers_query(background_query) =>
+                {
+                    let reply = Self::read_until_terminator()?;
+                    if let Some(color) = appearance::parse_reply(&reply) {
+                        return Ok(Event::Background(Background::of(color)));
+                    }
+                }
+                termion::event::Event::Key(key) => return Ok(Event::Key(key)),
+                termion::event::Event::Unsupported(bytes) => match bytes.as_slice() {
+                    b"\x1b[I" => return Ok(Event::FocusGained),
+                    b"\x1b[O" => return Ok(Event::FocusLost),
+                    b"\x1b[200~" => return Self::read_paste(),
+                    // termion gives up on private reports after the `?`.
+                    b"\x1b[?" => {
+                        if let Some(background) = Self::read_appearance_report()? {
+                            return Ok(Event::Background(background));
+                        }
+                    }
+                    _ => (),
+                },
+                termion::event::Event::Mouse(_) => (),
             }
         }
     }
 
-    pub fn cursor_hide() {
-        print!("{}", termion::cursor::Hide);
+    /// Returns whether an `OSC` sequence starting now is the answer to the
+    /// last background query, taking the query as answered if it is.
+    fn answers_query(background_query: &Mutex<Option<Instant>>) -> bool {
+        let Ok(mut query) = background_query.lock() else {
+            return false;
+        };
+        let answers = query.is_some_and(|asked| asked.elapsed() < BACKGROUND_REPLY_WINDOW);
+        *query = None;
+        answers
     }
 
-    pub fn cursor_show() {
-        print!("{}", termion::cursor::Show);
+    /// Reads the rest of an `OSC` sequence, up to the BEL or the `ESC \`
+    /// ending it.
+    fn read_until_terminator() -> Result<String, std::io::Error> {
+        let mut reply = Vec::new();
+        for byte in io::stdin().lock().bytes() {
+            match byte? {
+                b'\x07' => break,
+                b'\x1b' => {
+                    // The `\` that follows is part of the terminator.
+                    io::stdin().lock().read_exact(&mut [0])?;
+                    break;
+                }
+                byte => reply.push(byte),
+            }
+        }
+        Ok(String::from_utf8_lossy(&reply).into_owned())
+    }
+
+    /// Reads the rest of a private report, returning the background it
+    /// tells of if it is the `997;1n` or `997;2n` sent in mode 2031.
+    fn read_appearance_report() -> Result<Option<Background>, std::io::Error> {
+        let mut report = Vec::new();
+        for byte in io::stdin().lock().bytes() {
+            let byte = byte?;
+            report.push(byte);
+            if (0x40..=0x7e).contains(&byte) {
+                break;
+            }
+        }
+        Ok(match report.as_slice() {
+            b"997;1n" => Some(Background::Dark),
+            b"997;2n" => Some(Background::Light),
+            _ => None,
+        })
     }
-}
 
-impl Default for Terminal {
-    fn default() -> Self {
-        let size = termion::terminal_size().unwrap();
-        Self {
-            size: Size {
+    /// Reads the rest of a bracketed paste, up to the `ESC [201~` ending it,
+    /// byte for byte, so control characters and escapes are kept as sent.
+    /// Line breaks come as carriage returns, as if typed.
+    fn read_paste() -> Result<Event, std::io::Error> {
+        const END: &[u8] = b"\x1b[201~";
+        let mut bytes = Vec::new();
+        for byte in io::stdin().lock().bytes() {
+            bytes.push(byte?);
+            if bytes.ends_with(END) {
+                bytes.truncate(bytes.len().saturating_sub(END.len()));
+                break;
+            }
+        }
+        let text = String::from_utf8_lossy(&bytes)
+            .replace("\r\n", "\n")
+            .replace('\r', "\n");
+        Ok(Event::Paste(text))
+    }
+
+    /// Switches off every mode the editor turned on so the shell is usable
+    /// again, even if the editor is going down abruptly.
+    pub fn restore() {
+        print!(
+            "\x1b[?1004l\x1b[?2004l\x1b[0 q{}{}{}{}",
+            termion::screen::ToMainScreen,
+            termion::clear::All,
+            termion::cursor::Goto(1, 1),
+            termion::cursor::Show
+        );
+        let _ = io::stdout().flush();
+    }
+
+    /// Makes panics restore the terminal before reporting, instead of leaving
+    /// the user's shell in raw mode with a garbled message.
+    pub fn install_panic_hook() {
+        panic::set_hook(Box::new(|info| {
+            Self::restore();
+            let backtrace = Backtrace::capture();
+            let mut report = format!("hecto crashed: {info}\n");
+            if backtrace.status() == BacktraceStatus::Captured {
+                report.push_str(&format!("{backtrace}\n"));
+            } else {
+                report.push_str("Run with RUST_BACKTRACE=1 to display a backtrace.\n");
+            }
+            log::error!("{}", report.trim_end());
+            recovery::record_panic(&report);
+            // Raw mode is only left once the terminal is dropped during
+            // unwinding, so every line needs its carriage return.
+            eprint!("{}", report.replace('\n', "\r\n"));
+        }));
+    }
+
+    /// Puts the terminal in raw mode and starts reading its input.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::NoTerminal`] if standard input or output is not a
+    /// terminal.
+    pub fn new() -> Result<Self, Error> {
+        let size = termion::terminal_size().map_err(Error::NoTerminal)?;
+        let stdout = stdout().into_raw_mode().map_err(Error::NoTerminal)?;
+        let background_query = Arc::new(Mutex::new(None));
+        let (sender, events) = Self::spawn_input_reader(Arc::clone(&background_query));
+        Ok(Self {
+            size: Cell::new(Size {
                 width: size.0,
                 height: size.1.saturating_sub(2),
-            },
-            _stdout: stdout().into_raw_mode().unwrap(),
-        }
+            }),
+            _stdout: stdout,
+            events,
+            sender,
+            background_query,
+            output: Output::new(io::stdout()),
+        })
+    }
+}
+
+impl Drop for Terminal {
+    /// Sends whatever was written but not flushed, before raw mode is left.
+    fn drop(&mut self) {
+        let _ = self.flush();
     }
 }
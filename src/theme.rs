@@ -0,0 +1,186 @@
+use crate::appearance::Background;
+use crate::highlighting;
+use termion::color::Rgb;
+
+/// The colors used to draw the editor. A theme named with `-light` is the
+/// variant for a light background of the theme named without it.
+pub struct Theme {
+    pub name: &'static str,
+    pub text: Rgb,
+    pub status_fg: Rgb,
+    pub status_bg: Rgb,
+    pub line_number: Rgb,
+    pub number: Rgb,
+    pub search_match: Rgb,
+    pub string: Rgb,
+    pub character: Rgb,
+    pub error: Rgb,
+    pub warning: Rgb,
+    /// Colors of the rulers without one of their own, the first ruler in
+    /// the first, and so on, starting over when they run out.
+    pub rulers: &'static [Rgb],
+    /// Lines only in the second of two files compared.
+    pub added: Rgb,
+    /// Lines only in the first of two files compared.
+    pub removed: Rgb,
+    /// Behind the words that differ between two lines compared.
+    pub changed_bg: Rgb,
+    /// Behind our side of a merge conflict, the common ancestor's and
+    /// theirs.
+    pub ours_bg: Rgb,
+    pub base_bg: Rgb,
+    pub theirs_bg: Rgb,
+    /// Behind the line the cursor is on, and behind it while the terminal
+    /// does not have focus.
+    pub cursor_line_bg: Rgb,
+    pub unfocused_cursor_line_bg: Rgb,
+}
+
+pub const THEMES: &[Theme] = &[
+    Theme {
+        name: "default",
+        text: Rgb(255, 255, 255),
+        status_fg: Rgb(63, 63, 63),
+        status_bg: Rgb(239, 239, 239),
+        line_number: Rgb(128, 128, 128),
+        number: Rgb(220, 163, 163),
+        search_match: Rgb(38, 139, 210),
+        string: Rgb(211, 54, 130),
+        character: Rgb(108, 113, 196),
+        error: Rgb(255, 85, 85),
+        warning: Rgb(229, 192, 123),
+        rulers: &[Rgb(128, 128, 128), Rgb(150, 110, 110), Rgb(110, 110, 160)],
+        added: Rgb(80, 200, 120),
+        removed: Rgb(255, 85, 85),
+        changed_bg: Rgb(70, 70, 110),
+        ours_bg: Rgb(30, 70, 45),
+        base_bg: Rgb(60, 60, 60),
+        theirs_bg: Rgb(30, 50, 90),
+        cursor_line_bg: Rgb(44, 44, 44),
+        unfocused_cursor_line_bg: Rgb(26, 26, 26),
+    },
+    Theme {
+        name: "default-light",
+        text: Rgb(32, 32, 32),
+        status_fg: Rgb(239, 239, 239),
+        status_bg: Rgb(63, 63, 63),
+        line_number: Rgb(140, 140, 140),
+        number: Rgb(170, 60, 60),
+        search_match: Rgb(20, 100, 180),
+        string: Rgb(170, 30, 100),
+        character: Rgb(80, 80, 170),
+        error: Rgb(200, 30, 30),
+        warning: Rgb(160, 110, 0),
+        rulers: &[Rgb(190, 190, 190), Rgb(210, 170, 170), Rgb(170, 170, 220)],
+        added: Rgb(20, 130, 60),
+        removed: Rgb(200, 30, 30),
+        changed_bg: Rgb(210, 210, 240),
+        ours_bg: Rgb(210, 240, 215),
+        base_bg: Rgb(225, 225, 225),
+        theirs_bg: Rgb(210, 225, 250),
+        cursor_line_bg: Rgb(232, 232, 232),
+        unfocused_cursor_line_bg: Rgb(246, 246, 246),
+    },
+    Theme {
+        name: "solarized",
+        text: Rgb(131, 148, 150),
+        status_fg: Rgb(238, 232, 213),
+        status_bg: Rgb(7, 54, 66),
+        line_number: Rgb(88, 110, 117),
+        number: Rgb(203, 75, 22),
+        search_match: Rgb(38, 139, 210),
+        string: Rgb(42, 161, 152),
+        character: Rgb(108, 113, 196),
+        error: Rgb(220, 50, 47),
+        warning: Rgb(181, 137, 0),
+        rulers: &[Rgb(88, 110, 117), Rgb(108, 113, 196), Rgb(211, 54, 130)],
+        added: Rgb(133, 153, 0),
+        removed: Rgb(220, 50, 47),
+        changed_bg: Rgb(7, 54, 66),
+        ours_bg: Rgb(20, 70, 50),
+        base_bg: Rgb(7, 54, 66),
+        theirs_bg: Rgb(25, 50, 95),
+        cursor_line_bg: Rgb(7, 54, 66),
+        unfocused_cursor_line_bg: Rgb(3, 46, 58),
+    },
+    Theme {
+        name: "solarized-light",
+        text: Rgb(101, 123, 131),
+        status_fg: Rgb(7, 54, 66),
+        status_bg: Rgb(238, 232, 213),
+        line_number: Rgb(147, 161, 161),
+        number: Rgb(203, 75, 22),
+        search_match: Rgb(38, 139, 210),
+        string: Rgb(42, 161, 152),
+        character: Rgb(108, 113, 196),
+        error: Rgb(220, 50, 47),
+        warning: Rgb(181, 137, 0),
+        rulers: &[Rgb(147, 161, 161), Rgb(108, 113, 196), Rgb(211, 54, 130)],
+        added: Rgb(133, 153, 0),
+        removed: Rgb(220, 50, 47),
+        changed_bg: Rgb(238, 232, 213),
+        ours_bg: Rgb(225, 240, 210),
+        base_bg: Rgb(238, 232, 213),
+        theirs_bg: Rgb(215, 230, 245),
+        cursor_line_bg: Rgb(238, 232, 213),
+        unfocused_cursor_line_bg: Rgb(247, 241, 224),
+    },
+];
+
+impl Theme {
+    /// The theme used when the configuration does not name one.
+    #[must_use]
+    pub fn builtin() -> &'static Self {
+        #[allow(clippy::indexing_slicing)]
+        &THEMES[0]
+    }
+
+    #[must_use]
+    pub fn find(name: &str) -> Option<&'static Self> {
+        THEMES.iter().find(|theme| theme.name == name)
+    }
+
+    /// Returns the variant of this theme for `background`, or the theme
+    /// itself if it has none.
+    #[must_use]
+    pub fn variant(&'static self, background: Background) -> &'static Self {
+        let base = self.name.strip_suffix("-light").unwrap_or(self.name);
+        let name = match background {
+            Background::Light => format!("{base}-light"),
+            Background::Dark => base.to_string(),
+        };
+        Self::find(&name).unwrap_or(self)
+    }
+
+    /// Returns the color of the ruler at `index` among those shown.
+    #[must_use]
+    pub fn ruler(&self, index: usize) -> Rgb {
+        match self.rulers.len() {
+            0 => self.line_number,
+            len => self
+                .rulers
+                .get(index % len)
+                .copied()
+                .unwrap_or(self.line_number),
+        }
+    }
+
+    #[must_use]
+    pub fn color(&self, highlight: &highlighting::Type) -> Rgb {
+        match highlight {
+            highlighting::Type::None => self.text,
+            highlighting::Type::Number => self.number,
+            highlighting::Type::Match => self.search_match,
+            highlighting::Type::String => self.string,
+            highlighting::Type::Character => self.character,
+        }
+    }
+}
+
+/// Reads a color written as `#rrggbb`.
+#[must_use]
+pub fn parse_color(text: &str) -> Option<Rgb> {
+    let hex = text.strip_prefix('#').filter(|hex| hex.len() == 6)?;
+    let channel = |at: usize| u8::from_str_radix(hex.get(at..at.saturating_add(2))?, 16).ok();
+    Some(Rgb(channel(0)?, channel(2)?, channel(4)?))
+}
@@ -0,0 +1,679 @@
+use crate::blame;
+use crate::paths;
+use crate::Position;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Counts every time a state is reached, in any document, so the states
+/// used least recently can be told apart across documents.
+static USES: AtomicU64 = AtomicU64::new(0);
+
+/// A change to a document: the rows from `at` that read `removed` were
+/// replaced with `inserted`.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Edit {
+    pub at: usize,
+    pub removed: Vec<String>,
+    pub inserted: Vec<String>,
+}
+
+impl Edit {
+    /// Returns the edit that takes the rows back to how they were.
+    #[must_use]
+    pub fn inverse(&self) -> Self {
+        Self {
+            at: self.at,
+            removed: self.inserted.clone(),
+            inserted: self.removed.clone(),
+        }
+    }
+}
+
+/// A state of the document, reached from its parent by applying `edits`.
+#[derive(Clone, Serialize, Deserialize)]
+struct State {
+    parent: Option<usize>,
+    children: Vec<usize>,
+    /// The child redo goes to: the one created or left last.
+    redo: Option<usize>,
+    edits: Vec<Edit>,
+    /// Where the cursor was before and after the edits.
+    before: Position,
+    after: Position,
+    /// When the state was reached, in seconds since the Unix epoch.
+    time: u64,
+    /// Whether typing more may add to the edits, so a word is undone at once.
+    #[serde(skip)]
+    open: bool,
+    /// When the state was last reached, counted by [`USES`]. States read
+    /// from disk have not been used in this session.
+    #[serde(skip)]
+    used: u64,
+}
+
+/// How much undo history is kept for a file between sessions.
+#[derive(Clone, Copy)]
+pub struct Limits {
+    /// Bytes of edited text kept. Zero keeps no history at all.
+    pub size: usize,
+    /// Seconds a state is kept after it was reached.
+    pub age: u64,
+}
+
+/// The undo history of a file as it is written to disk.
+#[derive(Serialize, Deserialize)]
+struct History {
+    /// The absolute path of the file, in case two paths share a store name.
+    path: String,
+    /// A checksum of the text the file was saved with. History for a file
+    /// changed elsewhere since no longer applies to it.
+    checksum: u64,
+    /// When the history is too old to be restored, in seconds since the Unix
+    /// epoch.
+    expires: u64,
+    states: Vec<State>,
+    current: usize,
+}
+
+/// Moving between states: the edits to apply, in order, and where to put the
+/// cursor afterwards.
+pub struct Step {
+    pub edits: Vec<Edit>,
+    pub cursor: Position,
+}
+
+/// Every state a document has been in since it was opened. Edits made after
+/// an undo start a new branch rather than replacing the ones undone, so any
+/// earlier state can be gone back to.
+pub struct UndoTree {
+    /// The states in the order they were reached; the first is the document
+    /// as it was opened.
+    states: Vec<State>,
+    current: usize,
+    /// The state last saved, if it still exists.
+    saved: Option<usize>,
+    /// Whether the document was saved with edits pending, so the state they
+    /// become is the one saved.
+    saved_pending: bool,
+    /// Edits made since the last [`UndoTree::commit`].
+    pending: Vec<Edit>,
+    /// Bytes of text the edits of the states take.
+    memory: usize,
+}
+
+impl Default for UndoTree {
+    fn default() -> Self {
+        Self {
+            states: vec![State {
+                parent: None,
+                children: Vec::new(),
+                redo: None,
+                edits: Vec::new(),
+                before: Position::default(),
+                after: Position::default(),
+                time: now(),
+                open: false,
+                used: next_use(),
+            }],
+            current: 0,
+            saved: Some(0),
+            saved_pending: false,
+            pending: Vec::new(),
+            memory: 0,
+        }
+    }
+}
+
+impl UndoTree {
+    /// Notes an edit made to the document, to become part of the next state.
+    pub fn record(&mut self, edit: Edit) {
+        self.pending.push(edit);
+        coalesce(&mut self.pending);
+    }
+
+    /// Turns the edits recorded since the last call into a new state, moving
+    /// the cursor from `before` to `after`. If they typed the character
+    /// `typed`, they are added to the current state instead when it was made
+    /// by typing too and the cursor has not moved since, unless `typed` is a
+    /// space, which starts the next word.
+    pub fn commit(&mut self, before: Position, after: Position, typed: Option<char>) {
+        if self.pending.is_empty() {
+            return;
+        }
+        let edits: Vec<Edit> = self.pending.drain(..).collect();
+        if self.saved_pending {
+            self.saved_pending = false;
+            self.add_state(edits, before, after, false);
+            self.saved = Some(self.current);
+            return;
+        }
+        if typed.is_some_and(|c| !c.is_whitespace()) {
+            if let Some(state) = self.states.get_mut(self.current) {
+                if state.open && state.children.is_empty() && state.after == before {
+                    let size = edits_size(&state.edits);
+                    for edit in edits {
+                        state.edits.push(edit);
+                        coalesce(&mut state.edits);
+                    }
+                    state.after = after;
+                    state.time = now();
+                    state.used = next_use();
+                    self.memory = self
+                        .memory
+                        .saturating_sub(size)
+                        .saturating_add(edits_size(&state.edits));
+                    return;
+                }
+            }
+        }
+        self.add_state(edits, before, after, typed.is_some());
+    }
+
+    fn add_state(&mut self, edits: Vec<Edit>, before: Position, after: Position, open: bool) {
+        let index = self.states.len();
+        self.memory = self.memory.saturating_add(edits_size(&edits));
+        self.states.push(State {
+            parent: Some(self.current),
+            children: Vec::new(),
+            redo: None,
+            edits,
+            before,
+            after,
+            time: now(),
+            open,
+            used: next_use(),
+        });
+        if let Some(parent) = self.states.get_mut(self.current) {
+            parent.children.push(index);
+            parent.redo = Some(index);
+        }
+        self.current = index;
+    }
+
+    /// Steps back to the parent of the current state.
+    pub fn undo(&mut self) -> Option<Step> {
+        let state = self.states.get(self.current)?;
+        let parent = state.parent?;
+        let step = Step {
+            edits: state.edits.iter().rev().map(Edit::inverse).collect(),
+            cursor: state.before.clone(),
+        };
+        let child = self.current;
+        if let Some(parent) = self.states.get_mut(parent) {
+            parent.redo = Some(child);
+            parent.used = next_use();
+        }
+        self.current = parent;
+        Some(step)
+    }
+
+    /// Steps forward to the child of the current state last created or left.
+    pub fn redo(&mut self) -> Option<Step> {
+        let child = self.states.get(self.current)?.redo?;
+        let state = self.states.get_mut(child)?;
+        // Typing after a redo must not change the state redone.
+        state.open = false;
+        state.used = next_use();
+        let step = Step {
+            edits: state.edits.clone(),
+            cursor: state.after.clone(),
+        };
+        self.current = child;
+        Some(step)
+    }
+
+    /// Moves to `target`, undoing back to where its branch meets the current
+    /// one and redoing from there. Returns the steps taken, in order.
+    pub fn go_to(&mut self, target: usize) -> Vec<Step> {
+        if target >= self.states.len() {
+            return Vec::new();
+        }
+        let mut down = Vec::new();
+        let mut common = Some(target);
+        while let Some(state) = common {
+            if self.is_ancestor(state, self.current) {
+                break;
+            }
+            down.push(state);
+            common = self.states.get(state).and_then(|state| state.parent);
+        }
+        let Some(common) = common else {
+            return Vec::new();
+        };
+        let mut steps = Vec::new();
+        while self.current != common {
+            match self.undo() {
+                Some(step) => steps.push(step),
+                None => break,
+            }
+        }
+        for state in down.into_iter().rev() {
+            if let Some(parent) = self.states.get_mut(self.current) {
+                parent.redo = Some(state);
+            }
+            match self.redo() {
+                Some(step) => steps.push(step),
+                None => break,
+            }
+        }
+        steps
+    }
+
+    /// Remembers the current state as the one on disk.
+    pub fn mark_saved(&mut self) {
+        self.saved = Some(self.current);
+        self.saved_pending = !self.pending.is_empty();
+        if let Some(state) = self.states.get_mut(self.current) {
+            state.open = false;
+        }
+    }
+
+    /// Returns whether the document is as it was last saved.
+    #[must_use]
+    pub fn is_saved(&self) -> bool {
+        self.saved == Some(self.current) && self.pending.is_empty()
+    }
+
+    /// Draws the tree as one line per state, each with its index. The
+    /// newest child of a state follows it, while older branches are indented
+    /// above.
+    #[must_use]
+    pub fn lines(&self) -> Vec<(usize, String)> {
+        let mut lines = Vec::new();
+        let mut stack = vec![(0, 0)];
+        while let Some((index, depth)) = stack.pop() {
+            let Some(state) = self.states.get(index) else {
+                continue;
+            };
+            let marker = if index == self.current { "●" } else { "○" };
+            let label = if index == 0 {
+                "original".to_string()
+            } else {
+                blame::age(state.time)
+            };
+            let saved = if self.saved == Some(index) {
+                " (saved)"
+            } else {
+                ""
+            };
+            lines.push((
+                index,
+                format!("{}{marker} {index:<4} {label}{saved}", "│ ".repeat(depth)),
+            ));
+            // Pushed in reverse, so older branches come first and the newest
+            // child last, in line with its parent.
+            if let Some((newest, older)) = state.children.split_last() {
+                stack.push((*newest, depth));
+                for child in older.iter().rev() {
+                    stack.push((*child, depth.saturating_add(1)));
+                }
+            }
+        }
+        lines
+    }
+
+    #[must_use]
+    pub fn current(&self) -> usize {
+        self.current
+    }
+
+    /// Returns how many states are kept, the original one included.
+    #[must_use]
+    pub fn state_count(&self) -> usize {
+        self.states.len()
+    }
+
+    /// Returns roughly how many bytes the edits kept for undo take.
+    #[must_use]
+    pub fn memory(&self) -> usize {
+        self.memory.saturating_add(edits_size(&self.pending))
+    }
+
+    /// Returns when the current state was last reached, to compare with
+    /// other documents: the larger, the more recent.
+    #[must_use]
+    pub fn last_used(&self) -> u64 {
+        self.states.get(self.current).map_or(0, |state| state.used)
+    }
+
+    /// Drops the states used least recently until no more than `depth` are
+    /// left and their edits take no more than `memory` bytes. Only the
+    /// tips of branches off the way to the current state can go, and the
+    /// root, whose child then becomes the oldest state undo reaches. Returns
+    /// how many states were dropped.
+    pub fn trim(&mut self, depth: usize, memory: usize) -> usize {
+        let depth = depth.max(1);
+        if self.states.len() <= depth && self.memory <= memory {
+            return 0;
+        }
+        let mut on_path = vec![false; self.states.len()];
+        let mut state = Some(self.current);
+        while let Some(index) = state {
+            if let Some(on_path) = on_path.get_mut(index) {
+                *on_path = true;
+            }
+            state = self.states.get(index).and_then(|state| state.parent);
+        }
+        let mut children: Vec<usize> = self
+            .states
+            .iter()
+            .map(|state| state.children.len())
+            .collect();
+        let mut kept = vec![true; self.states.len()];
+        let mut root = 0;
+        let can_drop = |index: usize, root: usize, kept: &[bool], children: &[usize]| {
+            let children = children.get(index).copied().unwrap_or_default();
+            index != self.current
+                && kept.get(index) == Some(&true)
+                && if index == root {
+                    children == 1
+                } else {
+                    children == 0 && on_path.get(index) == Some(&false)
+                }
+        };
+        let mut candidates: BinaryHeap<Reverse<(u64, usize)>> = self
+            .states
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| can_drop(*index, root, &kept, &children))
+            .map(|(index, state)| Reverse((state.used, index)))
+            .collect();
+        let (mut count, mut size) = (self.states.len(), self.memory);
+        while count > depth || size > memory {
+            let Some(Reverse((_, index))) = candidates.pop() else {
+                break;
+            };
+            if !can_drop(index, root, &kept, &children) {
+                continue;
+            }
+            if let Some(kept) = kept.get_mut(index) {
+                *kept = false;
+            }
+            count = count.saturating_sub(1);
+            // The edits of the root went with it when it became the root.
+            if index != root {
+                size = size.saturating_sub(self.size(index));
+            }
+            let Some(state) = self.states.get(index) else {
+                continue;
+            };
+            let next = if index == root {
+                // The edits of the new root are dropped with the old one.
+                let child = state
+                    .children
+                    .iter()
+                    .copied()
+                    .find(|child| kept.get(*child) == Some(&true));
+                if let Some(child) = child {
+                    root = child;
+                    size = size.saturating_sub(self.size(child));
+                }
+                child
+            } else {
+                if let Some(children) = state.parent.and_then(|parent| children.get_mut(parent)) {
+                    *children = children.saturating_sub(1);
+                }
+                state.parent
+            };
+            if let Some(next) = next.filter(|next| can_drop(*next, root, &kept, &children)) {
+                let used = self.states.get(next).map_or(0, |state| state.used);
+                candidates.push(Reverse((used, next)));
+            }
+        }
+        let dropped = self.states.len().saturating_sub(count);
+        if dropped > 0 {
+            let (states, indices) = renumber(std::mem::take(&mut self.states), &kept);
+            let new_index = |old: usize| indices.get(old).copied().flatten();
+            self.states = states;
+            self.current = new_index(self.current).unwrap_or(0);
+            self.saved = self.saved.and_then(new_index);
+            self.memory = (0..self.states.len()).map(|state| self.size(state)).sum();
+        }
+        dropped
+    }
+
+    /// Reads the history saved for `file_name` when it was last saved with
+    /// the text `lines`, so its edits can be undone in this session too.
+    #[must_use]
+    pub fn load<'a>(file_name: &str, lines: impl Iterator<Item = &'a str>) -> Option<Self> {
+        let path = key(file_name);
+        let contents = fs::read_to_string(store_path(&path)?).ok()?;
+        let history: History = serde_json::from_str(&contents).ok()?;
+        if history.path != path || history.checksum != checksum(lines) || history.expires < now() {
+            return None;
+        }
+        if history.current >= history.states.len() {
+            return None;
+        }
+        let memory = history
+            .states
+            .iter()
+            .map(|state| edits_size(&state.edits))
+            .sum();
+        Some(Self {
+            states: history.states,
+            current: history.current,
+            saved: Some(history.current),
+            saved_pending: false,
+            pending: Vec::new(),
+            memory,
+        })
+    }
+
+    /// Saves the history for `file_name`, which now reads `lines`, dropping
+    /// states beyond `limits`, oldest first. Failing to do so is not worth
+    /// bothering the user with, so errors are ignored.
+    pub fn store<'a>(&self, file_name: &str, lines: impl Iterator<Item = &'a str>, limits: Limits) {
+        let path = key(file_name);
+        let Some(store) = store_path(&path) else {
+            return;
+        };
+        // Edits not made into a state yet are not part of the text saved.
+        if limits.size == 0 || !self.pending.is_empty() {
+            let _ = fs::remove_file(store);
+            return;
+        }
+        let (states, current) = self.trimmed(limits);
+        let history = History {
+            path,
+            checksum: checksum(lines),
+            expires: now().saturating_add(limits.age),
+            states,
+            current,
+        };
+        if let (Some(directory), Ok(contents)) = (store.parent(), serde_json::to_string(&history)) {
+            let _ = fs::create_dir_all(directory);
+            let _ = fs::write(store, contents);
+        }
+    }
+
+    /// Returns the states within `limits` and the index of the current one
+    /// among them. The tree is cut above a state on the way to the current
+    /// one, which becomes the new root: the oldest reached within the age
+    /// limit, moved closer to the current state until the edits left fit
+    /// the size limit.
+    fn trimmed(&self, limits: Limits) -> (Vec<State>, usize) {
+        let mut path = vec![self.current];
+        while let Some(parent) = path
+            .last()
+            .and_then(|state| self.states.get(*state))
+            .and_then(|state| state.parent)
+        {
+            path.push(parent);
+        }
+        path.reverse();
+        let cutoff = now().saturating_sub(limits.age);
+        let mut root = path
+            .iter()
+            .position(|state| {
+                self.states
+                    .get(*state)
+                    .is_some_and(|state| state.time >= cutoff)
+            })
+            .map_or(path.len().saturating_sub(1), |first| {
+                first.saturating_sub(1)
+            });
+        let mut kept = self.descendants(path.get(root).copied().unwrap_or(self.current));
+        while kept.iter().map(|state| self.size(*state)).sum::<usize>() > limits.size {
+            if root.saturating_add(1) >= path.len() {
+                kept = vec![self.current];
+                break;
+            }
+            root = root.saturating_add(1);
+            kept = self.descendants(path.get(root).copied().unwrap_or(self.current));
+        }
+        let mut mask = vec![false; self.states.len()];
+        for state in kept {
+            if let Some(keep) = mask.get_mut(state) {
+                *keep = true;
+            }
+        }
+        let (mut states, indices) = renumber(self.states.clone(), &mask);
+        for state in &mut states {
+            state.open = false;
+        }
+        (
+            states,
+            indices.get(self.current).copied().flatten().unwrap_or(0),
+        )
+    }
+
+    /// Returns `state` and every state below it.
+    fn descendants(&self, state: usize) -> Vec<usize> {
+        let mut found = Vec::new();
+        let mut stack = vec![state];
+        while let Some(state) = stack.pop() {
+            found.push(state);
+            if let Some(state) = self.states.get(state) {
+                stack.extend(&state.children);
+            }
+        }
+        found
+    }
+
+    /// Returns roughly how many bytes the edits of `state` take.
+    fn size(&self, state: usize) -> usize {
+        self.states
+            .get(state)
+            .map_or(0, |state| edits_size(&state.edits))
+    }
+
+    fn is_ancestor(&self, ancestor: usize, mut state: usize) -> bool {
+        loop {
+            if state == ancestor {
+                return true;
+            }
+            match self.states.get(state).and_then(|state| state.parent) {
+                Some(parent) => state = parent,
+                None => return false,
+            }
+        }
+    }
+}
+
+/// Keeps the `states` marked in `kept`, in order, with their links to one
+/// another renumbered. The first becomes the root, with no edits of its own.
+/// Returns them and the new index of each old state.
+fn renumber(states: Vec<State>, kept: &[bool]) -> (Vec<State>, Vec<Option<usize>>) {
+    let mut indices = Vec::with_capacity(kept.len());
+    let mut count = 0_usize;
+    for keep in kept {
+        indices.push(keep.then_some(count));
+        if *keep {
+            count = count.saturating_add(1);
+        }
+    }
+    let new_index = |old: usize| indices.get(old).copied().flatten();
+    let states = states
+        .into_iter()
+        .zip(kept)
+        .filter(|(_, keep)| **keep)
+        .map(|(mut state, _)| {
+            state.parent = state.parent.and_then(new_index);
+            state.children = state
+                .children
+                .iter()
+                .filter_map(|child| new_index(*child))
+                .collect();
+            state.redo = state.redo.and_then(new_index);
+            if state.parent.is_none() {
+                state.edits = Vec::new();
+            }
+            state
+        })
+        .collect();
+    (states, indices)
+}
+
+fn edits_size(edits: &[Edit]) -> usize {
+    edits
+        .iter()
+        .flat_map(|edit| edit.removed.iter().chain(&edit.inserted))
+        .map(String::len)
+        .sum()
+}
+
+fn next_use() -> u64 {
+    USES.fetch_add(1, Ordering::Relaxed).saturating_add(1)
+}
+
+/// Merges the last two edits if the last only changed the row the one
+/// before left. Typing in a row changes it over and over, and only its first
+/// and last text are worth keeping.
+fn coalesce(edits: &mut Vec<Edit>) {
+    let [.., previous, last] = edits.as_slice() else {
+        return;
+    };
+    if previous.at == last.at
+        && previous.inserted.len() == 1
+        && last.removed.len() == 1
+        && last.inserted.len() == 1
+        && previous.inserted == last.removed
+    {
+        if let Some(last) = edits.pop() {
+            if let Some(previous) = edits.last_mut() {
+                previous.inserted = last.inserted;
+            }
+        }
+    }
+}
+
+/// Returns the FNV-1a hash of the lines, which unlike the standard library's
+/// hasher stays the same from one build to the next.
+pub fn checksum<'a>(lines: impl Iterator<Item = &'a str>) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for line in lines {
+        for byte in line.bytes().chain([b'\n']) {
+            hash ^= u64::from(byte);
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+    hash
+}
+
+/// Files are remembered by their absolute path, so history survives opening
+/// the same file from another directory.
+fn key(file_name: &str) -> String {
+    fs::canonicalize(file_name)
+        .unwrap_or_else(|_| Path::new(file_name).to_path_buf())
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Returns where the history of the file at `path` is kept: `undo/` in the
+/// data directory, in a file named after a checksum of the path.
+fn store_path(path: &str) -> Option<PathBuf> {
+    let name = format!("{:016x}.json", checksum([path].into_iter()));
+    Some(paths::data_dir()?.join("undo").join(name))
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs())
+}
@@ -0,0 +1,151 @@
+use crate::changes::Change;
+use crate::Document;
+use crate::Position;
+use std::collections::BTreeMap;
+use std::sync::mpsc::{Receiver, TryRecvError};
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Where the cursor is in a document and which part of it is scrolled into
+/// sight. A document can be shown in more than one view at a time.
+#[derive(Default, Clone)]
+pub struct View {
+    pub cursor: Position,
+    /// The first row and column shown.
+    pub offset: Position,
+    /// The screen column moves up and down aim for, and where the cursor
+    /// was left by the last of them. Moving through shorter lines keeps it,
+    /// so the cursor goes back to it on a longer one.
+    pub goal: Option<(Position, usize)>,
+    /// The tab width set for the document shown, over the editor's.
+    pub tab_width: Option<usize>,
+    /// Which of the [`views::OPTIONS`](crate::views::OPTIONS) were set for
+    /// the document shown, over the editor's.
+    pub options: BTreeMap<String, bool>,
+}
+
+impl View {
+    /// Moves the cursor and the rows shown along with `change`, made to the
+    /// document in another view, so they stay on the same text.
+    pub fn follow(&mut self, change: &Change) {
+        follow(&mut self.cursor, change);
+        let span = change.span();
+        if self.offset.y >= span.old_end {
+            self.offset.y = self
+                .offset
+                .y
+                .saturating_sub(span.old_end)
+                .saturating_add(span.new_end);
+        } else if self.offset.y >= span.new_end {
+            self.offset.y = span.new_end.saturating_sub(1);
+        }
+    }
+}
+
+/// Moves `position` along with `change`. Positions in text that was removed
+/// go to where it was.
+fn follow(position: &mut Position, change: &Change) {
+    match change {
+        Change::Inserted { at, text } => {
+            if position.y != at.y {
+                if position.y > at.y {
+                    position.y = position.y.saturating_add(text.matches('\n').count());
+                }
+                return;
+            }
+            if position.x < at.x {
+                return;
+            }
+            let mut lines = text.split('\n');
+            let first = lines.next().unwrap_or_default();
+            match lines.next_back() {
+                None => position.x = position.x.saturating_add(first.graphemes(true).count()),
+                Some(last) => {
+                    position.y = position.y.saturating_add(text.matches('\n').count());
+                    position.x = position
+                        .x
+                        .saturating_sub(at.x)
+                        .saturating_add(last.graphemes(true).count());
+                }
+            }
+        }
+        Change::Deleted { start, end } => {
+            if (position.y, position.x) < (start.y, start.x) {
+                return;
+            }
+            if (position.y, position.x) < (end.y, end.x) {
+                *position = start.clone();
+            } else if position.y == end.y {
+                position.x = position.x.saturating_sub(end.x).saturating_add(start.x);
+                position.y = start.y;
+            } else {
+                position.y = position.y.saturating_sub(end.y.saturating_sub(start.y));
+            }
+        }
+        Change::Split { at } => {
+            if position.y == at.y && position.x >= at.x {
+                position.y = position.y.saturating_add(1);
+                position.x = position.x.saturating_sub(at.x);
+            } else if position.y > at.y {
+                position.y = position.y.saturating_add(1);
+            }
+        }
+        Change::Joined { at } => {
+            if position.y == at.y.saturating_add(1) {
+                position.y = at.y;
+                position.x = position.x.saturating_add(at.x);
+            } else if position.y > at.y {
+                position.y = position.y.saturating_sub(1);
+            }
+        }
+        Change::Rows {
+            at,
+            removed,
+            inserted,
+        } => {
+            let end = at.saturating_add(*removed);
+            if position.y >= end {
+                position.y = position
+                    .y
+                    .saturating_sub(*removed)
+                    .saturating_add(inserted.len());
+            } else if position.y >= at.saturating_add(inserted.len()) {
+                *position = Position {
+                    x: 0,
+                    y: at.saturating_add(inserted.len()),
+                };
+            }
+        }
+    }
+}
+
+/// A second view of a document, shown above or below the view with focus,
+/// that keeps up with the edits made there.
+pub struct Split {
+    pub view: View,
+    changes: Receiver<Change>,
+    /// Whether it is shown above the view with focus.
+    pub above: bool,
+}
+
+impl Split {
+    /// Opens a view of `document` showing what `view` shows.
+    pub fn new(document: &mut Document, view: View) -> Self {
+        Self {
+            view,
+            changes: document.subscribe(),
+            above: false,
+        }
+    }
+
+    /// Follows the changes made to the document since the last call.
+    /// Returns `false` if the document is gone.
+    pub fn catch_up(&mut self) -> bool {
+        loop {
+            match self.changes.try_recv() {
+                Ok(change) => self.view.follow(&change),
+                Err(TryRecvError::Empty) => return true,
+                Err(TryRecvError::Disconnected) => return false,
+            }
+        }
+    }
+}
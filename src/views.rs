@@ -0,0 +1,106 @@
+use crate::paths;
+use crate::view::View;
+use crate::Position;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+type Store = BTreeMap<String, ViewState>;
+
+/// How many files have their view remembered. The ones left longest ago are
+/// forgotten first.
+const MAX_VIEWS: usize = 500;
+
+/// Options of the [`command::OPTIONS`](crate::command::OPTIONS) that change
+/// how a file is shown, and are remembered with its view.
+pub const OPTIONS: &[&str] = &["highlight", "number"];
+
+/// How a file was being viewed when it was last left, put back the next time
+/// it is opened.
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ViewState {
+    /// Where the cursor was, missing from views saved before it was kept.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<Position>,
+    pub tab_width: usize,
+    /// The first row and column shown.
+    pub offset: Position,
+    /// Each of the [`OPTIONS`] set for the file, on or off.
+    pub options: BTreeMap<String, bool>,
+    /// When the file was left, in seconds since the Unix epoch.
+    pub left: u64,
+}
+
+impl From<&View> for ViewState {
+    fn from(view: &View) -> Self {
+        Self {
+            cursor: Some(view.cursor.clone()),
+            tab_width: view.tab_width.unwrap_or(0),
+            offset: view.offset.clone(),
+            options: view.options.clone(),
+            left: 0,
+        }
+    }
+}
+
+impl ViewState {
+    /// Reads the view saved for `file_name`, if there is one.
+    #[must_use]
+    pub fn load(file_name: &str) -> Option<Self> {
+        read_store().remove(&key(file_name))
+    }
+}
+
+/// Saves the view of each file, replacing the one saved before, in one
+/// write. Views of files that are gone are dropped, and so are the oldest
+/// past [`MAX_VIEWS`]. Errors are ignored, as for marks.
+pub fn store<'a>(views: impl IntoIterator<Item = (&'a str, ViewState)>) {
+    let Some(path) = store_path() else {
+        return;
+    };
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs());
+    let mut store = read_store();
+    for (file_name, mut view) in views {
+        view.left = now;
+        store.insert(key(file_name), view);
+    }
+    store.retain(|file, _| Path::new(file).exists());
+    if store.len() > MAX_VIEWS {
+        let mut left: Vec<u64> = store.values().map(|view| view.left).collect();
+        left.sort_unstable();
+        let oldest_kept = left
+            .get(left.len().saturating_sub(MAX_VIEWS))
+            .copied()
+            .unwrap_or(0);
+        store.retain(|_, view| view.left >= oldest_kept);
+    }
+    if let (Some(directory), Ok(contents)) = (path.parent(), toml::to_string(&store)) {
+        let _ = fs::create_dir_all(directory);
+        let _ = fs::write(path, contents);
+    }
+}
+
+/// Returns `views.toml` in the state directory.
+fn store_path() -> Option<PathBuf> {
+    Some(paths::state_dir()?.join("views.toml"))
+}
+
+fn read_store() -> Store {
+    store_path()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|contents| toml::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+/// Files are remembered by their absolute path, as marks are.
+fn key(file_name: &str) -> String {
+    fs::canonicalize(file_name)
+        .unwrap_or_else(|_| Path::new(file_name).to_path_buf())
+        .to_string_lossy()
+        .into_owned()
+}
@@ -0,0 +1,251 @@
+use crate::plugins::{Action, Context};
+use std::fs;
+use std::path::Path;
+use wasmi::{Caller, Config, Engine, Extern, Instance, Linker, Module, Store};
+
+/// Fuel a plugin gets for each call, about one unit per instruction, so one
+/// stuck in a loop is stopped instead of hanging the editor.
+const FUEL: u64 = 100_000_000;
+
+/// What the host functions work on during a call.
+#[derive(Default)]
+struct Host {
+    lines: Vec<String>,
+    cursor: (usize, usize),
+    actions: Vec<Action>,
+    commands: Vec<(String, String)>,
+}
+
+/// A WebAssembly plugin, run by an interpreter that keeps it from touching
+/// anything but its own memory and the functions below.
+///
+/// The module exports its `memory`, `hecto_alloc(len) -> ptr` for the editor
+/// to pass it strings, `hecto_command(name_ptr, name_len, args_ptr,
+/// args_len)` to run the commands it added, and optionally `hecto_init()`,
+/// called once loaded. Strings are UTF-8, passed as a pointer and a length.
+///
+/// It may import from the `hecto` module, counting lines and columns from
+/// zero:
+///
+/// - `register_command(name_ptr, name_len, description_ptr, description_len)`
+/// - `line_count() -> i32`
+/// - `read_line(line, ptr, capacity) -> i32`, which copies as much of the
+///   line as fits and returns its full length, or -1 past the end
+/// - `cursor_line() -> i32` and `cursor_column() -> i32`
+/// - `insert(ptr, len)`, `replace_line(line, ptr, len)`, `message(ptr, len)`
+///   and `execute(ptr, len)`, carried out once the call returns
+pub struct WasmPlugin {
+    name: String,
+    store: Store<Host>,
+    instance: Instance,
+}
+
+impl WasmPlugin {
+    /// Loads the module at `path` and calls its `hecto_init`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a message if the module cannot be read, is not valid, needs
+    /// imports the editor does not offer, or fails to initialize.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let bytes = fs::read(path).map_err(|error| error.to_string())?;
+        let mut config = Config::default();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config);
+        let module = Module::new(&engine, &bytes).map_err(|error| error.to_string())?;
+        let mut store = Store::new(&engine, Host::default());
+        let linker = linker(&engine).map_err(|error| error.to_string())?;
+        store.set_fuel(FUEL).map_err(|error| error.to_string())?;
+        let instance = linker
+            .instantiate(&mut store, &module)
+            .and_then(|instance| instance.start(&mut store))
+            .map_err(|error| error.to_string())?;
+        let mut plugin = Self {
+            name: path
+                .file_stem()
+                .map_or_else(String::new, |name| name.to_string_lossy().into_owned()),
+            store,
+            instance,
+        };
+        if let Ok(init) = plugin
+            .instance
+            .get_typed_func::<(), ()>(&plugin.store, "hecto_init")
+        {
+            init.call(&mut plugin.store, ())
+                .map_err(|error| error.to_string())?;
+        }
+        Ok(plugin)
+    }
+
+    /// Returns the commands the plugin added, with their descriptions.
+    #[must_use]
+    pub fn commands(&self) -> &[(String, String)] {
+        &self.store.data().commands
+    }
+
+    /// Runs the plugin's command `name` with `args`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a message if the plugin trapped or ran out of fuel.
+    pub fn run_command(
+        &mut self,
+        name: &str,
+        args: &str,
+        context: &Context,
+    ) -> Result<Vec<Action>, String> {
+        let host = self.store.data_mut();
+        let document = context.document;
+        host.lines = (0..document.len())
+            .filter_map(|y| document.row(y))
+            .map(|row| row.as_str().to_string())
+            .collect();
+        host.cursor = (context.cursor.y, context.cursor.x);
+        host.actions.clear();
+        self.store
+            .set_fuel(FUEL)
+            .map_err(|error| error.to_string())?;
+        let result = self.call_command(name, args);
+        let host = self.store.data_mut();
+        host.lines = Vec::new();
+        let actions = std::mem::take(&mut host.actions);
+        result.map_err(|error| {
+            log::warn!("Plugin {} failed: {error}", self.name);
+            error.to_string()
+        })?;
+        Ok(actions)
+    }
+
+    fn call_command(&mut self, name: &str, args: &str) -> Result<(), wasmi::Error> {
+        let command = self
+            .instance
+            .get_typed_func::<(i32, i32, i32, i32), ()>(&self.store, "hecto_command")?;
+        let (name_ptr, name_len) = self.pass(name)?;
+        let (args_ptr, args_len) = self.pass(args)?;
+        command.call(&mut self.store, (name_ptr, name_len, args_ptr, args_len))
+    }
+
+    /// Copies `text` into memory the plugin allocated for it.
+    fn pass(&mut self, text: &str) -> Result<(i32, i32), wasmi::Error> {
+        let len = i32::try_from(text.len()).map_err(|_| wasmi::Error::new("string too long"))?;
+        let alloc = self
+            .instance
+            .get_typed_func::<i32, i32>(&self.store, "hecto_alloc")?;
+        let ptr = alloc.call(&mut self.store, len)?;
+        let memory = self
+            .instance
+            .get_memory(&self.store, "memory")
+            .ok_or_else(|| wasmi::Error::new("no memory exported"))?;
+        memory
+            .write(&mut self.store, offset(ptr)?, text.as_bytes())
+            .map_err(|error| wasmi::Error::new(error.to_string()))?;
+        Ok((ptr, len))
+    }
+}
+
+fn offset(ptr: i32) -> Result<usize, wasmi::Error> {
+    usize::try_from(ptr).map_err(|_| wasmi::Error::new("negative pointer"))
+}
+
+fn read_string(caller: &Caller<'_, Host>, ptr: i32, len: i32) -> Result<String, wasmi::Error> {
+    let memory = caller
+        .get_export("memory")
+        .and_then(Extern::into_memory)
+        .ok_or_else(|| wasmi::Error::new("no memory exported"))?;
+    let mut buffer = vec![0; offset(len)?];
+    memory
+        .read(caller, offset(ptr)?, &mut buffer)
+        .map_err(|error| wasmi::Error::new(error.to_string()))?;
+    String::from_utf8(buffer).map_err(|_| wasmi::Error::new("string is not UTF-8"))
+}
+
+fn to_i32(value: usize) -> i32 {
+    i32::try_from(value).unwrap_or(i32::MAX)
+}
+
+/// Defines the functions plugins may import.
+fn linker(engine: &Engine) -> Result<Linker<Host>, wasmi::errors::LinkerError> {
+    let mut linker = Linker::new(engine);
+    linker.func_wrap(
+        "hecto",
+        "register_command",
+        |mut caller: Caller<'_, Host>, name_ptr: i32, name_len: i32, ptr: i32, len: i32| {
+            let name = read_string(&caller, name_ptr, name_len)?;
+            let description = read_string(&caller, ptr, len)?;
+            caller.data_mut().commands.push((name, description));
+            Ok(())
+        },
+    )?;
+    linker.func_wrap("hecto", "line_count", |caller: Caller<'_, Host>| {
+        to_i32(caller.data().lines.len())
+    })?;
+    linker.func_wrap(
+        "hecto",
+        "read_line",
+        |mut caller: Caller<'_, Host>, line: i32, ptr: i32, capacity: i32| {
+            let Some(text) = usize::try_from(line)
+                .ok()
+                .and_then(|line| caller.data().lines.get(line))
+                .cloned()
+            else {
+                return Ok(-1);
+            };
+            let copied = text.len().min(offset(capacity)?);
+            let memory = caller
+                .get_export("memory")
+                .and_then(Extern::into_memory)
+                .ok_or_else(|| wasmi::Error::new("no memory exported"))?;
+            memory
+                .write(&mut caller, offset(ptr)?, &text.as_bytes()[..copied])
+                .map_err(|error| wasmi::Error::new(error.to_string()))?;
+            Ok(to_i32(text.len()))
+        },
+    )?;
+    linker.func_wrap("hecto", "cursor_line", |caller: Caller<'_, Host>| {
+        to_i32(caller.data().cursor.0)
+    })?;
+    linker.func_wrap("hecto", "cursor_column", |caller: Caller<'_, Host>| {
+        to_i32(caller.data().cursor.1)
+    })?;
+    linker.func_wrap(
+        "hecto",
+        "insert",
+        |mut caller: Caller<'_, Host>, ptr: i32, len: i32| {
+            let text = read_string(&caller, ptr, len)?;
+            caller.data_mut().actions.push(Action::Insert(text));
+            Ok(())
+        },
+    )?;
+    linker.func_wrap(
+        "hecto",
+        "replace_line",
+        |mut caller: Caller<'_, Host>, line: i32, ptr: i32, len: i32| {
+            let text = read_string(&caller, ptr, len)?;
+            let line = offset(line)?;
+            caller
+                .data_mut()
+                .actions
+                .push(Action::ReplaceLine(line, text));
+            Ok(())
+        },
+    )?;
+    linker.func_wrap(
+        "hecto",
+        "message",
+        |mut caller: Caller<'_, Host>, ptr: i32, len: i32| {
+            let text = read_string(&caller, ptr, len)?;
+            caller.data_mut().actions.push(Action::Message(text));
+            Ok(())
+        },
+    )?;
+    linker.func_wrap(
+        "hecto",
+        "execute",
+        |mut caller: Caller<'_, Host>, ptr: i32, len: i32| {
+            let text = read_string(&caller, ptr, len)?;
+            caller.data_mut().actions.push(Action::Execute(text));
+            Ok(())
+        },
+    )?;
+    Ok(linker)
+}
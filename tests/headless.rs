@@ -0,0 +1,1223 @@
+use hecto::{Backend, Background, Editor, Event, Headless, Options, Size, Theme};
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, Once, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant};
+use termion::color::{Bg, Fg};
+use termion::event::Key;
+
+/// Points the editor's state and history at the tests' own, once, before any
+/// test reads them.
+static STATE: Once = Once::new();
+
+/// Held while an editor runs: they share their state, such as the views and
+/// breakpoints remembered, and would otherwise lose each other's.
+static RUNNING: Mutex<()> = Mutex::new(());
+
+/// Returns a directory of its own for `test`, and keeps the editor's state
+/// and history out of the user's home.
+fn scratch(test: &str) -> PathBuf {
+    let root = env::temp_dir().join(format!("hecto-tests-{}", std::process::id()));
+    STATE.call_once(|| {
+        env::set_var("XDG_STATE_HOME", root.join("state"));
+        env::set_var("XDG_DATA_HOME", root.join("data"));
+    });
+    let directory = root.join(test);
+    fs::create_dir_all(&directory).unwrap();
+    directory
+}
+
+fn keys(text: &str) -> impl Iterator<Item = Event> + '_ {
+    text.chars().map(|c| Event::Key(Key::Char(c)))
+}
+
+/// Runs the editor on `file`, with the default settings in English, until
+/// `events` run out, and returns the screen it drew on. Each frame is drawn
+/// at an [`Event::Tick`].
+fn run(directory: &Path, file: &str, width: u16, height: u16, events: Vec<Event>) -> Rc<Headless> {
+    run_with(directory, "", file, width, height, events)
+}
+
+/// Like [`run`], with `settings` added to the configuration.
+fn run_with(
+    directory: &Path,
+    settings: &str,
+    file: &str,
+    width: u16,
+    height: u16,
+    events: Vec<Event>,
+) -> Rc<Headless> {
+    let config = directory.join("config.toml");
+    let settings = format!("language = \"en\"\nundo_history_size = 0\n{settings}");
+    fs::write(&config, settings).unwrap();
+    let terminal = Rc::new(Headless::new(width, height, events));
+    let options = Options {
+        files: vec![directory.join(file).to_string_lossy().into_owned()],
+        config: Some(config),
+        ..Options::default()
+    };
+    let running = RUNNING.lock().unwrap_or_else(PoisonError::into_inner);
+    let mut editor = Editor::with_options(Box::new(terminal.clone()), options);
+    editor.run().unwrap();
+    drop(running);
+    terminal
+}
+
+/// Counts the files [`run_fresh`] made, to name each one differently.
+static FRESH_FILES: AtomicUsize = AtomicUsize::new(0);
+
+/// Like [`run_with`], on a new file holding `text`, named with `extension`,
+/// so the cursor is not put back where an earlier run left it. Returns the
+/// screen and what the file holds afterwards.
+fn run_fresh(
+    directory: &Path,
+    settings: &str,
+    extension: &str,
+    text: &str,
+    (width, height): (u16, u16),
+    events: Vec<Event>,
+) -> (Rc<Headless>, String) {
+    let file = format!(
+        "fresh{}.{extension}",
+        FRESH_FILES.fetch_add(1, Ordering::Relaxed)
+    );
+    fs::write(directory.join(&file), text).unwrap();
+    let terminal = run_with(directory, settings, &file, width, height, events);
+    let text = fs::read_to_string(directory.join(&file)).unwrap();
+    (terminal, text)
+}
+
+/// Presses `keys`, then marks where they left the cursor with a `|` and
+/// saves, to find it in the file.
+fn marked(keys: &[Key]) -> Vec<Event> {
+    keys.iter()
+        .copied()
+        .chain([Key::Char('|'), Key::Ctrl('s')])
+        .map(Event::Key)
+        .collect()
+}
+
+/// Returns the last frame drawn before the editor said goodbye.
+fn last_frame(terminal: &Headless) -> Vec<String> {
+    terminal
+        .frames()
+        .into_iter()
+        .rev()
+        .find(|frame| frame.first().is_some_and(|line| line != "Goodbye."))
+        .unwrap_or_default()
+}
+
+#[test]
+fn typing_and_saving() {
+    let directory = scratch("typing_and_saving");
+    fs::write(directory.join("notes.txt"), "world\n").unwrap();
+    let mut events: Vec<Event> = keys("hello ").collect();
+    events.push(Event::Key(Key::Ctrl('s')));
+    events.push(Event::Tick);
+    let terminal = run(&directory, "notes.txt", 40, 6, events);
+    assert_eq!(last_frame(&terminal)[0], "hello world");
+    let text = fs::read_to_string(directory.join("notes.txt")).unwrap();
+    assert_eq!(text, "hello world\n");
+}
+
+#[test]
+fn searching_moves_the_cursor_to_the_match() {
+    let directory = scratch("searching");
+    fs::write(directory.join("list.txt"), "one\ntwo\nthree\n").unwrap();
+    let mut events = vec![Event::Key(Key::Ctrl('f'))];
+    events.extend(keys("ree\n"));
+    events.push(Event::Key(Key::Char('!')));
+    events.push(Event::Tick);
+    let terminal = run(&directory, "list.txt", 40, 6, events);
+    assert_eq!(last_frame(&terminal)[2], "th!ree");
+}
+
+#[test]
+fn the_last_search_is_repeated_either_way_around_the_document() {
+    let directory = scratch("search-again");
+    fs::write(directory.join("list.txt"), "ab\nab\nab\n").unwrap();
+    let mut events = vec![Event::Key(Key::Alt('n')), Event::Tick];
+    events.push(Event::Key(Key::Ctrl('f')));
+    events.extend(keys("ab\n"));
+    events.extend([
+        Event::Key(Key::F(3)),
+        Event::Tick,
+        Event::Key(Key::F(3)),
+        Event::Key(Key::F(3)),
+        Event::Tick,
+        Event::Key(Key::Alt('N')),
+        Event::Key(Key::Char('|')),
+        Event::Key(Key::Ctrl('s')),
+    ]);
+    let terminal = run(&directory, "list.txt", 40, 6, events);
+    let messages: Vec<String> = terminal
+        .frames()
+        .iter()
+        .map(|frame| frame[5].clone())
+        .filter(|message| message.starts_with("No previous") || message.starts_with("Match"))
+        .collect();
+    assert_eq!(
+        messages,
+        [
+            "No previous search.",
+            "Match 2 of 3.",
+            "Match 1 of 3, wrapped around."
+        ]
+    );
+    assert_eq!(
+        fs::read_to_string(directory.join("list.txt")).unwrap(),
+        "ab\nab\n|ab\n"
+    );
+}
+
+#[test]
+fn inspecting_tells_what_the_character_under_the_cursor_is_made_of() {
+    let directory = scratch("inspect");
+    fs::write(directory.join("accent.txt"), "e\u{301}!\n").unwrap();
+    let mut events = vec![Event::Key(Key::Ctrl('p'))];
+    events.extend(keys("inspect\n"));
+    events.push(Event::Tick);
+    let terminal = run(&directory, "accent.txt", 100, 6, events);
+    assert_eq!(
+        last_frame(&terminal)[5],
+        "U+0065 LATIN SMALL LETTER E, U+0301 COMBINING ACUTE ACCENT | bytes 65 CC 81 | width 1"
+    );
+}
+
+#[test]
+fn paths_in_the_text_complete_from_the_files_next_to_it() {
+    let directory = scratch("path-completion");
+    fs::create_dir_all(directory.join("include")).unwrap();
+    fs::write(directory.join("include/header.h"), "").unwrap();
+    fs::write(directory.join("main.txt"), "#include \"inc\n").unwrap();
+    let mut events = vec![Event::Key(Key::End)];
+    events.extend(keys("lude/"));
+    events.push(Event::Key(Key::Alt('/')));
+    events.extend(keys("\n\""));
+    events.push(Event::Key(Key::Ctrl('s')));
+    run(&directory, "main.txt", 40, 6, events);
+    assert_eq!(
+        fs::read_to_string(directory.join("main.txt")).unwrap(),
+        "#include \"include/header.h\"\n"
+    );
+}
+
+#[test]
+fn text_that_matches_no_path_is_left_to_the_language_server() {
+    let directory = scratch("path-fallback");
+    fs::write(directory.join("sum.txt"), "total = a/b\n").unwrap();
+    let events = vec![Event::Key(Key::End), Event::Key(Key::Alt('/')), Event::Tick];
+    let terminal = run(&directory, "sum.txt", 60, 6, events);
+    assert_eq!(
+        last_frame(&terminal)[5],
+        "No language server for No filetype files."
+    );
+}
+
+#[test]
+fn resizing_redraws_at_the_new_size() {
+    let directory = scratch("resizing");
+    fs::write(directory.join("wide.txt"), "a line wider than the screen\n").unwrap();
+    let events = vec![
+        Event::Tick,
+        Event::Resize {
+            width: 12,
+            height: 4,
+        },
+        Event::Tick,
+    ];
+    let terminal = run(&directory, "wide.txt", 40, 8, events);
+    let frame = last_frame(&terminal);
+    assert_eq!(frame.len(), 4);
+    assert_eq!(frame[0], "a line wider");
+    assert!(frame.iter().all(|line| line.chars().count() <= 12));
+}
+
+#[test]
+fn edits_in_one_view_keep_the_other_on_its_text() {
+    let directory = scratch("split");
+    let text: String = (1..=30).map(|n| format!("line {n}\n")).collect();
+    fs::write(directory.join("lines.txt"), text).unwrap();
+    let split = || [Event::Key(Key::Ctrl('k')), Event::Key(Key::Char('w'))];
+    // Open a second view and go to line 20 in it, below the first.
+    let mut events: Vec<Event> = split().into_iter().chain(split()).collect();
+    events.push(Event::Key(Key::Ctrl('g')));
+    events.extend(keys("20\n"));
+    events.push(Event::Tick);
+    // Add a line at the top in the first.
+    events.extend(split());
+    events.extend(keys("new\n"));
+    events.push(Event::Tick);
+    let terminal = run(&directory, "lines.txt", 30, 14, events);
+    let frames = terminal.frames();
+    let before = &frames[frames.len() - 4];
+    let after = last_frame(&terminal);
+    assert_eq!(after[0], "new");
+    assert_eq!(after[1], "line 1");
+    assert!(after[5].ends_with("| 21/31"));
+    assert_eq!(after[6..12], before[6..12]);
+    assert_eq!(after[6], "line 17");
+}
+
+#[test]
+fn remapped_keys_are_translated_before_dispatch() {
+    let directory = scratch("remapping");
+    fs::write(directory.join("word.txt"), "word\n").unwrap();
+    let settings = "[remap]\n\"alt-l\" = \"right\"\n[mode_remap.insert]\n\"alt-l\" = \"end\"\n";
+    let events = vec![
+        // The user's remap comes before the mode's.
+        Event::Key(Key::Alt('l')),
+        Event::Key(Key::Alt('l')),
+        Event::Key(Key::Char('-')),
+        // Ctrl-H is taken as Backspace by default.
+        Event::Key(Key::Ctrl('h')),
+        Event::Key(Key::Ctrl('h')),
+        Event::Tick,
+    ];
+    let terminal = run_with(&directory, settings, "word.txt", 40, 6, events);
+    assert_eq!(last_frame(&terminal)[0], "wrd");
+}
+
+#[test]
+fn printing_to_a_file_heads_each_page() {
+    let directory = scratch("printing");
+    let text: String = (1..=100).map(|n| format!("line {n}\n")).collect();
+    fs::write(directory.join("long.txt"), text).unwrap();
+    let output = directory.join("long.ps");
+    let mut events = vec![Event::Key(Key::Ctrl('p'))];
+    events.extend(keys(&format!("print {}\n", output.display())));
+    events.push(Event::Tick);
+    let terminal = run(&directory, "long.txt", 60, 6, events);
+    assert!(last_frame(&terminal)[5].starts_with("Wrote 2 pages to "));
+    let postscript = fs::read_to_string(output).unwrap();
+    assert!(postscript.starts_with("%!PS-Adobe-3.0\n"));
+    assert!(postscript.contains("(Page 2 of 2) show"));
+    assert!(postscript.contains("(line 100) show"));
+}
+
+#[test]
+fn exported_state_reopens_buffers_where_they_were() {
+    let directory = scratch("state");
+    let text: String = (1..=20).map(|n| format!("line {n}\n")).collect();
+    fs::write(directory.join("a.txt"), text).unwrap();
+    fs::write(directory.join("b.txt"), "other\n").unwrap();
+    let state = directory.join("state.json");
+    let mut events = vec![Event::Key(Key::Down), Event::Key(Key::Down)];
+    events.push(Event::Key(Key::Ctrl('p')));
+    events.extend(keys(&format!("export_state {}\n", state.display())));
+    events.push(Event::Tick);
+    run(&directory, "a.txt", 40, 6, events);
+    let json = fs::read_to_string(&state).unwrap();
+    let mut json: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(json["buffers"][0]["cursor"]["y"], 2);
+    // Somewhere other than where the cursor was left, to tell the two apart.
+    json["buffers"][0]["cursor"]["y"] = 5.into();
+    fs::write(&state, json.to_string()).unwrap();
+
+    let mut events = vec![Event::Key(Key::Ctrl('p'))];
+    events.extend(keys(&format!("import_state {}\n", state.display())));
+    events.push(Event::Key(Key::Char('X')));
+    events.push(Event::Tick);
+    let terminal = run(&directory, "b.txt", 40, 8, events);
+    let frame = last_frame(&terminal);
+    assert_eq!(frame[5], "Xline 6");
+    assert!(frame[7].starts_with("Restored 1 buffers from "));
+}
+
+#[test]
+fn breakpoints_follow_their_line_and_are_remembered() {
+    let directory = scratch("breakpoints");
+    fs::write(directory.join("main.rs"), "one\ntwo\nthree\n").unwrap();
+    let events = vec![
+        Event::Key(Key::Down),
+        Event::Key(Key::F(9)),
+        Event::Key(Key::Up),
+        Event::Key(Key::Char('\n')),
+        Event::Key(Key::Ctrl('s')),
+        Event::Tick,
+    ];
+    let terminal = run(&directory, "main.rs", 30, 7, events);
+    let frame = last_frame(&terminal);
+    assert_eq!(frame[..4], ["", "  one", "● two", "  three"]);
+    let terminal = run(&directory, "main.rs", 30, 7, vec![Event::Tick]);
+    assert_eq!(last_frame(&terminal)[2], "● two");
+}
+
+#[test]
+fn hints_wider_than_the_screen_are_cut_between_characters() {
+    let directory = scratch("narrow_hints");
+    fs::write(directory.join("accents.txt"), "éééééé\nèèèèèè\n").unwrap();
+    let events = vec![
+        Event::Key(Key::Ctrl('c')),
+        Event::Key(Key::Down),
+        Event::Key(Key::Ctrl('c')),
+        Event::Key(Key::Ctrl('k')),
+        Event::Key(Key::Char('y')),
+        Event::Tick,
+    ];
+    let terminal = run(&directory, "accents.txt", 9, 8, events);
+    let frames = terminal.frames();
+    assert!(
+        frames
+            .iter()
+            .any(|frame| frame.contains(&"> lines è".to_string())),
+        "{frames:?}"
+    );
+}
+
+#[test]
+fn pastes_are_inserted_as_sent_like_typed_keys() {
+    let directory = scratch("pasting");
+    let paste = || Event::Paste("X\tY\x01".to_string());
+    let events = vec![Event::Key(Key::Insert), paste(), Event::Key(Key::Ctrl('s'))];
+    let (_, text) = run_fresh(&directory, "", "txt", "abcdef\n", (40, 6), events);
+    assert_eq!(text, "X\tY\x01ef\n");
+    let events = vec![Event::Key(Key::F(1)), paste(), Event::Tick];
+    let terminal = run(&directory, "help.txt", 40, 6, events);
+    assert_eq!(last_frame(&terminal)[5], "This buffer is read-only.");
+}
+
+#[test]
+fn read_only_buffers_keep_their_name_when_saved_as_another() {
+    let directory = scratch("save_read_only");
+    let mut events = vec![Event::Key(Key::F(1)), Event::Key(Key::Ctrl('p'))];
+    events.extend(keys("save renamed.txt\n"));
+    events.push(Event::Tick);
+    let terminal = run(&directory, "help.txt", 40, 6, events);
+    let frame = last_frame(&terminal);
+    assert_eq!(frame[5], "This buffer is read-only.");
+    assert!(!frame[4].contains("renamed.txt"), "{frame:?}");
+    assert!(!directory.join("renamed.txt").exists());
+}
+
+#[test]
+fn older_yanks_can_be_picked_and_pasted() {
+    let directory = scratch("yank_history");
+    fs::write(directory.join("list.txt"), "one\ntwo\nthree\n").unwrap();
+    let events = vec![
+        Event::Key(Key::Ctrl('c')),
+        Event::Key(Key::Down),
+        Event::Key(Key::Ctrl('c')),
+        Event::Key(Key::Down),
+        Event::Key(Key::Ctrl('k')),
+        Event::Key(Key::Char('y')),
+        Event::Key(Key::Down),
+        Event::Tick,
+        Event::Key(Key::Char('\n')),
+        Event::Tick,
+    ];
+    let terminal = run(&directory, "list.txt", 30, 8, events);
+    // The newest comes first, with what the chosen one holds above the list.
+    let frames = terminal.frames();
+    let picking = &frames[frames.len() - 4];
+    assert_eq!(picking[3..6], ["  │ one", "  lines two", "> lines one"]);
+    assert_eq!(last_frame(&terminal)[..4], ["one", "two", "three", "one"]);
+}
+
+#[test]
+fn saved_versions_can_be_compared_and_restored() {
+    let directory = scratch("file_history");
+    fs::write(directory.join("notes.txt"), "one\n").unwrap();
+    let mut events = vec![
+        Event::Key(Key::Char('A')),
+        Event::Key(Key::Ctrl('s')),
+        Event::Key(Key::Char('B')),
+        Event::Key(Key::Ctrl('s')),
+        Event::Key(Key::Char('C')),
+        Event::Key(Key::Ctrl('p')),
+    ];
+    events.extend(keys("file_history!\n"));
+    events.push(Event::Key(Key::Down));
+    events.push(Event::Tick);
+    events.push(Event::Key(Key::Char('\n')));
+    events.push(Event::Tick);
+    let terminal = run(&directory, "notes.txt", 40, 10, events);
+    // Each version kept on saving shows how the buffer differs from it.
+    let frames = terminal.frames();
+    let picking = &frames[frames.len() - 4];
+    assert_eq!(
+        picking[3..6],
+        ["  │ @@ -1,1 +1,1 @@", "  │ -Aone", "  │ +ABCone"]
+    );
+    let frame = last_frame(&terminal);
+    assert_eq!(frame[0], "Aone");
+    assert!(frame[9].starts_with("Restored the version from "));
+}
+
+#[test]
+fn escape_codes_in_captured_output_can_be_stripped_and_brought_back() {
+    let directory = scratch("ansi");
+    let log = "\u{1b}[1;31merror\u{1b}[0m: failed\n\u{1b}]0;title\u{7}done\n";
+    fs::write(directory.join("build.log"), log).unwrap();
+    let events = vec![
+        Event::Key(Key::Char('y')),
+        Event::Key(Key::Ctrl('z')),
+        Event::Tick,
+        Event::Key(Key::Ctrl('y')),
+        Event::Key(Key::Ctrl('s')),
+        Event::Tick,
+    ];
+    let terminal = run(&directory, "build.log", 50, 6, events);
+    // Undoing takes the buffer back to the file as it was.
+    let frames = terminal.frames();
+    assert!(!frames[frames.len() - 4][4].contains("(modified)"));
+    let text = fs::read_to_string(directory.join("build.log")).unwrap();
+    assert_eq!(text, "error: failed\ndone\n");
+}
+
+#[test]
+fn rulers_are_drawn_at_their_columns_unless_the_file_type_has_its_own() {
+    let directory = scratch("rulers");
+    fs::write(directory.join("notes.txt"), "ab\n").unwrap();
+    fs::write(directory.join("build.log"), "ab\n").unwrap();
+    let settings = "rulers = [8, { column = 4, color = \"#ff0000\" }]\n\
+                    [file_type_rulers]\nlog = [6]\n";
+    let notes = run_with(&directory, settings, "notes.txt", 30, 5, vec![Event::Tick]);
+    let log = run_with(&directory, settings, "build.log", 30, 5, vec![Event::Tick]);
+    assert_eq!(last_frame(&notes)[0], "ab  │   │");
+    assert_eq!(last_frame(&log)[0], "ab    │");
+}
+
+#[test]
+fn project_settings_are_taken_over_the_configuration() {
+    let directory = scratch("project");
+    fs::write(
+        directory.join(".hecto.toml"),
+        "tab_width = 2\nignore = [\"target\"]\n",
+    )
+    .unwrap();
+    fs::create_dir_all(directory.join("src")).unwrap();
+    fs::create_dir_all(directory.join("target")).unwrap();
+    fs::write(directory.join("todo.txt"), "").unwrap();
+    fs::write(directory.join("src/main.txt"), "\tx\n").unwrap();
+    let mut events = vec![Event::Tick, Event::Key(Key::Ctrl('p'))];
+    events.extend(keys(&format!("open {}/t\t", directory.display())));
+    events.push(Event::Key(Key::Esc));
+    let terminal = run(&directory, "src/main.txt", 80, 5, events);
+    let frames = terminal.frames();
+    assert_eq!(frames[0][0], "  x");
+    // The ignored directory comes first but is not offered.
+    let completed = frames
+        .iter()
+        .rev()
+        .find(|frame| frame[4].starts_with(":open"));
+    assert!(completed.unwrap()[4].ends_with("project/todo.txt"));
+}
+
+#[test]
+fn file_prompts_expand_variables_and_open_every_match_of_a_glob() {
+    let directory = scratch("expand");
+    env::set_var("HECTO_EXPAND_TEST", &directory);
+    fs::create_dir_all(directory.join("notes")).unwrap();
+    fs::write(directory.join("notes/a.md"), "first\n").unwrap();
+    fs::write(directory.join("notes/b.md"), "second\n").unwrap();
+    fs::write(directory.join("notes/c.txt"), "").unwrap();
+    fs::write(directory.join("start.txt"), "").unwrap();
+    let mut events = vec![Event::Tick, Event::Key(Key::Ctrl('p'))];
+    events.extend(keys("open $HECTO_EXPAND_TEST/notes/*.md\n"));
+    events.push(Event::Tick);
+    let terminal = run(&directory, "start.txt", 60, 8, events);
+    // The directory's contents are listed once the path reaches into it.
+    let frames = terminal.frames();
+    let listing = frames.iter().find(|frame| frame[7].ends_with("/notes/"));
+    assert_eq!(listing.unwrap()[3..6], [" a.md", " b.md", " c.txt"]);
+    let frame = last_frame(&terminal);
+    assert_eq!(frame[0], "first");
+    assert_eq!(frame[7], "Opened 2 files.");
+}
+
+#[test]
+fn two_files_are_compared_side_by_side() {
+    let directory = scratch("diff");
+    let config = directory.join("config.toml");
+    fs::write(&config, "language = \"en\"\nundo_history_size = 0\n").unwrap();
+    let old: String = (1..=20).map(|line| format!("line {line}\n")).collect();
+    let new = old
+        .replace("line 3\n", "line three\n")
+        .replace("line 15\n", "");
+    fs::write(directory.join("old.txt"), old).unwrap();
+    fs::write(directory.join("new.txt"), new).unwrap();
+    let events = vec![
+        Event::Tick,
+        Event::Key(Key::Char('n')),
+        Event::Tick,
+        Event::Key(Key::Char('q')),
+        Event::Tick,
+    ];
+    let terminal = Rc::new(Headless::new(100, 8, events));
+    let options = Options {
+        files: vec![
+            directory.join("old.txt").to_string_lossy().into_owned(),
+            directory.join("new.txt").to_string_lossy().into_owned(),
+        ],
+        config: Some(config),
+        diff: true,
+        ..Options::default()
+    };
+    let running = RUNNING.lock().unwrap_or_else(PoisonError::into_inner);
+    let mut editor = Editor::with_options(Box::new(terminal.clone()), options);
+    editor.run().unwrap();
+    drop(running);
+    // It starts at the first difference, a changed line, and moves on to
+    // the next, a line only in the first file.
+    let frames = terminal.frames();
+    let sides = |line: &str| -> Vec<String> {
+        line.split('│')
+            .map(|side| side.trim().to_string())
+            .collect()
+    };
+    assert_eq!(sides(&frames[0][2]), ["3 line 3", "3 line three"]);
+    assert!(frames[0][6].ends_with("Difference 1/2"));
+    assert_eq!(sides(&frames[1][2]), ["15 line 15", ""]);
+    assert_eq!(sides(&frames[1][3]), ["16 line 16", "15 line 16"]);
+    assert!(frames[1][6].ends_with("Difference 2/2"));
+    // Closing it leaves the first file to edit.
+    assert_eq!(last_frame(&terminal)[0], "line 1");
+}
+
+#[test]
+fn merge_conflicts_can_be_visited_and_resolved() {
+    let directory = scratch("conflicts");
+    let text = "top\n<<<<<<< HEAD\nours\n=======\ntheirs\n>>>>>>> branch\n\
+                middle\n<<<<<<< HEAD\nmine\n=======\nyours\n>>>>>>> branch\n";
+    fs::write(directory.join("merged.txt"), text).unwrap();
+    let mut events = vec![Event::Key(Key::Ctrl('k')), Event::Key(Key::Char('c'))];
+    events.push(Event::Key(Key::Ctrl('p')));
+    events.extend(keys("accept_theirs\n"));
+    events.extend([Event::Key(Key::Ctrl('k')), Event::Key(Key::Char('c'))]);
+    events.push(Event::Key(Key::Ctrl('p')));
+    events.extend(keys("accept_both\n"));
+    events.push(Event::Tick);
+    events.push(Event::Key(Key::Ctrl('s')));
+    events.push(Event::Tick);
+    let terminal = run(&directory, "merged.txt", 40, 10, events);
+    let frames = terminal.frames();
+    assert_eq!(frames[frames.len() - 4][9], "Conflict resolved, 0 left.");
+    let text = fs::read_to_string(directory.join("merged.txt")).unwrap();
+    assert_eq!(text, "top\ntheirs\nmiddle\nmine\nyours\n");
+}
+
+#[test]
+fn tasks_are_listed_by_file() {
+    let directory = scratch("tasks");
+    fs::write(directory.join(".hecto.toml"), "ignore = [\"vendor\"]\n").unwrap();
+    fs::create_dir_all(directory.join("src")).unwrap();
+    fs::create_dir_all(directory.join("vendor")).unwrap();
+    fs::write(
+        directory.join("src/main.txt"),
+        "start\n// TODO: tidy up\nTODOS are not tasks\n  # FIXME later\n",
+    )
+    .unwrap();
+    fs::write(directory.join("notes.txt"), "TODO write notes\n").unwrap();
+    fs::write(directory.join("vendor/lib.txt"), "TODO not ours\n").unwrap();
+    let mut events = vec![Event::Tick, Event::Key(Key::Ctrl('p'))];
+    events.extend(keys("tasks\n"));
+    let terminal = run(&directory, "src/main.txt", 60, 8, events);
+    let frame = last_frame(&terminal);
+    let root = directory.display();
+    assert_eq!(frame[0], format!("{root}/notes.txt"));
+    assert_eq!(frame[1], "  1:1 TODO write notes");
+    assert_eq!(frame[2], "");
+    assert_eq!(frame[3], format!("{root}/src/main.txt"));
+    assert_eq!(frame[4], "  2:4 TODO: tidy up");
+    assert_eq!(frame[5], "  4:5 FIXME later");
+    assert_eq!(frame[7], "Found 3 tasks.");
+}
+
+#[test]
+fn the_theme_follows_the_background_unless_one_is_set() {
+    let directory = scratch("background");
+    let mut events = vec![
+        Event::Tick,
+        Event::Background(Background::Light),
+        Event::Key(Key::Ctrl('p')),
+    ];
+    events.extend(keys("background\n"));
+    events.push(Event::Tick);
+    events.push(Event::Key(Key::Ctrl('p')));
+    events.extend(keys("background dark\n"));
+    events.push(Event::Background(Background::Light));
+    events.push(Event::Tick);
+    let terminal = run(&directory, "file.txt", 60, 5, events);
+    let reported = "Background: auto (light), theme default-light";
+    assert!(terminal.frames().iter().any(|frame| frame[4] == reported));
+    assert_eq!(last_frame(&terminal)[4], "Background: dark, theme default");
+}
+
+#[test]
+fn the_accessible_mode_draws_no_decorations() {
+    let directory = scratch("accessible");
+    fs::write(directory.join("notes.txt"), "first\nsecond\n").unwrap();
+    let events = vec![Event::Tick, Event::Key(Key::Down), Event::Tick];
+    let terminal = run_with(
+        &directory,
+        "accessible = true\nrulers = [4]",
+        "notes.txt",
+        40,
+        6,
+        events,
+    );
+    let frames = terminal.frames();
+    let frame = last_frame(&terminal);
+    assert_eq!(frame[..4], ["first", "second", "", ""]);
+    assert!(frame[4].ends_with(" - 2 lines | No file"));
+    // Moving down leaves the status bar as it was, and puts the cursor on the
+    // line moved to.
+    assert_eq!(frames[0][4], frame[4]);
+    assert_eq!(terminal.cursor(), (0, 1));
+}
+
+#[test]
+fn paragraph_and_sentence_motions_stop_at_blank_lines() {
+    let directory = scratch("motions");
+    let text = "One. Two!\nStill two? (Three.) Four\n\n\nFive \"six.\"\n";
+    // Marks where each series of motions leaves the cursor.
+    let marked_after =
+        |keys: &[Key]| run_fresh(&directory, "", "txt", text, (40, 10), marked(keys)).1;
+    let next = Key::Alt(')');
+    let previous = Key::Alt('(');
+    let at = |offset: usize| format!("{}|{}", &text[..offset], &text[offset..]);
+    assert_eq!(marked_after(&[next]), at(5));
+    assert_eq!(marked_after(&[next, next]), at(10));
+    assert_eq!(marked_after(&[next; 3]), at(21));
+    assert_eq!(marked_after(&[next; 4]), at(30));
+    assert_eq!(marked_after(&[next; 5]), at(35));
+    assert_eq!(marked_after(&[next; 6]), at(37));
+    // The document ends the last sentence.
+    assert_eq!(marked_after(&[next; 7]), at(48));
+    assert_eq!(marked_after(&[next; 8]), at(48));
+    assert_eq!(marked_after(&[next, next, next, next, previous]), at(21));
+    assert_eq!(marked_after(&[Key::CtrlDown, previous]), at(30));
+    assert_eq!(marked_after(&[Key::CtrlDown]), at(35));
+    assert_eq!(marked_after(&[Key::CtrlDown, Key::CtrlDown]), at(48));
+    assert_eq!(
+        marked_after(&[Key::CtrlDown, Key::CtrlDown, Key::CtrlUp]),
+        at(36)
+    );
+    assert_eq!(marked_after(&[Key::CtrlUp]), at(0));
+}
+
+#[test]
+fn word_motions_can_stop_inside_names() {
+    let directory = scratch("words");
+    let text = "let parseHTTPServer = snake_case_name;\n\nnext\n";
+    let marked_after = |settings: &str, keys: &[Key]| {
+        let (_, marked) = run_fresh(&directory, settings, "txt", text, (60, 10), marked(keys));
+        marked.find('|').unwrap()
+    };
+    let right = Key::CtrlRight;
+    let left = Key::CtrlLeft;
+    let stops: Vec<usize> = (1..=7)
+        .map(|count| marked_after("", &vec![right; count]))
+        .collect();
+    assert_eq!(stops, [4, 20, 22, 37, 39, 40, 44]);
+    let stops: Vec<usize> = (1..=6)
+        .map(|count| marked_after("subword = true", &vec![right; count]))
+        .collect();
+    assert_eq!(stops, [4, 9, 13, 20, 22, 28]);
+    // Going back stops at the empty line, and at the start of the name.
+    assert_eq!(
+        marked_after("", &[right, right, right, right, right, right, left]),
+        39
+    );
+    assert_eq!(marked_after("", &[right, right, left]), 4);
+    assert_eq!(
+        marked_after("subword = true", &[right, right, right, left]),
+        9
+    );
+}
+
+#[test]
+fn paging_keeps_the_cursor_on_its_screen_row() {
+    let directory = scratch("paging");
+    let text: String = (1..=100).map(|n| format!("line {n}\n")).collect();
+    // Returns the screen row the cursor ends up on and the line it is on.
+    let marked_after = |keys: &[Key]| {
+        let mut events: Vec<Event> = [Key::Down; 3]
+            .iter()
+            .chain(keys)
+            .map(|&key| Event::Key(key))
+            .collect();
+        events.push(Event::Key(Key::Char('|')));
+        events.push(Event::Tick);
+        let (terminal, _) = run_fresh(&directory, "", "txt", &text, (40, 12), events);
+        let frame = last_frame(&terminal);
+        let row = frame.iter().position(|line| line.starts_with('|'));
+        (row, row.map(|row| frame[row].trim_end().to_string()))
+    };
+    assert_eq!(marked_after(&[]), (Some(3), Some("|line 4".to_string())));
+    assert_eq!(
+        marked_after(&[Key::PageDown]),
+        (Some(3), Some("|line 14".to_string()))
+    );
+    assert_eq!(
+        marked_after(&[Key::Ctrl('d')]),
+        (Some(3), Some("|line 9".to_string()))
+    );
+    assert_eq!(
+        marked_after(&[Key::PageDown, Key::Ctrl('u')]),
+        (Some(3), Some("|line 9".to_string()))
+    );
+    // The view stops at the top, but the cursor moves as far.
+    assert_eq!(
+        marked_after(&[Key::Ctrl('u')]),
+        (Some(0), Some("|line 1".to_string()))
+    );
+    // And at the bottom, where the last page fills the screen.
+    let mut down = vec![Key::PageDown; 10];
+    assert_eq!(
+        marked_after(&down),
+        (Some(9), Some("|line 100".to_string()))
+    );
+    down.push(Key::PageUp);
+    assert_eq!(marked_after(&down), (Some(9), Some("|line 90".to_string())));
+}
+
+#[test]
+fn jumps_go_to_the_ends_or_part_of_the_way_through() {
+    let directory = scratch("document_jumps");
+    let text: String = (1..=100).map(|n| format!("line {n}\n")).collect();
+    // Returns the screen row the cursor ends up on and the line it is on.
+    let marked_after = |mut events: Vec<Event>| {
+        events.push(Event::Key(Key::Char('|')));
+        events.push(Event::Tick);
+        let (terminal, _) = run_fresh(&directory, "", "txt", &text, (40, 12), events);
+        let frame = last_frame(&terminal);
+        let row = frame.iter().position(|line| line.contains('|'));
+        (row, row.map(|row| frame[row].trim_end().to_string()))
+    };
+    assert_eq!(
+        marked_after(vec![Event::Key(Key::CtrlEnd)]),
+        (Some(5), Some("line 100|".to_string()))
+    );
+    assert_eq!(
+        marked_after(vec![
+            Event::Key(Key::Down),
+            Event::Key(Key::CtrlEnd),
+            Event::Key(Key::CtrlHome)
+        ]),
+        (Some(0), Some("|line 1".to_string()))
+    );
+    let mut events = vec![Event::Key(Key::Ctrl('p'))];
+    events.extend(keys("50%"));
+    events.push(Event::Key(Key::Char('\n')));
+    assert_eq!(
+        marked_after(events),
+        (Some(5), Some("|line 50".to_string()))
+    );
+}
+
+#[test]
+fn the_status_bar_shows_the_size_of_the_selection() {
+    let directory = scratch("selection_status");
+    let status_after = |keys: &[Key]| {
+        let mut events: Vec<Event> = keys.iter().map(|&key| Event::Key(key)).collect();
+        events.push(Event::Tick);
+        let text = "one\ntwo\nthree\n";
+        let (terminal, _) = run_fresh(&directory, "", "txt", text, (80, 10), events);
+        last_frame(&terminal)[8].clone()
+    };
+    assert!(!status_after(&[]).contains("selected"));
+    assert!(status_after(&[Key::ShiftRight]).contains("1 line, 1 character selected | "));
+    assert!(status_after(&[Key::ShiftRight, Key::ShiftRight])
+        .contains("1 line, 2 characters selected | "));
+    // The newline counts as a character.
+    assert!(status_after(&[Key::ShiftRight, Key::ShiftDown])
+        .contains("2 lines, 5 characters selected | "));
+}
+
+#[test]
+fn tab_and_new_lines_follow_the_files_indentation() {
+    let directory = scratch("indentation");
+    let typed_into = |extension: &str, text: &str, keys: &[Key]| {
+        let mut events: Vec<Event> = keys.iter().map(|&key| Event::Key(key)).collect();
+        events.push(Event::Tick);
+        events.push(Event::Key(Key::Ctrl('s')));
+        let settings = "auto_indent = true";
+        let (terminal, text) = run_fresh(&directory, settings, extension, text, (80, 10), events);
+        (text, last_frame(&terminal)[8].clone())
+    };
+    let code = "fn main() {\n  let x = 1;\n}\n";
+    let (text, status) = typed_into("rs", code, &[Key::End, Key::Char('\n'), Key::Char('a')]);
+    assert_eq!(text, "fn main() {\n  a\n  let x = 1;\n}\n");
+    assert!(status.contains(" 2 spaces | Rust | "));
+    let (text, _) = typed_into(
+        "rs",
+        code,
+        &[
+            Key::Down,
+            Key::End,
+            Key::Char('\n'),
+            Key::Char('\t'),
+            Key::Char('b'),
+        ],
+    );
+    assert_eq!(text, "fn main() {\n  let x = 1;\n    b\n}\n");
+    let (text, status) = typed_into(
+        "txt",
+        "int main() {\n\treturn 0;\n}\n",
+        &[Key::Down, Key::Char('\t')],
+    );
+    assert_eq!(text, "int main() {\n\t\treturn 0;\n}\n");
+    assert!(status.contains(" tabs | No filetype | "));
+    // A narrow bar drops the indentation before the line number.
+    let (terminal, _) = run_fresh(&directory, "", "rs", code, (50, 10), vec![Event::Tick]);
+    let status = &last_frame(&terminal)[8];
+    assert!(status.ends_with(" Rust | 1/3"), "{status}");
+    assert!(!status.contains("spaces"), "{status}");
+}
+
+#[test]
+fn section_jumps_skip_to_top_level_definitions_and_headings() {
+    let directory = scratch("sections");
+    let marked_after = |extension: &str, text: &str, keys: &[Key]| {
+        let (_, text) = run_fresh(&directory, "", extension, text, (40, 10), marked(keys));
+        text.lines().position(|line| line.starts_with('|'))
+    };
+    let code = "use std::fmt;\n\nstruct A;\n\nimpl A {\n    fn new() -> Self {\n        A\n    }\n}\n\nfn main() {}\n";
+    let next = Key::Alt('}');
+    let previous = Key::Alt('{');
+    assert_eq!(marked_after("rs", code, &[next]), Some(2));
+    assert_eq!(marked_after("rs", code, &[next, next]), Some(4));
+    // The method is inside the `impl`, so it is skipped.
+    assert_eq!(marked_after("rs", code, &[next, next, next]), Some(10));
+    assert_eq!(marked_after("rs", code, &[next; 4]), Some(10));
+    assert_eq!(marked_after("rs", code, &[Key::CtrlEnd, previous]), Some(4));
+    let notes = "# Notes\n\nText.\n\n## Details\n\nMore.\n";
+    assert_eq!(marked_after("md", notes, &[next]), Some(4));
+    assert_eq!(
+        marked_after("md", notes, &[Key::CtrlEnd, previous]),
+        Some(4)
+    );
+}
+
+#[test]
+fn reopening_a_file_shows_it_as_it_was_left() {
+    let directory = scratch("views");
+    let text: String = (1..=100).map(|n| format!("line {n}\n")).collect();
+    fs::write(directory.join("long.txt"), &text).unwrap();
+    fs::write(directory.join("other.txt"), &text).unwrap();
+    let mut events = vec![Event::Key(Key::Ctrl('p'))];
+    events.extend(keys("set number\n"));
+    events.extend([Event::Key(Key::PageDown), Event::Key(Key::Up)]);
+    run(&directory, "long.txt", 40, 12, events);
+    let terminal = run(&directory, "long.txt", 40, 12, vec![Event::Tick]);
+    let frame = last_frame(&terminal);
+    assert!(frame[0].ends_with(" line 10"), "{frame:?}");
+    assert!(frame[0].trim_start().starts_with("10"), "{frame:?}");
+    run(&directory, "long.txt", 40, 12, marked(&[]));
+    let marked = fs::read_to_string(directory.join("long.txt")).unwrap();
+    assert_eq!(marked.lines().nth(9), Some("|line 10"));
+    // Another file keeps its own view, and one never seen before is shown as
+    // configured, even opened next to it.
+    let terminal = run(&directory, "other.txt", 40, 12, vec![Event::Tick]);
+    assert_eq!(last_frame(&terminal)[0], "line 1");
+    let mut events = vec![Event::Key(Key::Ctrl('p'))];
+    fs::write(directory.join("new.txt"), &text).unwrap();
+    let open = format!("open {}\n", directory.join("new.txt").display());
+    events.extend(keys(&open));
+    events.push(Event::Tick);
+    let terminal = run(&directory, "long.txt", 40, 12, events);
+    assert_eq!(last_frame(&terminal)[0], "line 1");
+    // Views of files that are gone are forgotten.
+    fs::write(directory.join("gone.txt"), &text).unwrap();
+    run(
+        &directory,
+        "gone.txt",
+        40,
+        12,
+        vec![Event::Key(Key::PageDown)],
+    );
+    fs::remove_file(directory.join("gone.txt")).unwrap();
+    run(&directory, "other.txt", 40, 12, vec![]);
+    let views = directory.join("../state/hecto/views.toml");
+    let views = fs::read_to_string(views).unwrap();
+    assert!(views.contains("long.txt") && views.contains(".cursor]"));
+    assert!(!views.contains("gone.txt"));
+}
+
+#[test]
+fn moving_up_and_down_keeps_the_column_through_short_lines() {
+    let directory = scratch("goal_column");
+    let marked_after =
+        |text: &str, keys: &[Key]| run_fresh(&directory, "", "txt", text, (40, 10), marked(keys)).1;
+    let text = "0123456789\nab\n0123456789\n";
+    assert_eq!(
+        marked_after(text, &[Key::End, Key::Down, Key::Down]),
+        "0123456789\nab\n0123456789|\n"
+    );
+    assert_eq!(
+        marked_after(text, &[Key::End, Key::Down]),
+        "0123456789\nab|\n0123456789\n"
+    );
+    // Moving sideways sets a new column.
+    assert_eq!(
+        marked_after(text, &[Key::End, Key::Down, Key::Left, Key::Down]),
+        "0123456789\nab\n0|123456789\n"
+    );
+    // Wide characters take two columns, and the cursor lands on the one
+    // drawn at the column.
+    let wide = "abcd\n中文x\n";
+    assert_eq!(
+        marked_after(wide, &[Key::Right, Key::Right, Key::Down]),
+        "abcd\n中|文x\n"
+    );
+    assert_eq!(
+        marked_after(wide, &[Key::Right, Key::Right, Key::Right, Key::Down]),
+        "abcd\n中|文x\n"
+    );
+    assert_eq!(
+        marked_after(wide, &[Key::Down, Key::End, Key::Left, Key::Up]),
+        "abcd|\n中文x\n"
+    );
+}
+
+#[test]
+fn frames_are_sent_whole_and_only_with_what_changed() {
+    let directory = scratch("output");
+    fs::write(directory.join("notes.txt"), "hello\n").unwrap();
+    let events = vec![Event::Tick, Event::Tick, Event::Key(Key::Char('!'))];
+    let terminal = run(&directory, "notes.txt", 40, 6, events);
+    let output = terminal.output();
+    // Each frame is one synchronized update.
+    assert_eq!(
+        output.matches("\x1b[?2026h").count(),
+        output.matches("\x1b[?2026l").count()
+    );
+    assert!(output.matches("\x1b[?2026h").count() >= 3);
+    // The line is sent again only once it changes.
+    assert_eq!(output.matches("hello").count(), 2);
+    assert_eq!(output.matches("!hello").count(), 1);
+}
+
+#[test]
+fn the_cursor_line_is_dimmed_while_the_terminal_is_not_focused() {
+    let directory = scratch("focus");
+    fs::write(directory.join("notes.txt"), "hello\n").unwrap();
+    let theme = Theme::builtin();
+    let focused = Bg(theme.cursor_line_bg).to_string();
+    let unfocused = Bg(theme.unfocused_cursor_line_bg).to_string();
+    let events = vec![Event::Tick, Event::FocusLost, Event::Tick];
+    let output = run(&directory, "notes.txt", 40, 6, events).output();
+    let lost = output.find(&unfocused).unwrap();
+    assert!(output[..lost].contains(&focused));
+    assert!(!output[lost..].contains(&focused));
+}
+
+#[test]
+fn frames_drawn_after_a_burst_of_keys_leave_out_the_cursor_line() {
+    let directory = scratch("burst");
+    let cursor_line = Bg(Theme::builtin().cursor_line_bg).to_string();
+    // The last frame drawn, after the keys pressed since the one before.
+    let last_frame = |pressed: &[Key]| {
+        let mut events = vec![Event::Tick];
+        events.extend(pressed.iter().copied().map(Event::Key));
+        events.push(Event::Tick);
+        let (terminal, _) = run_fresh(&directory, "", "txt", "one\ntwo\nthree\n", (40, 6), events);
+        let output = terminal.output();
+        let frames: Vec<&str> = output.split("\x1b[?2026h").collect();
+        frames[frames.len() - 2].to_string()
+    };
+    assert!(last_frame(&[Key::Down]).contains(&cursor_line));
+    assert!(!last_frame(&[Key::Down, Key::Down]).contains(&cursor_line));
+}
+
+#[test]
+fn reverting_drops_unsaved_changes_and_keeps_the_cursor_near() {
+    let directory = scratch("revert");
+    let text = "one\ntwo\nthree\n";
+    for (file, command) in [("first.txt", "revert\n"), ("second.txt", "open!\n")] {
+        fs::write(directory.join(file), text).unwrap();
+        let mut events = vec![
+            Event::Key(Key::Down),
+            Event::Key(Key::Down),
+            Event::Key(Key::End),
+        ];
+        events.extend(keys("!!"));
+        events.push(Event::Key(Key::Ctrl('p')));
+        events.extend(keys(command));
+        events.push(Event::Tick);
+        events.push(Event::Key(Key::Ctrl('z')));
+        events.push(Event::Key(Key::Char('|')));
+        events.push(Event::Key(Key::Ctrl('s')));
+        let terminal = run(&directory, file, 40, 6, events);
+        assert_eq!(
+            fs::read_to_string(directory.join(file)).unwrap(),
+            "one\ntwo\nthree|\n"
+        );
+        assert!(terminal
+            .frames()
+            .iter()
+            .any(|frame| frame[5] == "Reverted to the file on disk."));
+    }
+}
+
+#[test]
+fn a_very_long_line_is_edited_in_place() {
+    let directory = scratch("long-line");
+    let line = "ab".repeat(100_000);
+    fs::write(directory.join("long.txt"), format!("{line}\n")).unwrap();
+    let mut events = vec![Event::Key(Key::End)];
+    events.extend(keys("xyz"));
+    events.push(Event::Key(Key::Home));
+    events.push(Event::Key(Key::Delete));
+    events.push(Event::Key(Key::Ctrl('s')));
+    run(&directory, "long.txt", 40, 6, events);
+    assert_eq!(
+        fs::read_to_string(directory.join("long.txt")).unwrap(),
+        format!("{}xyz\n", &line[1..])
+    );
+}
+
+/// A screen that takes `delay` to flush each frame, with input that arrives
+/// at the times it is given, counted from when it was made.
+struct Slow {
+    screen: Rc<Headless>,
+    delay: Duration,
+    started: Instant,
+    events: RefCell<VecDeque<(Duration, Event)>>,
+}
+
+impl Slow {
+    /// Returns the next event if it arrives within `timeout`, after waiting
+    /// for it, or at once if the input has run out.
+    fn next_event(&self, timeout: Duration) -> Option<Event> {
+        let mut events = self.events.borrow_mut();
+        let &(at, _) = events.front()?;
+        let wait = at.saturating_sub(self.started.elapsed());
+        if wait > timeout {
+            thread::sleep(timeout);
+            return None;
+        }
+        thread::sleep(wait);
+        events.pop_front().map(|(_, event)| event)
+    }
+}
+
+impl Backend for Slow {
+    fn size(&self) -> Size {
+        self.screen.size()
+    }
+
+    fn write(&self, text: &str) {
+        self.screen.write(text);
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        thread::sleep(self.delay);
+        self.screen.flush()
+    }
+
+    fn read_event(&self) -> io::Result<Event> {
+        self.next_event(Duration::MAX)
+            .ok_or_else(|| io::ErrorKind::UnexpectedEof.into())
+    }
+
+    fn poll_event(&self, timeout: Duration) -> io::Result<Option<Event>> {
+        Ok(self.next_event(timeout))
+    }
+}
+
+/// Runs the editor on `file` like [`run_with`], on a screen that takes
+/// `delay` to show each frame, with `events` given at the times they come
+/// with.
+fn run_timed(
+    directory: &Path,
+    settings: &str,
+    file: &str,
+    delay: Duration,
+    events: Vec<(Duration, Event)>,
+) -> Rc<Headless> {
+    let config = directory.join("config.toml");
+    let settings = format!("language = \"en\"\nundo_history_size = 0\n{settings}");
+    fs::write(&config, settings).unwrap();
+    let screen = Rc::new(Headless::new(40, 6, Vec::new()));
+    // The times count from when the editor may run.
+    let _running = RUNNING.lock().unwrap_or_else(PoisonError::into_inner);
+    let slow = Slow {
+        screen: screen.clone(),
+        delay,
+        started: Instant::now(),
+        events: RefCell::new(events.into()),
+    };
+    let options = Options {
+        files: vec![directory.join(file).to_string_lossy().into_owned()],
+        config: Some(config),
+        ..Options::default()
+    };
+    Editor::with_options(Box::new(slow), options).run().unwrap();
+    screen
+}
+
+#[test]
+fn frames_are_put_together_while_the_screen_is_slow() {
+    let directory = scratch("slow-output");
+    let typed = "abcdefghijklmnopqrst";
+    let mut frames = Vec::new();
+    for (file, delay) in [
+        ("fast.txt", Duration::ZERO),
+        ("slow.txt", Duration::from_millis(40)),
+    ] {
+        fs::write(directory.join(file), "").unwrap();
+        let events = keys(typed)
+            .chain([Event::Key(Key::Ctrl('s'))])
+            .zip(1..)
+            .map(|(event, n)| (Duration::from_millis(25) * n, event))
+            .collect();
+        let screen = run_timed(&directory, "", file, delay, events);
+        let text = fs::read_to_string(directory.join(file)).unwrap();
+        assert_eq!(text, format!("{typed}\n"));
+        frames.push(screen.frames().len());
+    }
+    // Keys typed faster than a slow screen can show them share frames.
+    assert!(frames[1] * 2 < frames[0], "{frames:?}");
+}
+
+#[test]
+fn search_matches_are_left_out_while_the_screen_is_slow() {
+    let directory = scratch("slow-matches");
+    fs::write(directory.join("notes.txt"), "one\ntwo\n").unwrap();
+    let search_match = Fg(Theme::builtin().search_match).to_string();
+    let events = [Key::Ctrl('f'), Key::Char('o'), Key::Esc]
+        .into_iter()
+        .zip(1..)
+        .map(|(key, n)| (Duration::from_millis(100) * n, Event::Key(key)));
+    let fast = run_timed(
+        &directory,
+        "",
+        "notes.txt",
+        Duration::ZERO,
+        events.clone().collect(),
+    );
+    assert!(fast.output().contains(&search_match));
+    let slow = run_timed(
+        &directory,
+        "",
+        "notes.txt",
+        Duration::from_millis(40),
+        events.collect(),
+    );
+    assert!(!slow.output().contains(&search_match));
+}
+
+#[test]
+fn the_keys_of_a_chord_left_unfinished_stop_being_shown() {
+    let directory = scratch("chord-timeout");
+    fs::write(directory.join("notes.txt"), "hello\n").unwrap();
+    let events = vec![
+        (Duration::from_millis(10), Event::Key(Key::Ctrl('k'))),
+        (Duration::from_millis(100), Event::Tick),
+        (Duration::from_millis(1800), Event::Tick),
+    ];
+    let screen = run_timed(&directory, "", "notes.txt", Duration::ZERO, events);
+    let shown = |frame: &Vec<String>| frame.iter().any(|line| line.starts_with(" Ctrl-K "));
+    assert!(screen.frames().iter().any(shown));
+    assert!(!shown(&last_frame(&screen)));
+}
@@ -0,0 +1,265 @@
+use hecto::{Document, Position, Row, SearchDirection, Span};
+use proptest::prelude::*;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Lines of graphemes that stay whole whatever is put next to them, so an
+/// inserted character is always a grapheme of its own.
+const LINE: &str = "[ab é👍\t\"'0-9]{0,20}";
+
+fn graphemes(text: &str) -> Vec<&str> {
+    text.graphemes(true).collect()
+}
+
+/// Finds `query` by trying every grapheme from `at` on, or before `at`
+/// searching backward, as [`Row::find`] should.
+fn naive_find(text: &str, query: &str, at: usize, direction: SearchDirection) -> Option<usize> {
+    let graphemes = graphemes(text);
+    if at > graphemes.len() || query.is_empty() {
+        return None;
+    }
+    let matches_at = |start: usize, end: usize| graphemes[start..end].concat().starts_with(query);
+    match direction {
+        SearchDirection::Forward => (at..graphemes.len()).find(|&x| matches_at(x, graphemes.len())),
+        SearchDirection::Backward => (0..at).rev().find(|&x| matches_at(x, at)),
+    }
+}
+
+/// An edit to make to a document, its numbers taken modulo its size.
+#[derive(Clone, Debug)]
+enum Edit {
+    Insert(usize, usize, char),
+    InsertStr(usize, usize, String),
+    Delete(usize, usize),
+    DeleteRange(usize, usize, usize, usize),
+    DeleteRow(usize),
+    ReplaceRows(usize, usize, String),
+    SwapRows(usize, usize),
+    Undo,
+    Redo,
+}
+
+fn edit() -> impl Strategy<Value = Edit> {
+    prop_oneof![
+        (
+            0..8_usize,
+            0..24_usize,
+            prop::sample::select(vec!['a', 'é', '\n', ' '])
+        )
+            .prop_map(|(y, x, c)| Edit::Insert(y, x, c)),
+        (0..8_usize, 0..24_usize, "[ab é\n]{1,8}")
+            .prop_map(|(y, x, text)| Edit::InsertStr(y, x, text)),
+        (0..8_usize, 0..24_usize).prop_map(|(y, x)| Edit::Delete(y, x)),
+        (0..8_usize, 0..24_usize, 0..3_usize, 0..24_usize)
+            .prop_map(|(y, x, rows, end)| Edit::DeleteRange(y, x, rows, end)),
+        (0..8_usize).prop_map(Edit::DeleteRow),
+        (0..8_usize, 0..3_usize, "[ab\n]{0,6}")
+            .prop_map(|(y, rows, text)| Edit::ReplaceRows(y, rows, text)),
+        (0..8_usize, 0..8_usize).prop_map(|(a, b)| Edit::SwapRows(a, b)),
+        Just(Edit::Undo),
+        Just(Edit::Redo),
+    ]
+}
+
+fn rows(document: &Document) -> Vec<String> {
+    (0..document.len())
+        .filter_map(|y| document.row(y))
+        .map(|row| row.as_str().to_string())
+        .collect()
+}
+
+fn document(lines: &[String]) -> Document {
+    let text: String = lines.iter().map(|line| format!("{line}\n")).collect();
+    Document::from(text.as_str())
+}
+
+proptest! {
+    #[test]
+    fn row_insert_then_delete_is_identity(
+        text in LINE,
+        at in 0..24_usize,
+        c in "[a-z0-9 \t\"]",
+    ) {
+        let mut row = Row::from(text.as_str());
+        let c = c.chars().next().unwrap_or('a');
+        row.insert(at, c);
+        prop_assert_eq!(row.len(), graphemes(&text).len() + 1);
+        row.delete(at.min(graphemes(&text).len()));
+        prop_assert_eq!(row.as_str(), text.as_str());
+    }
+
+    #[test]
+    fn row_split_then_append_restores_row(text in LINE, at in 0..24_usize) {
+        let mut row = Row::from(text.as_str());
+        let tail = row.split(at);
+        prop_assert_eq!(row.len(), at.min(graphemes(&text).len()));
+        prop_assert_eq!(row.len() + tail.len(), graphemes(&text).len());
+        row.append(&tail);
+        prop_assert_eq!(row.as_str(), text.as_str());
+        prop_assert_eq!(row.len(), graphemes(&text).len());
+    }
+
+    #[test]
+    fn row_x_at_finds_the_grapheme_drawn_at_its_column(
+        text in LINE,
+        at in 0..24_usize,
+        tab_width in 1..9_usize,
+    ) {
+        let row = Row::from(text.as_str());
+        let x = at.min(row.len());
+        prop_assert_eq!(row.x_at(row.render_x(x, tab_width), tab_width), x);
+    }
+
+    #[test]
+    fn row_find_agrees_with_naive_scanner(
+        text in LINE,
+        query in "[ab é]{1,3}",
+        at in 0..24_usize,
+    ) {
+        let row = Row::from(text.as_str());
+        for direction in [SearchDirection::Forward, SearchDirection::Backward] {
+            prop_assert_eq!(
+                row.find(&query, at, direction),
+                naive_find(&text, &query, at, direction)
+            );
+        }
+    }
+
+    #[test]
+    fn document_insert_then_delete_is_identity(
+        lines in prop::collection::vec(LINE, 1..6),
+        inserted in prop::collection::vec(LINE, 1..4),
+        y in 0..6_usize,
+        x in 0..24_usize,
+    ) {
+        let mut document = document(&lines);
+        let before = document.text();
+        let y = y % lines.len();
+        let at = Position { x: x.min(graphemes(&lines[y]).len()), y };
+        let end = document.insert_str(&at, &inserted.join("\n"));
+        prop_assert_eq!(end.y, at.y + inserted.len() - 1);
+        document.delete_range(&at, &end);
+        prop_assert_eq!(document.text(), before);
+    }
+
+    #[test]
+    fn document_find_agrees_with_naive_scanner(
+        lines in prop::collection::vec(LINE, 1..6),
+        query in "[ab é]{1,3}",
+        y in 0..6_usize,
+        x in 0..24_usize,
+    ) {
+        let document = document(&lines);
+        let at = Position { x, y: y % lines.len() };
+        let naive = lines.iter().enumerate().skip(at.y).find_map(|(y, line)| {
+            let from = if y == at.y { at.x } else { 0 };
+            naive_find(line, &query, from, SearchDirection::Forward).map(|x| Position { x, y })
+        });
+        let found = document.find(&query, &at, SearchDirection::Forward);
+        prop_assert_eq!(found.map(|at| (at.x, at.y)), naive.map(|at| (at.x, at.y)));
+    }
+
+    #[test]
+    fn undo_and_redo_step_through_each_edit(
+        lines in prop::collection::vec(LINE, 1..6),
+        edits in prop::collection::vec(edit(), 0..12),
+    ) {
+        let mut document = document(&lines);
+        // The rows after each edit that changed them, starting from the rows
+        // as loaded.
+        let mut states = vec![rows(&document)];
+        for edit in edits {
+            let len = document.len().max(1);
+            let at = |y: usize, x: usize| Position { x, y: y % len };
+            match edit {
+                Edit::Insert(y, x, c) => document.insert(&at(y, x), c),
+                Edit::InsertStr(y, x, text) => {
+                    document.insert_str(&at(y, x), &text);
+                }
+                Edit::Delete(y, x) => document.delete(&at(y, x)),
+                Edit::DeleteRange(y, x, rows, end) => {
+                    let start = at(y, x);
+                    let end = Position { x: end, y: (start.y + rows).min(len - 1) };
+                    document.delete_range(&start, &end);
+                }
+                Edit::DeleteRow(y) => document.delete_row(y % len),
+                Edit::ReplaceRows(y, rows, text) => {
+                    let first = y % len;
+                    document.replace_rows(first, (first + rows).min(len - 1), &text);
+                }
+                Edit::SwapRows(a, b) => document.swap_rows(a % len, b % len),
+                Edit::Undo | Edit::Redo => continue,
+            }
+            document.end_undo_group(Position::default(), Position::default(), None);
+            let after = rows(&document);
+            if states.last() != Some(&after) {
+                states.push(after);
+            }
+        }
+        for state in states.iter().rev().skip(1) {
+            document.undo();
+            prop_assert_eq!(&rows(&document), state);
+        }
+        prop_assert!(document.undo().is_none());
+        for state in states.iter().skip(1) {
+            document.redo();
+            prop_assert_eq!(&rows(&document), state);
+        }
+        prop_assert!(document.redo().is_none());
+    }
+
+    #[test]
+    fn changes_keep_a_copy_of_the_document_in_sync(
+        lines in prop::collection::vec(LINE, 0..6),
+        edits in prop::collection::vec(edit(), 1..12),
+    ) {
+        let mut document = document(&lines);
+        let changes = document.subscribe();
+        let mut copy = rows(&document);
+        let before = copy.clone();
+        for edit in edits {
+            let len = document.len().max(1);
+            let at = |y: usize, x: usize| Position { x, y: y % len };
+            match edit {
+                Edit::Insert(y, x, c) => document.insert(&at(y, x), c),
+                Edit::InsertStr(y, x, text) => {
+                    document.insert_str(&at(y, x), &text);
+                }
+                Edit::Delete(y, x) => document.delete(&at(y, x)),
+                Edit::DeleteRange(y, x, rows, end) => {
+                    let start = at(y, x);
+                    let end = Position { x: end, y: (start.y + rows).min(len - 1) };
+                    document.delete_range(&start, &end);
+                }
+                Edit::DeleteRow(y) => document.delete_row(y % len),
+                Edit::ReplaceRows(y, rows, text) => {
+                    let first = y % len;
+                    document.replace_rows(first, (first + rows).min(len - 1), &text);
+                }
+                Edit::SwapRows(a, b) => document.swap_rows(a % len, b % len),
+                Edit::Undo => {
+                    document.undo();
+                }
+                Edit::Redo => {
+                    document.redo();
+                }
+            }
+            document.end_undo_group(Position::default(), Position::default(), None);
+        }
+        let mut span: Option<Span> = None;
+        for change in changes.try_iter() {
+            change.apply(&mut copy);
+            span = Some(span.map_or(change.span(), |span| span.then(change.span())));
+        }
+        let after = rows(&document);
+        prop_assert_eq!(&copy, &after);
+        // The rows outside the span are those that were there before.
+        if let Some(span) = span {
+            let mut patched = before;
+            patched.splice(
+                span.start..span.old_end.min(patched.len()),
+                after[span.start..span.new_end.min(after.len())].iter().cloned(),
+            );
+            prop_assert_eq!(patched, after);
+        }
+    }
+}
@@ -0,0 +1,100 @@
+use hecto::Server;
+use serde_json::{json, Value};
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+/// Returns a file of its own for `test` holding `text`.
+fn scratch(test: &str, text: &str) -> PathBuf {
+    let root = env::temp_dir().join(format!("hecto-server-tests-{}", std::process::id()));
+    fs::create_dir_all(&root).unwrap();
+    let path = root.join(format!("{test}.txt"));
+    fs::write(&path, text).unwrap();
+    path
+}
+
+/// Sends each of `requests` as `method` and `params` pairs to a server, and
+/// returns what it answered to each, numbered from 1.
+fn serve(requests: &[(&str, Value)]) -> Vec<Value> {
+    let input: String = requests
+        .iter()
+        .zip(1..)
+        .map(|((method, params), id)| {
+            let request = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+            format!("{request}\n")
+        })
+        .collect();
+    let mut output = Vec::new();
+    Server::default()
+        .serve(input.as_bytes(), &mut output)
+        .unwrap();
+    String::from_utf8(output)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect()
+}
+
+fn at(line: usize, column: usize) -> Value {
+    json!({ "line": line, "column": column })
+}
+
+#[test]
+fn edits_are_made_at_the_positions_given() {
+    let path = scratch("edits", "one\ntwo\n");
+    let document = json!({ "document": 1 });
+    let responses = serve(&[
+        ("open", json!({ "path": path })),
+        (
+            "insert",
+            json!({ "document": 1, "at": at(1, 3), "text": "!\nthree" }),
+        ),
+        (
+            "delete",
+            json!({ "document": 1, "start": at(0, 1), "end": at(0, 3) }),
+        ),
+        ("text", document.clone()),
+        ("undo", document.clone()),
+        ("text", document),
+    ]);
+    assert_eq!(responses[0]["result"], json!({ "document": 1, "lines": 2 }));
+    assert_eq!(responses[1]["result"], at(2, 5));
+    assert_eq!(responses[3]["result"], "o\ntwo!\nthree\n");
+    assert_eq!(responses[5]["result"], "one\ntwo!\nthree\n");
+}
+
+#[test]
+fn edits_outside_the_document_are_refused() {
+    let path = scratch("outside", "one\ntwo\n");
+    let responses = serve(&[
+        ("open", json!({ "path": path })),
+        (
+            "insert",
+            json!({ "document": 1, "at": at(50, 99), "text": "lost" }),
+        ),
+        (
+            "insert",
+            json!({ "document": 1, "at": at(0, 4), "text": "lost" }),
+        ),
+        (
+            "delete",
+            json!({ "document": 1, "start": at(0, 0), "end": at(9, 0) }),
+        ),
+        (
+            "delete",
+            json!({ "document": 1, "start": at(1, 2), "end": at(0, 1) }),
+        ),
+        ("text", json!({ "document": 1 })),
+    ]);
+    for response in &responses[1..5] {
+        assert_eq!(response["error"]["code"], -32602, "{response}");
+    }
+    assert_eq!(responses[5]["result"], "one\ntwo\n");
+}
+
+#[test]
+fn unknown_methods_and_documents_are_errors() {
+    let responses = serve(&[("fly", json!({})), ("text", json!({ "document": 7 }))]);
+    assert_eq!(responses[0]["error"]["code"], -32601);
+    assert_eq!(responses[1]["error"]["code"], -32602);
+}